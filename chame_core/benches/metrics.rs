@@ -0,0 +1,64 @@
+//! Cost of counting a burst of events
+//!
+//! Run with `cargo bench -p chame_core`. Compares the counter families used
+//! by `MetricsCollector::record_event` with the `format!`-built string keys
+//! they replaced, on the same events.
+
+use chame_core::{CounterFamily, Event, MetricsCollector};
+use std::time::{Duration, Instant};
+
+/// Events in one burst
+const BURST: usize = 200_000;
+
+/// Runs per variant, the fastest is reported
+const RUNS: usize = 5;
+
+fn main() {
+    let events = generate_events(BURST);
+    
+    bench("string keys", &events, |metrics, event| {
+        metrics.increment_counter(&format!("event_count_{:?}", event.event_type));
+        metrics.increment_counter(&format!("event_source_{}", event.source));
+        metrics.increment_counter(&format!("severity_{:?}", event.severity()));
+    });
+    
+    bench("counter families", &events, |metrics, event| {
+        metrics.increment_family(CounterFamily::EventType, event.event_type.name());
+        metrics.increment_family(CounterFamily::EventSource, &event.source);
+        metrics.increment_family(CounterFamily::Severity, event.severity().name());
+    });
+}
+
+/// A mix of detector alerts, honeypot hits and custom events
+fn generate_events(count: usize) -> Vec<Event> {
+    (0..count)
+        .map(|i| match i % 4 {
+            0 => Event::security_alert("eye360", None),
+            1 => Event::network_activity("nettongue", None),
+            2 => Event::honeypot_activity("lurefield", None),
+            _ => Event::custom("scanner.finding", "nmap", None),
+        })
+        .collect()
+}
+
+/// Time counting every event with `count` and print the throughput
+fn bench(name: &str, events: &[Event], count: impl Fn(&MetricsCollector, &Event)) {
+    let mut best = Duration::MAX;
+    
+    for _ in 0..RUNS {
+        let metrics = MetricsCollector::new();
+        let start = Instant::now();
+        for event in events {
+            count(&metrics, event);
+        }
+        best = best.min(start.elapsed());
+    }
+    
+    println!(
+        "{}: {} events in {:.2?} ({:.0} events/s)",
+        name,
+        events.len(),
+        best,
+        events.len() as f64 / best.as_secs_f64()
+    );
+}
@@ -93,7 +93,7 @@ impl AdaptiveEngine {
     
     /// Register a handler for adaptive events
     pub async fn register_handler(
-        &mut self,
+        &self,
         name: impl Into<String>,
         handler: Arc<Mutex<dyn AdaptiveHandler>>,
     ) {
@@ -157,3 +157,13 @@ impl AdaptiveEngine {
         }
     }
 }
+
+impl Default for AdaptiveEngine {
+    fn default() -> Self {
+        Self {
+            handlers: RwLock::new(HashMap::new()),
+            history: RwLock::new(Vec::new()),
+            max_history: 1000,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::events::Event;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// Default number of events buffered per subscriber
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Fan-out event bus, every subscriber receives every published event
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    /// Broadcast channel sender
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    /// Create a new event bus buffering up to `capacity` events per subscriber
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+    
+    /// Publish an event to all current subscribers
+    ///
+    /// Returns the number of subscribers that will receive it. Publishing
+    /// with no subscribers is not an error, the event is simply dropped.
+    pub fn publish(&self, event: Event) -> usize {
+        match self.sender.send(event) {
+            Ok(receivers) => receivers,
+            Err(_) => {
+                debug!("Event published with no subscribers");
+                0
+            }
+        }
+    }
+    
+    /// Subscribe to all events published from now on
+    ///
+    /// A subscriber that falls more than the bus capacity behind receives
+    /// `RecvError::Lagged` and skips the missed events.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+    
+    /// Number of active subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    
+    #[tokio::test]
+    async fn test_every_subscriber_receives_every_event() {
+        let bus = EventBus::default();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        
+        assert_eq!(bus.publish(Event::new(EventType::SystemChange, "test", None)), 2);
+        
+        assert_eq!(first.recv().await.unwrap().event_type, EventType::SystemChange);
+        assert_eq!(second.recv().await.unwrap().event_type, EventType::SystemChange);
+    }
+    
+    #[test]
+    fn test_publish_without_subscribers() {
+        let bus = EventBus::default();
+        assert_eq!(bus.publish(Event::new(EventType::SystemChange, "test", None)), 0);
+    }
+}
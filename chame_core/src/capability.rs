@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether a privileged feature of a module does its job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum FeatureStatus {
+    /// Works as configured
+    Functional,
+
+    /// Runs, but only logs what it would do, with the reason
+    Simulated(String),
+
+    /// Enabled but could not be started, with the reason
+    Unavailable(String),
+
+    /// Turned off in the configuration
+    Disabled,
+}
+
+impl FeatureStatus {
+    /// `functional`, `simulated`, `unavailable` or `disabled`
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureStatus::Functional => "functional",
+            FeatureStatus::Simulated(_) => "simulated",
+            FeatureStatus::Unavailable(_) => "unavailable",
+            FeatureStatus::Disabled => "disabled",
+        }
+    }
+
+    /// Why the feature does not do its job
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            FeatureStatus::Simulated(reason) | FeatureStatus::Unavailable(reason) => Some(reason),
+            FeatureStatus::Functional | FeatureStatus::Disabled => None,
+        }
+    }
+
+    /// Whether the feature is enabled but does not do its job
+    pub fn is_degraded(&self) -> bool {
+        matches!(self, FeatureStatus::Simulated(_) | FeatureStatus::Unavailable(_))
+    }
+}
+
+/// A module feature relying on host capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feature {
+    /// Module and feature name
+    pub name: String,
+
+    /// What to expect from it
+    #[serde(flatten)]
+    pub status: FeatureStatus,
+}
+
+impl Feature {
+    /// Describe feature `name`
+    pub fn new(name: impl Into<String>, status: FeatureStatus) -> Self {
+        Self {
+            name: name.into(),
+            status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_json() {
+        let feature = Feature::new("nettongue packet capture", FeatureStatus::Unavailable("Interface eth0 not found".to_string()));
+        assert!(feature.status.is_degraded());
+        assert_eq!(
+            serde_json::to_value(&feature).unwrap(),
+            serde_json::json!({"name": "nettongue packet capture", "status": "unavailable", "reason": "Interface eth0 not found"})
+        );
+        assert_eq!(
+            serde_json::to_value(Feature::new("skinshift OS fingerprint", FeatureStatus::Functional)).unwrap(),
+            serde_json::json!({"name": "skinshift OS fingerprint", "status": "functional"})
+        );
+        assert!(!FeatureStatus::Disabled.is_degraded());
+    }
+}
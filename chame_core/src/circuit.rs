@@ -0,0 +1,316 @@
+use crate::metrics::MetricsCollector;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+
+    /// Calls are short-circuited until the cooldown elapses
+    Open,
+
+    /// One probe call goes through to check whether the dependency recovered
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Name used in logs
+    pub fn name(self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+
+    /// Value of the state gauge: 0 closed, 1 half-open, 2 open
+    fn gauge_value(self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+/// When a [`CircuitBreaker`] opens and how long it stays open
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+
+    /// Time the circuit stays open before a probe call is let through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Error of a call made through a [`CircuitBreaker`]
+#[derive(Debug, thiserror::Error)]
+pub enum CircuitError<E> {
+    /// The circuit is open, the call wasn't made
+    #[error("Circuit {0} is open, call skipped")]
+    Open(String),
+
+    /// The call was made and failed
+    #[error("{0}")]
+    Failed(E),
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+
+    /// When the circuit opened, or when the current probe started
+    since: Instant,
+}
+
+/// Stops calling a failing dependency for a while
+///
+/// After `failure_threshold` consecutive failures the circuit opens and
+/// calls fail fast with [`CircuitError::Open`]. Once `cooldown` has elapsed
+/// a single probe call goes through: its success closes the circuit, its
+/// failure opens it again. A probe that never reports back is replaced by
+/// another one after a further cooldown.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    circuit: Arc<Mutex<Circuit>>,
+    metrics: Option<Arc<MetricsCollector>>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit breaker for the dependency `name`
+    pub fn new(name: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        Self {
+            name: name.into(),
+            config,
+            circuit: Arc::new(Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                since: Instant::now(),
+            })),
+            metrics: None,
+        }
+    }
+
+    /// Export the state as the `circuit_<name>_state` gauge and count
+    /// skipped calls in `circuit_<name>_rejected`
+    pub fn with_metrics(self, metrics: Arc<MetricsCollector>) -> Self {
+        metrics.set_gauge(&self.gauge_key(), self.state().gauge_value());
+        Self {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    /// Name of the protected dependency
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Current state, an open circuit past its cooldown reading as half-open
+    pub fn state(&self) -> CircuitState {
+        let circuit = self.lock();
+        match circuit.state {
+            CircuitState::Open if circuit.since.elapsed() >= self.config.cooldown => CircuitState::HalfOpen,
+            state => state,
+        }
+    }
+
+    /// Whether a call may go through now
+    ///
+    /// Returning `true` while half-open makes the caller the probe, so each
+    /// `true` must be followed by [`record_success`](Self::record_success)
+    /// or [`record_failure`](Self::record_failure).
+    pub fn try_acquire(&self) -> bool {
+        let mut circuit = self.lock();
+        let allowed = match circuit.state {
+            CircuitState::Closed => true,
+            CircuitState::Open | CircuitState::HalfOpen if circuit.since.elapsed() >= self.config.cooldown => {
+                circuit.since = Instant::now();
+                self.transition(&mut circuit, CircuitState::HalfOpen);
+                true
+            }
+            CircuitState::Open | CircuitState::HalfOpen => false,
+        };
+        drop(circuit);
+
+        if !allowed {
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_counter(&format!("circuit_{}_rejected", self.name));
+            }
+        }
+        allowed
+    }
+
+    /// Report a successful call, closing the circuit
+    pub fn record_success(&self) {
+        let mut circuit = self.lock();
+        circuit.consecutive_failures = 0;
+        if circuit.state != CircuitState::Closed {
+            info!("Circuit {} closed after a successful call", self.name);
+            self.transition(&mut circuit, CircuitState::Closed);
+        }
+    }
+
+    /// Report a failed call, opening the circuit at the threshold or when
+    /// the probe failed
+    pub fn record_failure(&self) {
+        let mut circuit = self.lock();
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+
+        let opens = match circuit.state {
+            CircuitState::Closed => circuit.consecutive_failures >= self.config.failure_threshold.max(1),
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if opens {
+            warn!(
+                "Circuit {} open after {} consecutive failures, skipping calls for {:?}",
+                self.name, circuit.consecutive_failures, self.config.cooldown
+            );
+            circuit.since = Instant::now();
+            self.transition(&mut circuit, CircuitState::Open);
+        }
+    }
+
+    /// Run `op` unless the circuit is open, recording its outcome
+    pub async fn call<F, Fut, T, E>(&self, op: F) -> Result<T, CircuitError<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if !self.try_acquire() {
+            return Err(CircuitError::Open(self.name.clone()));
+        }
+
+        match op().await {
+            Ok(value) => {
+                self.record_success();
+                Ok(value)
+            }
+            Err(e) => {
+                self.record_failure();
+                Err(CircuitError::Failed(e))
+            }
+        }
+    }
+
+    fn transition(&self, circuit: &mut Circuit, state: CircuitState) {
+        circuit.state = state;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_gauge(&self.gauge_key(), state.gauge_value());
+        }
+    }
+
+    fn gauge_key(&self) -> String {
+        format!("circuit_{}_state", self.name)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(failure_threshold: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "webhook",
+            CircuitBreakerConfig {
+                failure_threshold,
+                cooldown,
+            },
+        )
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<(), CircuitError<&'static str>> {
+        breaker.call(|| async { Err("connection refused") }).await
+    }
+
+    async fn succeed(breaker: &CircuitBreaker) -> Result<(), CircuitError<&'static str>> {
+        breaker.call(|| async { Ok(()) }).await
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_consecutive_failures() {
+        let breaker = breaker(3, Duration::from_secs(60));
+
+        // A success in between resets the count
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+        succeed(&breaker).await.unwrap();
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        assert!(matches!(fail(&breaker).await, Err(CircuitError::Failed("connection refused"))));
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // Open: the call isn't made
+        let called = std::sync::atomic::AtomicBool::new(false);
+        let result: Result<(), CircuitError<&str>> = breaker
+            .call(|| async {
+                called.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(CircuitError::Open(name)) if name == "webhook"));
+        assert!(!called.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_closes_or_reopens() {
+        let cooldown = Duration::from_millis(20);
+        let breaker = breaker(1, cooldown);
+
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // A failed probe opens the circuit again
+        tokio::time::sleep(cooldown).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(matches!(fail(&breaker).await, Err(CircuitError::Failed(_))));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(succeed(&breaker).await, Err(CircuitError::Open(_))));
+
+        // A single probe at a time, its success closes the circuit
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        succeed(&breaker).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_is_exported_as_a_gauge() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let breaker = breaker(1, Duration::from_secs(60)).with_metrics(metrics.clone());
+        assert_eq!(metrics.get_gauge("circuit_webhook_state"), Some(0.0));
+
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(metrics.get_gauge("circuit_webhook_state"), Some(2.0));
+
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(metrics.get_counter("circuit_webhook_rejected"), 2);
+    }
+}
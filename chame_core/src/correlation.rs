@@ -0,0 +1,383 @@
+use crate::bus::EventBus;
+use crate::events::{Event, EventType, Severity};
+use crate::sender::EventSender;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Source name of the events synthesized by the correlation engine
+pub const CORRELATION_SOURCE: &str = "correlation";
+
+/// Key used when a rule does not require events to share a source
+const ANY_SOURCE: &str = "*";
+
+/// A declarative correlation rule
+///
+/// The rule fires when events of the types in `sequence` are seen in that
+/// order within `window_secs`, optionally all from the same source address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationRule {
+    /// Rule name, reported in the synthesized alert
+    pub name: String,
+    
+    /// Event types that must occur, in order
+    pub sequence: Vec<EventType>,
+    
+    /// Maximum time between the first and last event of the sequence
+    pub window_secs: u64,
+    
+    /// Whether all events must come from the same source address
+    pub same_source: bool,
+    
+    /// Severity of the synthesized alert
+    pub severity: Severity,
+}
+
+impl CorrelationRule {
+    /// Create a new correlation rule
+    pub fn new(
+        name: impl Into<String>,
+        sequence: Vec<EventType>,
+        window_secs: u64,
+        severity: Severity,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            sequence,
+            window_secs,
+            same_source: true,
+            severity,
+        }
+    }
+    
+    /// Set whether all events must come from the same source address
+    pub fn with_same_source(mut self, same_source: bool) -> Self {
+        self.same_source = same_source;
+        self
+    }
+}
+
+/// Configuration for the correlation engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationConfig {
+    /// Whether correlation is enabled
+    pub enabled: bool,
+    
+    /// Rules to evaluate
+    pub rules: Vec<CorrelationRule>,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: CorrelationEngine::default_rules(),
+        }
+    }
+}
+
+/// Engine turning sequences of related events into elevated alerts
+pub struct CorrelationEngine {
+    /// Rules to evaluate
+    rules: Vec<CorrelationRule>,
+    
+    /// Recent events per source key
+    history: HashMap<String, VecDeque<(EventType, DateTime<Utc>)>>,
+    
+    /// Last time each (rule, source key) fired, to avoid repeated alerts
+    last_fired: HashMap<(String, String), DateTime<Utc>>,
+}
+
+impl CorrelationEngine {
+    /// Create a correlation engine with the given rules
+    pub fn new(rules: Vec<CorrelationRule>) -> Self {
+        Self {
+            rules,
+            history: HashMap::new(),
+            last_fired: HashMap::new(),
+        }
+    }
+    
+    /// Create a correlation engine from its configuration
+    pub fn from_config(config: &CorrelationConfig) -> Self {
+        Self::new(config.rules.clone())
+    }
+    
+    /// Built-in rules
+    pub fn default_rules() -> Vec<CorrelationRule> {
+        vec![
+            // Reconnaissance followed by an attempt on a decoy
+            CorrelationRule::new(
+                "scan_then_honeypot",
+                vec![EventType::NetworkActivity, EventType::HoneypotActivity],
+                60,
+                Severity::Critical,
+            ),
+            // A burst of alerts, wherever they come from
+            CorrelationRule::new(
+                "alert_burst",
+                vec![EventType::SecurityAlert, EventType::SecurityAlert, EventType::SecurityAlert],
+                30,
+                Severity::Critical,
+            )
+            .with_same_source(false),
+        ]
+    }
+    
+    /// Get the configured rules
+    pub fn rules(&self) -> &[CorrelationRule] {
+        &self.rules
+    }
+    
+    /// Feed an event to the engine, returning the correlated alerts it triggers
+    pub fn process(&mut self, event: &Event) -> Vec<Event> {
+        // Never correlate our own output
+        if event.source == CORRELATION_SOURCE {
+            return Vec::new();
+        }
+        
+        let source_ip = Self::source_address(event);
+        let now = event.timestamp;
+        
+        self.prune(now);
+        
+        let mut correlated = Vec::new();
+        
+        for rule in &self.rules {
+            if rule.sequence.last() != Some(&event.event_type) {
+                continue;
+            }
+            
+            let key = if rule.same_source {
+                match &source_ip {
+                    Some(ip) => ip.clone(),
+                    None => continue,
+                }
+            } else {
+                ANY_SOURCE.to_string()
+            };
+            
+            let window = Duration::seconds(rule.window_secs as i64);
+            let fired_key = (rule.name.clone(), key.clone());
+            
+            if let Some(fired_at) = self.last_fired.get(&fired_key) {
+                if now - *fired_at < window {
+                    continue;
+                }
+            }
+            
+            let history = self.history.get(&key);
+            if !Self::matches(rule, history, now) {
+                continue;
+            }
+            
+            info!("Correlation rule '{}' fired for {}", rule.name, key);
+            self.last_fired.insert(fired_key, now);
+            
+            correlated.push(Event::security_alert_with_severity(
+                CORRELATION_SOURCE,
+                Some(serde_json::json!({
+                    "rule": rule.name,
+                    "source_ip": source_ip,
+                    "sequence": rule.sequence,
+                    "window_secs": rule.window_secs,
+                    "trigger_source": event.source,
+                })),
+                rule.severity,
+            ));
+        }
+        
+        // Remember the event for both per-source and global rules
+        if let Some(ip) = source_ip {
+            self.history
+                .entry(ip)
+                .or_default()
+                .push_back((event.event_type.clone(), now));
+        }
+        self.history
+            .entry(ANY_SOURCE.to_string())
+            .or_default()
+            .push_back((event.event_type.clone(), now));
+            
+        correlated
+    }
+    
+    /// Consume events from the bus and report correlated alerts through `events`
+    ///
+    /// `events` should lead to the core, so alerts are stored, counted and
+    /// scored like any other event before they reach the bus.
+    pub fn spawn(mut self, bus: &EventBus, events: EventSender, cancel: CancellationToken) -> JoinHandle<()> {
+        let mut receiver = bus.subscribe();
+        
+        tokio::spawn(async move {
+            info!("Correlation engine started with {} rules", self.rules.len());
+            
+            'events: loop {
+                let event = tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    received = receiver.recv() => match received {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Correlation engine lagged, skipped {} events", skipped);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                };
+                
+                for alert in self.process(&event) {
+                    if events.send(alert).await.is_err() {
+                        break 'events;
+                    }
+                }
+            }
+            
+            info!("Correlation engine stopped");
+        })
+    }
+    
+    /// Check whether the rule's sequence ends at `now` within the window
+    fn matches(
+        rule: &CorrelationRule,
+        history: Option<&VecDeque<(EventType, DateTime<Utc>)>>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        // The current event matched the last step, look backwards for the rest
+        let mut remaining = rule.sequence.iter().rev().skip(1).peekable();
+        if remaining.peek().is_none() {
+            return true;
+        }
+        
+        let start = now - Duration::seconds(rule.window_secs as i64);
+        
+        if let Some(history) = history {
+            for (event_type, timestamp) in history.iter().rev() {
+                if *timestamp < start {
+                    break;
+                }
+                
+                if Some(event_type) == remaining.peek().copied() {
+                    remaining.next();
+                    if remaining.peek().is_none() {
+                        return true;
+                    }
+                }
+            }
+        }
+        
+        false
+    }
+    
+    /// Drop history older than the longest rule window
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let max_window = self.rules.iter().map(|r| r.window_secs).max().unwrap_or(0);
+        let cutoff = now - Duration::seconds(max_window as i64);
+        
+        self.history.retain(|_, events| {
+            while events.front().is_some_and(|(_, t)| *t < cutoff) {
+                events.pop_front();
+            }
+            !events.is_empty()
+        });
+        
+        self.last_fired.retain(|_, fired_at| *fired_at >= cutoff);
+        
+        debug!("Correlation history tracks {} sources", self.history.len());
+    }
+    
+    /// Address the event originates from, if the detector reported one
+    fn source_address(event: &Event) -> Option<String> {
+        let data = event.data.as_ref()?;
+        
+        data.get("source_ip")
+            .or_else(|| data.get("details").and_then(|d| d.get("source_ip")))
+            .and_then(|ip| ip.as_str())
+            .map(String::from)
+    }
+}
+
+impl Default for CorrelationEngine {
+    fn default() -> Self {
+        Self::new(Self::default_rules())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::OverflowPolicy;
+    
+    fn event_from(event_type: EventType, ip: &str, seconds_ago: i64) -> Event {
+        let mut event = Event::new(event_type, "test", Some(serde_json::json!({ "source_ip": ip })));
+        event.timestamp = Utc::now() - Duration::seconds(seconds_ago);
+        event
+    }
+    
+    #[test]
+    fn test_scan_then_honeypot_fires() {
+        let mut engine = CorrelationEngine::default();
+        
+        assert!(engine.process(&event_from(EventType::NetworkActivity, "10.0.0.5", 30)).is_empty());
+        let alerts = engine.process(&event_from(EventType::HoneypotActivity, "10.0.0.5", 0));
+        
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].event_type, EventType::SecurityAlert);
+        assert_eq!(alerts[0].source, CORRELATION_SOURCE);
+        assert_eq!(alerts[0].severity(), Severity::Critical);
+        assert_eq!(alerts[0].data.as_ref().unwrap()["rule"], "scan_then_honeypot");
+        
+        // The same pair does not fire twice within the window
+        assert!(engine.process(&event_from(EventType::HoneypotActivity, "10.0.0.5", 0)).is_empty());
+    }
+    
+    #[test]
+    fn test_requires_same_source_and_window() {
+        let mut engine = CorrelationEngine::default();
+        
+        engine.process(&event_from(EventType::NetworkActivity, "10.0.0.5", 10));
+        assert!(engine.process(&event_from(EventType::HoneypotActivity, "10.0.0.6", 0)).is_empty());
+        
+        engine.process(&event_from(EventType::NetworkActivity, "10.0.0.7", 120));
+        assert!(engine.process(&event_from(EventType::HoneypotActivity, "10.0.0.7", 0)).is_empty());
+    }
+    
+    #[test]
+    fn test_ignores_own_events() {
+        let mut engine = CorrelationEngine::new(vec![
+            CorrelationRule::new("single", vec![EventType::SecurityAlert], 10, Severity::High)
+                .with_same_source(false),
+        ]);
+        
+        let alerts = engine.process(&Event::security_alert("eye360", None));
+        assert_eq!(alerts.len(), 1);
+        assert!(engine.process(&alerts[0]).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_spawned_engine_reports_through_the_sender() {
+        let bus = EventBus::new(16);
+        let mut bus_events = bus.subscribe();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+        let cancel = CancellationToken::new();
+        let engine = CorrelationEngine::new(vec![
+            CorrelationRule::new("single", vec![EventType::SecurityAlert], 10, Severity::High)
+                .with_same_source(false),
+        ]);
+        let task = engine.spawn(&bus, EventSender::new(sender, OverflowPolicy::Block), cancel.clone());
+        
+        bus.publish(Event::security_alert("eye360", None));
+        let alert = receiver.recv().await.unwrap();
+        assert_eq!(alert.source, CORRELATION_SOURCE);
+        
+        // Only the triggering event went out on the bus
+        assert_eq!(bus_events.recv().await.unwrap().source, "eye360");
+        assert!(bus_events.try_recv().is_err());
+        
+        cancel.cancel();
+        task.await.unwrap();
+    }
+}
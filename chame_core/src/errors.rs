@@ -39,6 +39,13 @@ pub enum ChameleonError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     
+    #[error("{module}: {source}")]
+    Module {
+        module: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -83,4 +90,59 @@ impl ChameleonError {
     pub fn new_unknown_error(msg: impl Into<String>) -> Self {
         Self::Unknown(msg.into())
     }
+    
+    /// Wrap a module error, keeping it available for downcasting
+    pub fn module(
+        module: impl Into<String>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Module {
+            module: module.into(),
+            source: Box::new(source),
+        }
+    }
+    
+    /// Name of the module the error came from, if any
+    pub fn module_name(&self) -> Option<&str> {
+        match self {
+            Self::Module { module, .. } => Some(module),
+            _ => None,
+        }
+    }
+    
+    /// Get the original module error as `E`
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        match self {
+            Self::Module { source, .. } => source.downcast_ref::<E>(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[derive(Error, Debug, PartialEq)]
+    enum TestModuleError {
+        #[error("Permission denied: {0}")]
+        PermissionDenied(String),
+    }
+    
+    #[test]
+    fn test_module_error_keeps_source() {
+        let error = ChameleonError::module(
+            "test_module",
+            TestModuleError::PermissionDenied("iptables".to_string()),
+        );
+        
+        assert_eq!(error.to_string(), "test_module: Permission denied: iptables");
+        assert_eq!(error.module_name(), Some("test_module"));
+        assert_eq!(
+            error.downcast_ref::<TestModuleError>(),
+            Some(&TestModuleError::PermissionDenied("iptables".to_string()))
+        );
+        assert!(error.downcast_ref::<std::io::Error>().is_none());
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
@@ -1,6 +1,51 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Version of the detection payloads sent by eye360, nettongue and formats
+///
+/// Carried in the payload's `schema_version` field. It is bumped when a
+/// field is renamed, removed or changes meaning; new fields keep the
+/// version, consumers must ignore the fields they don't know.
+///
+/// Version 1 payloads all have `detection_type`, `severity` (0-10) and
+/// `details` (string map). eye360 adds `source` and `timestamp`; nettongue
+/// adds `source_ip`, `dest_ip`, `source_port`, `dest_port`, `protocol` and
+/// `timestamp`; formats adds `location` and `file`.
+pub const DETECTION_SCHEMA_VERSION: u32 = 1;
+
+/// Serialize a detection as an event payload tagged with [`DETECTION_SCHEMA_VERSION`]
+pub fn versioned_detection(detection: &(impl Serialize + ?Sized)) -> serde_json::Value {
+    let mut payload = serde_json::to_value(detection).unwrap_or_default();
+    if let Some(fields) = payload.as_object_mut() {
+        fields.insert("schema_version".to_string(), DETECTION_SCHEMA_VERSION.into());
+    }
+    payload
+}
+
+/// A structured detector finding, e.g. eye360's `Detection`
+///
+/// Modules report detections as events through [`to_event`](Self::to_event);
+/// consumers sharing the process can use the detections as they are.
+pub trait ReportableDetection: Serialize {
+    /// Module reporting the detection, the source of its event
+    fn module(&self) -> &str;
+    
+    /// Type of the event the detection is reported as
+    fn event_type(&self) -> EventType;
+    
+    /// Name of the detection type, e.g. `PortScan`
+    fn detection_type(&self) -> String;
+    
+    /// Severity from 0 to 10
+    fn severity(&self) -> Severity0to10;
+    
+    /// The event the detection is reported as, its payload versioned
+    fn to_event(&self) -> Event {
+        Event::new(self.event_type(), self.module(), Some(versioned_detection(self)))
+            .with_severity(self.severity().level())
+    }
+}
+
 /// Types of events that the system can handle
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EventType {
@@ -32,6 +77,41 @@ pub enum EventType {
     Custom(String),
 }
 
+impl EventType {
+    /// Name of the type, the custom name for custom events
+    pub fn name(&self) -> &str {
+        match self {
+            Self::SecurityAlert => "SecurityAlert",
+            Self::SystemChange => "SystemChange",
+            Self::NetworkActivity => "NetworkActivity",
+            Self::PostureChange => "PostureChange",
+            Self::HoneypotActivity => "HoneypotActivity",
+            Self::FingerprintChange => "FingerprintChange",
+            Self::ServiceLifecycle => "ServiceLifecycle",
+            Self::MetricsReport => "MetricsReport",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+/// What produced an event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventOrigin {
+    /// A module or the core, observing something
+    #[default]
+    Module,
+    
+    /// An adaptive handler, reacting to another event
+    Handler,
+}
+
+impl EventOrigin {
+    fn is_module(&self) -> bool {
+        *self == Self::Module
+    }
+}
+
 /// An event in the CAMALEON system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -46,6 +126,14 @@ pub struct Event {
     
     /// Additional data payload (JSON format)
     pub data: Option<serde_json::Value>,
+    
+    /// Severity reported by the source, overriding the type-derived one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity_override: Option<Severity>,
+    
+    /// What produced the event, handlers never see what handlers produced
+    #[serde(default, skip_serializing_if = "EventOrigin::is_module")]
+    pub origin: EventOrigin,
 }
 
 impl Event {
@@ -60,14 +148,31 @@ impl Event {
             event_type,
             source: source.into(),
             data,
+            severity_override: None,
+            origin: EventOrigin::Module,
         }
     }
     
+    /// Set an explicit severity for this event
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity_override = Some(severity);
+        self
+    }
+    
     /// Create a security alert event
     pub fn security_alert(source: impl Into<String>, data: Option<serde_json::Value>) -> Self {
         Self::new(EventType::SecurityAlert, source, data)
     }
     
+    /// Create a security alert event with an explicit severity
+    pub fn security_alert_with_severity(
+        source: impl Into<String>,
+        data: Option<serde_json::Value>,
+        severity: Severity,
+    ) -> Self {
+        Self::security_alert(source, data).with_severity(severity)
+    }
+    
     /// Create a system change event
     pub fn system_change(source: impl Into<String>, data: Option<serde_json::Value>) -> Self {
         Self::new(EventType::SystemChange, source, data)
@@ -78,6 +183,15 @@ impl Event {
         Self::new(EventType::NetworkActivity, source, data)
     }
     
+    /// Create a network activity event with an explicit severity
+    pub fn network_activity_with_severity(
+        source: impl Into<String>,
+        data: Option<serde_json::Value>,
+        severity: Severity,
+    ) -> Self {
+        Self::network_activity(source, data).with_severity(severity)
+    }
+    
     /// Create a posture change event
     pub fn posture_change(source: impl Into<String>, data: Option<serde_json::Value>) -> Self {
         Self::new(EventType::PostureChange, source, data)
@@ -88,6 +202,15 @@ impl Event {
         Self::new(EventType::HoneypotActivity, source, data)
     }
     
+    /// Create a honeypot activity event with an explicit severity
+    pub fn honeypot_activity_with_severity(
+        source: impl Into<String>,
+        data: Option<serde_json::Value>,
+        severity: Severity,
+    ) -> Self {
+        Self::honeypot_activity(source, data).with_severity(severity)
+    }
+    
     /// Create a fingerprint change event
     pub fn fingerprint_change(source: impl Into<String>, data: Option<serde_json::Value>) -> Self {
         Self::new(EventType::FingerprintChange, source, data)
@@ -108,8 +231,12 @@ impl Event {
         Self::new(EventType::Custom(custom_type.into()), source, data)
     }
     
-    /// Get the event severity (derived from event type)
+    /// Get the event severity (the override if set, otherwise derived from event type)
     pub fn severity(&self) -> Severity {
+        if let Some(severity) = self.severity_override {
+            return severity;
+        }
+        
         match self.event_type {
             EventType::SecurityAlert => Severity::High,
             EventType::PostureChange => Severity::Medium,
@@ -122,6 +249,30 @@ impl Event {
             EventType::Custom(_) => Severity::Info,
         }
     }
+    
+    /// Detection schema version of the payload, 0 when it predates versioning
+    pub fn schema_version(&self) -> u32 {
+        self.data
+            .as_ref()
+            .and_then(|data| data.get("schema_version"))
+            .and_then(|version| version.as_u64())
+            .map_or(0, |version| version as u32)
+    }
+    
+    /// Precise 0-10 severity a detector put in the event data, if any
+    ///
+    /// Only detection events carry one; other events may use a `severity`
+    /// key for something else.
+    pub fn severity_score(&self) -> Option<u8> {
+        if !matches!(
+            self.event_type,
+            EventType::SecurityAlert | EventType::NetworkActivity | EventType::HoneypotActivity
+        ) {
+            return None;
+        }
+        let score = self.data.as_ref()?.get("severity")?.as_u64()?;
+        Some(score.min(10) as u8)
+    }
 }
 
 /// Severity levels for events
@@ -142,3 +293,212 @@ pub enum Severity {
     /// Informational - no action needed
     Info,
 }
+
+impl Severity {
+    /// Map a 0-10 detector score onto a severity level
+    pub fn from_score(score: u8) -> Self {
+        match score {
+            10..=u8::MAX => Severity::Critical,
+            8..=9 => Severity::High,
+            5..=7 => Severity::Medium,
+            2..=4 => Severity::Low,
+            _ => Severity::Info,
+        }
+    }
+    
+    /// Name of the level
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Critical => "Critical",
+            Severity::High => "High",
+            Severity::Medium => "Medium",
+            Severity::Low => "Low",
+            Severity::Info => "Info",
+        }
+    }
+}
+
+/// Score of a detection on the 0-10 scale shared by eye360, nettongue and formats
+///
+/// | Score | Level    |
+/// |-------|----------|
+/// | 10    | Critical |
+/// | 8-9   | High     |
+/// | 5-7   | Medium   |
+/// | 2-4   | Low      |
+/// | 0-1   | Info     |
+///
+/// Serialized as a plain number; deserializing a score above 10 fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Severity0to10(u8);
+
+/// A score above 10 given where a [`Severity0to10`] is expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Severity {0} is outside the 0-10 scale")]
+pub struct SeverityOutOfRange(pub u8);
+
+impl Severity0to10 {
+    /// Lowest score
+    pub const MIN: Self = Self(0);
+    
+    /// Highest score
+    pub const MAX: Self = Self(10);
+    
+    /// Validate a score, rejecting anything above 10
+    pub const fn new(score: u8) -> Result<Self, SeverityOutOfRange> {
+        if score <= 10 {
+            Ok(Self(score))
+        } else {
+            Err(SeverityOutOfRange(score))
+        }
+    }
+    
+    /// Bring a score onto the scale, anything above 10 becoming 10
+    pub const fn clamped(score: u8) -> Self {
+        if score <= 10 {
+            Self(score)
+        } else {
+            Self::MAX
+        }
+    }
+    
+    /// The score, between 0 and 10
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+    
+    /// Severity level the score maps onto
+    pub fn level(self) -> Severity {
+        Severity::from_score(self.0)
+    }
+}
+
+impl TryFrom<u8> for Severity0to10 {
+    type Error = SeverityOutOfRange;
+    
+    fn try_from(score: u8) -> Result<Self, Self::Error> {
+        Self::new(score)
+    }
+}
+
+impl From<Severity0to10> for u8 {
+    fn from(severity: Severity0to10) -> Self {
+        severity.0
+    }
+}
+
+impl From<Severity0to10> for Severity {
+    fn from(severity: Severity0to10) -> Self {
+        severity.level()
+    }
+}
+
+impl PartialEq<u8> for Severity0to10 {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<u8> for Severity0to10 {
+    fn partial_cmp(&self, other: &u8) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl std::fmt::Display for Severity0to10 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_severity_override() {
+        let event = Event::security_alert("test", None);
+        assert_eq!(event.severity(), Severity::High);
+        
+        let event = Event::security_alert_with_severity("test", None, Severity::Low);
+        assert_eq!(event.severity(), Severity::Low);
+        
+        // The override survives serialization, and is optional when parsing
+        let json = serde_json::to_value(&event).unwrap();
+        let parsed: Event = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.severity(), Severity::Low);
+        
+        let json = serde_json::to_value(Event::security_alert("test", None)).unwrap();
+        assert!(json.get("severity_override").is_none());
+    }
+    
+    #[test]
+    fn test_severity_score() {
+        let event = Event::security_alert("eye360", Some(serde_json::json!({ "severity": 7 })));
+        assert_eq!(event.severity_score(), Some(7));
+        
+        let event = Event::security_alert("formats", Some(serde_json::json!({ "severity": 42 })));
+        assert_eq!(event.severity_score(), Some(10));
+        
+        let event = Event::security_alert("test", Some(serde_json::json!({ "severity": "high" })));
+        assert_eq!(event.severity_score(), None);
+        assert_eq!(Event::security_alert("test", None).severity_score(), None);
+        
+        let event = Event::honeypot_activity("lurefield", Some(serde_json::json!({ "severity": 3 })));
+        assert_eq!(event.severity_score(), Some(3));
+        
+        // A posture change's data is not a detection
+        let event = Event::posture_change("posture_engine", Some(serde_json::json!({ "severity": 9 })));
+        assert_eq!(event.severity_score(), None);
+    }
+    
+    #[test]
+    fn test_severity_from_score() {
+        assert_eq!(Severity::from_score(10), Severity::Critical);
+        assert_eq!(Severity::from_score(9), Severity::High);
+        assert_eq!(Severity::from_score(6), Severity::Medium);
+        assert_eq!(Severity::from_score(3), Severity::Low);
+        assert_eq!(Severity::from_score(0), Severity::Info);
+    }
+    
+    #[test]
+    fn test_severity_scale_is_enforced() {
+        assert_eq!(Severity0to10::new(7).unwrap().get(), 7);
+        assert_eq!(Severity0to10::new(42), Err(SeverityOutOfRange(42)));
+        assert_eq!(Severity0to10::try_from(11), Err(SeverityOutOfRange(11)));
+        assert_eq!(Severity0to10::clamped(42), Severity0to10::MAX);
+        assert_eq!(Severity::from(Severity0to10::clamped(9)), Severity::High);
+        
+        // A plain number on the wire, rejected when off the scale
+        assert_eq!(serde_json::to_value(Severity0to10::clamped(8)).unwrap(), serde_json::json!(8));
+        assert_eq!(serde_json::from_str::<Severity0to10>("10").unwrap(), Severity0to10::MAX);
+        assert!(serde_json::from_str::<Severity0to10>("42").is_err());
+    }
+    
+    #[test]
+    fn test_detection_schema_version() {
+        #[derive(Serialize)]
+        struct Detection {
+            detection_type: &'static str,
+            severity: u8,
+        }
+        
+        let payload = versioned_detection(&Detection { detection_type: "PortScan", severity: 7 });
+        let event = Event::network_activity("nettongue", Some(payload.clone()));
+        assert_eq!(event.schema_version(), DETECTION_SCHEMA_VERSION);
+        
+        // Consumers written before versioning keep parsing the payload
+        #[derive(Deserialize)]
+        struct OldConsumer {
+            detection_type: String,
+        }
+        let parsed: OldConsumer = serde_json::from_value(payload).unwrap();
+        assert_eq!(parsed.detection_type, "PortScan");
+        
+        // Unversioned payloads are version 0
+        let event = Event::network_activity("nettongue", Some(serde_json::json!({"detection_type": "PortScan"})));
+        assert_eq!(event.schema_version(), 0);
+        assert_eq!(Event::network_activity("nettongue", None).schema_version(), 0);
+    }
+}
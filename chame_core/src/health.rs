@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Health of a running module, as checked by the orchestrator's watchdog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum ModuleHealth {
+    /// Running normally
+    Healthy,
+
+    /// Running with part of its work stopped
+    Degraded(String),
+
+    /// Not doing its work, the watchdog restarts it
+    Unhealthy(String),
+
+    /// Stopped on purpose, e.g. from the API
+    Stopped,
+}
+
+impl ModuleHealth {
+    /// Health of a module made of parts that each run or not
+    ///
+    /// Healthy when every part runs, stopped when none does and degraded
+    /// with the stopped parts listed otherwise.
+    pub fn from_parts<'a>(parts: impl IntoIterator<Item = (&'a str, bool)>) -> Self {
+        let parts: Vec<_> = parts.into_iter().collect();
+        let stopped: Vec<_> = parts.iter().filter(|(_, running)| !running).map(|(name, _)| *name).collect();
+
+        if stopped.is_empty() {
+            ModuleHealth::Healthy
+        } else if stopped.len() == parts.len() {
+            ModuleHealth::Stopped
+        } else {
+            ModuleHealth::Degraded(format!("{} stopped", stopped.join(", ")))
+        }
+    }
+
+    /// `healthy`, `degraded`, `unhealthy` or `stopped`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModuleHealth::Healthy => "healthy",
+            ModuleHealth::Degraded(_) => "degraded",
+            ModuleHealth::Unhealthy(_) => "unhealthy",
+            ModuleHealth::Stopped => "stopped",
+        }
+    }
+
+    /// Why the module is not healthy
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            ModuleHealth::Degraded(reason) | ModuleHealth::Unhealthy(reason) => Some(reason),
+            ModuleHealth::Healthy | ModuleHealth::Stopped => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_from_parts() {
+        assert_eq!(ModuleHealth::from_parts([("process monitor", true)]), ModuleHealth::Healthy);
+        assert_eq!(ModuleHealth::from_parts([]), ModuleHealth::Healthy);
+        assert_eq!(
+            ModuleHealth::from_parts([("process monitor", true), ("syscall monitor", false)]),
+            ModuleHealth::Degraded("syscall monitor stopped".to_string())
+        );
+        assert_eq!(
+            ModuleHealth::from_parts([("process monitor", false), ("syscall monitor", false)]),
+            ModuleHealth::Stopped
+        );
+
+        let json = serde_json::to_value(ModuleHealth::Unhealthy("supervisor exited".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({"status": "unhealthy", "reason": "supervisor exited"}));
+    }
+}
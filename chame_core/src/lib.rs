@@ -1,24 +1,50 @@
-mod adaptive;
-mod errors;
-mod events;
-mod metrics;
-mod state;
+pub mod adaptive;
+pub mod bus;
+pub mod capability;
+pub mod circuit;
+pub mod correlation;
+pub mod errors;
+pub mod events;
+pub mod health;
+pub mod metrics;
+pub mod registry;
+pub mod retry;
+pub mod rotation;
+pub mod sender;
+pub mod state;
+pub mod store;
 
-use adaptive::AdaptiveManager;
-use errors::ChameleonError;
-use events::{Event, EventType};
-use metrics::MetricsCollector;
-use state::{ChameleonState, SystemState};
+pub use adaptive::{AdaptiveEngine, AdaptiveEvent, AdaptiveHandler};
+pub use bus::EventBus;
+pub use capability::{Feature, FeatureStatus};
+pub use circuit::{CircuitBreaker, CircuitBreakerConfig, CircuitError, CircuitState};
+pub use correlation::{CorrelationConfig, CorrelationEngine, CorrelationRule};
+pub use errors::ChameleonError;
+pub use events::{
+    Event, EventOrigin, EventType, ReportableDetection, Severity, Severity0to10, SeverityOutOfRange,
+    DETECTION_SCHEMA_VERSION,
+};
+pub use health::ModuleHealth;
+pub use metrics::{CounterFamily, GroupBy, MetricsCollector, MetricsQuery, QueryResult};
+pub use registry::{ServiceFailures, ServiceRegistry};
+pub use retry::{with_backoff, RetryPolicy};
+pub use rotation::{RotatingWriter, RotationPolicy};
+pub use sender::{EventSender, OverflowPolicy};
+use state::ChameleonState;
+pub use state::SystemState;
+pub use store::EventStore;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
 /// Current posture of the system
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Posture {
     /// No visible services, minimal footprint
     Silent,
@@ -45,17 +71,74 @@ impl std::fmt::Display for Posture {
 }
 
 impl Posture {
+    /// Every posture, in declaration order
+    pub const ALL: [Posture; 5] = [
+        Posture::Silent,
+        Posture::Neutral,
+        Posture::Mimetic,
+        Posture::Fulgurant,
+        Posture::Unstable,
+    ];
+    
     /// Parse from string (case-insensitive)
     pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "silent" => Some(Posture::Silent),
-            "neutral" => Some(Posture::Neutral),
-            "mimetic" => Some(Posture::Mimetic),
-            "fulgurant" => Some(Posture::Fulgurant),
-            "unstable" => Some(Posture::Unstable),
-            _ => None,
+        Self::try_from(s).ok()
+    }
+    
+    /// Lowercase name, as used in configuration and events
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Posture::Silent => "silent",
+            Posture::Neutral => "neutral",
+            Posture::Mimetic => "mimetic",
+            Posture::Fulgurant => "fulgurant",
+            Posture::Unstable => "unstable",
         }
     }
+    
+    /// How aggressive the posture is, from 0 for `Silent` up to 4
+    ///
+    /// `Silent < Neutral < Mimetic < Fulgurant < Unstable`: `Unstable` ranks
+    /// above `Fulgurant` as it gives up any consistent appearance. Postures
+    /// compare by this level.
+    pub fn escalation_level(&self) -> u8 {
+        match self {
+            Posture::Silent => 0,
+            Posture::Neutral => 1,
+            Posture::Mimetic => 2,
+            Posture::Fulgurant => 3,
+            Posture::Unstable => 4,
+        }
+    }
+}
+
+/// A name that isn't one of the [`Posture`]s
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown posture: {0}")]
+pub struct InvalidPosture(pub String);
+
+impl TryFrom<&str> for Posture {
+    type Error = InvalidPosture;
+    
+    /// Parse a posture name, whatever its case
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Posture::ALL
+            .into_iter()
+            .find(|posture| posture.to_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| InvalidPosture(s.to_string()))
+    }
+}
+
+impl PartialOrd for Posture {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Posture {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.escalation_level().cmp(&other.escalation_level())
+    }
 }
 
 /// Core service trait that all CAMALEON components must implement
@@ -78,30 +161,73 @@ pub trait ChameleonService: Send + Sync {
     
     /// Get the current state
     async fn get_state(&self) -> Result<SystemState, ChameleonError>;
+    
+    /// Check whether the service is doing its work
+    ///
+    /// Must answer quickly, the watchdog treats a slow answer as a missed
+    /// heartbeat.
+    async fn health(&self) -> ModuleHealth {
+        ModuleHealth::Healthy
+    }
 }
 
 /// Main CAMALEON core service
 #[derive(Clone)]
 pub struct ChameleonCore {
     state: Arc<RwLock<ChameleonState>>,
-    adaptive_manager: Arc<AdaptiveManager>,
+    adaptive_engine: Arc<AdaptiveEngine>,
     metrics: Arc<MetricsCollector>,
+    event_bus: EventBus,
+    event_store: Option<Arc<EventStore>>,
 }
 
 impl ChameleonCore {
     /// Create a new ChameleonCore instance
     pub fn new() -> Self {
         let state = Arc::new(RwLock::new(ChameleonState::new()));
-        let adaptive_manager = Arc::new(AdaptiveManager::new(state.clone()));
+        let adaptive_engine = Arc::new(AdaptiveEngine::default());
         let metrics = Arc::new(MetricsCollector::new());
         
         Self {
             state,
-            adaptive_manager,
+            adaptive_engine,
             metrics,
+            event_bus: EventBus::default(),
+            event_store: None,
         }
     }
     
+    /// Persist every handled event to the given store
+    pub fn with_event_store(mut self, event_store: Arc<EventStore>) -> Self {
+        self.event_store = Some(event_store);
+        self
+    }
+    
+    /// Use an existing event bus, e.g. one shared with other components
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = event_bus;
+        self
+    }
+    
+    /// Register a handler that receives every event as an `AdaptiveEvent`
+    pub async fn register_handler(
+        &self,
+        name: impl Into<String>,
+        handler: Arc<Mutex<dyn AdaptiveHandler>>,
+    ) {
+        self.adaptive_engine.register_handler(name, handler).await;
+    }
+    
+    /// Get the adaptive engine events are routed through
+    pub fn adaptive_engine(&self) -> &Arc<AdaptiveEngine> {
+        &self.adaptive_engine
+    }
+    
+    /// Get the event bus every handled event is published to
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+    
     /// Register a new event
     pub async fn register_event(&self, event_type: EventType, source: &str, data: Option<serde_json::Value>) -> Result<(), ChameleonError> {
         let event = Event {
@@ -109,15 +235,110 @@ impl ChameleonCore {
             event_type,
             source: source.to_string(),
             data,
+            severity_override: None,
+            origin: EventOrigin::Module,
         };
         
         self.handle_event(event).await
     }
     
+    /// Get the metrics collector
+    pub fn metrics(&self) -> &Arc<MetricsCollector> {
+        &self.metrics
+    }
+    
+    /// Periodically publish the event rate and threat level as gauges
+    ///
+    /// Every `interval` the rate over the last `window` is stored in the
+    /// `events_per_second` gauge and the current threat level in the
+    /// `threat_level` gauge. The task ends when `cancel` is triggered.
+    pub fn spawn_metrics_sampler(
+        &self,
+        interval: std::time::Duration,
+        window: std::time::Duration,
+        cancel: CancellationToken,
+    ) -> JoinHandle<()> {
+        let core = self.clone();
+        let window_seconds = (window.as_secs() as i64).max(1);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                
+                match core.metrics.calculate_event_rate(window_seconds).await {
+                    Ok(rate) => core.metrics.set_gauge("events_per_second", rate),
+                    Err(e) => warn!("Failed to calculate event rate: {}", e),
+                }
+                
+                let threat_level = core.state.read().await.threat_level;
+                core.metrics.set_gauge("threat_level", threat_level);
+            }
+            
+            debug!("Metrics sampler stopped");
+        })
+    }
+    
+    /// Spawn a task publishing a metrics snapshot
+    ///
+    /// Every `interval` the metrics of the last `window` are published as a
+    /// `MetricsReport` event under `data.metrics`, without the time series.
+    /// The task ends when `cancel` is triggered.
+    pub fn spawn_metrics_reporter(
+        &self,
+        interval: std::time::Duration,
+        window: std::time::Duration,
+        cancel: CancellationToken,
+    ) -> JoinHandle<()> {
+        let core = self.clone();
+        let window = chrono::Duration::from_std(window).unwrap_or_else(|_| chrono::Duration::seconds(60));
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately, there is nothing to report yet
+            ticker.tick().await;
+            
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                
+                let end = Utc::now();
+                let mut metrics = match core.get_metrics(end - window, end).await {
+                    Ok(metrics) => metrics,
+                    Err(e) => {
+                        warn!("Failed to collect metrics: {}", e);
+                        continue;
+                    }
+                };
+                if let Some(metrics) = metrics.as_object_mut() {
+                    metrics.remove("time_series");
+                }
+                
+                let event = Event::metrics_report("core", Some(serde_json::json!({ "metrics": metrics })));
+                if let Err(e) = core.handle_event(event).await {
+                    warn!("Failed to publish metrics report: {}", e);
+                }
+            }
+            
+            debug!("Metrics reporter stopped");
+        })
+    }
+    
     /// Get metrics within a time range
     pub async fn get_metrics(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<serde_json::Value, ChameleonError> {
         self.metrics.get_metrics(start, end).await
     }
+    
+    /// Count the recorded events matching `query`
+    pub async fn query_metrics(&self, query: &MetricsQuery) -> QueryResult {
+        self.metrics.query(query).await
+    }
 }
 
 #[async_trait]
@@ -155,11 +376,17 @@ impl ChameleonService for ChameleonCore {
         // Record the event
         self.metrics.record_event(&event).await?;
         
+        // Persist the event, a failing store must not stop event handling
+        if let Some(store) = &self.event_store {
+            if let Err(e) = store.append(&event).await {
+                error!("Failed to persist event: {}", e);
+            }
+        }
+        
         // Process event based on type
         match event.event_type {
             EventType::SecurityAlert => {
                 warn!("Security alert detected: {:?}", event);
-                self.adaptive_manager.evaluate_posture_change(&event).await?;
             },
             EventType::SystemChange => {
                 info!("System change detected: {:?}", event);
@@ -179,6 +406,17 @@ impl ChameleonService for ChameleonCore {
             }
         }
         
+        // Let the module handlers react, except to events they produced
+        // themselves, which would otherwise loop back to them forever
+        if event.origin != EventOrigin::Handler {
+            if let Err(e) = self.adaptive_engine.process_event(AdaptiveEvent::from(event.clone())).await {
+                error!("Adaptive handler failed: {}", e);
+            }
+        }
+        
+        // Fan the event out to all subscribers
+        self.event_bus.publish(event);
+        
         Ok(())
     }
     
@@ -208,6 +446,15 @@ impl ChameleonService for ChameleonCore {
         let state = self.state.read().await;
         Ok(state.get_system_state())
     }
+    
+    async fn health(&self) -> ModuleHealth {
+        match self.state.read().await.status {
+            state::Status::Running => ModuleHealth::Healthy,
+            state::Status::Paused => ModuleHealth::Degraded("paused".to_string()),
+            state::Status::Error => ModuleHealth::Unhealthy("core reported an error".to_string()),
+            _ => ModuleHealth::Stopped,
+        }
+    }
 }
 
 impl Default for ChameleonCore {
@@ -226,6 +473,50 @@ mod tests {
         assert!(matches!(core.state.read().await.status, state::Status::Created));
     }
     
+    #[tokio::test]
+    async fn test_core_health_follows_status() {
+        let core = ChameleonCore::new();
+        assert_eq!(core.health().await, ModuleHealth::Stopped);
+        
+        core.init().await.unwrap();
+        core.start().await.unwrap();
+        assert_eq!(core.health().await, ModuleHealth::Healthy);
+        
+        core.stop().await.unwrap();
+        assert_eq!(core.health().await, ModuleHealth::Stopped);
+    }
+    
+    #[test]
+    fn test_postures_order_by_escalation_level() {
+        let levels: Vec<u8> = Posture::ALL.iter().map(Posture::escalation_level).collect();
+        assert_eq!(levels, [0, 1, 2, 3, 4]);
+        
+        assert!(Posture::Silent < Posture::Neutral);
+        assert!(Posture::Neutral < Posture::Mimetic);
+        assert!(Posture::Mimetic < Posture::Fulgurant);
+        assert!(Posture::Fulgurant < Posture::Unstable);
+        
+        let mut postures = vec![Posture::Unstable, Posture::Silent, Posture::Fulgurant, Posture::Neutral];
+        postures.sort();
+        assert_eq!(postures, [Posture::Silent, Posture::Neutral, Posture::Fulgurant, Posture::Unstable]);
+        assert_eq!(Posture::ALL.iter().max(), Some(&Posture::Unstable));
+    }
+    
+    #[test]
+    fn test_posture_names_round_trip() {
+        for posture in Posture::ALL {
+            assert_eq!(Posture::try_from(posture.to_str()), Ok(posture));
+            assert_eq!(Posture::try_from(posture.to_string().as_str()), Ok(posture));
+        }
+        assert_eq!(Posture::try_from("FULGURANT"), Ok(Posture::Fulgurant));
+        assert_eq!(Posture::from_str("mimetic"), Some(Posture::Mimetic));
+        
+        let error = Posture::try_from("stealth").unwrap_err();
+        assert_eq!(error, InvalidPosture("stealth".to_string()));
+        assert_eq!(error.to_string(), "Unknown posture: stealth");
+        assert_eq!(Posture::from_str("stealth"), None);
+    }
+    
     #[tokio::test]
     async fn test_change_posture() {
         let core = ChameleonCore::new();
@@ -245,4 +536,105 @@ mod tests {
             assert_eq!(state.current_posture, Posture::Silent);
         }
     }
+    
+    #[tokio::test]
+    async fn test_metrics_sampler_sets_gauges() {
+        let core = ChameleonCore::new();
+        core.register_event(EventType::SecurityAlert, "test", None).await.unwrap();
+        
+        let cancel = CancellationToken::new();
+        let sampler = core.spawn_metrics_sampler(
+            std::time::Duration::from_millis(10),
+            std::time::Duration::from_secs(10),
+            cancel.clone(),
+        );
+        
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        cancel.cancel();
+        sampler.await.unwrap();
+        
+        assert_eq!(core.metrics().get_gauge("events_per_second"), Some(0.1));
+        assert_eq!(core.metrics().get_gauge("threat_level"), Some(0.0));
+    }
+    
+    #[tokio::test]
+    async fn test_metrics_reporter_publishes_snapshots() {
+        let core = ChameleonCore::new();
+        core.register_event(EventType::SecurityAlert, "test", None).await.unwrap();
+        let mut receiver = core.event_bus().subscribe();
+        
+        let cancel = CancellationToken::new();
+        let reporter = core.spawn_metrics_reporter(
+            std::time::Duration::from_millis(10),
+            std::time::Duration::from_secs(60),
+            cancel.clone(),
+        );
+        
+        // The second snapshot must not count the first one
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            events.push(event);
+        }
+        cancel.cancel();
+        reporter.await.unwrap();
+        
+        for event in events {
+            assert_eq!(event.event_type, EventType::MetricsReport);
+            assert_eq!(event.source, "core");
+            let metrics = &event.data.unwrap()["metrics"];
+            assert_eq!(metrics["event_counts"]["total"], 1);
+            assert_eq!(metrics["event_counts"]["by_type"]["SecurityAlert"], 1);
+            assert!(metrics["event_counters"]["by_type"].get("MetricsReport").is_none());
+            assert_eq!(metrics["time_range"]["duration_seconds"], 60);
+            assert!(metrics.get("time_series").is_none());
+        }
+        assert!(core.metrics().calculate_event_rate(60).await.unwrap() * 60.0 < 1.5);
+    }
+    
+    struct MockHandler {
+        received: Vec<AdaptiveEvent>,
+    }
+    
+    #[async_trait]
+    impl AdaptiveHandler for MockHandler {
+        async fn handle_event(&mut self, event: &AdaptiveEvent) -> Result<(), adaptive::AdaptiveError> {
+            self.received.push(event.clone());
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_security_alert_reaches_handler() {
+        let core = ChameleonCore::new();
+        let handler = Arc::new(Mutex::new(MockHandler { received: Vec::new() }));
+        core.register_handler("mock", handler.clone()).await;
+        
+        core.handle_event(Event::security_alert("eye360", None)).await.unwrap();
+        let mut reaction = Event::new(EventType::SystemChange, "eye360", None);
+        reaction.origin = EventOrigin::Handler;
+        core.handle_event(reaction).await.unwrap();
+        core.handle_event(Event::new(EventType::SystemChange, "custom_handler", None)).await.unwrap();
+        
+        let handler = handler.lock().await;
+        assert_eq!(handler.received.len(), 2);
+        assert_eq!(handler.received[0].event_type, "SecurityAlert");
+        assert_eq!(handler.received[0].source, "eye360");
+        assert_eq!(handler.received[1].source, "custom_handler");
+    }
+    
+    #[tokio::test]
+    async fn test_handled_events_are_published() {
+        let core = ChameleonCore::new();
+        let mut receiver = core.event_bus().subscribe();
+        
+        core.change_posture(Posture::Mimetic).await.unwrap();
+        
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::PostureChange);
+        assert_eq!(event.source, "core");
+    }
 }
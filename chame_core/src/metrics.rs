@@ -1,11 +1,51 @@
 use crate::errors::ChameleonError;
-use crate::events::Event;
+use crate::events::{Event, EventType};
 use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Event counters labelled by one property of the event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CounterFamily {
+    /// Events by type, labelled with [`EventType::name`](crate::EventType::name)
+    EventType,
+    
+    /// Events by source module
+    EventSource,
+    
+    /// Events by severity level
+    Severity,
+}
+
+impl CounterFamily {
+    /// Every family, in export order
+    pub const ALL: [CounterFamily; 3] = [Self::EventType, Self::EventSource, Self::Severity];
+    
+    /// Name of the exported metric family
+    fn metric_name(self) -> &'static str {
+        match self {
+            Self::EventType => "camaleon_events",
+            Self::EventSource => "camaleon_events_by_source",
+            Self::Severity => "camaleon_events_by_severity",
+        }
+    }
+    
+    /// Name of the label holding the counted value
+    fn label_name(self) -> &'static str {
+        match self {
+            Self::EventType => "type",
+            Self::EventSource => "source",
+            Self::Severity => "severity",
+        }
+    }
+}
+
+/// Points kept per time series, the oldest are dropped beyond this
+pub const MAX_TIME_SERIES_POINTS: usize = 10_000;
+
 /// Store and analyze system metrics
 pub struct MetricsCollector {
     /// Event history with timestamp index
@@ -14,11 +54,17 @@ pub struct MetricsCollector {
     /// Various counters for quick lookups
     counters: Arc<DashMap<String, u64>>,
     
+    /// Event counters, one map of label to count per family
+    ///
+    /// Looked up by `&str`, so counting an event only allocates the first
+    /// time a label is seen.
+    families: Arc<[DashMap<String, u64>; CounterFamily::ALL.len()]>,
+    
     /// Gauges for continuous measurements
     gauges: Arc<DashMap<String, f64>>,
     
     /// Time series data for trends
-    time_series: Arc<DashMap<String, Vec<TimeSeriesPoint>>>,
+    time_series: Arc<DashMap<String, VecDeque<TimeSeriesPoint>>>,
 }
 
 /// A point in a time series
@@ -37,13 +83,21 @@ impl MetricsCollector {
         Self {
             events: Arc::new(RwLock::new(Vec::new())),
             counters: Arc::new(DashMap::new()),
+            families: Arc::new(Default::default()),
             gauges: Arc::new(DashMap::new()),
             time_series: Arc::new(DashMap::new()),
         }
     }
     
     /// Record an event
+    ///
+    /// `MetricsReport` events are snapshots of these metrics, counting them
+    /// would inflate every later report and the event rate.
     pub async fn record_event(&self, event: &Event) -> Result<(), ChameleonError> {
+        if event.event_type == EventType::MetricsReport {
+            return Ok(());
+        }
+        
         // Add to event history
         {
             let mut events = self.events.write().await;
@@ -51,25 +105,43 @@ impl MetricsCollector {
             
             // Trim event history if it gets too large (keep the most recent 10000 events)
             if events.len() > 10000 {
-                *events = events.split_off(events.len() - 10000);
+                let keep_from = events.len() - 10000;
+                *events = events.split_off(keep_from);
             }
         }
         
-        // Update counter for this event type
-        let counter_key = format!("event_count_{:?}", event.event_type);
-        self.increment_counter(&counter_key);
-        
-        // Update counter for this source
-        let source_key = format!("event_source_{}", event.source);
-        self.increment_counter(&source_key);
-        
-        // Update counter for severity
-        let severity_key = format!("severity_{:?}", event.severity());
-        self.increment_counter(&severity_key);
+        // Update the type, source and severity counters
+        self.increment_family(CounterFamily::EventType, event.event_type.name());
+        self.increment_family(CounterFamily::EventSource, &event.source);
+        self.increment_family(CounterFamily::Severity, event.severity().name());
         
         Ok(())
     }
     
+    /// Increment the counter of `label` in a family
+    pub fn increment_family(&self, family: CounterFamily, label: &str) {
+        let counters = &self.families[family as usize];
+        if let Some(mut count) = counters.get_mut(label) {
+            *count += 1;
+            return;
+        }
+        
+        *counters.entry(label.to_string()).or_insert(0) += 1;
+    }
+    
+    /// Get the counter of `label` in a family
+    pub fn get_family_counter(&self, family: CounterFamily, label: &str) -> u64 {
+        self.families[family as usize].get(label).map(|v| *v).unwrap_or(0)
+    }
+    
+    /// Get every counter of a family, by label
+    pub fn family_counters(&self, family: CounterFamily) -> std::collections::HashMap<String, u64> {
+        self.families[family as usize]
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+    
     /// Increment a counter
     pub fn increment_counter(&self, key: &str) {
         self.counters
@@ -99,16 +171,33 @@ impl MetricsCollector {
     }
     
     /// Add a time series point
+    ///
+    /// Each series keeps its last [`MAX_TIME_SERIES_POINTS`] points.
     pub fn add_time_series_point(&self, key: &str, value: f64) {
         let point = TimeSeriesPoint {
             timestamp: Utc::now(),
             value,
         };
         
+        let mut points = self.time_series.entry(key.to_string()).or_default();
+        if points.len() >= MAX_TIME_SERIES_POINTS {
+            points.pop_front();
+        }
+        points.push_back(point);
+    }
+    
+    /// Get the points of a time series recorded since `since`, oldest first
+    pub fn get_time_series(&self, key: &str, since: DateTime<Utc>) -> Vec<(DateTime<Utc>, f64)> {
         self.time_series
-            .entry(key.to_string())
-            .or_insert_with(Vec::new)
-            .push(point);
+            .get(key)
+            .map(|points| {
+                points
+                    .iter()
+                    .filter(|p| p.timestamp >= since)
+                    .map(|p| (p.timestamp, p.value))
+                    .collect()
+            })
+            .unwrap_or_default()
     }
     
     /// Get metrics within a time range
@@ -126,8 +215,7 @@ impl MetricsCollector {
         // Count events by type
         let mut event_counts = std::collections::HashMap::new();
         for event in &events {
-            let key = format!("{:?}", event.event_type);
-            *event_counts.entry(key).or_insert(0u64) += 1;
+            *event_counts.entry(event.event_type.name()).or_insert(0u64) += 1;
         }
         
         // Count events by source
@@ -139,8 +227,7 @@ impl MetricsCollector {
         // Count events by severity
         let mut severity_counts = std::collections::HashMap::new();
         for event in &events {
-            let key = format!("{:?}", event.severity());
-            *severity_counts.entry(key).or_insert(0u64) += 1;
+            *severity_counts.entry(event.severity().name()).or_insert(0u64) += 1;
         }
         
         // Get time series data within range
@@ -179,6 +266,11 @@ impl MetricsCollector {
                 "by_severity": severity_counts,
             },
             "counters": self.get_all_counters(),
+            "event_counters": {
+                "by_type": self.family_counters(CounterFamily::EventType),
+                "by_source": self.family_counters(CounterFamily::EventSource),
+                "by_severity": self.family_counters(CounterFamily::Severity),
+            },
             "gauges": self.get_all_gauges(),
             "time_series": time_series_data,
         });
@@ -186,6 +278,29 @@ impl MetricsCollector {
         Ok(metrics)
     }
     
+    /// Count the recorded events matching `query`
+    ///
+    /// A narrower alternative to [`get_metrics`](Self::get_metrics) for
+    /// dashboards that only need a few numbers.
+    pub async fn query(&self, query: &MetricsQuery) -> QueryResult {
+        let mut result = QueryResult {
+            start: query.start,
+            end: query.end,
+            total: 0,
+            groups: BTreeMap::new(),
+        };
+        
+        let events = self.events.read().await;
+        for event in events.iter().filter(|event| query.matches(event)) {
+            result.total += 1;
+            if let Some(label) = query.group_by.label(event) {
+                *result.groups.entry(label.to_string()).or_default() += 1;
+            }
+        }
+        
+        result
+    }
+    
     /// Get all counters as a HashMap
     fn get_all_counters(&self) -> std::collections::HashMap<String, u64> {
         let mut result = std::collections::HashMap::new();
@@ -204,6 +319,57 @@ impl MetricsCollector {
         result
     }
     
+    /// Render all counters and gauges in the OpenMetrics text format
+    ///
+    /// Each counter family becomes a labelled `camaleon_events*_total`
+    /// family, every other counter and gauge is exported under its
+    /// sanitized name.
+    pub fn render_prometheus(&self) -> String {
+        let mut families: BTreeMap<String, MetricFamily> = BTreeMap::new();
+        
+        for family in CounterFamily::ALL {
+            for (label, value) in self.family_counters(family) {
+                families
+                    .entry(family.metric_name().to_string())
+                    .or_insert(("counter", Vec::new()))
+                    .1
+                    .push((
+                        format!("_total{{{}=\"{}\"}}", family.label_name(), escape_label(&label)),
+                        value.to_string(),
+                    ));
+            }
+        }
+        
+        for (key, value) in self.get_all_counters() {
+            let name = metric_name(&key);
+            families
+                .entry(name.strip_suffix("_total").unwrap_or(&name).to_string())
+                .or_insert(("counter", Vec::new()))
+                .1
+                .push(("_total".to_string(), value.to_string()));
+        }
+        
+        for (key, value) in self.get_all_gauges() {
+            families
+                .entry(metric_name(&key))
+                .or_insert(("gauge", Vec::new()))
+                .1
+                .push((String::new(), value.to_string()));
+        }
+        
+        let mut output = String::new();
+        for (family, (metric_type, mut samples)) in families {
+            samples.sort();
+            output.push_str(&format!("# TYPE {} {}\n", family, metric_type));
+            for (suffix, value) in samples {
+                output.push_str(&format!("{}{} {}\n", family, suffix, value));
+            }
+        }
+        output.push_str("# EOF\n");
+        
+        output
+    }
+    
     /// Calculate the rate of events per second within a time window
     pub async fn calculate_event_rate(&self, window_seconds: i64) -> Result<f64, ChameleonError> {
         let now = Utc::now();
@@ -224,6 +390,146 @@ impl MetricsCollector {
     }
 }
 
+/// What the events counted by a [`MetricsQuery`] are grouped by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// Only the total is counted
+    #[default]
+    None,
+    
+    /// Event type name
+    Type,
+    
+    /// Module or component that emitted the event
+    Source,
+    
+    /// Severity of the event
+    Severity,
+}
+
+impl GroupBy {
+    /// Group `event` falls in, if any
+    fn label<'a>(&self, event: &'a Event) -> Option<&'a str> {
+        match self {
+            Self::None => None,
+            Self::Type => Some(event.event_type.name()),
+            Self::Source => Some(&event.source),
+            Self::Severity => Some(event.severity().name()),
+        }
+    }
+}
+
+/// Events to count over a time range, see [`MetricsCollector::query`]
+///
+/// ```
+/// # use chame_core::metrics::{GroupBy, MetricsQuery};
+/// # use chame_core::EventType;
+/// // Last 15 minutes of security alerts, by source
+/// let query = MetricsQuery::last(chrono::Duration::minutes(15))
+///     .with_event_types([EventType::SecurityAlert])
+///     .group_by(GroupBy::Source);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsQuery {
+    /// Start of the range, inclusive
+    pub start: DateTime<Utc>,
+    
+    /// End of the range, inclusive
+    pub end: DateTime<Utc>,
+    
+    /// Only count these event types, every type when unset
+    pub event_types: Option<Vec<EventType>>,
+    
+    /// Only count events from these sources, every source when unset
+    pub sources: Option<Vec<String>>,
+    
+    /// How the counted events are grouped
+    pub group_by: GroupBy,
+}
+
+impl MetricsQuery {
+    /// Count every event between `start` and `end`
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end,
+            event_types: None,
+            sources: None,
+            group_by: GroupBy::None,
+        }
+    }
+    
+    /// Count every event of the last `duration`, clamped to the earliest date
+    pub fn last(duration: Duration) -> Self {
+        let end = Utc::now();
+        Self::new(end.checked_sub_signed(duration).unwrap_or(DateTime::<Utc>::MIN_UTC), end)
+    }
+    
+    /// Only count events of these types
+    pub fn with_event_types(mut self, event_types: impl IntoIterator<Item = EventType>) -> Self {
+        self.event_types = Some(event_types.into_iter().collect());
+        self
+    }
+    
+    /// Only count events from these sources
+    pub fn with_sources(mut self, sources: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.sources = Some(sources.into_iter().map(Into::into).collect());
+        self
+    }
+    
+    /// Group the counted events
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+    
+    /// Whether `event` is counted
+    fn matches(&self, event: &Event) -> bool {
+        event.timestamp >= self.start
+            && event.timestamp <= self.end
+            && self.event_types.as_ref().map_or(true, |types| types.contains(&event.event_type))
+            && self.sources.as_ref().map_or(true, |sources| sources.contains(&event.source))
+    }
+}
+
+/// Event counts answering a [`MetricsQuery`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryResult {
+    /// Start of the queried range
+    pub start: DateTime<Utc>,
+    
+    /// End of the queried range
+    pub end: DateTime<Utc>,
+    
+    /// Number of matching events
+    pub total: u64,
+    
+    /// Matching events per group, empty with [`GroupBy::None`]
+    pub groups: BTreeMap<String, u64>,
+}
+
+/// Metric type and samples, as (label suffix, value), of one metric family
+type MetricFamily = (&'static str, Vec<(String, String)>);
+
+/// Turn an internal key into a valid metric name with the `camaleon_` prefix
+fn metric_name(key: &str) -> String {
+    let sanitized: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+        
+    format!("camaleon_{}", sanitized)
+}
+
+/// Escape a label value for the text exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 impl Default for MetricsCollector {
     fn default() -> Self {
         Self::new()
@@ -233,7 +539,6 @@ impl Default for MetricsCollector {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::events::{Event, EventType};
     
     #[tokio::test]
     async fn test_record_event() {
@@ -247,8 +552,12 @@ mod tests {
         
         collector.record_event(&event).await.unwrap();
         
-        assert_eq!(collector.get_counter("event_count_SecurityAlert"), 1);
-        assert_eq!(collector.get_counter("event_source_test"), 1);
+        assert_eq!(collector.get_family_counter(CounterFamily::EventType, "SecurityAlert"), 1);
+        assert_eq!(collector.get_family_counter(CounterFamily::EventSource, "test"), 1);
+        assert_eq!(collector.get_family_counter(CounterFamily::Severity, "High"), 1);
+        
+        // Event counts no longer go through string keys
+        assert_eq!(collector.get_counter("event_count_SecurityAlert"), 0);
     }
     
     #[tokio::test]
@@ -261,4 +570,90 @@ mod tests {
         collector.increment_counter("test_counter");
         assert_eq!(collector.get_counter("test_counter"), 43);
     }
+    
+    #[test]
+    fn test_time_series_keeps_the_latest_points() {
+        let collector = MetricsCollector::new();
+        
+        for value in 0..MAX_TIME_SERIES_POINTS + 5 {
+            collector.add_time_series_point("threat_level", value as f64);
+        }
+        
+        let points = collector.get_time_series("threat_level", DateTime::<Utc>::MIN_UTC);
+        assert_eq!(points.len(), MAX_TIME_SERIES_POINTS);
+        assert_eq!(points[0].1, 5.0);
+    }
+    
+    #[tokio::test]
+    async fn test_render_prometheus() {
+        let collector = MetricsCollector::new();
+        
+        collector.record_event(&Event::security_alert("eye360", None)).await.unwrap();
+        collector.record_event(&Event::security_alert("eye360", None)).await.unwrap();
+        collector.record_event(&Event::custom("probe", "lurefield", None)).await.unwrap();
+        collector.increment_counter("honeypot.connections");
+        collector.set_gauge("events_per_second", 0.5);
+        
+        let output = collector.render_prometheus();
+        
+        assert!(output.contains("# TYPE camaleon_events counter\n"));
+        assert!(output.contains("camaleon_events_total{type=\"SecurityAlert\"} 2\n"));
+        assert!(output.contains("camaleon_events_total{type=\"probe\"} 1\n"));
+        assert!(output.contains("camaleon_events_by_source_total{source=\"eye360\"} 2\n"));
+        assert!(output.contains("camaleon_events_by_severity_total{severity=\"High\"} 2\n"));
+        assert!(output.contains("# TYPE camaleon_honeypot_connections counter\n"));
+        assert!(output.contains("camaleon_honeypot_connections_total 1\n"));
+        assert!(output.contains("# TYPE camaleon_events_per_second gauge\n"));
+        assert!(output.contains("camaleon_events_per_second 0.5\n"));
+        assert!(output.ends_with("# EOF\n"));
+    }
+    
+    #[tokio::test]
+    async fn test_query_groups_matching_events() {
+        let collector = MetricsCollector::new();
+        let now = Utc::now();
+        let at = |event: Event, minutes_ago: i64| Event {
+            timestamp: now - Duration::minutes(minutes_ago),
+            ..event
+        };
+        
+        for event in [
+            at(Event::security_alert("eye360", None), 1),
+            at(Event::security_alert("eye360", None), 2),
+            at(Event::security_alert("nettongue", None), 3),
+            at(Event::network_activity("nettongue", None), 4),
+            at(Event::honeypot_activity("lurefield", None), 5),
+            // Outside the queried range
+            at(Event::security_alert("eye360", None), 30),
+        ] {
+            collector.record_event(&event).await.unwrap();
+        }
+        
+        let alerts = MetricsQuery::new(now - Duration::minutes(10), now)
+            .with_event_types([EventType::SecurityAlert])
+            .group_by(GroupBy::Source);
+        let result = collector.query(&alerts).await;
+        assert_eq!(result.total, 3);
+        assert_eq!(result.groups, BTreeMap::from([("eye360".to_string(), 2), ("nettongue".to_string(), 1)]));
+        
+        let nettongue = MetricsQuery::new(now - Duration::minutes(10), now)
+            .with_sources(["nettongue"])
+            .group_by(GroupBy::Type);
+        let result = collector.query(&nettongue).await;
+        assert_eq!(
+            result.groups,
+            BTreeMap::from([("NetworkActivity".to_string(), 1), ("SecurityAlert".to_string(), 1)])
+        );
+        
+        let everything = collector.query(&MetricsQuery::new(now - Duration::hours(1), now)).await;
+        assert_eq!(everything.total, 6);
+        assert!(everything.groups.is_empty());
+        
+        let by_severity = MetricsQuery::new(now - Duration::minutes(10), now).group_by(GroupBy::Severity);
+        assert_eq!(collector.query(&by_severity).await.groups["High"], 3);
+        
+        let forever = MetricsQuery::last(Duration::weeks(52 * 300_000));
+        assert_eq!(forever.start, DateTime::<Utc>::MIN_UTC);
+        assert_eq!(collector.query(&forever).await.total, 6);
+    }
 }
@@ -0,0 +1,243 @@
+use crate::{ChameleonError, ChameleonService, Posture};
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Services of a [`ServiceRegistry`] that failed an operation, by name
+#[derive(Debug, thiserror::Error)]
+#[error("{} service(s) failed: {}", .0.len(), describe(.0))]
+pub struct ServiceFailures(pub Vec<(String, ChameleonError)>);
+
+fn describe(failures: &[(String, ChameleonError)]) -> String {
+    failures
+        .iter()
+        .map(|(name, e)| format!("{}: {}", name, e))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Named services driven together, in registration order
+///
+/// Services are initialized, started and sent posture changes in the order
+/// they were registered, and stopped in reverse. A failing service doesn't
+/// keep the others from being called: every failure is collected into the
+/// returned [`ServiceFailures`].
+#[derive(Clone, Default)]
+pub struct ServiceRegistry {
+    services: Vec<(String, Arc<dyn ChameleonService>)>,
+}
+
+impl ServiceRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a service after the ones already registered
+    ///
+    /// Registering a name twice replaces the first service, keeping its place.
+    pub fn register(&mut self, name: impl Into<String>, service: Arc<dyn ChameleonService>) {
+        let name = name.into();
+        match self.services.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = service,
+            None => self.services.push((name, service)),
+        }
+    }
+
+    /// The service registered under `name`
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn ChameleonService>> {
+        self.services
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, service)| service)
+    }
+
+    /// Names of the services, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.services.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Number of registered services
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    /// Whether no service is registered
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Initialize every service, in registration order
+    pub async fn init_all(&self) -> Result<(), ServiceFailures> {
+        let mut failures = Vec::new();
+        for (name, service) in &self.services {
+            record(&mut failures, name, "initialize", service.init().await);
+        }
+        into_result(failures)
+    }
+
+    /// Start every service, in registration order
+    pub async fn start_all(&self) -> Result<(), ServiceFailures> {
+        let mut failures = Vec::new();
+        for (name, service) in &self.services {
+            if record(&mut failures, name, "start", service.start().await) {
+                info!("Service {} started", name);
+            }
+        }
+        into_result(failures)
+    }
+
+    /// Stop every service, in reverse registration order
+    pub async fn stop_all(&self) -> Result<(), ServiceFailures> {
+        let mut failures = Vec::new();
+        for (name, service) in self.services.iter().rev() {
+            if record(&mut failures, name, "stop", service.stop().await) {
+                info!("Service {} stopped", name);
+            }
+        }
+        into_result(failures)
+    }
+
+    /// Switch every service to `posture`, in registration order
+    pub async fn change_posture(&self, posture: Posture) -> Result<(), ServiceFailures> {
+        let mut failures = Vec::new();
+        for (name, service) in &self.services {
+            record(&mut failures, name, "change the posture of", service.change_posture(posture).await);
+        }
+        into_result(failures)
+    }
+}
+
+/// Log and keep a failure, returning whether the call succeeded
+fn record(
+    failures: &mut Vec<(String, ChameleonError)>,
+    name: &str,
+    action: &str,
+    result: Result<(), ChameleonError>,
+) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Failed to {} {}: {}", action, name, e);
+            failures.push((name.to_string(), e));
+            false
+        }
+    }
+}
+
+fn into_result(failures: Vec<(String, ChameleonError)>) -> Result<(), ServiceFailures> {
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceFailures(failures))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ChameleonState;
+    use crate::{Event, SystemState};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Service appending `<name> <call>` to a shared log
+    struct MockService {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        fail_start: bool,
+    }
+
+    impl MockService {
+        fn push(&self, call: &str) {
+            self.log.lock().unwrap().push(format!("{} {}", self.name, call));
+        }
+    }
+
+    #[async_trait]
+    impl ChameleonService for MockService {
+        async fn init(&self) -> Result<(), ChameleonError> {
+            self.push("init");
+            Ok(())
+        }
+
+        async fn start(&self) -> Result<(), ChameleonError> {
+            self.push("start");
+            if self.fail_start {
+                return Err(ChameleonError::ServiceUnavailable("port in use".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), ChameleonError> {
+            self.push("stop");
+            Ok(())
+        }
+
+        async fn handle_event(&self, _event: Event) -> Result<(), ChameleonError> {
+            Ok(())
+        }
+
+        async fn change_posture(&self, posture: Posture) -> Result<(), ChameleonError> {
+            self.push(&posture.to_string());
+            Ok(())
+        }
+
+        async fn get_state(&self) -> Result<SystemState, ChameleonError> {
+            Ok(ChameleonState::new().get_system_state())
+        }
+    }
+
+    fn registry(log: &Arc<Mutex<Vec<String>>>, fail_start: &[&'static str]) -> ServiceRegistry {
+        let mut registry = ServiceRegistry::new();
+        for name in ["skinshift", "lurefield"] {
+            let service = MockService {
+                name,
+                log: log.clone(),
+                fail_start: fail_start.contains(&name),
+            };
+            registry.register(name, Arc::new(service));
+        }
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_starts_in_order_and_stops_in_reverse() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&log, &[]);
+        assert_eq!(registry.names(), ["skinshift", "lurefield"]);
+
+        registry.init_all().await.unwrap();
+        registry.start_all().await.unwrap();
+        registry.change_posture(Posture::Mimetic).await.unwrap();
+        registry.stop_all().await.unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "skinshift init",
+                "lurefield init",
+                "skinshift start",
+                "lurefield start",
+                "skinshift Mimetic",
+                "lurefield Mimetic",
+                "lurefield stop",
+                "skinshift stop",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_collected_without_stopping_iteration() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&log, &["skinshift"]);
+
+        let failures = registry.start_all().await.unwrap_err();
+        assert_eq!(*log.lock().unwrap(), ["skinshift start", "lurefield start"]);
+        assert_eq!(failures.0.len(), 1);
+        assert_eq!(failures.0[0].0, "skinshift");
+        assert_eq!(
+            failures.to_string(),
+            "1 service(s) failed: skinshift: Service unavailable: port in use"
+        );
+    }
+}
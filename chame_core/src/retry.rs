@@ -0,0 +1,144 @@
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tracing::debug;
+
+/// How often and how patiently to retry a failing operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one
+    pub max_attempts: u32,
+
+    /// Delay before the second attempt
+    pub initial_delay: Duration,
+
+    /// Cap on the delay between two attempts
+    pub max_delay: Duration,
+
+    /// Factor applied to the delay after each failure
+    pub multiplier: f64,
+
+    /// Fraction of each delay that is randomized, from 0 to 1
+    ///
+    /// Keeps clients that failed together from retrying in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt following failed attempt `attempt` (1-based)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let base = base.min(self.max_delay.as_secs_f64());
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0)
+        } else {
+            1.0
+        };
+
+        Duration::from_secs_f64(base * factor)
+    }
+}
+
+/// Run `op` until it succeeds or `policy.max_attempts` is reached
+///
+/// Waits with exponential backoff and jitter between attempts and returns
+/// the last error once every attempt failed.
+pub async fn with_backoff<F, Fut, T, E>(mut op: F, policy: &RetryPolicy) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= policy.max_attempts => return Err(e),
+            Err(e) => {
+                let delay = policy.delay(attempt);
+                debug!("Attempt {} of {} failed, retrying in {:?}: {}", attempt, policy.max_attempts, delay, e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_succeeds_after_failures() {
+        let calls = &AtomicU32::new(0);
+        let result = with_backoff(
+            || async move {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0..=2 => Err("connection refused"),
+                    _ => Ok("delivered"),
+                }
+            },
+            &fast_policy(5),
+        )
+        .await;
+
+        assert_eq!(result, Ok("delivered"));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_exhausted() {
+        let calls = &AtomicU32::new(0);
+        let result: Result<(), String> = with_backoff(
+            || async move { Err(format!("failure {}", calls.fetch_add(1, Ordering::SeqCst) + 1)) },
+            &fast_policy(3),
+        )
+        .await;
+
+        assert_eq!(result, Err("failure 3".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_delay_grows_up_to_the_cap() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let delays: Vec<_> = (1..=5).map(|attempt| policy.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+
+        let policy = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2), "{:?}", delay);
+        }
+    }
+}
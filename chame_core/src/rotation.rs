@@ -0,0 +1,303 @@
+use crate::errors::ChameleonError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// When a [`RotatingWriter`] rolls its file over and what it keeps
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationPolicy {
+    /// Roll over before the file grows past this many bytes, 0 for no limit
+    pub max_bytes: u64,
+
+    /// Roll over once the file is this old, `None` for no limit
+    pub max_age: Option<Duration>,
+
+    /// Rotated files kept, `<file>.1` being the most recent
+    pub keep: usize,
+
+    /// Gzip rotated files into `<file>.<n>.gz`
+    pub compress: bool,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 100 * 1024 * 1024,
+            max_age: Some(Duration::from_secs(24 * 60 * 60)),
+            keep: 7,
+            compress: false,
+        }
+    }
+}
+
+impl RotationPolicy {
+    /// Never roll over, the file grows unbounded
+    pub fn disabled() -> Self {
+        Self {
+            max_bytes: 0,
+            max_age: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// Append-only file rolled over by size and age, for JSONL sinks
+///
+/// On rotation `<file>` becomes `<file>.1`, the previous `<file>.1` becomes
+/// `<file>.2` and so on; files past `keep` are deleted. A single write is
+/// never split across two files.
+pub struct RotatingWriter {
+    path: PathBuf,
+    policy: RotationPolicy,
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl RotatingWriter {
+    /// Open (or create) `path` for appending
+    pub async fn open(path: impl Into<PathBuf>, policy: RotationPolicy) -> Result<Self, ChameleonError> {
+        let path = path.into();
+
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        let metadata = file.metadata().await?;
+
+        Ok(Self {
+            path,
+            policy,
+            file,
+            size: metadata.len(),
+            opened_at: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+        })
+    }
+
+    /// Path of the file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `data` and flush it, rolling the file over first if needed
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), ChameleonError> {
+        if self.should_rotate(data.len() as u64) {
+            self.rotate().await?;
+        }
+
+        self.file.write_all(data).await?;
+        self.file.flush().await?;
+        self.size += data.len() as u64;
+
+        Ok(())
+    }
+
+    /// Whether writing `incoming` more bytes must go to a new file
+    fn should_rotate(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        let too_big = self.policy.max_bytes > 0 && self.size + incoming > self.policy.max_bytes;
+        let too_old = self
+            .policy
+            .max_age
+            .is_some_and(|max_age| self.opened_at.elapsed().unwrap_or_default() >= max_age);
+        too_big || too_old
+    }
+
+    /// Roll the file over now, whatever its size and age
+    ///
+    /// With `compress`, the rotated file is gzipped before this returns.
+    pub async fn rotate(&mut self) -> Result<(), ChameleonError> {
+        self.file.flush().await?;
+
+        // Oldest first, so every rename targets a free name
+        for (index, rotated) in rotated_entries(&self.path).await?.into_iter().rev() {
+            if index >= self.policy.keep {
+                tokio::fs::remove_file(&rotated).await?;
+            } else {
+                let compressed = rotated.extension().is_some_and(|extension| extension == "gz");
+                tokio::fs::rename(&rotated, rotated_path(&self.path, index + 1, compressed)).await?;
+            }
+        }
+
+        if self.policy.keep == 0 {
+            tokio::fs::remove_file(&self.path).await?;
+        } else {
+            let rotated = rotated_path(&self.path, 1, false);
+            tokio::fs::rename(&self.path, &rotated).await?;
+
+            if self.policy.compress {
+                let source = rotated.clone();
+                match tokio::task::spawn_blocking(move || gzip(&source)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => warn!("Failed to compress {}, keeping it as is: {}", rotated.display(), e),
+                    Err(e) => warn!("Failed to compress {}, keeping it as is: {}", rotated.display(), e),
+                }
+            }
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path).await?;
+        self.size = 0;
+        self.opened_at = SystemTime::now();
+        info!("Rotated {}", self.path.display());
+
+        Ok(())
+    }
+}
+
+/// Rotated files of `path`, most recent first
+///
+/// Only `<file>.<n>` and `<file>.<n>.gz` count, in order of `n`.
+pub async fn rotated_files(path: &Path) -> Result<Vec<PathBuf>, ChameleonError> {
+    Ok(rotated_entries(path).await?.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Rotated files of `path` with their number, in order of number
+async fn rotated_entries(path: &Path) -> Result<Vec<(usize, PathBuf)>, ChameleonError> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|name| name.to_str())) else {
+        return Ok(Vec::new());
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let prefix = format!("{}.", name);
+
+    let mut rotated = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let index = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_prefix(&prefix))
+            .map(|suffix| suffix.strip_suffix(".gz").unwrap_or(suffix))
+            .and_then(|index| index.parse::<usize>().ok())
+            .filter(|index| *index > 0);
+        if let Some(index) = index {
+            rotated.push((index, dir.join(file_name)));
+        }
+    }
+    rotated.sort();
+
+    Ok(rotated)
+}
+
+/// `<path>.<index>`, with `.gz` when `compressed`
+fn rotated_path(path: &Path, index: usize, compressed: bool) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", index));
+    if compressed {
+        name.push(".gz");
+    }
+    PathBuf::from(name)
+}
+
+/// Replace `path` by `<path>.gz`
+fn gzip(path: &Path) -> std::io::Result<()> {
+    let mut target = OsString::from(path.as_os_str());
+    target.push(".gz");
+
+    let mut input = std::fs::File::open(path)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&target)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn policy(max_bytes: u64, keep: usize) -> RotationPolicy {
+        RotationPolicy {
+            max_bytes,
+            max_age: None,
+            keep,
+            compress: false,
+        }
+    }
+
+    fn names(paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rolls_over_past_the_size_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let mut writer = RotatingWriter::open(&path, policy(16, 2)).await.unwrap();
+
+        // Each line fills the file, so every write after the first rolls it over
+        for line in ["line 1 ........\n", "line 2 ........\n", "line 3 ........\n", "line 4 ........\n"] {
+            writer.write(line.as_bytes()).await.unwrap();
+        }
+
+        let rotated = rotated_files(&path).await.unwrap();
+        assert_eq!(names(&rotated), ["events.jsonl.1", "events.jsonl.2"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 4 ........\n");
+        assert_eq!(std::fs::read_to_string(&rotated[0]).unwrap(), "line 3 ........\n");
+        assert_eq!(std::fs::read_to_string(&rotated[1]).unwrap(), "line 2 ........\n");
+
+        // Reopening picks up the current size
+        let mut writer = RotatingWriter::open(&path, policy(64, 2)).await.unwrap();
+        writer.write(b"line 5\n").await.unwrap();
+        assert_eq!(rotated_files(&path).await.unwrap().len(), 2);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 4 ........\nline 5\n");
+    }
+
+    #[tokio::test]
+    async fn test_rotated_files_are_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let policy = RotationPolicy {
+            compress: true,
+            ..policy(0, 3)
+        };
+        let mut writer = RotatingWriter::open(&path, policy).await.unwrap();
+
+        writer.write(b"{\"n\":1}\n").await.unwrap();
+        writer.rotate().await.unwrap();
+        writer.write(b"{\"n\":2}\n").await.unwrap();
+        writer.rotate().await.unwrap();
+
+        let rotated = rotated_files(&path).await.unwrap();
+        assert_eq!(names(&rotated), ["events.jsonl.1.gz", "events.jsonl.2.gz"]);
+
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(&rotated[1]).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "{\"n\":1}\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_rolls_over_with_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let policy = RotationPolicy {
+            max_age: Some(Duration::from_millis(20)),
+            ..policy(0, 1)
+        };
+        let mut writer = RotatingWriter::open(&path, policy).await.unwrap();
+
+        writer.write(b"old\n").await.unwrap();
+        writer.write(b"still young\n").await.unwrap();
+        assert!(rotated_files(&path).await.unwrap().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        writer.write(b"new\n").await.unwrap();
+        let rotated = rotated_files(&path).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&rotated[0]).unwrap(), "old\nstill young\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+    }
+}
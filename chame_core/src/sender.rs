@@ -0,0 +1,221 @@
+use crate::events::{Event, EventOrigin};
+use crate::metrics::MetricsCollector;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{debug, warn};
+
+/// What to do with an event when the channel to the core is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Wait until the channel has room
+    Block,
+
+    /// Drop the event and count it
+    #[default]
+    Drop,
+
+    /// Queue the event in an unbounded buffer drained in the background
+    Spill,
+}
+
+/// Error returned when the receiving side of the channel is gone
+#[derive(Debug, thiserror::Error)]
+#[error("Event channel closed")]
+pub struct EventChannelClosed;
+
+/// Sender modules use to report events without stalling on a slow consumer
+#[derive(Clone)]
+pub struct EventSender {
+    /// Channel to the core
+    sender: mpsc::Sender<Event>,
+
+    /// Overflow handling
+    policy: OverflowPolicy,
+
+    /// Secondary queue used by `OverflowPolicy::Spill`
+    spill: Option<mpsc::UnboundedSender<Event>>,
+
+    /// Events dropped because the channel was full
+    dropped: Arc<AtomicU64>,
+
+    /// Metrics the dropped counter is mirrored to, with the module name
+    metrics: Option<(Arc<MetricsCollector>, String)>,
+
+    /// Origin stamped on every event sent
+    origin: Option<EventOrigin>,
+}
+
+impl EventSender {
+    /// Wrap a channel sender with the given overflow policy
+    ///
+    /// With `OverflowPolicy::Spill` this spawns the task draining the
+    /// spill queue, so it must be called from within a Tokio runtime.
+    pub fn new(sender: mpsc::Sender<Event>, policy: OverflowPolicy) -> Self {
+        let spill = match policy {
+            OverflowPolicy::Spill => Some(Self::spawn_spill_drain(sender.clone())),
+            _ => None,
+        };
+
+        Self {
+            sender,
+            policy,
+            spill,
+            dropped: Arc::new(AtomicU64::new(0)),
+            metrics: None,
+            origin: None,
+        }
+    }
+
+    /// Also count dropped events in `events_dropped_<module>`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>, module: impl Into<String>) -> Self {
+        self.metrics = Some((metrics, module.into()));
+        self
+    }
+
+    /// Mark every event sent as coming from `origin`
+    pub fn with_origin(mut self, origin: EventOrigin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Get the overflow policy
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Number of events dropped so far by this sender and its clones
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Send an event according to the overflow policy
+    ///
+    /// Only a closed channel is an error; a full channel never is.
+    pub async fn send(&self, mut event: Event) -> Result<(), EventChannelClosed> {
+        if let Some(origin) = self.origin {
+            event.origin = origin;
+        }
+
+        if self.policy == OverflowPolicy::Block {
+            return self.sender.send(event).await.map_err(|_| EventChannelClosed);
+        }
+
+        match self.sender.try_send(event) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(EventChannelClosed),
+            Err(TrySendError::Full(event)) => {
+                match &self.spill {
+                    Some(spill) => spill.send(event).map_err(|_| EventChannelClosed),
+                    None => {
+                        self.record_drop();
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Count a dropped event
+    fn record_drop(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some((metrics, module)) = &self.metrics {
+            metrics.increment_counter(&format!("events_dropped_{}", module));
+        }
+
+        // Don't flood the log while the channel stays full
+        if dropped.is_power_of_two() {
+            warn!("Event channel full, {} events dropped so far", dropped);
+        }
+    }
+
+    /// Forward spilled events into the channel as room frees up
+    fn spawn_spill_drain(sender: mpsc::Sender<Event>) -> mpsc::UnboundedSender<Event> {
+        let (spill, mut spilled) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Some(event) = spilled.recv().await {
+                if sender.send(event).await.is_err() {
+                    break;
+                }
+            }
+            debug!("Spill queue drained");
+        });
+
+        spill
+    }
+}
+
+impl From<mpsc::Sender<Event>> for EventSender {
+    fn from(sender: mpsc::Sender<Event>) -> Self {
+        Self::new(sender, OverflowPolicy::Block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+
+    fn event() -> Event {
+        Event::new(EventType::NetworkActivity, "test", None)
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_counts_overflow() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let metrics = Arc::new(MetricsCollector::new());
+        let sender = EventSender::new(tx, OverflowPolicy::Drop).with_metrics(metrics.clone(), "test");
+
+        sender.send(event()).await.unwrap();
+        sender.send(event()).await.unwrap();
+        sender.send(event()).await.unwrap();
+
+        assert_eq!(sender.dropped(), 2);
+        assert_eq!(metrics.get_counter("events_dropped_test"), 2);
+        assert!(rx.recv().await.is_some());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spill_policy_delivers_everything() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let sender = EventSender::new(tx, OverflowPolicy::Spill);
+
+        for _ in 0..5 {
+            sender.send(event()).await.unwrap();
+        }
+
+        for _ in 0..5 {
+            assert!(rx.recv().await.is_some());
+        }
+        assert_eq!(sender.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_origin_is_stamped() {
+        let (tx, mut rx) = mpsc::channel(2);
+
+        EventSender::new(tx.clone(), OverflowPolicy::Block).send(event()).await.unwrap();
+        EventSender::new(tx, OverflowPolicy::Block)
+            .with_origin(EventOrigin::Handler)
+            .send(event())
+            .await
+            .unwrap();
+
+        assert_eq!(rx.recv().await.unwrap().origin, EventOrigin::Module);
+        assert_eq!(rx.recv().await.unwrap().origin, EventOrigin::Handler);
+    }
+
+    #[tokio::test]
+    async fn test_closed_channel_is_an_error() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+
+        let sender = EventSender::new(tx, OverflowPolicy::Drop);
+        assert!(sender.send(event()).await.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::errors::ChameleonError;
+use crate::events::Event;
+use crate::rotation::{rotated_files, RotatingWriter, RotationPolicy};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::LinesStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::warn;
+
+/// Append-only event log stored as JSON lines
+pub struct EventStore {
+    /// Path of the JSONL file
+    path: PathBuf,
+    
+    /// Writer used for appending
+    file: Mutex<RotatingWriter>,
+}
+
+impl EventStore {
+    /// Open (or create) an event store at the given path, never rotated
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, ChameleonError> {
+        Self::open_with_rotation(path, RotationPolicy::disabled()).await
+    }
+    
+    /// Open (or create) an event store rotated according to `policy`
+    pub async fn open_with_rotation(path: impl Into<PathBuf>, policy: RotationPolicy) -> Result<Self, ChameleonError> {
+        let path = path.into();
+        let file = RotatingWriter::open(&path, policy).await?;
+        
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+    
+    /// Path of the underlying file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Append an event to the store
+    pub async fn append(&self, event: &Event) -> Result<(), ChameleonError> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        
+        self.file.lock().await.write(&line).await
+    }
+    
+    /// Read back the events whose timestamp lies within `[from, to]`
+    ///
+    /// Events are yielded in the order they were appended, rotated files
+    /// first; compressed ones are not read back. Lines that cannot be parsed
+    /// are skipped with a warning.
+    pub async fn replay(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<impl Stream<Item = Event>, ChameleonError> {
+        let mut paths: Vec<PathBuf> = rotated_files(&self.path)
+            .await?
+            .into_iter()
+            .filter(|path| path.extension().map_or(true, |extension| extension != "gz"))
+            .rev()
+            .collect();
+        paths.push(self.path.clone());
+        
+        let mut lines: Pin<Box<dyn Stream<Item = std::io::Result<String>> + Send>> = Box::pin(tokio_stream::empty());
+        for path in paths {
+            let file = match File::open(&path).await {
+                Ok(file) => file,
+                // Pruned by a rotation since it was listed
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && path != self.path => continue,
+                Err(e) => return Err(e.into()),
+            };
+            lines = Box::pin(lines.chain(LinesStream::new(BufReader::new(file).lines())));
+        }
+        
+        Ok(lines.filter_map(move |line| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!("Failed to read event store: {}", e);
+                    return None;
+                }
+            };
+            
+            if line.trim().is_empty() {
+                return None;
+            }
+            
+            match serde_json::from_str::<Event>(&line) {
+                Ok(event) if event.timestamp >= from && event.timestamp <= to => Some(event),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("Skipping malformed event in store: {}", e);
+                    None
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventType, Severity};
+    use chrono::Duration;
+    
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EventStore::open(dir.path().join("events.jsonl")).await.unwrap();
+        
+        let mut old = Event::system_change("test", None);
+        old.timestamp = Utc::now() - Duration::hours(2);
+        let alert = Event::security_alert_with_severity(
+            "eye360",
+            Some(serde_json::json!({"process": "nc"})),
+            Severity::Critical,
+        );
+        let activity = Event::network_activity("nettongue", None);
+        
+        for event in [&old, &alert, &activity] {
+            store.append(event).await.unwrap();
+        }
+        
+        let start = Utc::now() - Duration::hours(1);
+        let replayed: Vec<Event> = store.replay(start, Utc::now()).await.unwrap().collect().await;
+        
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[0].event_type, EventType::SecurityAlert);
+        assert_eq!(replayed[0].source, "eye360");
+        assert_eq!(replayed[0].data, alert.data);
+        assert_eq!(replayed[0].severity(), Severity::Critical);
+        assert_eq!(replayed[0].timestamp, alert.timestamp);
+        assert_eq!(replayed[1].event_type, EventType::NetworkActivity);
+    }
+    
+    #[tokio::test]
+    async fn test_replay_spans_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RotationPolicy {
+            max_bytes: 1,
+            max_age: None,
+            keep: 2,
+            compress: false,
+        };
+        let store = EventStore::open_with_rotation(dir.path().join("events.jsonl"), policy).await.unwrap();
+        
+        // One event per file, the oldest pruned
+        for source in ["first", "second", "third", "fourth"] {
+            store.append(&Event::network_activity(source, None)).await.unwrap();
+        }
+        assert_eq!(rotated_files(store.path()).await.unwrap().len(), 2);
+        
+        let start = Utc::now() - Duration::hours(1);
+        let replayed: Vec<Event> = store.replay(start, Utc::now()).await.unwrap().collect().await;
+        let sources: Vec<_> = replayed.iter().map(|event| event.source.as_str()).collect();
+        assert_eq!(sources, ["second", "third", "fourth"]);
+    }
+}
@@ -1,4 +1,5 @@
 use chame_core::events::Event;
+use chame_core::{InvalidPosture, Posture};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use std::path::PathBuf;
@@ -51,8 +52,8 @@ pub enum Commands {
     /// Start the CAMALEON protective service
     Start {
         /// Operation mode (silent, neutral, mimetic, fulgurant, unstable)
-        #[arg(short, long, default_value = "neutral")]
-        mode: String,
+        #[arg(short, long, default_value = "neutral", value_parser = parse_posture)]
+        mode: Posture,
     },
 
     /// Manage skin shifting capabilities (OS fingerprint, banners)
@@ -110,8 +111,8 @@ pub enum Commands {
         rotate_services: bool,
 
         /// Set specific posture (silent, neutral, mimetic, fulgurant, unstable)
-        #[arg(long)]
-        set: Option<String>,
+        #[arg(long, value_parser = parse_posture)]
+        set: Option<Posture>,
     },
     
     /// Manage the API server
@@ -133,6 +134,11 @@ pub enum Commands {
     Status,
 }
 
+/// Parse a `--mode` or `--set` posture name, whatever its case
+fn parse_posture(name: &str) -> Result<Posture, InvalidPosture> {
+    Posture::try_from(name)
+}
+
 /// Main CLI handler
 pub struct CliHandler {
     /// Event sender
@@ -164,7 +170,7 @@ impl CliHandler {
         // Process commands
         match &cli.command {
             Commands::Start { mode } => {
-                println!("{} CAMALEON in {} mode", "Starting".green().bold(), mode.cyan());
+                println!("{} CAMALEON in {} mode", "Starting".green().bold(), mode.to_str().cyan());
                 println!("{}...", "Initializing adaptive defense systems".yellow());
                 
                 // Send start event
@@ -172,7 +178,7 @@ impl CliHandler {
                     "cli",
                     Some(serde_json::json!({
                         "action": "start",
-                        "mode": mode,
+                        "mode": mode.to_str(),
                     })),
                 );
                 
@@ -313,14 +319,14 @@ impl CliHandler {
                 }
                 
                 if let Some(posture) = set {
-                    println!("{} defensive posture to: {}", "Setting".green().bold(), posture.cyan());
+                    println!("{} defensive posture to: {}", "Setting".green().bold(), posture.to_str().cyan());
                     
                     // Send posture event
                     let event = Event::posture_change(
                         "cli",
                         Some(serde_json::json!({
                             "action": "set_posture",
-                            "posture": posture,
+                            "posture": posture.to_str(),
                         })),
                     );
                     
@@ -1,18 +1,17 @@
 use chame_core::adaptive::{AdaptiveError, AdaptiveEvent, AdaptiveHandler};
 use chame_core::events::{Event, EventType};
+use chame_core::EventSender;
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::mpsc;
 
 /// Handler for Eye360 events
 pub struct Eye360Handler {
     /// Event sender
-    event_sender: mpsc::Sender<Event>,
+    event_sender: EventSender,
 }
 
 impl Eye360Handler {
     /// Create a new Eye360 handler
-    pub fn new(event_sender: mpsc::Sender<Event>) -> Self {
+    pub fn new(event_sender: EventSender) -> Self {
         Self { event_sender }
     }
 }
@@ -1,4 +1,9 @@
-use chame_core::events::{Event, EventType};
+pub mod handler;
+pub mod syscalls;
+
+use chame_core::events::{EventType, ReportableDetection, Severity0to10};
+use chame_core::{ChameleonError, EventSender, Feature, FeatureStatus, ModuleHealth};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
@@ -23,6 +28,12 @@ pub enum Eye360Error {
     Io(#[from] std::io::Error),
 }
 
+impl From<Eye360Error> for ChameleonError {
+    fn from(error: Eye360Error) -> Self {
+        ChameleonError::module("eye360", error)
+    }
+}
+
 /// Configuration for the Eye360 module
 #[derive(Debug, Clone)]
 pub struct Eye360Config {
@@ -59,7 +70,7 @@ impl Default for Eye360Config {
 }
 
 /// A detection for suspicious system activity
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Detection {
     /// Type of detection
     pub detection_type: DetectionType,
@@ -71,14 +82,35 @@ pub struct Detection {
     pub details: HashMap<String, String>,
     
     /// Severity level (0-10)
-    pub severity: u8,
+    pub severity: Severity0to10,
     
     /// Timestamp of the detection
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl ReportableDetection for Detection {
+    fn module(&self) -> &str {
+        "eye360"
+    }
+    
+    fn event_type(&self) -> EventType {
+        EventType::SecurityAlert
+    }
+    
+    fn detection_type(&self) -> String {
+        match &self.detection_type {
+            DetectionType::Other(name) => name.clone(),
+            detection_type => format!("{:?}", detection_type),
+        }
+    }
+    
+    fn severity(&self) -> Severity0to10 {
+        self.severity
+    }
+}
+
 /// Types of system detections
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum DetectionType {
     /// Suspicious syscall activity
     SuspiciousSyscall,
@@ -108,7 +140,7 @@ pub struct Eye360 {
     detections: RwLock<Vec<Detection>>,
     
     /// Event sender
-    event_sender: tokio::sync::mpsc::Sender<Event>,
+    event_sender: EventSender,
     
     /// Process monitor
     process_monitor: Option<Arc<ProcessMonitor>>,
@@ -118,13 +150,16 @@ pub struct Eye360 {
     
     /// eBPF monitor
     ebpf_monitor: Option<Arc<EbpfMonitor>>,
+    
+    /// Whether eBPF monitoring could be set up
+    ebpf_status: FeatureStatus,
 }
 
 impl Eye360 {
     /// Create a new Eye360 instance
     pub async fn new(
         config: Eye360Config,
-        event_sender: tokio::sync::mpsc::Sender<Event>,
+        event_sender: EventSender,
     ) -> Result<Self, Eye360Error> {
         let process_monitor = Some(Arc::new(ProcessMonitor::new()?));
         
@@ -134,16 +169,16 @@ impl Eye360 {
             None
         };
         
-        let ebpf_monitor = if config.ebpf_enabled {
+        let (ebpf_monitor, ebpf_status) = if config.ebpf_enabled {
             match EbpfMonitor::new() {
-                Ok(monitor) => Some(Arc::new(monitor)),
+                Ok(monitor) => (Some(Arc::new(monitor)), FeatureStatus::Functional),
                 Err(e) => {
                     tracing::warn!("Failed to initialize eBPF monitor: {}", e);
-                    None
+                    (None, FeatureStatus::Unavailable(e.to_string()))
                 }
             }
         } else {
-            None
+            (None, FeatureStatus::Disabled)
         };
         
         Ok(Self {
@@ -153,6 +188,7 @@ impl Eye360 {
             process_monitor,
             syscall_monitor,
             ebpf_monitor,
+            ebpf_status,
         })
     }
     
@@ -200,6 +236,27 @@ impl Eye360 {
         Ok(())
     }
     
+    /// Health of the enabled monitors
+    pub async fn health(&self) -> ModuleHealth {
+        let mut parts = Vec::new();
+        if let Some(monitor) = &self.process_monitor {
+            parts.push(("process monitor", monitor.is_running().await));
+        }
+        if let Some(monitor) = &self.syscall_monitor {
+            parts.push(("syscall monitor", monitor.is_running().await));
+        }
+        if let Some(monitor) = &self.ebpf_monitor {
+            parts.push(("eBPF monitor", monitor.is_running().await));
+        }
+        
+        ModuleHealth::from_parts(parts)
+    }
+    
+    /// Privileged features and whether they could be set up
+    pub fn features(&self) -> Vec<Feature> {
+        vec![Feature::new("eye360 eBPF monitoring", self.ebpf_status.clone())]
+    }
+    
     /// Add a detection
     pub async fn add_detection(&self, detection: Detection) -> Result<(), Eye360Error> {
         // Add to history
@@ -209,10 +266,7 @@ impl Eye360 {
         }
         
         // Send event
-        let event = Event::security_alert(
-            "eye360",
-            Some(serde_json::to_value(&detection).unwrap_or_default()),
-        );
+        let event = detection.to_event();
         
         if let Err(e) = self.event_sender.send(event).await {
             tracing::error!("Failed to send detection event: {}", e);
@@ -257,6 +311,11 @@ impl ProcessMonitor {
         tracing::info!("Process monitoring stopped");
         Ok(())
     }
+    
+    /// Whether the monitor is running
+    pub async fn is_running(&self) -> bool {
+        *self.running.read().await
+    }
 }
 
 /// Monitor for system calls
@@ -270,10 +329,12 @@ pub struct SyscallMonitor {
 
 impl SyscallMonitor {
     /// Create a new syscall monitor
+    ///
+    /// Fails with every name unknown on this architecture, see [`syscalls::resolve`].
     pub fn new(syscalls: &[String]) -> Result<Self, Eye360Error> {
         Ok(Self {
             running: RwLock::new(false),
-            syscalls: syscalls.to_vec(),
+            syscalls: syscalls::resolve_all(syscalls)?,
         })
     }
     
@@ -292,6 +353,11 @@ impl SyscallMonitor {
         tracing::info!("Syscall monitoring stopped");
         Ok(())
     }
+    
+    /// Whether the monitor is running
+    pub async fn is_running(&self) -> bool {
+        *self.running.read().await
+    }
 }
 
 /// Monitor using eBPF
@@ -330,4 +396,9 @@ impl EbpfMonitor {
         tracing::info!("eBPF monitoring stopped");
         Ok(())
     }
+    
+    /// Whether the monitor is running
+    pub async fn is_running(&self) -> bool {
+        *self.running.read().await
+    }
 }
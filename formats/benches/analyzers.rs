@@ -0,0 +1,116 @@
+//! Throughput of the file analyzers on generated input
+//!
+//! Run with `cargo bench -p formats`. CSV matching is spread over the rayon
+//! thread pool, so each analyzer is also timed on a single thread, which is
+//! how it ran before, to show the parallel speedup on the current machine.
+//! Both analyzers test all their patterns in one pass with a `RegexSet`.
+
+use formats::{CsvAnalyzer, FileAnalyzer, LogAnalyzer};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Rows in the generated CSV export
+const CSV_ROWS: usize = 200_000;
+
+/// Lines in the generated log
+const LOG_LINES: usize = 500_000;
+
+/// Runs per analyzer, the fastest is reported
+const RUNS: usize = 5;
+
+fn main() {
+    let csv = generate_csv(CSV_ROWS);
+    bench("csv", csv.path(), CSV_ROWS, &CsvAnalyzer::new());
+    
+    let log = generate_log(LOG_LINES);
+    bench("log", log.path(), LOG_LINES, &LogAnalyzer::new());
+}
+
+/// Write a SIEM-like export where a few rows trip the default patterns
+fn generate_csv(rows: usize) -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+    writeln!(file, "id,timestamp,host,user,process,message,status").unwrap();
+    
+    for i in 0..rows {
+        let message = match i % 100 {
+            0 => "ransom note dropped in user profile",
+            13 => "suspicious login outside business hours",
+            42 => "possible exploit of print spooler",
+            _ => "routine scheduled task completed",
+        };
+        writeln!(
+            file,
+            "{},2025-05-28T05:{:02}:{:02}Z,host-{},user{},svchost.exe,{},ok",
+            i,
+            (i / 60) % 60,
+            i % 60,
+            i % 97,
+            i % 13,
+            message
+        )
+        .unwrap();
+    }
+    
+    file.flush().unwrap();
+    file
+}
+
+/// Write a syslog-like file where a few lines trip the default patterns
+fn generate_log(lines: usize) -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+    
+    for i in 0..lines {
+        let message = match i % 100 {
+            0 => "ERROR: authentication failure for user admin from 10.0.0.5",
+            13 => "WARNING: permission denied opening /etc/shadow",
+            42 => "CRITICAL: possible sql injection in login form",
+            _ => "INFO: request served in 12ms",
+        };
+        writeln!(file, "2025-05-28 05:{:02}:{:02} {}", (i / 60) % 60, i % 60, message).unwrap();
+    }
+    
+    file.flush().unwrap();
+    file
+}
+
+/// Time an analyzer over a file on one thread, then on the whole pool
+fn bench(name: &str, path: &Path, units: usize, analyzer: &(dyn FileAnalyzer + Sync)) {
+    let single = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let sequential = single.install(|| run(name, path, units, analyzer));
+    let parallel = run(name, path, units, analyzer);
+    
+    println!(
+        "{}: {:.2}x speedup on {} threads",
+        name,
+        sequential.as_secs_f64() / parallel.as_secs_f64(),
+        rayon::current_num_threads()
+    );
+}
+
+/// Time an analyzer over a file, print its throughput and return the best run
+fn run(name: &str, path: &Path, units: usize, analyzer: &(dyn FileAnalyzer + Sync)) -> Duration {
+    let size_mb = path.metadata().unwrap().len() as f64 / (1024.0 * 1024.0);
+    let mut best = Duration::MAX;
+    let mut detections = 0;
+    
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        detections = analyzer.analyze(path).unwrap().len();
+        best = best.min(start.elapsed());
+    }
+    
+    println!(
+        "{}: {} rows ({:.1} MB), {} detections in {:.2?} on {} threads ({:.0} rows/s, {:.1} MB/s)",
+        name,
+        units,
+        size_mb,
+        detections,
+        best,
+        rayon::current_num_threads(),
+        units as f64 / best.as_secs_f64(),
+        size_mb / best.as_secs_f64()
+    );
+    
+    best
+}
@@ -1,8 +1,15 @@
-use chame_core::events::{Event, EventType};
+use chame_core::events::{Event, EventType, Severity, Severity0to10, DETECTION_SCHEMA_VERSION};
+use rayon::prelude::*;
 use std::collections::HashMap;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
 
+#[cfg(feature = "plugins")]
+pub mod plugin;
+
 /// Errors that can occur in the Formats module
 #[derive(Error, Debug)]
 pub enum FormatsError {
@@ -15,15 +22,26 @@ pub enum FormatsError {
     #[error("Parse error: {0}")]
     ParseError(String),
     
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
     #[error("CSV error: {0}")]
     Csv(#[from] csv::Error),
+    
+    #[cfg(feature = "yara")]
+    #[error("YARA error: {0}")]
+    Yara(#[from] yara::Error),
+    
+    #[cfg(feature = "plugins")]
+    #[error("Plugin error: {0}")]
+    Plugin(String),
 }
 
 /// Types of file formats supported
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum FileFormat {
     /// CSV format
     Csv,
@@ -34,6 +52,9 @@ pub enum FileFormat {
     /// VMDK format
     Vmdk,
     
+    /// Windows Event Log format
+    Evtx,
+    
     /// Unknown format
     Unknown,
 }
@@ -45,10 +66,22 @@ impl FileFormat {
             "csv" => Self::Csv,
             "log" | "txt" => Self::Log,
             "vmdk" => Self::Vmdk,
+            "evtx" => Self::Evtx,
             _ => Self::Unknown,
         }
     }
     
+    /// Lowercase name, e.g. `csv`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Log => "log",
+            Self::Vmdk => "vmdk",
+            Self::Evtx => "evtx",
+            Self::Unknown => "unknown",
+        }
+    }
+    
     /// Get format from file path
     pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
         path.as_ref()
@@ -66,7 +99,7 @@ pub struct DetectionResult {
     pub detection_type: String,
     
     /// Severity level (0-10)
-    pub severity: u8,
+    pub severity: Severity0to10,
     
     /// Location in file (line number, offset, etc.)
     pub location: String,
@@ -78,13 +111,73 @@ pub struct DetectionResult {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl DetectionResult {
+    /// Map the numeric severity onto the shared severity levels
+    pub fn severity_level(&self) -> Severity {
+        self.severity.level()
+    }
+}
+
+/// Collapse detections sharing a location into one result
+///
+/// The merged result keeps the type, severity and details of its most
+/// severe detection and lists every matched type in `matched_types`.
+/// Locations keep the order in which they were first seen.
+pub fn merge_overlapping(results: Vec<DetectionResult>) -> Vec<DetectionResult> {
+    let mut merged: Vec<(DetectionResult, Vec<String>)> = Vec::new();
+    let mut by_location: HashMap<String, usize> = HashMap::new();
+    
+    for result in results {
+        match by_location.get(&result.location) {
+            Some(&index) => {
+                let (existing, types) = &mut merged[index];
+                if !types.contains(&result.detection_type) {
+                    types.push(result.detection_type.clone());
+                }
+                if result.severity > existing.severity {
+                    *existing = result;
+                }
+            }
+            None => {
+                by_location.insert(result.location.clone(), merged.len());
+                let types = vec![result.detection_type.clone()];
+                merged.push((result, types));
+            }
+        }
+    }
+    
+    merged
+        .into_iter()
+        .map(|(mut result, types)| {
+            result.details.insert("matched_types".to_string(), types.join(","));
+            result
+        })
+        .collect()
+}
+
+/// Replace the severity of results whose detection type has an override
+///
+/// Overrides above 10 are capped at 10.
+pub fn apply_severity_overrides(results: &mut [DetectionResult], overrides: &HashMap<String, u8>) {
+    for result in results {
+        if let Some(&severity) = overrides.get(&result.detection_type) {
+            result.severity = Severity0to10::clamped(severity);
+        }
+    }
+}
+
 /// File analyzer trait
 pub trait FileAnalyzer {
     /// Analyze a file and return detections
-    fn analyze<P: AsRef<Path>>(&self, path: P) -> Result<Vec<DetectionResult>, FormatsError>;
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError>;
     
     /// Get supported file format
     fn supported_format(&self) -> FileFormat;
+    
+    /// Whether the analyzer should run on files of the given format
+    fn applies_to(&self, format: &FileFormat) -> bool {
+        self.supported_format() == *format
+    }
 }
 
 /// Main Formats service
@@ -94,6 +187,18 @@ pub struct Formats {
     
     /// Event sender
     event_sender: tokio::sync::mpsc::Sender<Event>,
+    
+    /// Detections below this severity are dropped
+    min_severity: u8,
+    
+    /// Detections dropped for being below `min_severity`
+    suppressed: AtomicU64,
+    
+    /// Severities replacing the analyzers' ones, keyed by detection type
+    severity_overrides: HashMap<String, u8>,
+    
+    /// Patterns of the built-in analyzers, replaceable at runtime
+    live_patterns: Vec<(FileFormat, LivePatterns)>,
 }
 
 impl Formats {
@@ -102,21 +207,116 @@ impl Formats {
         let mut formats = Self {
             analyzers: Vec::new(),
             event_sender,
+            min_severity: 0,
+            suppressed: AtomicU64::new(0),
+            severity_overrides: HashMap::new(),
+            live_patterns: Vec::new(),
         };
         
         // Register default analyzers
-        formats.register_analyzer(Box::new(CsvAnalyzer::new()));
-        formats.register_analyzer(Box::new(LogAnalyzer::new()));
+        let csv = CsvAnalyzer::new();
+        formats.live_patterns.push((FileFormat::Csv, csv.live_patterns()));
+        formats.register_analyzer(Box::new(csv));
+        let log = LogAnalyzer::new();
+        formats.live_patterns.push((FileFormat::Log, log.live_patterns()));
+        formats.register_analyzer(Box::new(log));
+        #[cfg(feature = "evtx")]
+        formats.register_analyzer(Box::new(EvtxAnalyzer::new()));
         
         formats
     }
     
+    /// Only return and report detections with at least this severity
+    pub fn with_min_severity(mut self, min_severity: u8) -> Self {
+        self.min_severity = min_severity;
+        self
+    }
+    
+    /// Replace the severity of detections of the given types, e.g. to make
+    /// `phishing_indicator` critical
+    ///
+    /// Overrides apply to every analyzer, plugins and YARA rules included, and
+    /// take precedence over the severity they define, be it built in, given to
+    /// `add_pattern` or read from a rule's `severity` meta. `min_severity` and
+    /// the reported events use the overridden severity.
+    pub fn with_severity_overrides(mut self, overrides: HashMap<String, u8>) -> Self {
+        self.severity_overrides = overrides;
+        self
+    }
+    
+    /// Number of detections suppressed by `min_severity` so far
+    pub fn suppressed_count(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+    
+    /// Patterns of the built-in CSV and log analyzers, by format
+    pub fn patterns(&self) -> HashMap<FileFormat, Vec<PatternRule>> {
+        self.live_patterns
+            .iter()
+            .map(|(format, patterns)| (format.clone(), patterns.rules()))
+            .collect()
+    }
+    
+    /// Replace the patterns of the built-in analyzers of the given formats
+    ///
+    /// Every pattern is compiled before any set is swapped in, so a single
+    /// invalid pattern rejects the whole update. Formats not listed keep
+    /// their patterns; analyses already running finish with the old ones.
+    pub fn replace_patterns(&self, sets: &HashMap<FileFormat, Vec<PatternRule>>) -> Result<(), FormatsError> {
+        let mut compiled = Vec::with_capacity(sets.len());
+        for (format, rules) in sets {
+            let Some((_, patterns)) = self.live_patterns.iter().find(|(live, _)| live == format) else {
+                return Err(FormatsError::InvalidFormat(format!("No replaceable patterns for format: {:?}", format)));
+            };
+            compiled.push((patterns, PatternSet::from_rules(rules)?));
+        }
+        
+        for (patterns, set) in compiled {
+            patterns.swap(set);
+        }
+        tracing::info!("Replaced the detection patterns of {} formats", sets.len());
+        
+        Ok(())
+    }
+    
     /// Register a file analyzer
     pub fn register_analyzer(&mut self, analyzer: Box<dyn FileAnalyzer + Send + Sync>) {
         self.analyzers.push(analyzer);
     }
     
+    /// Compile the YARA rules in `rules_dir` and scan every analyzed file
+    /// with them, whatever its format
+    #[cfg(feature = "yara")]
+    pub fn with_yara_rules<P: AsRef<Path>>(mut self, rules_dir: P) -> Result<Self, FormatsError> {
+        self.register_analyzer(Box::new(YaraAnalyzer::from_dir(rules_dir)?));
+        Ok(self)
+    }
+    
+    /// Load and register every analyzer plugin in `dir`
+    ///
+    /// Returns the names of the plugins loaded. A plugin that fails to load
+    /// is logged and skipped, see [`plugin`] for the interface.
+    #[cfg(feature = "plugins")]
+    pub fn load_plugins<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<String>, FormatsError> {
+        let mut names = Vec::new();
+        for path in plugin::plugin_paths(dir.as_ref())? {
+            match plugin::PluginAnalyzer::load(&path) {
+                Ok(analyzer) => {
+                    tracing::info!("Loaded analyzer plugin {} from {}", analyzer.name(), path.display());
+                    names.push(analyzer.name().to_string());
+                    self.register_analyzer(Box::new(analyzer));
+                }
+                Err(e) => tracing::warn!("Skipping plugin {}: {}", path.display(), e),
+            }
+        }
+        
+        Ok(names)
+    }
+    
     /// Analyze a file
+    ///
+    /// An analyzer failing on the file is logged and skipped; the file only
+    /// fails when none of its analyzers could read it.
     pub async fn analyze_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<DetectionResult>, FormatsError> {
         let path_ref = path.as_ref();
         
@@ -130,29 +330,66 @@ impl Formats {
         // Detect format
         let format = FileFormat::from_path(path_ref);
         
-        // Find analyzer
-        let analyzer = self
+        // Find analyzers
+        let analyzers: Vec<_> = self
             .analyzers
             .iter()
-            .find(|a| a.supported_format() == format)
-            .ok_or_else(|| {
-                FormatsError::InvalidFormat(format!("No analyzer for format: {:?}", format))
-            })?;
+            .filter(|a| a.applies_to(&format))
+            .collect();
+        
+        if analyzers.is_empty() {
+            return Err(FormatsError::InvalidFormat(format!("No analyzer for format: {:?}", format)));
+        }
         
         // Analyze file
-        let results = analyzer.analyze(path_ref)?;
+        let mut results = Vec::new();
+        let mut analyzed = false;
+        let mut failure = None;
+        for analyzer in analyzers {
+            match analyzer.analyze(path_ref) {
+                Ok(found) => {
+                    results.extend(found);
+                    analyzed = true;
+                }
+                Err(e) => {
+                    tracing::warn!("An analyzer failed on {}, skipping it: {}", path_ref.display(), e);
+                    failure = Some(e);
+                }
+            }
+        }
+        if let (false, Some(e)) = (analyzed, failure) {
+            return Err(e);
+        }
+        
+        apply_severity_overrides(&mut results, &self.severity_overrides);
+        
+        // Drop low-value detections before they reach the event bus
+        let total = results.len();
+        results.retain(|r| r.severity >= self.min_severity);
+        let suppressed = (total - results.len()) as u64;
+        if suppressed > 0 {
+            self.suppressed.fetch_add(suppressed, Ordering::Relaxed);
+            tracing::debug!(
+                "Suppressed {} detections below severity {} in {}",
+                suppressed,
+                self.min_severity,
+                path_ref.display()
+            );
+        }
         
-        // Send events for detections
+        // Send events for detections, with the severity of the detection
         for result in &results {
-            let event = Event::security_alert(
+            let event = Event::security_alert_with_severity(
                 "formats",
                 Some(serde_json::json!({
+                    "schema_version": DETECTION_SCHEMA_VERSION,
                     "detection_type": result.detection_type,
                     "severity": result.severity,
                     "location": result.location,
                     "details": result.details,
                     "file": path_ref.to_string_lossy(),
                 })),
+                result.severity_level(),
             );
             
             if let Err(e) = self.event_sender.send(event).await {
@@ -162,19 +399,401 @@ impl Formats {
         
         Ok(results)
     }
+    
+    /// Analyze several files, a failure only affecting its own file
+    pub async fn analyze_files<P: AsRef<Path>>(&self, paths: impl IntoIterator<Item = P>) -> BatchReport {
+        let mut report = BatchReport::default();
+        for path in paths {
+            let path = path.as_ref();
+            let result = self.analyze_file(path).await;
+            if let Err(e) = &result {
+                tracing::warn!("Failed to analyze {}: {}", path.display(), e);
+            }
+            report.files.push(FileReport {
+                path: path.to_path_buf(),
+                result,
+            });
+        }
+        
+        tracing::info!(
+            "Analyzed {} files: {} succeeded, {} failed, {} detections",
+            report.files.len(),
+            report.succeeded(),
+            report.failed(),
+            report.detection_count()
+        );
+        
+        report
+    }
+    
+    /// Analyze every file under `dir` that an analyzer handles
+    ///
+    /// Subdirectories are walked too, except through symlinks, and files are
+    /// analyzed in path order. Only an unreadable `dir` fails the whole batch.
+    pub async fn analyze_dir<P: AsRef<Path>>(&self, dir: P) -> Result<BatchReport, FormatsError> {
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+        let mut files = Vec::new();
+        let mut first = true;
+        
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if first => return Err(e.into()),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable directory {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+            first = false;
+            
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                // Symlinked directories aren't followed, they could loop back
+                match entry.file_type() {
+                    Ok(kind) if kind.is_dir() => pending.push(path),
+                    Ok(kind) if kind.is_symlink() && path.is_dir() => {
+                        tracing::debug!("Not following symlinked directory {}", path.display());
+                    }
+                    Ok(_) => {
+                        let format = FileFormat::from_path(&path);
+                        if self.analyzers.iter().any(|a| a.applies_to(&format)) {
+                            files.push(path);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Skipping {}: {}", path.display(), e),
+                }
+            }
+        }
+        files.sort();
+        
+        Ok(self.analyze_files(files).await)
+    }
+}
+
+/// Outcome of analyzing one file of a batch
+#[derive(Debug)]
+pub struct FileReport {
+    /// Analyzed file
+    pub path: PathBuf,
+    
+    /// Detections, or why the file couldn't be analyzed
+    pub result: Result<Vec<DetectionResult>, FormatsError>,
+}
+
+/// Outcome of [`Formats::analyze_files`] and [`Formats::analyze_dir`]
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// One report per file, in the order they were analyzed
+    pub files: Vec<FileReport>,
+}
+
+impl BatchReport {
+    /// Number of files analyzed successfully
+    pub fn succeeded(&self) -> usize {
+        self.files.iter().filter(|file| file.result.is_ok()).count()
+    }
+    
+    /// Number of files that couldn't be analyzed
+    pub fn failed(&self) -> usize {
+        self.files.len() - self.succeeded()
+    }
+    
+    /// Detections of every successfully analyzed file
+    pub fn detections(&self) -> impl Iterator<Item = &DetectionResult> {
+        self.files.iter().filter_map(|file| file.result.as_ref().ok()).flatten()
+    }
+    
+    /// Number of detections across the batch
+    pub fn detection_count(&self) -> usize {
+        self.detections().count()
+    }
+    
+    /// Files that couldn't be analyzed, with their error
+    pub fn failures(&self) -> impl Iterator<Item = (&Path, &FormatsError)> {
+        self.files
+            .iter()
+            .filter_map(|file| Some((file.path.as_path(), file.result.as_ref().err()?)))
+    }
+}
+
+/// A detection pattern, as listed and replaced through [`Formats::patterns`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternRule {
+    /// Regular expression
+    pub pattern: String,
+    
+    /// Detection type reported on a match
+    pub detection_type: String,
+    
+    /// Severity reported on a match
+    pub severity: Severity0to10,
+}
+
+/// Compiled size a detection pattern may reach, in bytes
+///
+/// Patterns are compiled by the `regex` crate, which never backtracks:
+/// matching takes time linear in the input whatever the pattern, so crafted
+/// input can't hang an analyzer. A pattern can still take a lot of memory,
+/// e.g. `(a{1000}){1000}`; patterns compiling past this size are refused
+/// with [`FormatsError::InvalidPattern`].
+pub const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+/// Cache of the lazy DFA each pattern matches with, in bytes
+///
+/// Past it matching falls back to a slower engine, still linear in time.
+pub const PATTERN_DFA_SIZE_LIMIT: usize = 2 << 20;
+
+/// Compile a detection pattern within the size limits
+fn compile_pattern(pattern: &str) -> Result<regex::Regex, FormatsError> {
+    regex::RegexBuilder::new(pattern)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .dfa_size_limit(PATTERN_DFA_SIZE_LIMIT)
+        .build()
+        .map_err(|e| pattern_error(pattern, e))
+}
+
+/// Compile patterns into one set, the limits growing with their number
+fn compile_set<'a>(patterns: impl ExactSizeIterator<Item = &'a str>) -> Result<regex::RegexSet, FormatsError> {
+    let count = patterns.len().max(1);
+    regex::RegexSetBuilder::new(patterns)
+        .size_limit(PATTERN_SIZE_LIMIT.saturating_mul(count))
+        .dfa_size_limit(PATTERN_DFA_SIZE_LIMIT.saturating_mul(count))
+        .build()
+        .map_err(|e| pattern_error("pattern set", e))
+}
+
+fn pattern_error(pattern: &str, error: regex::Error) -> FormatsError {
+    match error {
+        regex::Error::CompiledTooBig(limit) => FormatsError::InvalidPattern(format!(
+            "{}: compiles past the {} byte size limit, use smaller repetitions",
+            pattern, limit
+        )),
+        e => FormatsError::InvalidPattern(format!("{}: {}", pattern, e)),
+    }
+}
+
+/// Patterns tested together in a single pass over the input
+#[derive(Clone)]
+struct PatternSet {
+    /// Individual patterns with their detection type and severity
+    patterns: Vec<(regex::Regex, String, Severity0to10)>,
+    
+    /// All patterns combined, index `i` is `patterns[i]`
+    set: regex::RegexSet,
+}
+
+impl PatternSet {
+    /// Create an empty pattern set
+    fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+            set: regex::RegexSet::empty(),
+        }
+    }
+    
+    /// Compile every rule, failing on the first invalid pattern
+    fn from_rules(rules: &[PatternRule]) -> Result<Self, FormatsError> {
+        let patterns = rules
+            .iter()
+            .map(|rule| Ok((compile_pattern(&rule.pattern)?, rule.detection_type.clone(), rule.severity)))
+            .collect::<Result<Vec<_>, FormatsError>>()?;
+        let set = compile_set(rules.iter().map(|rule| rule.pattern.as_str()))?;
+        
+        Ok(Self { patterns, set })
+    }
+    
+    /// The patterns, in the order they were added
+    fn rules(&self) -> Vec<PatternRule> {
+        self.patterns
+            .iter()
+            .map(|(regex, detection_type, severity)| PatternRule {
+                pattern: regex.as_str().to_string(),
+                detection_type: detection_type.clone(),
+                severity: *severity,
+            })
+            .collect()
+    }
+    
+    /// Add a pattern, ignoring it if it doesn't compile within the limits
+    fn add(&mut self, pattern: &str, detection_type: &str, severity: Severity0to10) {
+        let regex = match compile_pattern(pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                tracing::warn!("Ignoring pattern: {}", e);
+                return;
+            }
+        };
+        
+        // Patterns are only added at setup, so rebuilding the set is cheap enough
+        let patterns: Vec<&str> = self.patterns.iter().map(|(r, _, _)| r.as_str()).chain([pattern]).collect();
+        match compile_set(patterns.into_iter()) {
+            Ok(set) => {
+                self.patterns.push((regex, detection_type.to_string(), severity));
+                self.set = set;
+            }
+            Err(e) => tracing::warn!("Ignoring pattern {}: {}", pattern, e),
+        }
+    }
+    
+    /// Replace the severity of the patterns whose detection type has an override
+    fn override_severities(&mut self, overrides: &HashMap<String, u8>) {
+        for (_, detection_type, severity) in &mut self.patterns {
+            if let Some(&new_severity) = overrides.get(detection_type) {
+                *severity = Severity0to10::clamped(new_severity);
+            }
+        }
+    }
+    
+    /// Patterns matching the text, in the order they were added
+    fn matches<'a>(&'a self, text: &str) -> impl Iterator<Item = &'a (regex::Regex, String, Severity0to10)> + 'a {
+        self.set.matches(text).into_iter().map(move |index| &self.patterns[index])
+    }
+    
+    /// Detections for every line of `content`, located by line number
+    fn scan_lines(&self, content: &str) -> Vec<DetectionResult> {
+        let mut results = Vec::new();
+        
+        for (line_idx, line) in content.lines().enumerate() {
+            // Only patterns the set matched are run again to find the text
+            for (pattern, detection_type, severity) in self.matches(line) {
+                let matched_text = pattern.find(line).map_or("", |m| m.as_str());
+                
+                let mut details = HashMap::new();
+                details.insert("matched_text".to_string(), matched_text.to_string());
+                details.insert("full_line".to_string(), line.to_string());
+                
+                results.push(DetectionResult {
+                    detection_type: detection_type.clone(),
+                    severity: *severity,
+                    location: format!("line:{}", line_idx + 1),
+                    details,
+                    timestamp: chrono::Utc::now(),
+                });
+            }
+        }
+        
+        results
+    }
+}
+
+/// An analyzer's pattern set, replaceable while files are being analyzed
+///
+/// Clones share the same set. An analysis runs with the set current when it
+/// started, a replacement only applies to the next one.
+#[derive(Clone)]
+pub struct LivePatterns(Arc<RwLock<Arc<PatternSet>>>);
+
+impl LivePatterns {
+    fn new() -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(PatternSet::new()))))
+    }
+    
+    /// The set to analyze a file with
+    fn current(&self) -> Arc<PatternSet> {
+        self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+    
+    /// Change the set in place
+    fn update(&self, change: impl FnOnce(&mut PatternSet)) {
+        let mut current = self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        change(Arc::make_mut(&mut current));
+    }
+    
+    /// Swap in an already compiled set
+    fn swap(&self, set: PatternSet) {
+        *self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(set);
+    }
+    
+    /// The current patterns
+    pub fn rules(&self) -> Vec<PatternRule> {
+        self.current().rules()
+    }
+    
+    /// Replace every pattern, keeping the current ones if any is invalid
+    pub fn replace(&self, rules: &[PatternRule]) -> Result<(), FormatsError> {
+        self.swap(PatternSet::from_rules(rules)?);
+        Ok(())
+    }
+}
+
+/// A CSV column, by header name or 0-based index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    /// Position of the column, the first one being 0
+    Index(usize),
+    
+    /// Name of the column in the header row
+    Name(String),
+}
+
+impl From<usize> for CsvColumn {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for CsvColumn {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl std::fmt::Display for CsvColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "#{}", index),
+            Self::Name(name) => write!(f, "'{}'", name),
+        }
+    }
+}
+
+/// A column for [`CsvAnalyzer::with_columns`] to scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvColumnConfig {
+    /// Column to scan
+    pub column: CsvColumn,
+    
+    /// Patterns for this column only, the analyzer's patterns when unset
+    #[serde(default)]
+    pub patterns: Option<Vec<PatternRule>>,
+}
+
+impl CsvColumnConfig {
+    /// Scan `column` with the analyzer's patterns
+    pub fn new(column: impl Into<CsvColumn>) -> Self {
+        Self {
+            column: column.into(),
+            patterns: None,
+        }
+    }
+    
+    /// Scan the column with its own patterns instead
+    pub fn with_patterns(mut self, patterns: Vec<PatternRule>) -> Self {
+        self.patterns = Some(patterns);
+        self
+    }
 }
 
 /// CSV file analyzer
 pub struct CsvAnalyzer {
     /// Patterns to look for
-    patterns: Vec<(regex::Regex, String, u8)>,
+    patterns: LivePatterns,
+    
+    /// Collapse detections on the same field into one
+    merge_overlapping: bool,
+    
+    /// Columns to scan, with their own patterns if any; every column when empty
+    columns: Vec<(CsvColumn, Option<Arc<PatternSet>>)>,
 }
 
 impl CsvAnalyzer {
     /// Create a new CSV analyzer
     pub fn new() -> Self {
         let mut analyzer = Self {
-            patterns: Vec::new(),
+            patterns: LivePatterns::new(),
+            merge_overlapping: false,
+            columns: Vec::new(),
         };
         
         // Add default patterns
@@ -192,37 +811,145 @@ impl CsvAnalyzer {
     
     /// Add a pattern to look for
     pub fn add_pattern(&mut self, pattern: &str, detection_type: &str, severity: u8) {
-        if let Ok(regex) = regex::Regex::new(pattern) {
-            self.patterns.push((regex, detection_type.to_string(), severity));
+        self.patterns.update(|set| set.add(pattern, detection_type, Severity0to10::clamped(severity)));
+    }
+    
+    /// Handle to read or replace the patterns while the analyzer is in use
+    pub fn live_patterns(&self) -> LivePatterns {
+        self.patterns.clone()
+    }
+    
+    /// Report one detection per location, see `merge_overlapping`
+    pub fn with_merge_overlapping(mut self, merge: bool) -> Self {
+        self.merge_overlapping = merge;
+        self
+    }
+    
+    /// Replace the severity of the patterns added so far, keyed by detection type
+    pub fn with_severity_overrides(self, overrides: &HashMap<String, u8>) -> Self {
+        self.patterns.update(|set| set.override_severities(overrides));
+        self
+    }
+    
+    /// Only scan the given columns, e.g. a free-text `description` column
+    ///
+    /// Columns are looked up in the header row of each file, by exact name or
+    /// by index. A column missing from a file is skipped with a warning.
+    /// Without any column every field is scanned, header row excluded.
+    pub fn with_columns(mut self, columns: &[CsvColumnConfig]) -> Result<Self, FormatsError> {
+        self.columns = columns
+            .iter()
+            .map(|config| {
+                let patterns = config
+                    .patterns
+                    .as_deref()
+                    .map(|rules| PatternSet::from_rules(rules).map(Arc::new))
+                    .transpose()?;
+                Ok((config.column.clone(), patterns))
+            })
+            .collect::<Result<_, FormatsError>>()?;
+        Ok(self)
+    }
+    
+    /// Columns of `headers` to scan and the patterns to scan each with
+    fn resolve_columns(
+        &self,
+        headers: &csv::StringRecord,
+        patterns: &Arc<PatternSet>,
+        path: &Path,
+    ) -> Vec<(usize, Arc<PatternSet>)> {
+        if self.columns.is_empty() {
+            return (0..headers.len()).map(|index| (index, patterns.clone())).collect();
+        }
+        
+        self.columns
+            .iter()
+            .filter_map(|(column, column_patterns)| {
+                let index = match column {
+                    CsvColumn::Index(index) => Some(*index).filter(|index| *index < headers.len()),
+                    CsvColumn::Name(name) => headers.iter().position(|header| header.trim() == name),
+                };
+                if index.is_none() {
+                    tracing::warn!("No column {} in {}, skipping it", column, path.display());
+                }
+                Some((index?, column_patterns.clone().unwrap_or_else(|| patterns.clone())))
+            })
+            .collect()
+    }
+    
+    /// Match the selected fields of a row against their patterns
+    fn analyze_record(
+        columns: &[(usize, Arc<PatternSet>)],
+        headers: &csv::StringRecord,
+        row_idx: usize,
+        record: &csv::StringRecord,
+    ) -> Vec<DetectionResult> {
+        let mut results = Vec::new();
+        
+        for (col_idx, patterns) in columns {
+            let col_idx = *col_idx;
+            let Some(field) = record.get(col_idx) else {
+                continue;
+            };
+            for (_, detection_type, severity) in patterns.matches(field) {
+                let mut details = HashMap::new();
+                details.insert("matched_text".to_string(), field.to_string());
+                details.insert("column".to_string(), col_idx.to_string());
+                if let Some(name) = headers.get(col_idx) {
+                    details.insert("column_name".to_string(), name.trim().to_string());
+                }
+                
+                results.push(DetectionResult {
+                    detection_type: detection_type.clone(),
+                    severity: *severity,
+                    location: format!("row:{},col:{}", row_idx + 1, col_idx + 1),
+                    details,
+                    timestamp: chrono::Utc::now(),
+                });
+            }
         }
+        
+        results
     }
 }
 
+/// Rows read before handing a batch to the thread pool
+///
+/// Bounds memory on large exports while giving each thread enough work.
+const CSV_BATCH_ROWS: usize = 16 * 1024;
+
 impl FileAnalyzer for CsvAnalyzer {
-    fn analyze<P: AsRef<Path>>(&self, path: P) -> Result<Vec<DetectionResult>, FormatsError> {
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError> {
         let mut reader = csv::Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        let columns = self.resolve_columns(&headers, &self.patterns.current(), path);
+        let mut records = reader.records();
         let mut results = Vec::new();
+        let mut first_row = 0;
         
-        for (row_idx, result) in reader.records().enumerate() {
-            let record = result?;
-            
-            for (col_idx, field) in record.iter().enumerate() {
-                for (pattern, detection_type, severity) in &self.patterns {
-                    if pattern.is_match(field) {
-                        let mut details = HashMap::new();
-                        details.insert("matched_text".to_string(), field.to_string());
-                        details.insert("column".to_string(), col_idx.to_string());
-                        
-                        results.push(DetectionResult {
-                            detection_type: detection_type.clone(),
-                            severity: *severity,
-                            location: format!("row:{},col:{}", row_idx + 1, col_idx + 1),
-                            details,
-                            timestamp: chrono::Utc::now(),
-                        });
-                    }
-                }
+        // Parsing is sequential, matching runs on all cores; collecting an
+        // indexed parallel iterator keeps the detections in row order
+        loop {
+            let batch = records
+                .by_ref()
+                .take(CSV_BATCH_ROWS)
+                .collect::<Result<Vec<_>, _>>()?;
+            if batch.is_empty() {
+                break;
             }
+            
+            let detections: Vec<Vec<DetectionResult>> = batch
+                .par_iter()
+                .enumerate()
+                .map(|(offset, record)| Self::analyze_record(&columns, &headers, first_row + offset, record))
+                .collect();
+            results.extend(detections.into_iter().flatten());
+            
+            first_row += batch.len();
+        }
+        
+        if self.merge_overlapping {
+            results = merge_overlapping(results);
         }
         
         Ok(results)
@@ -236,14 +963,18 @@ impl FileAnalyzer for CsvAnalyzer {
 /// Log file analyzer
 pub struct LogAnalyzer {
     /// Patterns to look for
-    patterns: Vec<(regex::Regex, String, u8)>,
+    patterns: LivePatterns,
+    
+    /// Collapse detections on the same line into one
+    merge_overlapping: bool,
 }
 
 impl LogAnalyzer {
     /// Create a new Log analyzer
     pub fn new() -> Self {
         let mut analyzer = Self {
-            patterns: Vec::new(),
+            patterns: LivePatterns::new(),
+            merge_overlapping: false,
         };
         
         // Add default patterns
@@ -261,41 +992,327 @@ impl LogAnalyzer {
     
     /// Add a pattern to look for
     pub fn add_pattern(&mut self, pattern: &str, detection_type: &str, severity: u8) {
-        if let Ok(regex) = regex::Regex::new(pattern) {
-            self.patterns.push((regex, detection_type.to_string(), severity));
-        }
+        self.patterns.update(|set| set.add(pattern, detection_type, Severity0to10::clamped(severity)));
+    }
+    
+    /// Handle to read or replace the patterns while the analyzer is in use
+    pub fn live_patterns(&self) -> LivePatterns {
+        self.patterns.clone()
+    }
+    
+    /// Report one detection per location, see `merge_overlapping`
+    pub fn with_merge_overlapping(mut self, merge: bool) -> Self {
+        self.merge_overlapping = merge;
+        self
+    }
+    
+    /// Replace the severity of the patterns added so far, keyed by detection type
+    pub fn with_severity_overrides(self, overrides: &HashMap<String, u8>) -> Self {
+        self.patterns.update(|set| set.override_severities(overrides));
+        self
     }
 }
 
 impl FileAnalyzer for LogAnalyzer {
-    fn analyze<P: AsRef<Path>>(&self, path: P) -> Result<Vec<DetectionResult>, FormatsError> {
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError> {
         let content = std::fs::read_to_string(path)?;
+        let mut results = self.patterns.current().scan_lines(&content);
+        
+        if self.merge_overlapping {
+            results = merge_overlapping(results);
+        }
+        
+        Ok(results)
+    }
+    
+    fn supported_format(&self) -> FileFormat {
+        FileFormat::Log
+    }
+}
+
+/// Windows Event Log (`.evtx`) analyzer
+#[cfg(feature = "evtx")]
+pub struct EvtxAnalyzer {
+    /// Patterns to look for in the event message
+    patterns: Vec<(regex::Regex, String, Severity0to10)>,
+    
+    /// Event IDs reported on sight (event ID -> detection type, severity)
+    suspicious_event_ids: HashMap<u32, (String, Severity0to10)>,
+}
+
+#[cfg(feature = "evtx")]
+impl EvtxAnalyzer {
+    /// Create a new EVTX analyzer
+    pub fn new() -> Self {
+        let mut analyzer = Self {
+            patterns: Vec::new(),
+            suspicious_event_ids: HashMap::new(),
+        };
+        
+        // Add default event IDs
+        analyzer.add_suspicious_event_id(4625, "failed_logon", 6);
+        analyzer.add_suspicious_event_id(4688, "process_creation", 3);
+        analyzer.add_suspicious_event_id(4720, "account_created", 6);
+        analyzer.add_suspicious_event_id(7045, "service_installed", 7);
+        analyzer.add_suspicious_event_id(1102, "audit_log_cleared", 9);
+        
+        // Add default patterns
+        analyzer.add_pattern(r"(?i)mimikatz|sekurlsa", "credential_dumping", 10);
+        analyzer.add_pattern(r"(?i)powershell(\.exe)?.*\s-e(nc|ncodedcommand)?\s", "encoded_powershell", 8);
+        analyzer.add_pattern(r"(?i)vssadmin.*delete\s+shadows", "shadow_copy_deletion", 9);
+        analyzer.add_pattern(r"(?i)psexe(c|svc)", "lateral_movement", 8);
+        analyzer.add_pattern(r"(?i)malware|backdoor|trojan", "malware_indicator", 9);
+        
+        analyzer
+    }
+    
+    /// Add a pattern to look for
+    pub fn add_pattern(&mut self, pattern: &str, detection_type: &str, severity: u8) {
+        match compile_pattern(pattern) {
+            Ok(regex) => self.patterns.push((regex, detection_type.to_string(), Severity0to10::clamped(severity))),
+            Err(e) => tracing::warn!("Ignoring pattern: {}", e),
+        }
+    }
+    
+    /// Replace the severity of the patterns and event IDs added so far, keyed by detection type
+    pub fn with_severity_overrides(mut self, overrides: &HashMap<String, u8>) -> Self {
+        let rules = self
+            .patterns
+            .iter_mut()
+            .map(|(_, detection_type, severity)| (&*detection_type, severity))
+            .chain(self.suspicious_event_ids.values_mut().map(|(detection_type, severity)| (&*detection_type, severity)));
+        
+        for (detection_type, severity) in rules {
+            if let Some(&new_severity) = overrides.get(detection_type) {
+                *severity = Severity0to10::clamped(new_severity);
+            }
+        }
+        self
+    }
+    
+    /// Report every record with the given event ID
+    pub fn add_suspicious_event_id(&mut self, event_id: u32, detection_type: &str, severity: u8) {
+        self.suspicious_event_ids.insert(event_id, (detection_type.to_string(), Severity0to10::clamped(severity)));
+    }
+    
+    /// Read the event ID, which is either a number or `{"#text": number}`
+    fn event_id(system: &serde_json::Value) -> Option<u32> {
+        let id = system.get("EventID")?;
+        id.get("#text")
+            .unwrap_or(id)
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+    }
+    
+    /// Look for detections in one record, as the `evtx` crate renders it to JSON
+    fn analyze_record(
+        &self,
+        record: &serde_json::Value,
+        record_id: u64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<DetectionResult> {
         let mut results = Vec::new();
+        let event = &record["Event"];
+        let system = &event["System"];
+        let event_id = Self::event_id(system);
+        let channel = system["Channel"].as_str().unwrap_or_default().to_string();
         
-        for (line_idx, line) in content.lines().enumerate() {
-            for (pattern, detection_type, severity) in &self.patterns {
-                if let Some(captures) = pattern.captures(line) {
-                    let matched_text = captures.get(0).map_or("", |m| m.as_str());
-                    
-                    let mut details = HashMap::new();
-                    details.insert("matched_text".to_string(), matched_text.to_string());
-                    details.insert("full_line".to_string(), line.to_string());
-                    
-                    results.push(DetectionResult {
-                        detection_type: detection_type.clone(),
-                        severity: *severity,
-                        location: format!("line:{}", line_idx + 1),
-                        details,
-                        timestamp: chrono::Utc::now(),
-                    });
-                }
+        // The log only holds the message parameters, not the rendered text
+        let message = event
+            .get("EventData")
+            .or_else(|| event.get("UserData"))
+            .map(|data| data.to_string())
+            .unwrap_or_default();
+        
+        let location = format!("record:{}", record_id);
+        let detection = |detection_type: &str, severity: Severity0to10, matched_text: &str| {
+            let mut details = HashMap::new();
+            details.insert("matched_text".to_string(), matched_text.to_string());
+            details.insert("channel".to_string(), channel.clone());
+            details.insert("message".to_string(), message.clone());
+            if let Some(event_id) = event_id {
+                details.insert("event_id".to_string(), event_id.to_string());
+            }
+            
+            DetectionResult {
+                detection_type: detection_type.to_string(),
+                severity,
+                location: location.clone(),
+                details,
+                timestamp,
+            }
+        };
+        
+        if let Some((id, (detection_type, severity))) =
+            event_id.and_then(|id| Some((id, self.suspicious_event_ids.get(&id)?)))
+        {
+            results.push(detection(detection_type.as_str(), *severity, &format!("EventID {}", id)));
+        }
+        
+        for (pattern, detection_type, severity) in &self.patterns {
+            if let Some(matched) = pattern.find(&message) {
+                results.push(detection(detection_type.as_str(), *severity, matched.as_str()));
             }
         }
         
+        results
+    }
+}
+
+#[cfg(feature = "evtx")]
+impl FileAnalyzer for EvtxAnalyzer {
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError> {
+        let mut parser = evtx::EvtxParser::from_path(path)
+            .map_err(|e| FormatsError::ParseError(format!("Failed to open EVTX file: {}", e)))?;
+        let mut results = Vec::new();
+        
+        for record in parser.records_json_value() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    // A damaged record shouldn't hide the rest of the log
+                    tracing::warn!("Skipping unreadable EVTX record: {}", e);
+                    continue;
+                }
+            };
+            
+            results.extend(self.analyze_record(&record.data, record.event_record_id, record.timestamp));
+        }
+        
         Ok(results)
     }
     
     fn supported_format(&self) -> FileFormat {
-        FileFormat::Log
+        FileFormat::Evtx
     }
 }
+
+/// YARA rule scanner, run on every file whatever its format
+#[cfg(feature = "yara")]
+pub struct YaraAnalyzer {
+    /// Compiled rules
+    rules: yara::Rules,
+    
+    /// Scan timeout in seconds
+    timeout_secs: i32,
+    
+    /// Severity reported for rules without a `severity` meta
+    default_severity: Severity0to10,
+}
+
+#[cfg(feature = "yara")]
+impl YaraAnalyzer {
+    /// Compile every `.yar`/`.yara` file in a directory
+    pub fn from_dir<P: AsRef<Path>>(rules_dir: P) -> Result<Self, FormatsError> {
+        let rules_dir = rules_dir.as_ref();
+        let mut compiler = yara::Compiler::new().map_err(yara::Error::from)?;
+        let mut count = 0;
+        
+        let mut entries: Vec<_> = std::fs::read_dir(rules_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("yar") || ext.eq_ignore_ascii_case("yara"))
+            })
+            .collect();
+        entries.sort();
+        
+        for path in entries {
+            compiler = compiler.add_rules_file(&path)?;
+            count += 1;
+        }
+        
+        if count == 0 {
+            return Err(FormatsError::FileNotFound(format!(
+                "No YARA rules in {}",
+                rules_dir.display()
+            )));
+        }
+        
+        tracing::info!("Compiled {} YARA rule files from {}", count, rules_dir.display());
+        
+        Ok(Self {
+            rules: compiler.compile_rules().map_err(yara::Error::from)?,
+            timeout_secs: 30,
+            default_severity: Severity0to10::clamped(8),
+        })
+    }
+    
+    /// Set the scan timeout
+    pub fn with_timeout(mut self, timeout_secs: i32) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+    
+    /// Set the severity of rules without a `severity` meta
+    pub fn with_default_severity(mut self, severity: u8) -> Self {
+        self.default_severity = Severity0to10::clamped(severity);
+        self
+    }
+}
+
+#[cfg(feature = "yara")]
+impl FileAnalyzer for YaraAnalyzer {
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError> {
+        let matches = self.rules.scan_file(path, self.timeout_secs)?;
+        
+        let results = matches
+            .into_iter()
+            .map(|rule| {
+                let mut offsets: Vec<usize> = rule
+                    .strings
+                    .iter()
+                    .flat_map(|string| string.matches.iter().map(|m| m.offset))
+                    .collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                
+                let location = offsets
+                    .iter()
+                    .map(|offset| format!("0x{:x}", offset))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                
+                // Rules can carry their own severity, e.g. `severity = 10`
+                let severity = rule
+                    .metadatas
+                    .iter()
+                    .find(|meta| meta.identifier == "severity")
+                    .and_then(|meta| match meta.value {
+                        yara::MetadataValue::Integer(value) => u8::try_from(value).ok().map(Severity0to10::clamped),
+                        _ => None,
+                    })
+                    .unwrap_or(self.default_severity);
+                
+                let strings: Vec<&str> = rule.strings.iter().map(|string| string.identifier).collect();
+                
+                let mut details = HashMap::new();
+                details.insert("namespace".to_string(), rule.namespace.to_string());
+                details.insert("tags".to_string(), rule.tags.join(","));
+                details.insert("matched_strings".to_string(), strings.join(","));
+                
+                DetectionResult {
+                    detection_type: rule.identifier.to_string(),
+                    severity,
+                    location: format!("offsets:{}", location),
+                    details,
+                    timestamp: chrono::Utc::now(),
+                }
+            })
+            .collect();
+        
+        Ok(results)
+    }
+    
+    fn supported_format(&self) -> FileFormat {
+        FileFormat::Unknown
+    }
+    
+    fn applies_to(&self, _format: &FileFormat) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,290 @@
+//! Analyzers loaded from shared libraries
+//!
+//! A plugin is a `cdylib` exporting a `camaleon_formats_plugin` function
+//! that returns a [`PluginDescriptor`]: the plugin name, the file format it
+//! handles and its pattern set. Every field is copied when the plugin is
+//! loaded and no plugin code runs during analysis, which scans the file line
+//! by line like [`LogAnalyzer`](crate::LogAnalyzer).
+//!
+//! A minimal plugin:
+//!
+//! ```ignore
+//! use formats::plugin::{PluginDescriptor, PluginPattern, PLUGIN_ABI_VERSION};
+//!
+//! static PATTERNS: [PluginPattern; 1] = [PluginPattern {
+//!     pattern: b"(?i)mimikatz\0".as_ptr().cast(),
+//!     detection_type: b"credential_dumping\0".as_ptr().cast(),
+//!     severity: 9,
+//! }];
+//!
+//! static DESCRIPTOR: PluginDescriptor = PluginDescriptor {
+//!     abi_version: PLUGIN_ABI_VERSION,
+//!     name: b"mimikatz\0".as_ptr().cast(),
+//!     format: b"log\0".as_ptr().cast(),
+//!     patterns: PATTERNS.as_ptr(),
+//!     pattern_count: PATTERNS.len(),
+//! };
+//!
+//! #[no_mangle]
+//! pub extern "C" fn camaleon_formats_plugin() -> *const PluginDescriptor {
+//!     &DESCRIPTOR
+//! }
+//! ```
+
+use chame_core::Severity0to10;
+use crate::{DetectionResult, FileAnalyzer, FileFormat, FormatsError, PatternSet};
+use std::ffi::{c_char, CStr};
+use std::path::{Path, PathBuf};
+
+/// Version of the plugin interface, changed whenever its layout changes
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports
+pub const PLUGIN_ENTRY_POINT: &str = "camaleon_formats_plugin";
+
+/// Signature of the plugin entry point
+pub type PluginEntryPoint = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+/// A pattern of a plugin, as in `LogAnalyzer::add_pattern`
+#[repr(C)]
+pub struct PluginPattern {
+    /// Regular expression, a NUL-terminated UTF-8 string
+    pub pattern: *const c_char,
+
+    /// Detection type reported on a match, a NUL-terminated UTF-8 string
+    pub detection_type: *const c_char,
+
+    /// Severity (0-10)
+    pub severity: u8,
+}
+
+/// What a plugin's entry point returns
+///
+/// The descriptor and everything it points to must stay valid until the
+/// entry point is called again or the library is unloaded.
+#[repr(C)]
+pub struct PluginDescriptor {
+    /// Must be [`PLUGIN_ABI_VERSION`]
+    pub abi_version: u32,
+
+    /// Plugin name, a NUL-terminated UTF-8 string
+    pub name: *const c_char,
+
+    /// Extension of the files to analyze (`log`, `csv`, `vmdk`...), a NUL-terminated UTF-8 string
+    pub format: *const c_char,
+
+    /// First of `pattern_count` patterns
+    pub patterns: *const PluginPattern,
+
+    /// Number of patterns
+    pub pattern_count: usize,
+}
+
+// Plugins point to immutable statics
+unsafe impl Sync for PluginPattern {}
+unsafe impl Sync for PluginDescriptor {}
+
+/// Analyzer built from a plugin's descriptor
+pub struct PluginAnalyzer {
+    /// Plugin name
+    name: String,
+
+    /// Format the plugin handles
+    format: FileFormat,
+
+    /// Patterns to look for
+    patterns: PatternSet,
+
+    /// Kept loaded for as long as the analyzer lives
+    _library: Option<libloading::Library>,
+}
+
+impl PluginAnalyzer {
+    /// Load the plugin at `path`
+    pub fn load(path: &Path) -> Result<Self, FormatsError> {
+        let plugin_error = |e: libloading::Error| FormatsError::Plugin(format!("{}: {}", path.display(), e));
+
+        // SAFETY: loading runs the library's initializers, plugins are trusted
+        // code installed by the administrator like the binary itself.
+        let library = unsafe { libloading::Library::new(path) }.map_err(plugin_error)?;
+        let descriptor = unsafe {
+            let entry_point = library
+                .get::<PluginEntryPoint>(PLUGIN_ENTRY_POINT.as_bytes())
+                .map_err(plugin_error)?;
+            entry_point()
+        };
+
+        // SAFETY: the descriptor is valid while the library is loaded
+        let mut analyzer = unsafe { Self::from_descriptor(descriptor) }?;
+        analyzer._library = Some(library);
+
+        Ok(analyzer)
+    }
+
+    /// Build an analyzer from a descriptor, copying everything it points to
+    ///
+    /// # Safety
+    ///
+    /// `descriptor` must be null or point to a descriptor laid out as
+    /// documented on [`PluginDescriptor`].
+    pub unsafe fn from_descriptor(descriptor: *const PluginDescriptor) -> Result<Self, FormatsError> {
+        let descriptor = descriptor
+            .as_ref()
+            .ok_or_else(|| FormatsError::Plugin("The plugin returned no descriptor".to_string()))?;
+        if descriptor.abi_version != PLUGIN_ABI_VERSION {
+            return Err(FormatsError::Plugin(format!(
+                "Unsupported plugin ABI version {}, expected {}",
+                descriptor.abi_version, PLUGIN_ABI_VERSION
+            )));
+        }
+
+        let name = string(descriptor.name, "name")?;
+        let format = FileFormat::from_extension(&string(descriptor.format, "format")?);
+        if format == FileFormat::Unknown {
+            return Err(FormatsError::Plugin(format!("Plugin {} declares an unknown format", name)));
+        }
+
+        let patterns = match descriptor.pattern_count {
+            0 => &[][..],
+            _ if descriptor.patterns.is_null() => {
+                return Err(FormatsError::Plugin(format!("Plugin {} has no patterns", name)));
+            }
+            count => std::slice::from_raw_parts(descriptor.patterns, count),
+        };
+
+        let mut pattern_set = PatternSet::new();
+        for pattern in patterns {
+            let detection_type = string(pattern.detection_type, "detection_type")?;
+            pattern_set.add(&string(pattern.pattern, "pattern")?, &detection_type, Severity0to10::clamped(pattern.severity));
+        }
+
+        Ok(Self {
+            name,
+            format,
+            patterns: pattern_set,
+            _library: None,
+        })
+    }
+
+    /// Plugin name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl FileAnalyzer for PluginAnalyzer {
+    fn analyze(&self, path: &Path) -> Result<Vec<DetectionResult>, FormatsError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut results = self.patterns.scan_lines(&content);
+        for result in &mut results {
+            result.details.insert("plugin".to_string(), self.name.clone());
+        }
+
+        Ok(results)
+    }
+
+    fn supported_format(&self) -> FileFormat {
+        self.format.clone()
+    }
+}
+
+/// Shared libraries in `dir`, sorted by name
+pub(crate) fn plugin_paths(dir: &Path) -> Result<Vec<PathBuf>, FormatsError> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+        .collect();
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Copy a NUL-terminated UTF-8 string from a descriptor
+unsafe fn string(ptr: *const c_char, field: &str) -> Result<String, FormatsError> {
+    if ptr.is_null() {
+        return Err(FormatsError::Plugin(format!("Missing {}", field)));
+    }
+
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(str::to_string)
+        .map_err(|_| FormatsError::Plugin(format!("{} is not valid UTF-8", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Formats;
+    use std::io::Write;
+
+    static PATTERNS: [PluginPattern; 2] = [
+        PluginPattern {
+            pattern: b"(?i)mimikatz\0".as_ptr().cast(),
+            detection_type: b"credential_dumping\0".as_ptr().cast(),
+            severity: 9,
+        },
+        PluginPattern {
+            pattern: b"vssadmin delete shadows\0".as_ptr().cast(),
+            detection_type: b"shadow_copy_deletion\0".as_ptr().cast(),
+            severity: 12,
+        },
+    ];
+
+    /// Descriptor of a test plugin, `format` ending with a NUL
+    fn descriptor(format: &'static [u8]) -> PluginDescriptor {
+        PluginDescriptor {
+            abi_version: PLUGIN_ABI_VERSION,
+            name: b"windows_tools\0".as_ptr().cast(),
+            format: format.as_ptr().cast(),
+            patterns: PATTERNS.as_ptr(),
+            pattern_count: PATTERNS.len(),
+        }
+    }
+
+    #[test]
+    fn test_analyzer_from_descriptor() {
+        let analyzer = unsafe { PluginAnalyzer::from_descriptor(&descriptor(b"log\0")) }.unwrap();
+        assert_eq!(analyzer.name(), "windows_tools");
+        assert_eq!(analyzer.supported_format(), FileFormat::Log);
+
+        let mut file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+        writeln!(file, "user ran MimiKatz.exe").unwrap();
+        writeln!(file, "nothing to see").unwrap();
+        writeln!(file, "cmd: vssadmin delete shadows /all").unwrap();
+
+        let results = analyzer.analyze(file.path()).unwrap();
+        let found: Vec<_> = results
+            .iter()
+            .map(|r| (r.detection_type.as_str(), r.severity.get(), r.location.as_str()))
+            .collect();
+        assert_eq!(found, [("credential_dumping", 9, "line:1"), ("shadow_copy_deletion", 10, "line:3")]);
+        assert_eq!(results[0].details["plugin"], "windows_tools");
+    }
+
+    #[test]
+    fn test_invalid_descriptors_are_rejected() {
+        assert!(unsafe { PluginAnalyzer::from_descriptor(std::ptr::null()) }.is_err());
+        assert!(unsafe { PluginAnalyzer::from_descriptor(&descriptor(b"exe\0")) }.is_err());
+
+        let old = PluginDescriptor { abi_version: 0, ..descriptor(b"log\0") };
+        let error = unsafe { PluginAnalyzer::from_descriptor(&old) }.err().unwrap();
+        assert!(error.to_string().contains("ABI version 0"), "{}", error);
+
+        let no_name = PluginDescriptor { name: std::ptr::null(), ..descriptor(b"log\0") };
+        assert!(unsafe { PluginAnalyzer::from_descriptor(&no_name) }.is_err());
+    }
+
+    #[test]
+    fn test_load_plugins_skips_invalid_libraries() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join(format!("broken.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&library, b"not a shared library").unwrap();
+        std::fs::write(dir.path().join("README.txt"), b"plugins go here").unwrap();
+        assert_eq!(plugin_paths(dir.path()).unwrap(), [library]);
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(16);
+        let mut formats = Formats::new(tx);
+        assert!(formats.load_plugins(dir.path()).unwrap().is_empty());
+        assert!(formats.load_plugins(dir.path().join("missing")).is_err());
+    }
+}
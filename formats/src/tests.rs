@@ -1,102 +1,510 @@
-#[cfg(test)]
-mod tests {
-    use formats::{FileAnalyzer, FileFormat, FormatsError};
-    use std::collections::HashMap;
-    use std::path::Path;
-    use tempfile::NamedTempFile;
-    use std::io::Write;
-
-    #[test]
-    fn test_csv_analyzer() {
-        // Create a temporary CSV file with test data
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "id,name,description,status").unwrap();
-        writeln!(temp_file, "1,test1,normal activity,ok").unwrap();
-        writeln!(temp_file, "2,test2,suspicious activity detected,warning").unwrap();
-        writeln!(temp_file, "3,test3,ransomware indicators found,critical").unwrap();
-        writeln!(temp_file, "4,test4,potential phishing attempt,warning").unwrap();
-        
-        // Create CSV analyzer
-        let analyzer = super::CsvAnalyzer::new();
-        
-        // Analyze the file
-        let results = analyzer.analyze(temp_file.path()).unwrap();
-        
-        // Verify results
-        assert_eq!(results.len(), 3); // Should detect 3 issues: suspicious, ransomware, phishing
-        
-        // Check for ransomware detection
-        let ransomware_detection = results.iter().find(|r| r.detection_type == "ransomware_indicator").unwrap();
-        assert_eq!(ransomware_detection.severity, 9);
-        assert!(ransomware_detection.location.contains("row:3"));
-        
-        // Check for phishing detection
-        let phishing_detection = results.iter().find(|r| r.detection_type == "phishing_indicator").unwrap();
-        assert_eq!(phishing_detection.severity, 7);
-        assert!(phishing_detection.location.contains("row:4"));
-        
-        // Check for suspicious activity detection
-        let suspicious_detection = results.iter().find(|r| r.detection_type == "suspicious_activity").unwrap();
-        assert_eq!(suspicious_detection.severity, 5);
-        assert!(suspicious_detection.location.contains("row:2"));
-    }
+use crate::{FileAnalyzer, FileFormat, Formats};
+use std::collections::HashMap;
+use std::path::Path;
+use tempfile::NamedTempFile;
+use std::io::Write;
 
-    #[test]
-    fn test_log_analyzer() {
-        // Create a temporary log file with test data
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "2025-05-28 05:30:00 INFO: System started normally").unwrap();
-        writeln!(temp_file, "2025-05-28 05:35:12 WARNING: Failed login attempt from 192.168.1.100").unwrap();
-        writeln!(temp_file, "2025-05-28 05:36:45 ERROR: Multiple authentication failures, possible brute force attempt").unwrap();
-        writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: Suspicious activity detected, possible malware execution").unwrap();
-        
-        // Create Log analyzer
-        let analyzer = super::LogAnalyzer::new();
-        
-        // Analyze the file
-        let results = analyzer.analyze(temp_file.path()).unwrap();
-        
-        // Verify results
-        assert!(results.len() >= 2); // Should detect at least 2 issues: auth failure and brute force
-        
-        // Check for brute force detection
-        let brute_force = results.iter().find(|r| r.detection_type == "brute_force_attempt");
-        assert!(brute_force.is_some());
-        
-        // Check for auth failure detection
-        let auth_failure = results.iter().find(|r| r.detection_type == "auth_failure");
-        assert!(auth_failure.is_some());
-        
-        // Check for malware indicator detection
-        let malware = results.iter().find(|r| r.detection_type == "malware_indicator");
-        assert!(malware.is_some());
-    }
+#[test]
+fn test_csv_analyzer() {
+    // Create a temporary CSV file with test data
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "id,name,description,status").unwrap();
+    writeln!(temp_file, "1,test1,normal activity,ok").unwrap();
+    writeln!(temp_file, "2,test2,suspicious activity detected,warning").unwrap();
+    writeln!(temp_file, "3,test3,ransomware indicators found,critical").unwrap();
+    writeln!(temp_file, "4,test4,potential phishing attempt,warning").unwrap();
+    
+    // Create CSV analyzer
+    let analyzer = crate::CsvAnalyzer::new();
+    
+    // Analyze the file
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    
+    // Verify results
+    assert_eq!(results.len(), 3); // Should detect 3 issues: suspicious, ransomware, phishing
+    
+    // Check for ransomware detection
+    let ransomware_detection = results.iter().find(|r| r.detection_type == "ransomware_indicator").unwrap();
+    assert_eq!(ransomware_detection.severity, 9);
+    assert!(ransomware_detection.location.contains("row:3"));
+    
+    // Check for phishing detection
+    let phishing_detection = results.iter().find(|r| r.detection_type == "phishing_indicator").unwrap();
+    assert_eq!(phishing_detection.severity, 7);
+    assert!(phishing_detection.location.contains("row:4"));
+    
+    // Check for suspicious activity detection
+    let suspicious_detection = results.iter().find(|r| r.detection_type == "suspicious_activity").unwrap();
+    assert_eq!(suspicious_detection.severity, 5);
+    assert!(suspicious_detection.location.contains("row:2"));
+}
 
-    #[test]
-    fn test_file_format_detection() {
-        assert_eq!(FileFormat::from_extension("csv"), FileFormat::Csv);
-        assert_eq!(FileFormat::from_extension("log"), FileFormat::Log);
-        assert_eq!(FileFormat::from_extension("txt"), FileFormat::Log);
-        assert_eq!(FileFormat::from_extension("vmdk"), FileFormat::Vmdk);
-        assert_eq!(FileFormat::from_extension("unknown"), FileFormat::Unknown);
-        
-        assert_eq!(FileFormat::from_path(Path::new("test.csv")), FileFormat::Csv);
-        assert_eq!(FileFormat::from_path(Path::new("test.log")), FileFormat::Log);
-        assert_eq!(FileFormat::from_path(Path::new("test.vmdk")), FileFormat::Vmdk);
-        assert_eq!(FileFormat::from_path(Path::new("test")), FileFormat::Unknown);
-    }
+#[test]
+fn test_csv_analyzer_scans_only_configured_columns() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "id,description,notes").unwrap();
+    writeln!(temp_file, "1,suspicious login from new device,reviewed").unwrap();
+    writeln!(temp_file, "2,routine backup,suspicious? no - checked by ops").unwrap();
+    writeln!(temp_file, "3,CVE-2024-3400 probe,ransomware playbook attached").unwrap();
+    
+    // Every field is scanned by default, notes included
+    let results = crate::CsvAnalyzer::new().analyze(temp_file.path()).unwrap();
+    assert_eq!(results.len(), 3);
+    
+    let analyzer = crate::CsvAnalyzer::new()
+        .with_columns(&[crate::CsvColumnConfig::new("description")])
+        .unwrap();
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].location, "row:1,col:2");
+    assert_eq!(results[0].details["column_name"], "description");
+    
+    // A column can bring its own patterns, missing columns are skipped
+    let cve = crate::PatternRule {
+        pattern: r"CVE-\d{4}-\d+".to_string(),
+        detection_type: "cve_reference".to_string(),
+        severity: chame_core::Severity0to10::clamped(6),
+    };
+    let analyzer = crate::CsvAnalyzer::new()
+        .with_columns(&[
+            crate::CsvColumnConfig::new(1).with_patterns(vec![cve]),
+            crate::CsvColumnConfig::new("owner"),
+        ])
+        .unwrap();
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!((results[0].detection_type.as_str(), results[0].location.as_str()), ("cve_reference", "row:3,col:2"));
+    
+    let broken = crate::CsvColumnConfig::new("notes").with_patterns(Vec::from([crate::PatternRule {
+        pattern: "(".to_string(),
+        detection_type: "broken".to_string(),
+        severity: chame_core::Severity0to10::MAX,
+    }]));
+    assert!(matches!(
+        crate::CsvAnalyzer::new().with_columns(&[broken]),
+        Err(crate::FormatsError::InvalidPattern(_))
+    ));
+}
+
+#[test]
+fn test_log_analyzer() {
+    // Create a temporary log file with test data
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "2025-05-28 05:30:00 INFO: System started normally").unwrap();
+    writeln!(temp_file, "2025-05-28 05:35:12 WARNING: Failed login attempt from 192.168.1.100").unwrap();
+    writeln!(temp_file, "2025-05-28 05:36:45 ERROR: Multiple authentication failures, possible brute force attempt").unwrap();
+    writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: Suspicious activity detected, possible malware execution").unwrap();
+    
+    // Create Log analyzer
+    let analyzer = crate::LogAnalyzer::new();
+    
+    // Analyze the file
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    
+    // Verify results
+    assert!(results.len() >= 2); // Should detect at least 2 issues: auth failure and brute force
+    
+    // Check for brute force detection
+    let brute_force = results.iter().find(|r| r.detection_type == "brute_force_attempt");
+    assert!(brute_force.is_some());
+    
+    // Check for auth failure detection
+    let auth_failure = results.iter().find(|r| r.detection_type == "auth_failure");
+    assert!(auth_failure.is_some());
+    
+    // Check for malware indicator detection
+    let malware = results.iter().find(|r| r.detection_type == "malware_indicator");
+    assert!(malware.is_some());
+}
+
+#[test]
+fn test_log_analyzer_merges_overlapping_detections() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: malware dropped via exploit").unwrap();
+    writeln!(temp_file, "2025-05-28 05:41:00 WARNING: Failed login attempt from 192.168.1.100").unwrap();
+    
+    // Without the flag every pattern reports separately
+    let analyzer = crate::LogAnalyzer::new();
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.iter().filter(|r| r.location == "line:1").count(), 2);
+    
+    let analyzer = crate::LogAnalyzer::new().with_merge_overlapping(true);
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.len(), 2);
+    
+    let merged = &results[0];
+    assert_eq!(merged.location, "line:1");
+    assert_eq!(merged.detection_type, "malware_indicator");
+    assert_eq!(merged.severity, 9);
+    assert_eq!(merged.details["matched_types"], "malware_indicator,exploit_attempt");
+}
+
+#[test]
+fn test_merge_overlapping_keeps_most_severe() {
+    let detection = |detection_type: &str, severity: u8, location: &str| crate::DetectionResult {
+        detection_type: detection_type.to_string(),
+        severity: chame_core::Severity0to10::clamped(severity),
+        location: location.to_string(),
+        details: HashMap::from([("source".to_string(), detection_type.to_string())]),
+        timestamp: chrono::Utc::now(),
+    };
+    
+    let merged = crate::merge_overlapping(vec![
+        detection("suspicious_activity", 5, "line:2"),
+        detection("brute_force", 6, "line:1"),
+        detection("ransomware_indicator", 9, "line:2"),
+        detection("suspicious_activity", 5, "line:2"),
+    ]);
+    
+    // Locations keep their first-seen order, each type is listed once
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].location, "line:2");
+    assert_eq!(merged[0].detection_type, "ransomware_indicator");
+    assert_eq!(merged[0].details["source"], "ransomware_indicator");
+    assert_eq!(merged[0].details["matched_types"], "suspicious_activity,ransomware_indicator");
+    assert_eq!(merged[1].location, "line:1");
+    assert_eq!(merged[1].details["matched_types"], "brute_force");
+}
+
+#[test]
+fn test_added_patterns_join_the_set() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "2025-05-28 05:30:00 INFO: cobalt strike beacon checked in").unwrap();
+    
+    let mut analyzer = crate::LogAnalyzer::new();
+    analyzer.add_pattern(r"(", "broken", 10);
+    analyzer.add_pattern(r"(?i)cobalt\s*strike", "c2_beacon", 10);
+    
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "c2_beacon");
+    assert_eq!(results[0].details["matched_text"], "cobalt strike");
+}
+
+#[test]
+fn test_pathological_patterns_are_bounded() {
+    let rule = |pattern: &str| crate::PatternRule {
+        pattern: pattern.to_string(),
+        detection_type: "redos".to_string(),
+        severity: chame_core::Severity0to10::MAX,
+    };
+    
+    // Blows up a backtracking engine, the line is still scanned in linear time
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "{}!", "a".repeat(50_000)).unwrap();
+    let analyzer = crate::LogAnalyzer::new();
+    analyzer.live_patterns().replace(&[rule(r"^(a+)+$"), rule(r"(a|aa)*c")]).unwrap();
+    let started = std::time::Instant::now();
+    assert!(analyzer.analyze(temp_file.path()).unwrap().is_empty());
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "{:?}", started.elapsed());
+    
+    // Nested repetitions compile past the size limit and are refused
+    let error = analyzer.live_patterns().replace(&[rule(r"(?:\w{100}){100}")]).unwrap_err();
+    assert!(error.to_string().contains("size limit"), "{}", error);
+    assert_eq!(analyzer.live_patterns().rules().len(), 2);
+}
+
+#[tokio::test]
+async fn test_min_severity_suppresses_detections_and_events() {
+    let mut temp_file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+    writeln!(temp_file, "2025-05-28 05:36:00 WARNING: permission denied for /etc/shadow").unwrap();
+    writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: backdoor installed").unwrap();
+    
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx).with_min_severity(8);
+    
+    let results = formats.analyze_file(temp_file.path()).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "backdoor_indicator");
+    assert_eq!(formats.suppressed_count(), 1);
+    
+    // Only the kept detection was turned into an event
+    let event = rx.try_recv().unwrap();
+    assert_eq!(event.schema_version(), chame_core::DETECTION_SCHEMA_VERSION);
+    assert_eq!(event.data.unwrap()["detection_type"], "backdoor_indicator");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_events_carry_detection_severity() {
+    let mut temp_file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+    writeln!(temp_file, "2025-05-28 05:36:00 WARNING: permission denied for /etc/shadow").unwrap();
+    writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: backdoor installed").unwrap();
+    
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx);
+    formats.analyze_file(temp_file.path()).await.unwrap();
+    
+    let event = rx.try_recv().unwrap();
+    assert_eq!(event.data.as_ref().unwrap()["severity"], 5);
+    assert_eq!(event.severity(), chame_core::Severity::Medium);
+    assert_eq!(rx.try_recv().unwrap().severity(), chame_core::Severity::High);
+}
+
+#[tokio::test]
+async fn test_severity_overrides_remap_results_and_events() {
+    let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+    writeln!(temp_file, "id,description").unwrap();
+    writeln!(temp_file, "1,potential phishing attempt").unwrap();
+    writeln!(temp_file, "2,suspicious login").unwrap();
+    
+    let overrides = HashMap::from([("phishing_indicator".to_string(), 10), ("suspicious_activity".to_string(), 2)]);
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx).with_severity_overrides(overrides.clone()).with_min_severity(3);
+    
+    // The overridden severity is the one filtered on and reported
+    let results = formats.analyze_file(temp_file.path()).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "phishing_indicator");
+    assert_eq!(results[0].severity, 10);
+    assert_eq!(formats.suppressed_count(), 1);
+    
+    let event = rx.try_recv().unwrap();
+    assert_eq!(event.severity(), chame_core::Severity::Critical);
+    assert_eq!(event.data.unwrap()["severity"], 10);
+    
+    // Analyzers remap their patterns when used on their own
+    let analyzer = crate::CsvAnalyzer::new().with_severity_overrides(&overrides);
+    let results = analyzer.analyze(temp_file.path()).unwrap();
+    assert_eq!(results.iter().map(|r| r.severity).collect::<Vec<_>>(), [10, 2]);
+}
+
+#[tokio::test]
+async fn test_replace_patterns_swaps_whole_sets() {
+    let mut temp_file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+    writeln!(temp_file, "2025-05-28 05:40:22 CRITICAL: backdoor installed").unwrap();
+    writeln!(temp_file, "2025-05-28 05:41:03 beacon to cobalt strike server").unwrap();
+    
+    let (tx, _rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx);
+    let rule = |pattern: &str, detection_type: &str| crate::PatternRule {
+        pattern: pattern.to_string(),
+        detection_type: detection_type.to_string(),
+        severity: chame_core::Severity0to10::MAX,
+    };
+    
+    // One invalid pattern rejects the update, every set is kept
+    let defaults = formats.patterns();
+    let invalid = HashMap::from([
+        (FileFormat::Csv, vec![rule("(?i)lockbit", "ransomware_lockbit")]),
+        (FileFormat::Log, vec![rule(r"(?i)cobalt\s*strike", "c2_beacon"), rule("(", "broken")]),
+    ]);
+    assert!(matches!(formats.replace_patterns(&invalid), Err(crate::FormatsError::InvalidPattern(_))));
+    assert_eq!(formats.patterns(), defaults);
+    
+    let update = HashMap::from([(FileFormat::Log, vec![rule(r"(?i)cobalt\s*strike", "c2_beacon")])]);
+    formats.replace_patterns(&update).unwrap();
+    assert_eq!(formats.patterns()[&FileFormat::Log], update[&FileFormat::Log]);
+    assert_eq!(formats.patterns()[&FileFormat::Csv].len(), 8);
+    
+    let results = formats.analyze_file(temp_file.path()).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!((results[0].detection_type.as_str(), results[0].location.as_str()), ("c2_beacon", "line:2"));
+    
+    let unsupported = HashMap::from([(FileFormat::Vmdk, Vec::new())]);
+    assert!(matches!(formats.replace_patterns(&unsupported), Err(crate::FormatsError::InvalidFormat(_))));
+}
+
+#[tokio::test]
+async fn test_batch_isolates_failing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("auth.log"), "backdoor installed\nnothing\n").unwrap();
+    std::fs::create_dir(dir.path().join("evidence")).unwrap();
+    std::fs::write(dir.path().join("evidence/damaged.log"), b"malware \xff\xfe truncated").unwrap();
+    std::fs::write(dir.path().join("evidence/accounts.csv"), "id,note\n1,ransomware\n").unwrap();
+    std::fs::write(dir.path().join("disk.img"), b"no analyzer for this").unwrap();
+    
+    let (tx, _rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx);
+    
+    let report = formats.analyze_dir(dir.path()).await.unwrap();
+    let paths: Vec<_> = report.files.iter().map(|file| file.path.strip_prefix(dir.path()).unwrap()).collect();
+    assert_eq!(paths, [Path::new("auth.log"), Path::new("evidence/accounts.csv"), Path::new("evidence/damaged.log")]);
+    assert_eq!((report.succeeded(), report.failed(), report.detection_count()), (2, 1, 2));
+    
+    let failures: Vec<_> = report.failures().collect();
+    assert!(failures[0].0.ends_with("damaged.log"));
+    assert!(matches!(failures[0].1, crate::FormatsError::Io(_)));
+    
+    // Explicit paths are reported even without an analyzer
+    let report = formats.analyze_files([dir.path().join("auth.log"), dir.path().join("disk.img")]).await;
+    assert_eq!((report.succeeded(), report.failed()), (1, 1));
+    assert!(formats.analyze_dir(dir.path().join("missing")).await.is_err());
+}
 
-    #[test]
-    fn test_error_handling() {
-        // Test file not found error
-        let analyzer = super::CsvAnalyzer::new();
-        let result = analyzer.analyze(Path::new("/nonexistent/file.csv"));
-        assert!(result.is_err());
-        
-        // Create an empty file (invalid CSV)
-        let temp_file = NamedTempFile::new().unwrap();
-        let result = analyzer.analyze(temp_file.path());
-        assert!(result.is_ok()); // Should not error on empty file, just return empty results
-        assert_eq!(result.unwrap().len(), 0);
+#[cfg(unix)]
+#[tokio::test]
+async fn test_analyze_dir_does_not_follow_symlinked_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("logs")).unwrap();
+    std::fs::write(dir.path().join("logs/auth.log"), "backdoor installed\n").unwrap();
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("logs/loop")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("logs/auth.log"), dir.path().join("linked.log")).unwrap();
+    
+    let (tx, _rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx);
+    
+    // The loop is skipped, symlinked files are still analyzed
+    let report = formats.analyze_dir(dir.path()).await.unwrap();
+    let paths: Vec<_> = report.files.iter().map(|file| file.path.strip_prefix(dir.path()).unwrap()).collect();
+    assert_eq!(paths, [Path::new("linked.log"), Path::new("logs/auth.log")]);
+    assert_eq!((report.succeeded(), report.detection_count()), (2, 2));
+}
+
+/// Analyzer failing on every log file
+struct BrokenAnalyzer;
+
+impl FileAnalyzer for BrokenAnalyzer {
+    fn analyze(&self, _path: &Path) -> Result<Vec<crate::DetectionResult>, crate::FormatsError> {
+        Err(crate::FormatsError::ParseError("unsupported encoding".to_string()))
     }
+    
+    fn supported_format(&self) -> FileFormat {
+        FileFormat::Log
+    }
+}
+
+#[tokio::test]
+async fn test_failing_analyzer_is_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("auth.log"), "backdoor installed\n").unwrap();
+    std::fs::write(dir.path().join("damaged.log"), b"malware \xff\xfe truncated").unwrap();
+    
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let mut formats = Formats::new(tx);
+    formats.register_analyzer(Box::new(BrokenAnalyzer));
+    
+    // The log analyzer still reports what it found
+    let results = formats.analyze_file(dir.path().join("auth.log")).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "backdoor_indicator");
+    assert!(rx.try_recv().is_ok());
+    
+    // A file none of the analyzers can read fails
+    assert!(formats.analyze_file(dir.path().join("damaged.log")).await.is_err());
+}
+
+#[test]
+fn test_file_format_detection() {
+    assert_eq!(FileFormat::from_extension("csv"), FileFormat::Csv);
+    assert_eq!(FileFormat::from_extension("log"), FileFormat::Log);
+    assert_eq!(FileFormat::from_extension("txt"), FileFormat::Log);
+    assert_eq!(FileFormat::from_extension("vmdk"), FileFormat::Vmdk);
+    assert_eq!(FileFormat::from_extension("EVTX"), FileFormat::Evtx);
+    assert_eq!(FileFormat::from_extension("unknown"), FileFormat::Unknown);
+    
+    assert_eq!(FileFormat::from_path(Path::new("test.csv")), FileFormat::Csv);
+    assert_eq!(FileFormat::from_path(Path::new("test.log")), FileFormat::Log);
+    assert_eq!(FileFormat::from_path(Path::new("test.vmdk")), FileFormat::Vmdk);
+    assert_eq!(FileFormat::from_path(Path::new("test")), FileFormat::Unknown);
+}
+
+#[test]
+fn test_error_handling() {
+    // Test file not found error
+    let analyzer = crate::CsvAnalyzer::new();
+    let result = analyzer.analyze(Path::new("/nonexistent/file.csv"));
+    assert!(result.is_err());
+    
+    // Create an empty file (invalid CSV)
+    let temp_file = NamedTempFile::new().unwrap();
+    let result = analyzer.analyze(temp_file.path());
+    assert!(result.is_ok()); // Should not error on empty file, just return empty results
+    assert_eq!(result.unwrap().len(), 0);
+}
+
+#[cfg(feature = "evtx")]
+#[test]
+fn test_evtx_analyzer() {
+    // Records as the evtx crate renders them from the event XML
+    let cleared = serde_json::json!({
+        "Event": {
+            "System": { "EventID": 1102, "Channel": "Security", "EventRecordID": 7 },
+            "UserData": { "LogFileCleared": { "SubjectUserName": "admin" } }
+        }
+    });
+    let process = serde_json::json!({
+        "Event": {
+            "System": {
+                "EventID": { "#attributes": { "Qualifiers": 0 }, "#text": 4688 },
+                "Channel": "Security"
+            },
+            "EventData": {
+                "NewProcessName": "C:\\Temp\\m.exe",
+                "CommandLine": "m.exe sekurlsa::logonpasswords"
+            }
+        }
+    });
+    let timestamp = chrono::Utc::now();
+    let analyzer = crate::EvtxAnalyzer::new();
+    
+    let results = analyzer.analyze_record(&cleared, 7, timestamp);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "audit_log_cleared");
+    assert_eq!(results[0].severity, 9);
+    assert_eq!(results[0].location, "record:7");
+    assert_eq!(results[0].details["channel"], "Security");
+    assert_eq!(results[0].details["event_id"], "1102");
+    
+    // The event ID and the message parameters are both checked
+    let results = analyzer.analyze_record(&process, 8, timestamp);
+    let types: Vec<_> = results.iter().map(|r| r.detection_type.as_str()).collect();
+    assert_eq!(types, ["process_creation", "credential_dumping"]);
+    assert_eq!(results[1].details["matched_text"], "sekurlsa");
+    assert_eq!(results[1].details["event_id"], "4688");
+    
+    // Anything but an EVTX file is a parse error
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "not an event log").unwrap();
+    assert!(matches!(analyzer.analyze(temp_file.path()), Err(crate::FormatsError::ParseError(_))));
+}
+
+#[cfg(feature = "yara")]
+#[tokio::test]
+async fn test_yara_rules_scan_every_file() {
+    let rules = tempfile::tempdir().unwrap();
+    std::fs::write(
+        rules.path().join("dropper.yar"),
+        r#"
+rule dropper : loader
+{
+    meta:
+        severity = 9
+    strings:
+        $url = "http://evil.example/payload"
+    condition:
+        $url
+}
+"#,
+    )
+    .unwrap();
+    
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let formats = Formats::new(tx).with_yara_rules(rules.path()).unwrap();
+    
+    // No extension, so only the YARA rules apply
+    let mut sample = NamedTempFile::new().unwrap();
+    sample.write_all(b"MZ\x90\x00 fetch http://evil.example/payload now").unwrap();
+    assert_eq!(FileFormat::from_path(sample.path()), FileFormat::Unknown);
+    
+    let results = formats.analyze_file(sample.path()).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].detection_type, "dropper");
+    assert_eq!(results[0].severity, 9);
+    assert_eq!(results[0].location, "offsets:0xb");
+    assert_eq!(results[0].details["tags"], "loader");
+    assert_eq!(results[0].details["matched_strings"], "$url");
+    assert!(rx.try_recv().is_ok());
+    
+    // They also run alongside the built-in analyzers
+    let mut log = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+    writeln!(log, "backdoor fetched http://evil.example/payload").unwrap();
+    let results = formats.analyze_file(log.path()).await.unwrap();
+    let mut types: Vec<_> = results.iter().map(|r| r.detection_type.as_str()).collect();
+    types.sort_unstable();
+    assert_eq!(types, ["backdoor_indicator", "dropper"]);
+    
+    let (tx, _rx) = tokio::sync::mpsc::channel(16);
+    let empty = tempfile::tempdir().unwrap();
+    assert!(Formats::new(tx).with_yara_rules(empty.path()).is_err());
 }
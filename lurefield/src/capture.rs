@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+/// Bytes sent by an attacker during one connection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Payload {
+    /// Captured bytes, at most the configured cap
+    pub bytes: Vec<u8>,
+
+    /// Whether the attacker sent more than the cap
+    pub truncated: bool,
+}
+
+impl Payload {
+    /// Lowercase hex encoding of the payload
+    pub fn hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Payload with non-printable bytes replaced by `.`, like a hexdump
+    pub fn printable(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect()
+    }
+
+    /// Names of the credential patterns found in the payload
+    pub fn credential_indicators(&self) -> Vec<&'static str> {
+        credential_indicators(&String::from_utf8_lossy(&self.bytes))
+    }
+
+    /// Interaction details for the payload
+    ///
+    /// The bytes themselves are only included when `log_keystroke` is set;
+    /// the size and credential flags always are.
+    pub fn details(&self, log_keystroke: bool) -> HashMap<String, String> {
+        let mut details = HashMap::new();
+        details.insert("bytes_received".to_string(), self.bytes.len().to_string());
+        details.insert("payload_truncated".to_string(), self.truncated.to_string());
+
+        if log_keystroke {
+            details.insert("payload_hex".to_string(), self.hex());
+            details.insert("payload_text".to_string(), self.printable());
+        }
+
+        let indicators = self.credential_indicators();
+        details.insert("credentials_detected".to_string(), (!indicators.is_empty()).to_string());
+        if !indicators.is_empty() {
+            details.insert("credential_patterns".to_string(), indicators.join(","));
+        }
+
+        details
+    }
+}
+
+/// Look for login attempts in the text an attacker sent
+pub fn credential_indicators(text: &str) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    let mut flag = |name: &'static str| {
+        if !found.contains(&name) {
+            found.push(name);
+        }
+    };
+
+    for line in text.lines() {
+        let line = line.trim().to_lowercase();
+
+        if line.starts_with("user ") {
+            flag("ftp_user");
+        }
+        if line.starts_with("pass ") {
+            flag("ftp_pass");
+        }
+        if line.starts_with("authorization: basic ") {
+            flag("http_basic_auth");
+        }
+        if line.starts_with("auth ") || line.contains(" login ") || line.starts_with("login ") {
+            flag("login_command");
+        }
+        if ["password=", "passwd=", "pwd=", "pass="].iter().any(|key| line.contains(key)) {
+            flag("password_field");
+        }
+        if ["username=", "user=", "login="].iter().any(|key| line.contains(key)) {
+            flag("username_field");
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_encodings() {
+        let payload = Payload {
+            bytes: b"ls -la\r\n\x00".to_vec(),
+            truncated: false,
+        };
+
+        assert_eq!(payload.hex(), "6c73202d6c610d0a00");
+        assert_eq!(payload.printable(), "ls -la...");
+    }
+
+    #[test]
+    fn test_credential_indicators() {
+        assert_eq!(credential_indicators("USER admin\r\nPASS hunter2\r\n"), ["ftp_user", "ftp_pass"]);
+        assert_eq!(
+            credential_indicators("GET / HTTP/1.1\r\nAuthorization: Basic YWRtaW46YWRtaW4=\r\n\r\n"),
+            ["http_basic_auth"]
+        );
+        assert_eq!(
+            credential_indicators("POST /login HTTP/1.1\r\n\r\nusername=root&password=toor"),
+            ["password_field", "username_field"]
+        );
+        assert!(credential_indicators("GET /index.html HTTP/1.1\r\n").is_empty());
+    }
+
+    #[test]
+    fn test_details_respect_log_keystroke() {
+        let payload = Payload {
+            bytes: b"PASS secret\r\n".to_vec(),
+            truncated: true,
+        };
+
+        let details = payload.details(false);
+        assert!(!details.contains_key("payload_text"));
+        assert_eq!(details["credentials_detected"], "true");
+        assert_eq!(details["payload_truncated"], "true");
+
+        let details = payload.details(true);
+        assert_eq!(details["payload_text"], "PASS secret..");
+    }
+}
@@ -0,0 +1,414 @@
+//! Honeypots described by TOML files in `honeypot_dir`
+//!
+//! ```toml
+//! # redis.toml, deployed with `deploy_from_definition("redis")`
+//! description = "Redis 6 without authentication"
+//! type = "db:redis"        # Same names as `HoneypotType::from_str`
+//! port = 6379              # Optional, defaults to the type's port
+//! banner = ""              # Optional, overrides the type's banner
+//! fake_auth = true         # Optional
+//! log_keystroke = true     # Optional
+//! connection_timeout_secs = 30       # Optional, idle or slow clients are dropped after it
+//! max_bytes_per_connection = 65536   # Optional, clients sending more are dropped
+//! default_response = "-ERR unknown command\r\n"   # Optional
+//!
+//! [[responses]]
+//! match = "INFO"           # Case-insensitive prefix of a received line
+//! response = "$19\r\nredis_version:6.2.6\r\n"
+//!
+//! [[responses]]
+//! match = "QUIT"
+//! response = "+OK\r\n"
+//! close = true             # Close the connection after answering
+//! ```
+//!
+//! Banners and responses are handlebars templates. They can use
+//! `honeypot_id`, `name`, `port`, `remote_addr` and `timestamp`, responses
+//! also `input` (the line received), and they can include the `.hbs`
+//! templates of `honeypot_dir` as partials, e.g. `{{> es_version}}`.
+//! An empty `match` only matches an empty line, such as the one ending
+//! HTTP request headers. For a multi-step dialog, use a `[script]` instead
+//! of `responses`, see [`crate::script`].
+
+use crate::script::{HoneypotScript, Machine, Session, Transition};
+use crate::LurefieldError;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A honeypot loaded from a definition file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoneypotDefinition {
+    /// What the honeypot imitates
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Honeypot type, e.g. `ssh`, `http` or `db:redis`
+    #[serde(rename = "type")]
+    pub honeypot_type: String,
+
+    /// Port to listen on, the type's default port when unset
+    #[serde(default)]
+    pub port: Option<u16>,
+
+    /// Banner template sent when a client connects
+    #[serde(default)]
+    pub banner: Option<String>,
+
+    /// Whether to answer login attempts
+    #[serde(default)]
+    pub fake_auth: Option<bool>,
+
+    /// Whether to keep the captured bytes
+    #[serde(default)]
+    pub log_keystroke: Option<bool>,
+
+    /// Seconds a connection may stay open
+    #[serde(default)]
+    pub connection_timeout_secs: Option<u64>,
+
+    /// Bytes a client may send on one connection
+    #[serde(default)]
+    pub max_bytes_per_connection: Option<usize>,
+
+    /// Responses to the lines a client sends, first match wins
+    #[serde(default)]
+    pub responses: Vec<ScriptedResponse>,
+
+    /// Response template for lines no response matches
+    #[serde(default)]
+    pub default_response: Option<String>,
+
+    /// Scripted dialog, replacing `responses`
+    #[serde(default)]
+    pub script: Option<HoneypotScript>,
+}
+
+/// Response sent when a received line matches
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedResponse {
+    /// Case-insensitive prefix of the received line
+    #[serde(rename = "match")]
+    pub pattern: String,
+
+    /// Response template
+    pub response: String,
+
+    /// Whether to close the connection after answering
+    #[serde(default)]
+    pub close: bool,
+}
+
+impl HoneypotDefinition {
+    /// Parse a definition from TOML
+    pub fn from_toml(content: &str) -> Result<Self, LurefieldError> {
+        let definition: Self = toml::from_str(content).map_err(|e| LurefieldError::Definition(e.to_string()))?;
+        if let Some(script) = &definition.script {
+            if !definition.responses.is_empty() {
+                return Err(LurefieldError::Definition("Use either responses or a script, not both".to_string()));
+            }
+            script.compile()?;
+        }
+
+        Ok(definition)
+    }
+
+    /// Read a definition file
+    pub fn load(path: &Path) -> Result<Self, LurefieldError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_toml(&content)
+            .map_err(|e| LurefieldError::Definition(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Response matching a received line, if any
+    fn response_for(&self, line: &str) -> Option<(&str, bool)> {
+        let line = line.trim();
+        let matched = self.responses.iter().find(|response| {
+            if response.pattern.is_empty() {
+                line.is_empty()
+            } else {
+                line.len() >= response.pattern.len()
+                    && line.as_bytes()[..response.pattern.len()].eq_ignore_ascii_case(response.pattern.as_bytes())
+            }
+        });
+
+        match matched {
+            Some(response) => Some((response.response.as_str(), response.close)),
+            None => self.default_response.as_deref().map(|response| (response, false)),
+        }
+    }
+}
+
+/// A definition bound to a deployed honeypot, rendering its templates
+pub(crate) struct Script {
+    /// Name of the definition
+    name: String,
+
+    /// The definition
+    definition: HoneypotDefinition,
+
+    /// Engine holding the `honeypot_dir` templates
+    engine: Arc<handlebars::Handlebars<'static>>,
+
+    /// ID of the honeypot
+    honeypot_id: String,
+
+    /// Port of the honeypot
+    port: u16,
+
+    /// Compiled `[script]` of the definition
+    machine: Option<Machine>,
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").field("name", &self.name).field("honeypot_id", &self.honeypot_id).finish()
+    }
+}
+
+impl Script {
+    pub(crate) fn new(
+        name: &str,
+        definition: HoneypotDefinition,
+        engine: Arc<handlebars::Handlebars<'static>>,
+        honeypot_id: &str,
+        port: u16,
+    ) -> Result<Self, LurefieldError> {
+        let machine = definition.script.as_ref().map(HoneypotScript::compile).transpose()?;
+        Ok(Self {
+            name: name.to_string(),
+            definition,
+            engine,
+            honeypot_id: honeypot_id.to_string(),
+            port,
+            machine,
+        })
+    }
+
+    /// Render the banner for a new connection
+    pub(crate) fn banner(&self, peer: SocketAddr) -> Option<String> {
+        let template = self.definition.banner.as_deref()?;
+        self.render(template, peer, "", "")
+    }
+
+    /// Script session of a new connection, for scripted definitions
+    pub(crate) fn session(&self) -> Option<Session> {
+        self.machine.as_ref().map(Machine::start)
+    }
+
+    /// Render the response to a received line, and whether to close afterwards
+    ///
+    /// Scripted definitions advance `session`; a connection that used up its
+    /// steps is closed without an answer.
+    pub(crate) fn reply(&self, peer: SocketAddr, line: &str, session: Option<&mut Session>) -> Option<(String, bool)> {
+        let (Some(machine), Some(session)) = (&self.machine, session) else {
+            let (template, close) = self.definition.response_for(line)?;
+            return Some((self.render(template, peer, line.trim(), "")?, close));
+        };
+
+        let state = session.state.clone();
+        let input = machine.input(line);
+        let (template, close) = match machine.advance(session, line) {
+            Transition::Step(step) => (step.response.as_str(), step.close),
+            Transition::NoMatch => (self.definition.default_response.as_deref()?, false),
+            Transition::Exhausted => return Some((String::new(), true)),
+        };
+        Some((self.render(template, peer, input, &state)?, close))
+    }
+
+    fn render(&self, template: &str, peer: SocketAddr, input: &str, state: &str) -> Option<String> {
+        let context = serde_json::json!({
+            "honeypot_id": self.honeypot_id,
+            "name": self.name,
+            "port": self.port,
+            "remote_addr": peer.to_string(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "input": input,
+            "state": state,
+        });
+
+        match self.engine.render_template(template, &context) {
+            Ok(rendered) => Some(rendered),
+            Err(e) => {
+                tracing::warn!("Failed to render honeypot definition {}: {}", self.name, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lurefield, LurefieldConfig};
+    use chame_core::EventSender;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+
+    const REDIS: &str = r#"
+description = "Redis 6 without authentication"
+type = "db:redis"
+default_response = "-ERR unknown command '{{input}}'\r\n"
+
+[[responses]]
+match = "INFO"
+response = "$19\r\nredis_version:6.2.6\r\n"
+
+[[responses]]
+match = "QUIT"
+response = "+OK\r\n"
+close = true
+"#;
+
+    const ELASTICSEARCH: &str = r#"
+type = "http"
+fake_auth = false
+
+[[responses]]
+match = ""
+response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{{> es_version}}"
+close = true
+"#;
+
+    const SHELL: &str = r#"
+type = "ssh"
+log_keystroke = true
+banner = "SSH-2.0-OpenSSH_8.2p1\r\nlogin: "
+default_response = "-bash: {{input}}: command not found\r\n$ "
+
+[script]
+max_steps = 6
+
+[[script.steps]]
+state = "login"
+expect = ".+"
+response = "Password: "
+next = "password"
+
+[[script.steps]]
+state = "password"
+expect = ".*"
+response = "Last login from {{remote_addr}}\r\n$ "
+next = "shell"
+
+[[script.steps]]
+state = "shell"
+expect = "^whoami$"
+response = "root\r\n$ "
+
+[[script.steps]]
+state = "shell"
+expect = "^exit$"
+response = "logout\r\n"
+close = true
+"#;
+
+    #[test]
+    fn test_parse_definition() {
+        let definition = HoneypotDefinition::from_toml(REDIS).unwrap();
+        assert_eq!(definition.honeypot_type, "db:redis");
+        assert_eq!(definition.port, None);
+        assert_eq!(definition.responses.len(), 2);
+
+        assert_eq!(definition.response_for("info server\r"), Some(("$19\r\nredis_version:6.2.6\r\n", false)));
+        assert_eq!(definition.response_for("QUIT"), Some(("+OK\r\n", true)));
+        assert!(definition.response_for("FLUSHALL").unwrap().0.starts_with("-ERR"));
+
+        assert!(HoneypotDefinition::from_toml("type = \"ssh\"\nprot = 22").is_err());
+        
+        // Scripts are checked when loaded
+        assert!(HoneypotDefinition::from_toml(SHELL).unwrap().script.is_some());
+        assert!(HoneypotDefinition::from_toml(&SHELL.replace("next = \"shell\"", "next = \"sh\"")).is_err());
+        assert!(HoneypotDefinition::from_toml(&format!("{}\n{}", REDIS, &SHELL[SHELL.find("[script]").unwrap()..])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deploy_from_definition() {
+        let dir = tempfile::tempdir().unwrap();
+        let redis_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let es_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        std::fs::write(dir.path().join("redis.toml"), format!("port = {}\n{}", redis_port, REDIS)).unwrap();
+        std::fs::write(dir.path().join("elasticsearch.toml"), format!("port = {}\n{}", es_port, ELASTICSEARCH)).unwrap();
+        std::fs::write(dir.path().join("es_version.hbs"), r#"{"version":{"number":"7.10.2"}}"#).unwrap();
+
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        assert_eq!(lurefield.definition_names(), ["elasticsearch", "redis"]);
+        assert!(lurefield.deploy_from_definition("memcached").await.is_err());
+
+        lurefield.deploy_from_definition("redis").await.unwrap();
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", redis_port)).await.unwrap();
+        stream.write_all(b"INFO\r\nCONFIG GET *\r\nQUIT\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "$19\r\nredis_version:6.2.6\r\n-ERR unknown command 'CONFIG GET *'\r\n+OK\r\n");
+
+        lurefield.deploy_from_definition("elasticsearch").await.unwrap();
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", es_port)).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: es\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with(r#"{"version":{"number":"7.10.2"}}"#), "{}", response);
+
+        lurefield.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scripted_dialog() {
+        let dir = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        std::fs::write(dir.path().join("shell.toml"), format!("port = {}\n{}", port, SHELL)).unwrap();
+
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            interaction_milestones: Vec::new(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        let id = lurefield.deploy_from_definition("shell").await.unwrap();
+        rx.recv().await.unwrap();
+
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(b"root\r\nhunter2\r\nls\r\nwhoami\r\nexit\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert_eq!(
+            response,
+            format!(
+                "SSH-2.0-OpenSSH_8.2p1\r\nlogin: Password: Last login from {}\r\n$ \
+                 -bash: ls: command not found\r\n$ root\r\n$ logout\r\n",
+                stream.local_addr().unwrap()
+            )
+        );
+
+        // One interaction per input, then one for the connection
+        let mut inputs = Vec::new();
+        for _ in 0..5 {
+            let details = rx.recv().await.unwrap().data.unwrap()["details"].clone();
+            inputs.push(format!("{}:{}", details["script_state"].as_str().unwrap(), details["input"].as_str().unwrap()));
+        }
+        assert_eq!(inputs, ["login:root", "password:hunter2", "shell:ls", "shell:whoami", "shell:exit"]);
+        let details = rx.recv().await.unwrap().data.unwrap()["details"].clone();
+        assert!(details.get("script_state").is_none());
+        assert_eq!(lurefield.stats().await[0].interactions, 6);
+
+        // Connections are closed once they used up their steps
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(b"root\r\npw\r\nid\r\nid\r\nid\r\nid\r\nid\r\nid\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response.matches("command not found").count(), 4);
+
+        lurefield.stop_honeypot(&id).await.unwrap();
+    }
+}
@@ -1,18 +1,17 @@
 use chame_core::adaptive::{AdaptiveError, AdaptiveEvent, AdaptiveHandler};
 use chame_core::events::{Event, EventType};
+use chame_core::EventSender;
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::mpsc;
 
 /// Handler for Lurefield events
 pub struct LurefieldHandler {
     /// Event sender
-    event_sender: mpsc::Sender<Event>,
+    event_sender: EventSender,
 }
 
 impl LurefieldHandler {
     /// Create a new Lurefield handler
-    pub fn new(event_sender: mpsc::Sender<Event>) -> Self {
+    pub fn new(event_sender: EventSender) -> Self {
         Self { event_sender }
     }
 }
@@ -1,9 +1,26 @@
+pub mod capture;
+pub mod definition;
+pub mod handler;
+pub mod listener;
+pub mod registry;
+pub mod script;
+mod supervisor;
+
 use chame_core::events::{Event, EventType};
+use chame_core::{EventSender, MetricsCollector, ModuleHealth};
+use definition::{HoneypotDefinition, Script};
+use listener::{CaptureSettings, ConnectionSlots};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use dashmap::DashSet;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex as AsyncMutex, RwLock, Semaphore};
+use tokio::task::JoinHandle;
 
 /// Errors that can occur in the Lurefield module
 #[derive(Error, Debug)]
@@ -17,11 +34,29 @@ pub enum LurefieldError {
     #[error("Template error: {0}")]
     Template(String),
     
+    #[error("Honeypot definition error: {0}")]
+    Definition(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
     #[error("Maximum honeypots reached")]
     MaxHoneypotsReached,
+    
+    #[error("Unsafe honeypot port: {0}")]
+    UnsafePort(String),
+}
+
+/// What to do when a honeypot is deployed on a risky port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortPolicy {
+    /// Log a warning and deploy anyway
+    #[default]
+    Warn,
+    
+    /// Refuse the deployment with `LurefieldError::UnsafePort`
+    Deny,
 }
 
 /// Configuration for the Lurefield module
@@ -35,6 +70,58 @@ pub struct LurefieldConfig {
     
     /// Whether to automatically deploy honeypots
     pub auto_deploy: bool,
+    
+    /// Address honeypot listeners bind to
+    pub bind_address: IpAddr,
+    
+    /// Maximum bytes captured from each connection
+    pub capture_bytes: usize,
+    
+    /// Longest a connection may stay open, whatever the honeypot's
+    /// `connection_timeout`
+    pub capture_timeout: Duration,
+    
+    /// Times a dead honeypot listener is restarted before giving up
+    pub restart_attempts: u32,
+    
+    /// Delay before the first restart, doubled for each further attempt
+    pub restart_backoff: Duration,
+    
+    /// How long a restarted listener must stay up for its restarts to be forgotten
+    pub restart_reset_after: Duration,
+    
+    /// Honeypots to run in each posture, by lowercase posture name
+    ///
+    /// Entries are definition names or honeypot types such as `ssh` or
+    /// `db:mysql`. Postures not listed run none of them.
+    pub posture_honeypots: HashMap<String, Vec<String>>,
+    
+    /// Interaction counts at which a honeypot raises a `SecurityAlert`
+    ///
+    /// Repeated interaction with a lure is a stronger signal than the
+    /// `HoneypotActivity` of each single hit.
+    pub interaction_milestones: Vec<u32>,
+    
+    /// File the active honeypots are saved to, and restored from on startup
+    ///
+    /// Without it honeypots only live as long as the process.
+    pub registry_path: Option<PathBuf>,
+    
+    /// What to do with honeypots on a privileged port without root, or on
+    /// the port of a real service
+    pub port_policy: PortPolicy,
+    
+    /// Connections handled at once across all honeypots, 0 for no limit
+    ///
+    /// Connections over the limit are closed right away, so a flood can't
+    /// exhaust the file descriptors of the process.
+    pub max_concurrent_connections: usize,
+    
+    /// Connections each honeypot handles at once, 0 for no limit
+    pub max_connections_per_honeypot: usize,
+    
+    /// Pending connections the kernel queues for each honeypot listener
+    pub listen_backlog: u32,
 }
 
 impl Default for LurefieldConfig {
@@ -43,6 +130,25 @@ impl Default for LurefieldConfig {
             honeypot_dir: PathBuf::from("./honeypots"),
             max_honeypots: 5,
             auto_deploy: false,
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            capture_bytes: 4096,
+            capture_timeout: Duration::from_secs(30),
+            restart_attempts: 5,
+            restart_backoff: Duration::from_secs(1),
+            restart_reset_after: Duration::from_secs(600),
+            posture_honeypots: HashMap::from([
+                ("mimetic".to_string(), vec!["ssh".to_string(), "http".to_string(), "db:mysql".to_string()]),
+                (
+                    "fulgurant".to_string(),
+                    vec!["ssh".to_string(), "http".to_string(), "ftp".to_string(), "db:mysql".to_string()],
+                ),
+            ]),
+            interaction_milestones: vec![1, 10, 100],
+            registry_path: None,
+            port_policy: PortPolicy::Warn,
+            max_concurrent_connections: 512,
+            max_connections_per_honeypot: 128,
+            listen_backlog: 1024,
         }
     }
 }
@@ -102,6 +208,15 @@ impl HoneypotType {
             Self::Custom(_) => 8080,
         }
     }
+    
+    /// Greeting sent by the real service before the client says anything
+    pub fn default_banner(&self) -> Option<&'static str> {
+        match self {
+            Self::Ssh => Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6\r\n"),
+            Self::Ftp => Some("220 (vsFTPd 3.0.5)\r\n"),
+            _ => None,
+        }
+    }
 }
 
 /// Options for honeypot deployment
@@ -121,6 +236,14 @@ pub struct HoneypotOptions {
     
     /// Additional options
     pub extra_options: HashMap<String, String>,
+    
+    /// How long a connection may stay open before it is dropped
+    pub connection_timeout: Duration,
+    
+    /// Bytes a client may send on one connection before it is dropped
+    ///
+    /// Only the first `capture_bytes` of them are kept.
+    pub max_bytes_per_connection: usize,
 }
 
 impl Default for HoneypotOptions {
@@ -131,6 +254,8 @@ impl Default for HoneypotOptions {
             log_keystroke: true,
             custom_banner: None,
             extra_options: HashMap::new(),
+            connection_timeout: Duration::from_secs(30),
+            max_bytes_per_connection: 64 * 1024,
         }
     }
 }
@@ -153,8 +278,24 @@ pub struct Honeypot {
     /// When the honeypot was deployed
     pub deployed_at: chrono::DateTime<chrono::Utc>,
     
-    /// Number of interactions with the honeypot
-    pub interaction_count: u32,
+    /// Number of interactions with the honeypot in this session
+    ///
+    /// Counted without the honeypot's write lock, so a flood of connections
+    /// doesn't serialize on it.
+    pub interaction_count: AtomicU32,
+    
+    /// Interactions from earlier sessions, restored from the registry
+    pub persisted_interactions: u32,
+    
+    /// Distinct addresses that interacted with the honeypot, at most
+    /// [`MAX_TRACKED_SOURCES`]
+    pub sources: DashSet<IpAddr>,
+    
+    /// First interaction, in milliseconds since the epoch, 0 before any
+    first_interaction_ms: AtomicI64,
+    
+    /// Last interaction, in milliseconds since the epoch, 0 before any
+    last_interaction_ms: AtomicI64,
     
     /// Whether the honeypot is currently active
     pub active: bool,
@@ -162,6 +303,110 @@ pub struct Honeypot {
     /// Process handle (if applicable)
     #[allow(dead_code)]
     process_handle: Option<tokio::process::Child>,
+    
+    /// Times the listener was restarted after dying
+    pub restarts: u32,
+    
+    /// Task accepting connections
+    listener_handle: Option<JoinHandle<()>>,
+    
+    /// When the supervisor may next restart the listener
+    next_restart: Option<tokio::time::Instant>,
+    
+    /// When the listener was last restarted
+    restarted_at: Option<tokio::time::Instant>,
+    
+    /// Scripted behavior, for honeypots deployed from a definition
+    script: Option<Arc<Script>>,
+    
+    /// Slots bounding the connections handled at once, kept across restarts
+    connection_slots: ConnectionSlots,
+}
+
+impl Honeypot {
+    /// Interactions over every session, persisted ones included
+    pub fn total_interactions(&self) -> u32 {
+        self.persisted_interactions + self.interaction_count.load(Ordering::Relaxed)
+    }
+    
+    /// When the first interaction happened
+    pub fn first_interaction(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        from_millis(self.first_interaction_ms.load(Ordering::Relaxed))
+    }
+    
+    /// When the last interaction happened
+    pub fn last_interaction(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        from_millis(self.last_interaction_ms.load(Ordering::Relaxed))
+    }
+    
+    /// Count an interaction from `source`, safe to call concurrently
+    ///
+    /// Returns the interaction count including this one.
+    fn count_interaction(&self, source: Option<IpAddr>, at: chrono::DateTime<chrono::Utc>) -> u32 {
+        let count = self.interaction_count.fetch_add(1, Ordering::Relaxed) + 1;
+        
+        let millis = at.timestamp_millis();
+        let _ = self.first_interaction_ms.compare_exchange(0, millis, Ordering::Relaxed, Ordering::Relaxed);
+        self.last_interaction_ms.fetch_max(millis, Ordering::Relaxed);
+        
+        if let Some(source) = source {
+            // A dual-stack listener sees IPv4 peers as ::ffff:a.b.c.d
+            let source = match source {
+                IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+                v4 => v4,
+            };
+            // A scan from many addresses must not grow the set without bound
+            if self.sources.len() < MAX_TRACKED_SOURCES {
+                self.sources.insert(source);
+            }
+        }
+        
+        count
+    }
+}
+
+/// Distinct source addresses remembered per honeypot
+pub const MAX_TRACKED_SOURCES: usize = 10_000;
+
+/// Interaction time stored as milliseconds, 0 meaning never
+fn from_millis(millis: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+    (millis != 0).then(|| chrono::DateTime::from_timestamp_millis(millis)).flatten()
+}
+
+/// Interaction time as stored by [`from_millis`]
+fn to_millis(at: Option<chrono::DateTime<chrono::Utc>>) -> i64 {
+    at.map_or(0, |at| at.timestamp_millis())
+}
+
+/// Engagement of a honeypot, from [`Lurefield::stats`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HoneypotStats {
+    /// Honeypot ID
+    pub id: String,
+    
+    /// Type of honeypot
+    pub honeypot_type: String,
+    
+    /// Port the honeypot listens on
+    pub port: u16,
+    
+    /// Whether the honeypot is still deployed
+    pub active: bool,
+    
+    /// Interactions in this session
+    pub interactions: u32,
+    
+    /// Interactions from earlier sessions, restored from the registry
+    pub persisted_interactions: u32,
+    
+    /// Distinct source addresses, counting up to [`MAX_TRACKED_SOURCES`]
+    pub unique_sources: usize,
+    
+    /// When the first interaction happened
+    pub first_interaction: Option<chrono::DateTime<chrono::Utc>>,
+    
+    /// When the last interaction happened
+    pub last_interaction: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Main Lurefield honeypot management service
@@ -170,20 +415,41 @@ pub struct Lurefield {
     config: LurefieldConfig,
     
     /// Active honeypots
-    honeypots: RwLock<HashMap<String, Arc<RwLock<Honeypot>>>>,
+    honeypots: Arc<RwLock<HashMap<String, Arc<RwLock<Honeypot>>>>>,
+    
+    /// Task restarting dead honeypot listeners
+    supervisor: Mutex<Option<JoinHandle<()>>>,
     
     /// Event sender
-    event_sender: tokio::sync::mpsc::Sender<Event>,
+    event_sender: EventSender,
+    
+    /// Shared metrics, recording honeypot engagement
+    metrics: Option<Arc<MetricsCollector>>,
     
     /// Template engine
-    template_engine: handlebars::Handlebars<'static>,
+    template_engine: Arc<handlebars::Handlebars<'static>>,
+    
+    /// Honeypot definitions by name
+    definitions: HashMap<String, HoneypotDefinition>,
+    
+    /// Sequence number of the next honeypot, keeping IDs unique
+    next_id: AtomicU64,
+    
+    /// Honeypots deployed for the current posture, by mapping entry
+    posture_deployed: AsyncMutex<HashMap<String, String>>,
+    
+    /// Ports real services run on, which honeypots must not shadow
+    reserved_ports: Option<Arc<dyn Fn() -> Vec<u16> + Send + Sync>>,
+    
+    /// Connection slots shared by every honeypot
+    connection_slots: Arc<Semaphore>,
 }
 
 impl Lurefield {
     /// Create a new Lurefield instance
     pub async fn new(
         config: LurefieldConfig,
-        event_sender: tokio::sync::mpsc::Sender<Event>,
+        event_sender: EventSender,
     ) -> Result<Self, LurefieldError> {
         // Create honeypot directory if it doesn't exist
         if !config.honeypot_dir.exists() {
@@ -194,11 +460,21 @@ impl Lurefield {
         let mut template_engine = handlebars::Handlebars::new();
         template_engine.set_strict_mode(true);
         
-        // Load templates
+        // Load templates and definitions
+        let mut definitions = HashMap::new();
         if let Ok(entries) = std::fs::read_dir(&config.honeypot_dir) {
             for entry in entries.filter_map(Result::ok) {
                 let path = entry.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == "hbs") {
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
+                    if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                        match HoneypotDefinition::load(&path) {
+                            Ok(definition) => {
+                                definitions.insert(name.to_string(), definition);
+                            }
+                            Err(e) => tracing::warn!("Failed to load honeypot definition {}: {}", name, e),
+                        }
+                    }
+                } else if path.is_file() && path.extension().is_some_and(|ext| ext == "hbs") {
                     if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
                         if let Ok(template) = std::fs::read_to_string(&path) {
                             if let Err(e) = template_engine.register_template_string(name, template) {
@@ -210,18 +486,123 @@ impl Lurefield {
             }
         }
         
-        Ok(Self {
+        let lurefield = Self {
+            connection_slots: ConnectionSlots::pool(config.max_concurrent_connections),
             config,
-            honeypots: RwLock::new(HashMap::new()),
+            honeypots: Arc::new(RwLock::new(HashMap::new())),
+            supervisor: Mutex::new(None),
             event_sender,
-            template_engine,
-        })
+            metrics: None,
+            template_engine: Arc::new(template_engine),
+            definitions,
+            next_id: AtomicU64::new(1),
+            posture_deployed: AsyncMutex::new(HashMap::new()),
+            reserved_ports: None,
+        };
+        
+        if let Some(path) = &lurefield.config.registry_path {
+            let entries = registry::load(path)?;
+            lurefield.restore(entries).await;
+        }
+        
+        Ok(lurefield)
+    }
+    
+    /// Deploy the honeypots saved in the registry again
+    ///
+    /// A honeypot that can't be deployed, e.g. because its port is taken,
+    /// is logged and dropped from the registry.
+    async fn restore(&self, entries: Vec<registry::RegistryEntry>) {
+        // New IDs must not collide with the restored ones
+        let next_id = entries.iter().filter_map(|entry| entry.sequence()).max().map_or(1, |last| last + 1);
+        self.next_id.store(next_id, Ordering::Relaxed);
+        
+        let mut posture_deployed = self.posture_deployed.lock().await;
+        for entry in &entries {
+            let definition = entry
+                .extra_options
+                .get("definition")
+                .and_then(|name| Some((name.as_str(), self.definitions.get(name)?.clone())));
+            let result = match HoneypotType::from_str(&entry.honeypot_type) {
+                Ok(honeypot_type) => self.deploy(honeypot_type, entry.options(), definition, Some(entry)).await,
+                Err(e) => Err(e),
+            };
+            
+            match result {
+                Ok(id) => {
+                    tracing::info!("Restored honeypot {} on port {}", id, entry.port);
+                    if let Some(name) = &entry.posture_entry {
+                        posture_deployed.insert(name.clone(), id);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to restore honeypot {}: {}", entry.id, e),
+            }
+        }
+        drop(posture_deployed);
+        
+        self.save_registry().await;
+    }
+    
+    /// Save the active honeypots to the registry, if one is configured
+    ///
+    /// Failures are logged, the honeypots keep running.
+    async fn save_registry(&self) {
+        let Some(path) = &self.config.registry_path else {
+            return;
+        };
+        
+        let posture_entries: HashMap<String, String> = self
+            .posture_deployed
+            .lock()
+            .await
+            .iter()
+            .map(|(name, id)| (id.clone(), name.clone()))
+            .collect();
+        
+        let mut entries = Vec::new();
+        for honeypot in self.honeypots.read().await.values() {
+            let honeypot = honeypot.read().await;
+            if honeypot.active {
+                entries.push(registry::RegistryEntry::from_honeypot(
+                    &honeypot,
+                    posture_entries.get(&honeypot.id).cloned(),
+                ));
+            }
+        }
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        
+        if let Err(e) = registry::save(path, &entries).await {
+            tracing::warn!("Failed to save the honeypot registry to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Record per-honeypot engagement into `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Check new honeypots against the ports `reserved_ports` returns, e.g.
+    /// those of the services Skinshift configured
+    pub fn with_reserved_ports(mut self, reserved_ports: impl Fn() -> Vec<u16> + Send + Sync + 'static) -> Self {
+        self.reserved_ports = Some(Arc::new(reserved_ports));
+        self
     }
     
     /// Start the Lurefield service
     pub async fn start(&self) -> Result<(), LurefieldError> {
         tracing::info!("Starting Lurefield honeypot service");
         
+        let supervisor = supervisor::spawn(
+            self.config.clone(),
+            self.honeypots.clone(),
+            self.event_sender.clone(),
+            self.metrics.clone(),
+        );
+        if let Some(previous) = self.supervisor.lock().unwrap().replace(supervisor) {
+            previous.abort();
+        }
+        
         // Auto-deploy honeypots if configured
         if self.config.auto_deploy {
             self.auto_deploy_honeypots().await?;
@@ -234,10 +615,18 @@ impl Lurefield {
     pub async fn stop(&self) -> Result<(), LurefieldError> {
         tracing::info!("Stopping Lurefield honeypot service");
         
+        if let Some(supervisor) = self.supervisor.lock().unwrap().take() {
+            supervisor.abort();
+        }
+        
+        // Save the honeypots with their counts before they are stopped, so
+        // the next start deploys them again
+        self.save_registry().await;
+        
         // Stop all honeypots
         let honeypots = self.honeypots.read().await;
-        for (id, honeypot) in honeypots.iter() {
-            if let Err(e) = self.stop_honeypot(id).await {
+        for id in honeypots.keys() {
+            if let Err(e) = self.deactivate(id).await {
                 tracing::warn!("Failed to stop honeypot {}: {}", id, e);
             }
         }
@@ -245,48 +634,192 @@ impl Lurefield {
         Ok(())
     }
     
+    /// Health of the supervisor and of the active honeypots' listeners
+    pub async fn health(&self) -> ModuleHealth {
+        let finished = self.supervisor.lock().unwrap().as_ref().map(JoinHandle::is_finished);
+        match finished {
+            None => return ModuleHealth::Stopped,
+            Some(true) => return ModuleHealth::Unhealthy("honeypot supervisor exited".to_string()),
+            Some(false) => {}
+        }
+        
+        // The supervisor restarts dead listeners, until it gives up
+        let mut down = Vec::new();
+        for honeypot in self.honeypots.read().await.values() {
+            let honeypot = honeypot.read().await;
+            let alive = honeypot.listener_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+            if honeypot.active && !alive {
+                down.push(honeypot.id.clone());
+            }
+        }
+        
+        match down.is_empty() {
+            true => ModuleHealth::Healthy,
+            false => ModuleHealth::Degraded(format!("listener down for {}", down.join(", "))),
+        }
+    }
+    
+    /// Names of the loaded honeypot definitions
+    pub fn definition_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.definitions.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+    
+    /// Get a loaded honeypot definition
+    pub fn get_definition(&self, name: &str) -> Option<&HoneypotDefinition> {
+        self.definitions.get(name)
+    }
+    
     /// Deploy a new honeypot
+    ///
+    /// A privileged port without root, or the port of a reserved service,
+    /// is logged or refused depending on `LurefieldConfig::port_policy`.
     pub async fn deploy_honeypot(
         &self,
         honeypot_type: HoneypotType,
         options: Option<HoneypotOptions>,
     ) -> Result<String, LurefieldError> {
-        // Check if we've reached the maximum number of honeypots
+        let id = self.deploy(honeypot_type, options.unwrap_or_default(), None, None).await?;
+        self.save_registry().await;
+        Ok(id)
+    }
+    
+    /// Deploy a honeypot described by a definition file of `honeypot_dir`
+    pub async fn deploy_from_definition(&self, name: &str) -> Result<String, LurefieldError> {
+        let id = self.deploy_definition(name).await?;
+        self.save_registry().await;
+        Ok(id)
+    }
+    
+    /// Deploy a honeypot from a definition, without saving the registry
+    async fn deploy_definition(&self, name: &str) -> Result<String, LurefieldError> {
+        let definition = self.definitions.get(name).ok_or_else(|| {
+            LurefieldError::Definition(format!("Unknown honeypot definition: {}", name))
+        })?;
+        
+        let defaults = HoneypotOptions::default();
+        let mut extra_options = HashMap::new();
+        extra_options.insert("definition".to_string(), name.to_string());
+        let options = HoneypotOptions {
+            port: definition.port.unwrap_or(0),
+            fake_auth: definition.fake_auth.unwrap_or(defaults.fake_auth),
+            log_keystroke: definition.log_keystroke.unwrap_or(defaults.log_keystroke),
+            custom_banner: None,
+            extra_options,
+            connection_timeout: definition
+                .connection_timeout_secs
+                .map_or(defaults.connection_timeout, Duration::from_secs),
+            max_bytes_per_connection: definition.max_bytes_per_connection.unwrap_or(defaults.max_bytes_per_connection),
+        };
+        
+        let honeypot_type = HoneypotType::from_str(&definition.honeypot_type)?;
+        self.deploy(honeypot_type, options, Some((name, definition.clone())), None).await
+    }
+    
+    /// Bind, register and announce a honeypot
+    ///
+    /// A honeypot `restored` from the registry keeps its ID, deployment time
+    /// and interactions.
+    async fn deploy(
+        &self,
+        honeypot_type: HoneypotType,
+        mut options: HoneypotOptions,
+        definition: Option<(&str, HoneypotDefinition)>,
+        restored: Option<&registry::RegistryEntry>,
+    ) -> Result<String, LurefieldError> {
+        // Check if we've reached the maximum number of honeypots, stopped
+        // ones stay listed but don't count
         let honeypots = self.honeypots.read().await;
-        if honeypots.len() >= self.config.max_honeypots as usize {
+        let mut active = 0;
+        for honeypot in honeypots.values() {
+            if honeypot.read().await.active {
+                active += 1;
+            }
+        }
+        if active >= self.config.max_honeypots as usize {
             return Err(LurefieldError::MaxHoneypotsReached);
         }
         drop(honeypots);
         
-        // Generate a unique ID
-        let id = format!(
-            "hp-{}-{}",
-            honeypot_type.to_str(),
-            chrono::Utc::now().timestamp()
-        );
+        // Generate a unique ID, the sequence number tells apart honeypots
+        // deployed within the same second
+        let id = match restored {
+            Some(entry) => entry.id.clone(),
+            None => format!(
+                "hp-{}-{}-{}",
+                honeypot_type.to_str(),
+                chrono::Utc::now().timestamp(),
+                self.next_id.fetch_add(1, Ordering::Relaxed)
+            ),
+        };
         
         // Prepare options
-        let mut options = options.unwrap_or_default();
         if options.port == 0 {
             options.port = honeypot_type.default_port();
         }
         
+        // Restored honeypots were checked when first deployed
+        if restored.is_none() {
+            self.check_port(options.port)?;
+        }
+        
+        let script = match definition {
+            Some((name, definition)) => Some(Arc::new(Script::new(
+                name,
+                definition,
+                self.template_engine.clone(),
+                &id,
+                options.port,
+            )?)),
+            None => None,
+        };
+        
+        // Bind before registering so a taken port doesn't leave a dead honeypot
+        let listener = bind_listener(&self.config, options.port).await?;
+        
         // Create the honeypot
-        let honeypot = Honeypot {
+        let honeypot = Arc::new(RwLock::new(Honeypot {
             id: id.clone(),
             honeypot_type: honeypot_type.clone(),
             port: options.port,
             options: options.clone(),
-            deployed_at: chrono::Utc::now(),
-            interaction_count: 0,
+            deployed_at: restored.map_or_else(chrono::Utc::now, |entry| entry.deployed_at),
+            interaction_count: AtomicU32::new(0),
+            persisted_interactions: restored.map_or(0, |entry| entry.interactions),
+            sources: DashSet::new(),
+            first_interaction_ms: AtomicI64::new(to_millis(restored.and_then(|entry| entry.first_interaction))),
+            last_interaction_ms: AtomicI64::new(to_millis(restored.and_then(|entry| entry.last_interaction))),
             active: true,
             process_handle: None,
+            restarts: 0,
+            listener_handle: None,
+            next_restart: None,
+            restarted_at: None,
+            script,
+            connection_slots: ConnectionSlots {
+                shared: self.connection_slots.clone(),
+                own: ConnectionSlots::pool(self.config.max_connections_per_honeypot),
+            },
+        }));
+        
+        let settings = {
+            let honeypot = honeypot.read().await;
+            capture_settings(&self.config, &honeypot)
         };
+        let handle = listener::spawn(
+            listener,
+            honeypot.clone(),
+            settings,
+            self.event_sender.clone(),
+            self.metrics.clone(),
+        );
+        honeypot.write().await.listener_handle = Some(handle);
         
         // Store the honeypot
         {
             let mut honeypots = self.honeypots.write().await;
-            honeypots.insert(id.clone(), Arc::new(RwLock::new(honeypot)));
+            honeypots.insert(id.clone(), honeypot);
         }
         
         // Send event
@@ -297,6 +830,7 @@ impl Lurefield {
                 "honeypot_id": id,
                 "honeypot_type": honeypot_type.to_str(),
                 "port": options.port,
+                "restored": restored.is_some(),
             })),
         );
         
@@ -313,17 +847,49 @@ impl Lurefield {
         Ok(id)
     }
     
+    /// Apply the port policy to a honeypot about to listen on `port`
+    fn check_port(&self, port: u16) -> Result<(), LurefieldError> {
+        let problem = if port < 1024 && !is_root() {
+            format!("port {} is privileged and Lurefield is not running as root", port)
+        } else if self.reserved_ports.as_ref().is_some_and(|reserved| reserved().contains(&port)) {
+            format!("port {} is used by a real service", port)
+        } else {
+            return Ok(());
+        };
+        
+        match self.config.port_policy {
+            PortPolicy::Warn => {
+                tracing::warn!("Deploying a honeypot anyway: {}", problem);
+                Ok(())
+            }
+            PortPolicy::Deny => Err(LurefieldError::UnsafePort(problem)),
+        }
+    }
+    
     /// Stop a honeypot
     pub async fn stop_honeypot(&self, id: &str) -> Result<(), LurefieldError> {
+        self.deactivate(id).await?;
+        self.save_registry().await;
+        Ok(())
+    }
+    
+    /// Stop a honeypot, without saving the registry
+    async fn deactivate(&self, id: &str) -> Result<(), LurefieldError> {
         let honeypots = self.honeypots.read().await;
         let honeypot_lock = honeypots.get(id).ok_or_else(|| {
             LurefieldError::HoneypotDeployment(format!("Honeypot {} not found", id))
         })?;
         
-        // Mark as inactive
-        {
+        // Mark as inactive and close the port
+        let listener = {
             let mut honeypot = honeypot_lock.write().await;
             honeypot.active = false;
+            honeypot.listener_handle.take()
+        };
+        if let Some(handle) = listener {
+            handle.abort();
+            // The port is free once the task is gone
+            let _ = handle.await;
         }
         
         // Send event
@@ -358,9 +924,19 @@ impl Lurefield {
                     port: honeypot.port,
                     options: honeypot.options.clone(),
                     deployed_at: honeypot.deployed_at,
-                    interaction_count: honeypot.interaction_count,
+                    interaction_count: AtomicU32::new(honeypot.interaction_count.load(Ordering::Relaxed)),
+                    persisted_interactions: honeypot.persisted_interactions,
+                    sources: honeypot.sources.clone(),
+                    first_interaction_ms: AtomicI64::new(honeypot.first_interaction_ms.load(Ordering::Relaxed)),
+                    last_interaction_ms: AtomicI64::new(honeypot.last_interaction_ms.load(Ordering::Relaxed)),
                     active: honeypot.active,
                     process_handle: None,
+                    restarts: honeypot.restarts,
+                    listener_handle: None,
+                    next_restart: None,
+                    restarted_at: honeypot.restarted_at,
+                    script: honeypot.script.clone(),
+                    connection_slots: honeypot.connection_slots.clone(),
                 });
             }
         }
@@ -379,40 +955,247 @@ impl Lurefield {
             LurefieldError::HoneypotDeployment(format!("Honeypot {} not found", id))
         })?;
         
-        // Increment interaction count
-        {
-            let mut honeypot = honeypot_lock.write().await;
-            honeypot.interaction_count += 1;
+        report_interaction(
+            honeypot_lock,
+            &self.event_sender,
+            self.metrics.as_deref(),
+            &self.config.interaction_milestones,
+            details,
+        )
+        .await;
+        
+        Ok(())
+    }
+    
+    /// Engagement of every honeypot deployed so far, most interactions first
+    ///
+    /// Stopped honeypots are included so ineffective lures can be spotted.
+    pub async fn stats(&self) -> Vec<HoneypotStats> {
+        let honeypots = self.honeypots.read().await;
+        let mut stats = Vec::with_capacity(honeypots.len());
+        
+        for honeypot_lock in honeypots.values() {
+            let honeypot = honeypot_lock.read().await;
+            stats.push(HoneypotStats {
+                id: honeypot.id.clone(),
+                honeypot_type: honeypot.honeypot_type.to_str().to_string(),
+                port: honeypot.port,
+                active: honeypot.active,
+                interactions: honeypot.interaction_count.load(Ordering::Relaxed),
+                persisted_interactions: honeypot.persisted_interactions,
+                unique_sources: honeypot.sources.len(),
+                first_interaction: honeypot.first_interaction(),
+                last_interaction: honeypot.last_interaction(),
+            });
         }
         
-        // Send event
-        let event = Event::honeypot_activity(
-            "lurefield",
-            Some(serde_json::json!({
-                "action": "interaction",
-                "honeypot_id": id,
-                "details": details,
-            })),
-        );
+        stats.sort_by(|a, b| b.interactions.cmp(&a.interactions).then_with(|| a.id.cmp(&b.id)));
+        stats
+    }
+    
+    /// React to posture changes published by the core
+    pub async fn handle_event(&self, event: &Event) -> Result<(), LurefieldError> {
+        if event.event_type != EventType::PostureChange {
+            return Ok(());
+        }
         
-        if let Err(e) = self.event_sender.send(event).await {
-            tracing::error!("Failed to send honeypot interaction event: {}", e);
+        let posture = event
+            .data
+            .as_ref()
+            .and_then(|data| data.get("new_posture"))
+            .and_then(|posture| posture.as_str());
+        if let Some(posture) = posture {
+            self.apply_posture(posture).await;
         }
         
         Ok(())
     }
     
+    /// Deploy the honeypots mapped to `posture` and stop the ones deployed
+    /// for a previous posture that it doesn't list
+    ///
+    /// Honeypots deployed by hand or by `auto_deploy` are left alone.
+    /// Failures are logged, the other honeypots are still deployed.
+    pub async fn apply_posture(&self, posture: &str) {
+        let wanted = self
+            .config
+            .posture_honeypots
+            .get(&posture.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        let mut deployed = self.posture_deployed.lock().await;
+        
+        let unwanted: Vec<String> = deployed.keys().filter(|name| !wanted.contains(name)).cloned().collect();
+        for name in unwanted {
+            if let Some(id) = deployed.remove(&name) {
+                if let Err(e) = self.deactivate(&id).await {
+                    tracing::warn!("Failed to stop {} honeypot for posture {}: {}", name, posture, e);
+                }
+            }
+        }
+        
+        for name in wanted {
+            if deployed.contains_key(&name) {
+                continue;
+            }
+            
+            let result = if self.definitions.contains_key(&name) {
+                self.deploy_definition(&name).await
+            } else {
+                match HoneypotType::from_str(&name) {
+                    Ok(honeypot_type) => self.deploy(honeypot_type, HoneypotOptions::default(), None, None).await,
+                    Err(e) => Err(e),
+                }
+            };
+            
+            match result {
+                Ok(id) => {
+                    deployed.insert(name, id);
+                }
+                Err(e) => tracing::warn!("Failed to deploy {} honeypot for posture {}: {}", name, posture, e),
+            }
+        }
+        drop(deployed);
+        
+        self.save_registry().await;
+    }
+    
     /// Auto-deploy honeypots based on configuration
     async fn auto_deploy_honeypots(&self) -> Result<(), LurefieldError> {
-        // Deploy a basic set of honeypots
-        self.deploy_honeypot(HoneypotType::Ssh, None).await?;
-        self.deploy_honeypot(HoneypotType::Http, None).await?;
-        self.deploy_honeypot(HoneypotType::Database("mongodb".to_string()), None).await?;
+        // Deploy a basic set of honeypots, skipping ports that are already taken
+        for honeypot_type in [
+            HoneypotType::Ssh,
+            HoneypotType::Http,
+            HoneypotType::Database("mongodb".to_string()),
+        ] {
+            if let Err(e) = self.deploy_honeypot(honeypot_type.clone(), None).await {
+                tracing::warn!("Failed to auto-deploy {} honeypot: {}", honeypot_type.to_str(), e);
+            }
+        }
         
         Ok(())
     }
 }
 
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+/// Listen on `port` at the configured address, with the configured backlog
+pub(crate) async fn bind_listener(
+    config: &LurefieldConfig,
+    port: u16,
+) -> Result<tokio::net::TcpListener, LurefieldError> {
+    let address = SocketAddr::new(config.bind_address, port);
+    let listen = || {
+        let socket = match address {
+            SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
+        };
+        // Like `TcpListener::bind`, so a restarted listener gets its port back
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        socket.bind(address)?;
+        socket.listen(config.listen_backlog.max(1))
+    };
+    listen().map_err(|e| {
+        LurefieldError::HoneypotDeployment(format!("Failed to listen on {}: {}", address, e))
+    })
+}
+
+/// How a honeypot's listener should capture connections
+pub(crate) fn capture_settings(config: &LurefieldConfig, honeypot: &Honeypot) -> CaptureSettings {
+    let options = &honeypot.options;
+    CaptureSettings {
+        honeypot_type: honeypot.honeypot_type.clone(),
+        banner: options
+            .custom_banner
+            .clone()
+            .or_else(|| honeypot.honeypot_type.default_banner().map(String::from)),
+        max_bytes: config.capture_bytes,
+        max_connection_bytes: options.max_bytes_per_connection,
+        timeout: options.connection_timeout.min(config.capture_timeout),
+        fake_auth: options.fake_auth,
+        log_keystroke: options.log_keystroke,
+        script: honeypot.script.clone(),
+        milestones: config.interaction_milestones.clone(),
+        connection_slots: honeypot.connection_slots.clone(),
+    }
+}
+
+/// Count an interaction with a honeypot and report it
+///
+/// Reaching one of `milestones` interactions also raises a `SecurityAlert`.
+pub(crate) async fn report_interaction(
+    honeypot_lock: &RwLock<Honeypot>,
+    event_sender: &EventSender,
+    metrics: Option<&MetricsCollector>,
+    milestones: &[u32],
+    details: HashMap<String, String>,
+) {
+    let source = details.get("remote_addr").and_then(|addr| {
+        addr.parse::<SocketAddr>().map(|addr| addr.ip()).or_else(|_| addr.parse()).ok()
+    });
+    let now = chrono::Utc::now();
+    
+    // Increment interaction count and track engagement, a read lock is enough
+    let (id, honeypot_type, count, unique_sources) = {
+        let honeypot = honeypot_lock.read().await;
+        // Milestones count the interactions of earlier sessions too
+        let count = honeypot.persisted_interactions + honeypot.count_interaction(source, now);
+        
+        // Per-honeypot figures are in `Lurefield::stats`, a metric per ID
+        // would add a series for every honeypot ever deployed
+        if let Some(metrics) = metrics {
+            metrics.increment_counter("honeypot_interactions");
+            metrics.set_gauge("honeypot_last_interaction", now.timestamp() as f64);
+        }
+        
+        (honeypot.id.clone(), honeypot.honeypot_type.to_str().to_string(), count, honeypot.sources.len())
+    };
+    
+    // Each count is reached by exactly one interaction, so a milestone alerts once
+    let milestone = milestones.contains(&count).then(|| {
+        tracing::warn!("Honeypot {} reached {} interactions", id, count);
+        Event::security_alert(
+            "lurefield",
+            Some(serde_json::json!({
+                "action": "interaction_milestone",
+                "honeypot_id": id,
+                "honeypot_type": honeypot_type,
+                "interactions": count,
+                "unique_sources": unique_sources,
+                "remote_addr": details.get("remote_addr"),
+            })),
+        )
+    });
+    
+    // Send event
+    let event = Event::honeypot_activity(
+        "lurefield",
+        Some(serde_json::json!({
+            "action": "interaction",
+            "honeypot_id": id,
+            "details": details,
+        })),
+    );
+    
+    if let Err(e) = event_sender.send(event).await {
+        tracing::error!("Failed to send honeypot interaction event: {}", e);
+    }
+    if let Some(event) = milestone {
+        if let Err(e) = event_sender.send(event).await {
+            tracing::error!("Failed to send honeypot milestone alert: {}", e);
+        }
+    }
+}
+
 impl HoneypotType {
     /// Convert a honeypot type to a string
     pub fn to_str(&self) -> &str {
@@ -426,3 +1209,348 @@ impl HoneypotType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    
+    #[tokio::test]
+    async fn test_same_type_honeypots_get_distinct_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        
+        let mut ids = Vec::new();
+        for _ in 0..5 {
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let options = HoneypotOptions { port, ..Default::default() };
+            ids.push(lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap());
+        }
+        
+        let honeypots = lurefield.get_honeypots().await;
+        assert_eq!(honeypots.len(), 5);
+        for id in &ids {
+            assert!(id.starts_with("hp-http-"));
+            assert_eq!(&honeypots[id].id, id);
+        }
+        
+        lurefield.stop().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reserved_ports_are_refused_or_warned_about() {
+        let dir = tempfile::tempdir().unwrap();
+        let reserved = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let free = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            port_policy: PortPolicy::Deny,
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config.clone(), EventSender::from(tx.clone()))
+            .await
+            .unwrap()
+            .with_reserved_ports(move || vec![reserved]);
+        
+        let options = HoneypotOptions { port: reserved, ..Default::default() };
+        let error = lurefield.deploy_honeypot(HoneypotType::Http, Some(options.clone())).await.unwrap_err();
+        assert!(matches!(&error, LurefieldError::UnsafePort(message) if message.contains(&reserved.to_string())), "{}", error);
+        assert!(lurefield.get_honeypots().await.is_empty());
+        
+        let free_options = HoneypotOptions { port: free, ..Default::default() };
+        lurefield.deploy_honeypot(HoneypotType::Http, Some(free_options)).await.unwrap();
+        lurefield.stop().await.unwrap();
+        
+        // With the default policy the deployment only logs a warning
+        let config = LurefieldConfig { port_policy: PortPolicy::Warn, ..config };
+        let lurefield = Lurefield::new(config, EventSender::from(tx))
+            .await
+            .unwrap()
+            .with_reserved_ports(move || vec![reserved]);
+        lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap();
+        lurefield.stop().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_posture_changes_deploy_mapped_honeypots() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ports = HashMap::new();
+        for (name, honeypot_type) in [("ssh-lure", "ssh"), ("web-lure", "http"), ("ftp-lure", "ftp"), ("manual", "http")] {
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let definition = format!("type = \"{}\"\nport = {}\n", honeypot_type, port);
+            std::fs::write(dir.path().join(format!("{}.toml", name)), definition).unwrap();
+            ports.insert(name, port);
+        }
+        
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            posture_honeypots: HashMap::from([
+                ("mimetic".to_string(), vec!["ssh-lure".to_string(), "web-lure".to_string()]),
+                ("fulgurant".to_string(), vec!["ssh-lure".to_string(), "ftp-lure".to_string()]),
+            ]),
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(64);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        let change = |posture: &str| {
+            Event::posture_change("core", Some(serde_json::json!({ "old_posture": "Neutral", "new_posture": posture })))
+        };
+        let deployed_ports = || async {
+            let mut ports: Vec<u16> = lurefield.get_honeypots().await.values().map(|honeypot| honeypot.port).collect();
+            ports.sort();
+            ports
+        };
+        let sorted = |mut expected: Vec<u16>| {
+            expected.sort();
+            expected
+        };
+        
+        // Honeypots deployed by hand are left alone
+        let manual = lurefield.deploy_from_definition("manual").await.unwrap();
+        
+        lurefield.handle_event(&change("Mimetic")).await.unwrap();
+        assert_eq!(deployed_ports().await, sorted(vec![ports["manual"], ports["ssh-lure"], ports["web-lure"]]));
+        tokio::net::TcpStream::connect(("127.0.0.1", ports["web-lure"])).await.unwrap();
+        let ssh_id = lurefield.posture_deployed.lock().await["ssh-lure"].clone();
+        
+        // The SSH lure is kept, the web one swapped for FTP
+        lurefield.handle_event(&change("Fulgurant")).await.unwrap();
+        assert_eq!(deployed_ports().await, sorted(vec![ports["manual"], ports["ssh-lure"], ports["ftp-lure"]]));
+        assert_eq!(lurefield.posture_deployed.lock().await["ssh-lure"], ssh_id);
+        
+        // Postures without honeypots tear them all down, other events are ignored
+        lurefield.handle_event(&change("Silent")).await.unwrap();
+        lurefield.handle_event(&Event::security_alert("eye360", None)).await.unwrap();
+        assert_eq!(lurefield.get_honeypots().await.into_keys().collect::<Vec<_>>(), [manual]);
+        
+        lurefield.stop().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_stats_track_engagement() {
+        use tokio::io::AsyncWriteExt;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            interaction_milestones: Vec::new(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let metrics = Arc::new(MetricsCollector::new());
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap().with_metrics(metrics.clone());
+        
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let options = HoneypotOptions { port, ..Default::default() };
+            ids.push(lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap());
+            rx.recv().await.unwrap();
+        }
+        let (busy, idle) = (&ids[1], &ids[0]);
+        
+        // Two connections from the same address, one reported from elsewhere
+        let port = lurefield.get_honeypots().await[busy].port;
+        for _ in 0..2 {
+            let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+            stream.shutdown().await.unwrap();
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+            assert_eq!(event.data.unwrap()["action"], "interaction");
+        }
+        let details = HashMap::from([("remote_addr".to_string(), "10.0.0.7:40000".to_string())]);
+        lurefield.record_interaction(busy, details).await.unwrap();
+        
+        let stats = lurefield.stats().await;
+        assert_eq!(stats.iter().map(|stats| &stats.id).collect::<Vec<_>>(), [busy, idle]);
+        assert_eq!(stats[0].interactions, 3);
+        assert_eq!(stats[0].unique_sources, 2);
+        assert!(stats[0].first_interaction.unwrap() <= stats[0].last_interaction.unwrap());
+        assert_eq!((stats[1].interactions, stats[1].unique_sources, stats[1].first_interaction), (0, 0, None));
+        
+        assert_eq!(metrics.get_counter("honeypot_interactions"), 3);
+        assert_eq!(
+            metrics.get_gauge("honeypot_last_interaction"),
+            stats[0].last_interaction.map(|at| at.timestamp() as f64)
+        );
+        
+        // Past the cap, new addresses are no longer remembered
+        {
+            let honeypots = lurefield.honeypots.read().await;
+            let honeypot = honeypots[idle].read().await;
+            for i in 0..MAX_TRACKED_SOURCES as u32 + 10 {
+                let source = IpAddr::V4(std::net::Ipv4Addr::from(0x0a00_0000 + i));
+                honeypot.count_interaction(Some(source), chrono::Utc::now());
+            }
+        }
+        let stats = lurefield.stats().await;
+        let idle = stats.iter().find(|stats| &stats.id == idle).unwrap();
+        assert_eq!(idle.unique_sources, MAX_TRACKED_SOURCES);
+        
+        lurefield.stop().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_interaction_milestones_raise_alerts() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            interaction_milestones: vec![1, 3],
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = lurefield.deploy_honeypot(HoneypotType::Ftp, Some(options)).await.unwrap();
+        rx.recv().await.unwrap();
+        
+        for _ in 0..4 {
+            let details = HashMap::from([("remote_addr".to_string(), "10.0.0.7:40000".to_string())]);
+            lurefield.record_interaction(&id, details).await.unwrap();
+        }
+        
+        let alerts: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|event| event.event_type == EventType::SecurityAlert)
+            .collect();
+        assert_eq!(alerts.len(), 2);
+        for (alert, interactions) in alerts.iter().zip([1, 3]) {
+            let data = alert.data.as_ref().unwrap();
+            assert_eq!(data["action"], "interaction_milestone");
+            assert_eq!(data["honeypot_id"], id.as_str());
+            assert_eq!(data["interactions"], interactions);
+            assert_eq!(data["remote_addr"], "10.0.0.7:40000");
+            assert_eq!(alert.severity(), chame_core::Severity::High);
+        }
+        
+        lurefield.stop().await.unwrap();
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_interactions_are_all_counted() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let metrics = Arc::new(MetricsCollector::new());
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap().with_metrics(metrics.clone());
+        
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap();
+        let drain = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        
+        let lurefield = Arc::new(lurefield);
+        let tasks: Vec<_> = (0..50u8)
+            .map(|task| {
+                let lurefield = lurefield.clone();
+                let id = id.clone();
+                tokio::spawn(async move {
+                    for _ in 0..20 {
+                        let remote_addr = format!("10.0.0.{}:40000", task % 10);
+                        let details = HashMap::from([("remote_addr".to_string(), remote_addr)]);
+                        lurefield.record_interaction(&id, details).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        
+        let stats = lurefield.stats().await;
+        assert_eq!((stats[0].interactions, stats[0].unique_sources), (1000, 10));
+        assert_eq!(lurefield.get_honeypots().await[&id].interaction_count.load(Ordering::Relaxed), 1000);
+        assert_eq!(metrics.get_counter("honeypot_interactions"), 1000);
+        
+        lurefield.stop().await.unwrap();
+        drain.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_registry_restores_honeypots_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            interaction_milestones: Vec::new(),
+            registry_path: Some(dir.path().join("state").join("registry.json")),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(64);
+        let lurefield = Lurefield::new(config.clone(), EventSender::from(tx)).await.unwrap();
+        
+        let mut deployed = Vec::new();
+        for (honeypot_type, interactions) in [(HoneypotType::Http, 3), (HoneypotType::Database("mysql".to_string()), 1)] {
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let options = HoneypotOptions { port, fake_auth: true, ..Default::default() };
+            let id = lurefield.deploy_honeypot(honeypot_type.clone(), Some(options)).await.unwrap();
+            for _ in 0..interactions {
+                let details = HashMap::from([("remote_addr".to_string(), "10.0.0.7:40000".to_string())]);
+                lurefield.record_interaction(&id, details).await.unwrap();
+            }
+            deployed.push((id, honeypot_type, port, interactions));
+        }
+        lurefield.stop().await.unwrap();
+        drop(lurefield);
+        // Stop events come once the listeners released their ports
+        let mut stopped = 0;
+        while stopped < deployed.len() {
+            let event = rx.recv().await.unwrap();
+            if event.data.is_some_and(|data| data["action"] == "stop") {
+                stopped += 1;
+            }
+        }
+        
+        // Milestones count the restored interactions
+        let config = LurefieldConfig { interaction_milestones: vec![4], ..config };
+        let (tx, mut rx) = mpsc::channel(64);
+        let restarted = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        let honeypots = restarted.get_honeypots().await;
+        assert_eq!(honeypots.len(), 2);
+        for (id, honeypot_type, port, interactions) in &deployed {
+            let honeypot = &honeypots[id];
+            assert_eq!((&honeypot.honeypot_type, honeypot.port, honeypot.active), (honeypot_type, *port, true));
+            assert!(honeypot.options.fake_auth);
+            assert_eq!(honeypot.persisted_interactions, *interactions);
+            assert_eq!(honeypot.interaction_count.load(Ordering::Relaxed), 0);
+            assert!(honeypot.first_interaction().is_some());
+        }
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.data.unwrap()["restored"], true);
+        
+        let (http, ..) = &deployed[0];
+        let details = HashMap::from([("remote_addr".to_string(), "10.0.0.7:40000".to_string())]);
+        restarted.record_interaction(http, details).await.unwrap();
+        let alert = loop {
+            let event = rx.recv().await.unwrap();
+            if event.event_type == EventType::SecurityAlert {
+                break event;
+            }
+        };
+        assert_eq!(alert.data.unwrap()["interactions"], 4);
+        
+        // New honeypots don't reuse a restored ID
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = restarted.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap();
+        assert!(deployed.iter().all(|(restored, ..)| *restored != id));
+        
+        restarted.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,536 @@
+use crate::capture::Payload;
+use crate::definition::Script;
+use crate::script::Session;
+use crate::{report_interaction, Honeypot, HoneypotType};
+use chame_core::{EventSender, MetricsCollector};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Consecutive accept failures after which the listener gives up
+const MAX_ACCEPT_ERRORS: u32 = 10;
+
+/// How a honeypot listener talks to and records its visitors
+#[derive(Debug, Clone)]
+pub struct CaptureSettings {
+    /// Type of service to imitate
+    pub honeypot_type: HoneypotType,
+
+    /// Banner sent as soon as a client connects
+    pub banner: Option<String>,
+
+    /// Maximum bytes captured per connection
+    pub max_bytes: usize,
+
+    /// Bytes a client may send before the connection is dropped
+    pub max_connection_bytes: usize,
+
+    /// How long a connection may stay open
+    pub timeout: Duration,
+
+    /// Whether to answer login attempts so attackers keep going
+    pub fake_auth: bool,
+
+    /// Whether to keep the captured bytes in the interaction details
+    pub log_keystroke: bool,
+
+    /// Scripted behavior, replacing the built-in one
+    pub(crate) script: Option<Arc<Script>>,
+
+    /// Interaction counts raising a `SecurityAlert`
+    pub milestones: Vec<u32>,
+
+    /// Slots bounding the connections handled at once
+    pub(crate) connection_slots: ConnectionSlots,
+}
+
+/// Connections a honeypot may handle at once
+///
+/// A connection takes a slot of the honeypot and one of the pool shared by
+/// every honeypot; connections finding either full are closed right away.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionSlots {
+    /// Slots shared by every honeypot
+    pub(crate) shared: Arc<Semaphore>,
+
+    /// Slots of this honeypot
+    pub(crate) own: Arc<Semaphore>,
+}
+
+impl ConnectionSlots {
+    /// `limit` slots, 0 for no limit
+    pub(crate) fn pool(limit: usize) -> Arc<Semaphore> {
+        match limit {
+            0 => Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)),
+            limit => Arc::new(Semaphore::new(limit)),
+        }
+    }
+
+    /// Take a slot of each pool, or name the one that is full
+    fn acquire(&self) -> Result<[OwnedSemaphorePermit; 2], &'static str> {
+        let own = self.own.clone().try_acquire_owned().map_err(|_| "per-honeypot")?;
+        let shared = self.shared.clone().try_acquire_owned().map_err(|_| "global")?;
+        Ok([own, shared])
+    }
+}
+
+/// Accept connections, recording one interaction per connection
+///
+/// The task runs until aborted, or until accepting keeps failing; the
+/// supervisor then restarts it. Connections over the honeypot's or the
+/// global connection limit are closed without being read, and counted in
+/// `honeypot_rejected_connections`.
+pub fn spawn(
+    listener: TcpListener,
+    honeypot: Arc<RwLock<Honeypot>>,
+    settings: CaptureSettings,
+    event_sender: EventSender,
+    metrics: Option<Arc<MetricsCollector>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let id = honeypot.read().await.id.clone();
+        let mut errors = 0;
+        let mut saturated = false;
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // Usually transient, e.g. too many open files
+                    errors += 1;
+                    tracing::warn!("Honeypot failed to accept a connection: {}", e);
+                    if errors >= MAX_ACCEPT_ERRORS {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            errors = 0;
+
+            let slots = match settings.connection_slots.acquire() {
+                Ok(slots) => slots,
+                Err(limit) => {
+                    // Logged once per burst, a flood would drown the logs
+                    if !saturated {
+                        tracing::warn!("Honeypot {} reached the {} connection limit, rejecting connections", id, limit);
+                    }
+                    tracing::debug!("Rejected honeypot connection from {}", peer);
+                    saturated = true;
+                    if let Some(metrics) = &metrics {
+                        metrics.increment_counter("honeypot_rejected_connections");
+                    }
+                    continue;
+                }
+            };
+            if std::mem::take(&mut saturated) {
+                tracing::info!("Honeypot {} accepting connections again", id);
+            }
+
+            let honeypot = honeypot.clone();
+            let settings = settings.clone();
+            let event_sender = event_sender.clone();
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                handle_connection(stream, peer, &honeypot, &settings, &event_sender, metrics.as_deref()).await;
+                drop(slots);
+            });
+        }
+    })
+}
+
+/// Where the interactions of a connection are reported
+struct Recorder<'a> {
+    honeypot: &'a RwLock<Honeypot>,
+    event_sender: &'a EventSender,
+    metrics: Option<&'a MetricsCollector>,
+    peer: SocketAddr,
+    settings: &'a CaptureSettings,
+}
+
+impl Recorder<'_> {
+    /// Report an interaction, with the connection's address and protocol
+    async fn record(&self, mut details: HashMap<String, String>) {
+        details.insert("remote_addr".to_string(), self.peer.to_string());
+        details.insert("protocol".to_string(), self.settings.honeypot_type.to_str().to_string());
+        report_interaction(self.honeypot, self.event_sender, self.metrics, &self.settings.milestones, details).await;
+    }
+
+    /// Report a line answered by a script
+    ///
+    /// Like the payload, the line itself is only kept with `log_keystroke`.
+    async fn record_input(&self, state: &str, line: &str) {
+        let mut details = HashMap::new();
+        details.insert("script_state".to_string(), state.to_string());
+        details.insert("input_bytes".to_string(), line.len().to_string());
+        if self.settings.log_keystroke {
+            details.insert("input".to_string(), line.to_string());
+        }
+        self.record(details).await;
+    }
+}
+
+/// Capture what a client sends and report it
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    honeypot: &RwLock<Honeypot>,
+    settings: &CaptureSettings,
+    event_sender: &EventSender,
+    metrics: Option<&MetricsCollector>,
+) {
+    let recorder = Recorder {
+        honeypot,
+        event_sender,
+        metrics,
+        peer,
+        settings,
+    };
+    let (payload, dropped) = match capture(&mut stream, &recorder).await {
+        Ok(captured) => captured,
+        Err(e) => {
+            tracing::debug!("Honeypot connection from {} failed: {}", peer, e);
+            return;
+        }
+    };
+
+    let mut details = payload.details(settings.log_keystroke);
+    if let Some(reason) = dropped {
+        tracing::info!("Dropped honeypot connection from {}: {}", peer, reason);
+        details.insert("reason".to_string(), reason.to_string());
+    }
+    recorder.record(details).await;
+}
+
+/// Send the banner, then read until the client stops or hits a limit
+///
+/// Returns the captured payload and, when the connection was dropped for
+/// staying open too long or sending too much, `"timeout"` or `"size_limit"`.
+/// The timeout also covers writes, so a client that never reads its
+/// replies is dropped too.
+/// With a scripted definition, every line is also recorded as it comes.
+async fn capture(stream: &mut TcpStream, recorder: &Recorder<'_>) -> std::io::Result<(Payload, Option<&'static str>)> {
+    let (peer, settings) = (recorder.peer, recorder.settings);
+    let mut session = settings.script.as_ref().and_then(|script| script.session());
+
+    let banner = match &settings.script {
+        Some(script) => script.banner(peer).or_else(|| settings.banner.clone()),
+        None => settings.banner.clone(),
+    };
+    let deadline = Instant::now() + settings.timeout;
+    let mut payload = Payload::default();
+    if let Some(banner) = banner {
+        if !write_before(stream, banner.as_bytes(), deadline).await? {
+            return Ok((payload, Some("timeout")));
+        }
+    }
+
+    let mut buffer = [0u8; 1024];
+    let mut answered = 0;
+    let mut received = 0;
+
+    loop {
+        let read = match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+            Ok(read) => read?,
+            Err(_) => return Ok((payload, Some("timeout"))),
+        };
+        if read == 0 {
+            break;
+        }
+
+        // Bytes past the capture cap are read and discarded until the
+        // connection's own limit
+        let room = settings.max_bytes.saturating_sub(payload.bytes.len());
+        payload.truncated |= read > room;
+        payload.bytes.extend_from_slice(&buffer[..read.min(room)]);
+
+        received += read;
+        if received > settings.max_connection_bytes {
+            return Ok((payload, Some("size_limit")));
+        }
+
+        // Answer every line completed by this read
+        while let Some(end) = payload.bytes[answered..].iter().position(|&b| b == b'\n') {
+            let line = String::from_utf8_lossy(&payload.bytes[answered..answered + end]).to_string();
+            answered += end + 1;
+
+            if let Some(session) = &session {
+                recorder.record_input(&session.state, line.trim_end_matches('\r')).await;
+            }
+
+            let (response, close) = match reply(settings, peer, &line, session.as_mut()) {
+                Some(Reply::Line(response)) => (response, false),
+                Some(Reply::Close(response)) => (response, true),
+                None => continue,
+            };
+            if !write_before(stream, response.as_bytes(), deadline).await? {
+                return Ok((payload, Some("timeout")));
+            }
+            if close {
+                return Ok((payload, None));
+            }
+        }
+    }
+
+    Ok((payload, None))
+}
+
+/// Write `data` to the client, `false` when `deadline` passes first
+async fn write_before(stream: &mut TcpStream, data: &[u8], deadline: Instant) -> std::io::Result<bool> {
+    match tokio::time::timeout_at(deadline, stream.write_all(data)).await {
+        Ok(written) => written.map(|()| true),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Response to a line sent by the client
+enum Reply {
+    /// Answer and keep reading
+    Line(Cow<'static, str>),
+
+    /// Answer and close the connection
+    Close(Cow<'static, str>),
+}
+
+/// Answer a line with the honeypot's script, or imitate just enough of the
+/// protocol for clients to send credentials
+fn reply(settings: &CaptureSettings, peer: SocketAddr, line: &str, session: Option<&mut Session>) -> Option<Reply> {
+    if let Some(script) = &settings.script {
+        return script.reply(peer, line, session).map(|(response, close)| match close {
+            true => Reply::Close(response.into()),
+            false => Reply::Line(response.into()),
+        });
+    }
+
+    let line = line.trim_end_matches('\r');
+    let command = line.split_whitespace().next().unwrap_or_default().to_uppercase();
+
+    match settings.honeypot_type {
+        HoneypotType::Ftp if settings.fake_auth => Some(match command.as_str() {
+            "USER" => Reply::Line("331 Please specify the password.\r\n".into()),
+            "PASS" => Reply::Line("530 Login incorrect.\r\n".into()),
+            "QUIT" => Reply::Close("221 Goodbye.\r\n".into()),
+            _ => Reply::Line("530 Please login with USER and PASS.\r\n".into()),
+        }),
+        // An empty line ends the request headers
+        HoneypotType::Http if line.is_empty() => Some(Reply::Close(if settings.fake_auth {
+            "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"Restricted\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        } else {
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        }.into())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HoneypotOptions, HoneypotType, Lurefield, LurefieldConfig};
+    use chame_core::events::EventType;
+    use chame_core::EventSender;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::sync::mpsc;
+
+    /// Whether the honeypot closes a new connection before reading it
+    async fn rejected(port: u16) -> (bool, tokio::net::TcpStream) {
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut buffer = [0u8; 16];
+        let closed = match tokio::time::timeout(Duration::from_millis(300), stream.read(&mut buffer)).await {
+            Ok(read) => matches!(read, Ok(0) | Err(_)),
+            Err(_) => false,
+        };
+        (closed, stream)
+    }
+
+    #[tokio::test]
+    async fn test_connections_over_the_limits_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            max_concurrent_connections: 2,
+            max_connections_per_honeypot: 1,
+            listen_backlog: 16,
+            interaction_milestones: Vec::new(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let metrics = std::sync::Arc::new(chame_core::MetricsCollector::new());
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap().with_metrics(metrics.clone());
+
+        let mut ids = Vec::new();
+        let mut ports = Vec::new();
+        for _ in 0..3 {
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let options = HoneypotOptions { port, ..Default::default() };
+            ids.push(lurefield.deploy_honeypot(HoneypotType::Custom("telnet".to_string()), Some(options)).await.unwrap());
+            ports.push(port);
+            rx.recv().await.unwrap();
+        }
+
+        // One connection per honeypot
+        let (closed, first) = rejected(ports[0]).await;
+        assert!(!closed);
+        let (closed, _) = rejected(ports[0]).await;
+        assert!(closed);
+        assert_eq!(metrics.get_counter("honeypot_rejected_connections"), 1);
+
+        // Two across all of them
+        let (closed, _second) = rejected(ports[1]).await;
+        assert!(!closed);
+        let (closed, _) = rejected(ports[2]).await;
+        assert!(closed);
+
+        // A slot frees up once its connection is handled
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let (closed, _) = rejected(ports[2]).await;
+        assert!(!closed);
+
+        for id in &ids {
+            lurefield.stop_honeypot(id).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ftp_honeypot_captures_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = lurefield.deploy_honeypot(HoneypotType::Ftp, Some(options)).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().data.unwrap()["action"], "deploy");
+
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("220"));
+
+        for (command, expected) in [("USER admin\r\n", "331"), ("PASS hunter2\r\n", "530"), ("QUIT\r\n", "221")] {
+            stream.get_mut().write_all(command.as_bytes()).await.unwrap();
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with(expected), "{}", line);
+        }
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::HoneypotActivity);
+        let data = event.data.unwrap();
+        assert_eq!(data["honeypot_id"], id);
+        assert_eq!(data["details"]["credentials_detected"], "true");
+        assert_eq!(data["details"]["credential_patterns"], "ftp_user,ftp_pass");
+        assert_eq!(data["details"]["payload_text"], "USER admin..PASS hunter2..QUIT..");
+
+        lurefield.stop_honeypot(&id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_dropped_at_their_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            capture_bytes: 16,
+            interaction_milestones: Vec::new(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions {
+            port,
+            connection_timeout: Duration::from_millis(200),
+            max_bytes_per_connection: 4096,
+            ..Default::default()
+        };
+        let id = lurefield.deploy_honeypot(HoneypotType::Custom("telnet".to_string()), Some(options)).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().data.unwrap()["action"], "deploy");
+
+        // A client that connects and says nothing is dropped after the timeout
+        let mut idle = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        let details = &event.data.unwrap()["details"];
+        assert_eq!(details["reason"], "timeout");
+        assert_eq!(details["bytes_received"], "0");
+        let mut rest = Vec::new();
+        assert_eq!(idle.read_to_end(&mut rest).await.unwrap(), 0);
+
+        // A client streaming data is dropped past the byte cap, only the
+        // capture cap being kept
+        let mut flood = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let chunk = [b'A'; 1024];
+        for _ in 0..8 {
+            if flood.write_all(&chunk).await.is_err() {
+                break;
+            }
+        }
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        let details = &event.data.unwrap()["details"];
+        assert_eq!(details["reason"], "size_limit");
+        assert_eq!(details["bytes_received"], "16");
+        assert_eq!(details["payload_truncated"], "true");
+
+        // A client closing on its own has no reason
+        let mut polite = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        polite.write_all(b"hello\r\n").await.unwrap();
+        polite.shutdown().await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(event.data.unwrap()["details"].get("reason").is_none());
+
+        lurefield.stop_honeypot(&id).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_clients_that_never_read_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        // Every line is answered with far more than it costs to send
+        let definition = format!(
+            "type = \"ssh\"\nport = {}\nconnection_timeout_secs = 1\ndefault_response = \"{}\"\n",
+            port,
+            "A".repeat(4 * 1024)
+        );
+        std::fs::write(dir.path().join("chatty.toml"), definition).unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            interaction_milestones: Vec::new(),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        let id = lurefield.deploy_from_definition("chatty").await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().data.unwrap()["action"], "deploy");
+
+        // The replies fill the socket buffers, the client reading none of them
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.set_recv_buffer_size(4096).unwrap();
+        let mut client = socket.connect(([127, 0, 0, 1], port).into()).await.unwrap();
+        for _ in 0..256 {
+            if client.write_all(b"x\n").await.is_err() {
+                break;
+            }
+        }
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::HoneypotActivity);
+        assert_eq!(event.data.unwrap()["details"]["reason"], "timeout");
+
+        lurefield.stop_honeypot(&id).await.unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+//! Honeypots persisted across restarts
+//!
+//! With `LurefieldConfig::registry_path` set, the active honeypots are saved
+//! whenever one is deployed or stopped and when Lurefield stops. The next
+//! `Lurefield::new` deploys them again with the same IDs, ports and options,
+//! carrying their interaction counts over as `persisted_interactions`.
+
+use crate::{Honeypot, HoneypotOptions, HoneypotType, LurefieldError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// A honeypot as saved in the registry file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// Honeypot ID, kept across restarts
+    pub id: String,
+
+    /// Type, in the form `HoneypotType::from_str` reads back, e.g. `db:mysql`
+    pub honeypot_type: String,
+
+    /// Port the honeypot listens on
+    pub port: u16,
+
+    /// Whether to enable fake authentication
+    pub fake_auth: bool,
+
+    /// Whether to log keystrokes
+    pub log_keystroke: bool,
+
+    /// Custom banner or response
+    pub custom_banner: Option<String>,
+
+    /// Additional options, including the definition it was deployed from
+    #[serde(default)]
+    pub extra_options: HashMap<String, String>,
+
+    /// Milliseconds a connection may stay open
+    #[serde(default = "default_connection_timeout_ms")]
+    pub connection_timeout_ms: u64,
+
+    /// Bytes a client may send on one connection
+    #[serde(default = "default_max_bytes_per_connection")]
+    pub max_bytes_per_connection: usize,
+
+    /// When the honeypot was first deployed
+    pub deployed_at: chrono::DateTime<chrono::Utc>,
+
+    /// Interactions over every session so far
+    pub interactions: u32,
+
+    /// When the first interaction happened
+    pub first_interaction: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// When the last interaction happened
+    pub last_interaction: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Posture mapping entry the honeypot was deployed for, if any
+    #[serde(default)]
+    pub posture_entry: Option<String>,
+}
+
+impl RegistryEntry {
+    /// Describe a deployed honeypot, `posture_entry` naming the mapping it was deployed for
+    pub(crate) fn from_honeypot(honeypot: &Honeypot, posture_entry: Option<String>) -> Self {
+        let honeypot_type = match &honeypot.honeypot_type {
+            HoneypotType::Database(db_type) => format!("db:{}", db_type),
+            other => other.to_str().to_string(),
+        };
+
+        Self {
+            id: honeypot.id.clone(),
+            honeypot_type,
+            port: honeypot.port,
+            fake_auth: honeypot.options.fake_auth,
+            log_keystroke: honeypot.options.log_keystroke,
+            custom_banner: honeypot.options.custom_banner.clone(),
+            extra_options: honeypot.options.extra_options.clone(),
+            connection_timeout_ms: honeypot.options.connection_timeout.as_millis() as u64,
+            max_bytes_per_connection: honeypot.options.max_bytes_per_connection,
+            deployed_at: honeypot.deployed_at,
+            interactions: honeypot.total_interactions(),
+            first_interaction: honeypot.first_interaction(),
+            last_interaction: honeypot.last_interaction(),
+            posture_entry,
+        }
+    }
+
+    /// Options to deploy the honeypot with again
+    pub(crate) fn options(&self) -> HoneypotOptions {
+        HoneypotOptions {
+            port: self.port,
+            fake_auth: self.fake_auth,
+            log_keystroke: self.log_keystroke,
+            custom_banner: self.custom_banner.clone(),
+            extra_options: self.extra_options.clone(),
+            connection_timeout: Duration::from_millis(self.connection_timeout_ms),
+            max_bytes_per_connection: self.max_bytes_per_connection,
+        }
+    }
+
+    /// Sequence number at the end of the ID, e.g. 3 for `hp-ssh-1700000000-3`
+    pub(crate) fn sequence(&self) -> Option<u64> {
+        self.id.rsplit('-').next()?.parse().ok()
+    }
+}
+
+fn default_connection_timeout_ms() -> u64 {
+    HoneypotOptions::default().connection_timeout.as_millis() as u64
+}
+
+fn default_max_bytes_per_connection() -> usize {
+    HoneypotOptions::default().max_bytes_per_connection
+}
+
+/// Read the registry, empty when the file doesn't exist yet
+pub fn load(path: &Path) -> Result<Vec<RegistryEntry>, LurefieldError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    serde_json::from_str(&content).map_err(|e| {
+        LurefieldError::HoneypotDeployment(format!("Invalid honeypot registry {}: {}", path.display(), e))
+    })
+}
+
+/// Replace the registry, through a temporary file so a crash can't truncate it
+pub async fn save(path: &Path, entries: &[RegistryEntry]) -> Result<(), LurefieldError> {
+    let content = serde_json::to_vec_pretty(entries).map_err(|e| {
+        LurefieldError::HoneypotDeployment(format!("Failed to serialize the honeypot registry: {}", e))
+    })?;
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temporary = path.with_extension("tmp");
+    tokio::fs::write(&temporary, content).await?;
+    tokio::fs::rename(&temporary, path).await?;
+
+    Ok(())
+}
@@ -0,0 +1,284 @@
+//! Scripted dialogs for honeypots defined in `honeypot_dir`
+//!
+//! A script is a small state machine: in each state, the first step whose
+//! `expect` regex matches the received line is answered with its response,
+//! then the connection moves to the step's `next` state. A fake SSH login
+//! followed by a shell:
+//!
+//! ```toml
+//! type = "ssh"
+//! banner = "SSH-2.0-OpenSSH_8.2p1 Ubuntu-4ubuntu0.5\r\nlogin: "
+//! default_response = "-bash: {{input}}: command not found\r\n$ "
+//!
+//! [script]
+//! max_steps = 32           # Optional, inputs answered before closing
+//!
+//! [[script.steps]]
+//! state = "login"          # The first step's state is the initial one
+//! expect = ".+"
+//! response = "Password: "
+//! next = "password"
+//!
+//! [[script.steps]]
+//! state = "password"
+//! expect = ".*"            # Any password works
+//! response = "Welcome to Ubuntu 20.04.6 LTS\r\n$ "
+//! next = "shell"
+//!
+//! [[script.steps]]
+//! state = "shell"
+//! expect = "^whoami$"
+//! response = "root\r\n$ "
+//!
+//! [[script.steps]]
+//! state = "shell"
+//! expect = "^(exit|logout)$"
+//! response = "logout\r\n"
+//! close = true
+//! ```
+//!
+//! Lines no step matches get the definition's `default_response`, if any,
+//! and leave the state unchanged. Responses are templates like the other
+//! definition templates and can also use `state`, the state the line was
+//! received in. Every line is recorded as an interaction of its own.
+
+use crate::LurefieldError;
+use serde::Deserialize;
+
+/// Default number of inputs a connection may send before it is closed
+pub const DEFAULT_MAX_STEPS: usize = 64;
+
+/// Default length of the part of a line matched against the steps
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024;
+
+/// Size limit of each compiled `expect` regex
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Scripted dialog of a honeypot definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoneypotScript {
+    /// State a connection starts in, the first step's state when unset
+    #[serde(default)]
+    pub initial: Option<String>,
+
+    /// Inputs answered before the connection is closed
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+
+    /// Longer lines are cut to this many bytes before matching
+    #[serde(default = "default_max_line_bytes")]
+    pub max_line_bytes: usize,
+
+    /// Steps of every state, in matching order
+    pub steps: Vec<ScriptStep>,
+}
+
+fn default_max_steps() -> usize {
+    DEFAULT_MAX_STEPS
+}
+
+fn default_max_line_bytes() -> usize {
+    DEFAULT_MAX_LINE_BYTES
+}
+
+/// One `(expected input -> response)` transition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptStep {
+    /// State the step applies in
+    pub state: String,
+
+    /// Regex the received line must match, without its line ending
+    pub expect: String,
+
+    /// Response template
+    pub response: String,
+
+    /// State to move to, the current one when unset
+    #[serde(default)]
+    pub next: Option<String>,
+
+    /// Whether to close the connection after answering
+    #[serde(default)]
+    pub close: bool,
+}
+
+impl HoneypotScript {
+    /// Check the script and compile its regexes
+    pub(crate) fn compile(&self) -> Result<Machine, LurefieldError> {
+        let invalid = |message: String| LurefieldError::Definition(format!("Invalid script: {}", message));
+
+        let first = self.steps.first().ok_or_else(|| invalid("no steps".to_string()))?;
+        let initial = self.initial.clone().unwrap_or_else(|| first.state.clone());
+        if self.max_steps == 0 || self.max_line_bytes == 0 {
+            return Err(invalid("max_steps and max_line_bytes must be positive".to_string()));
+        }
+
+        let known = |state: &str| self.steps.iter().any(|step| step.state == state);
+        if !known(&initial) {
+            return Err(invalid(format!("initial state '{}' has no steps", initial)));
+        }
+
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            if let Some(next) = step.next.as_deref().filter(|next| !known(next)) {
+                return Err(invalid(format!("state '{}' has no steps", next)));
+            }
+            let expect = regex::RegexBuilder::new(&step.expect)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+                .map_err(|e| invalid(format!("expect '{}': {}", step.expect, e)))?;
+            steps.push((expect, step.clone()));
+        }
+
+        Ok(Machine {
+            initial,
+            max_steps: self.max_steps,
+            max_line_bytes: self.max_line_bytes,
+            steps,
+        })
+    }
+}
+
+/// A compiled script, shared by the connections of a honeypot
+#[derive(Debug)]
+pub(crate) struct Machine {
+    initial: String,
+    max_steps: usize,
+    max_line_bytes: usize,
+    steps: Vec<(regex::Regex, ScriptStep)>,
+}
+
+/// Where a connection is in the script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Session {
+    /// Current state
+    pub(crate) state: String,
+
+    /// Inputs received so far
+    pub(crate) inputs: usize,
+}
+
+/// What to do with a received line
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Transition<'a> {
+    /// Answer with the step's response
+    Step(&'a ScriptStep),
+
+    /// No step matched
+    NoMatch,
+
+    /// The connection sent `max_steps` inputs already
+    Exhausted,
+}
+
+impl Machine {
+    /// Session of a new connection
+    pub(crate) fn start(&self) -> Session {
+        Session {
+            state: self.initial.clone(),
+            inputs: 0,
+        }
+    }
+
+    /// The received line, cut to `max_line_bytes`
+    pub(crate) fn input<'l>(&self, line: &'l str) -> &'l str {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut end = line.len().min(self.max_line_bytes);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        &line[..end]
+    }
+
+    /// Advance `session` on a received line
+    pub(crate) fn advance(&self, session: &mut Session, line: &str) -> Transition<'_> {
+        if session.inputs >= self.max_steps {
+            return Transition::Exhausted;
+        }
+        session.inputs += 1;
+
+        let input = self.input(line);
+        let step = self
+            .steps
+            .iter()
+            .find(|(expect, step)| step.state == session.state && expect.is_match(input));
+
+        match step {
+            Some((_, step)) => {
+                if let Some(next) = &step.next {
+                    session.state = next.clone();
+                }
+                Transition::Step(step)
+            }
+            None => Transition::NoMatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL: &str = r#"
+initial = "login"
+max_steps = 5
+max_line_bytes = 8
+
+[[steps]]
+state = "login"
+expect = ".+"
+response = "Password: "
+next = "shell"
+
+[[steps]]
+state = "shell"
+expect = "^whoami$"
+response = "root\r\n$ "
+
+[[steps]]
+state = "shell"
+expect = "^exit$"
+response = "logout\r\n"
+close = true
+"#;
+
+    fn machine(script: &str) -> Result<Machine, LurefieldError> {
+        toml::from_str::<HoneypotScript>(script).unwrap().compile()
+    }
+
+    #[test]
+    fn test_script_transitions() {
+        let machine = machine(SHELL).unwrap();
+        let mut session = machine.start();
+        let response = |transition: Transition| match transition {
+            Transition::Step(step) => Some((step.response.clone(), step.close)),
+            _ => None,
+        };
+
+        // Nothing matches an empty login
+        assert_eq!(machine.advance(&mut session, "\r\n"), Transition::NoMatch);
+        assert_eq!(response(machine.advance(&mut session, "admin\r\n")), Some(("Password: ".to_string(), false)));
+        assert_eq!(session.state, "shell");
+        assert_eq!(response(machine.advance(&mut session, "whoami\r\n")), Some(("root\r\n$ ".to_string(), false)));
+
+        // Matching only sees the first max_line_bytes bytes
+        assert_eq!(machine.input("whoami; cat /etc/passwd"), "whoami; ");
+        assert_eq!(machine.advance(&mut session, "whoami; cat /etc/passwd"), Transition::NoMatch);
+        assert_eq!(response(machine.advance(&mut session, "exit")), Some(("logout\r\n".to_string(), true)));
+        assert_eq!(machine.advance(&mut session, "whoami"), Transition::Exhausted);
+        assert_eq!(session.inputs, 5);
+
+        assert_eq!(machine.input("héhéhéhé"), "héhéh");
+    }
+
+    #[test]
+    fn test_invalid_scripts() {
+        assert!(machine("steps = []").is_err());
+        assert!(machine(&SHELL.replace("next = \"shell\"", "next = \"root\"")).is_err());
+        assert!(machine(&SHELL.replace("initial = \"login\"", "initial = \"motd\"")).is_err());
+        assert!(machine(&SHELL.replace("^whoami$", "(whoami")).is_err());
+        assert!(machine(&SHELL.replace("max_steps = 5", "max_steps = 0")).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{bind_listener, capture_settings, listener, Honeypot, LurefieldConfig};
+use chame_core::events::{Event, Severity};
+use chame_core::{EventSender, MetricsCollector};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// How often listener tasks are checked
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest wait between two restarts of the same honeypot
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Watch every honeypot's listener task and bring back the ones that died
+pub fn spawn(
+    config: LurefieldConfig,
+    honeypots: Arc<RwLock<HashMap<String, Arc<RwLock<Honeypot>>>>>,
+    event_sender: EventSender,
+    metrics: Option<Arc<MetricsCollector>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SUPERVISE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let honeypots: Vec<_> = honeypots.read().await.values().cloned().collect();
+            for honeypot in honeypots {
+                supervise(&config, &honeypot, &event_sender, metrics.as_ref()).await;
+            }
+        }
+    })
+}
+
+/// Delay before the given restart, doubling with each attempt
+fn backoff(config: &LurefieldConfig, attempt: u32) -> Duration {
+    config
+        .restart_backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RESTART_BACKOFF)
+}
+
+/// Restart a honeypot whose listener exited while it is still meant to be active
+async fn supervise(
+    config: &LurefieldConfig,
+    honeypot_lock: &Arc<RwLock<Honeypot>>,
+    event_sender: &EventSender,
+    metrics: Option<&Arc<MetricsCollector>>,
+) {
+    let mut honeypot = honeypot_lock.write().await;
+
+    let alive = honeypot.listener_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+    if alive {
+        // A listener that stayed up long enough starts over with every attempt
+        let stable = honeypot.restarted_at.is_some_and(|at| at.elapsed() >= config.restart_reset_after);
+        if stable {
+            tracing::debug!("Honeypot {} is stable again after {} restarts", honeypot.id, honeypot.restarts);
+            honeypot.restarts = 0;
+            honeypot.restarted_at = None;
+        }
+        return;
+    }
+    if !honeypot.active {
+        return;
+    }
+
+    if honeypot.restarts >= config.restart_attempts {
+        honeypot.active = false;
+        honeypot.listener_handle = None;
+        tracing::error!(
+            "Honeypot {} listener died, giving up after {} restarts",
+            honeypot.id,
+            honeypot.restarts
+        );
+
+        let event = Event::security_alert(
+            "lurefield",
+            Some(serde_json::json!({
+                "action": "honeypot_failed",
+                "honeypot_id": honeypot.id,
+                "port": honeypot.port,
+                "restarts": honeypot.restarts,
+            })),
+        )
+        .with_severity(Severity::High);
+        drop(honeypot);
+        if let Err(e) = event_sender.send(event).await {
+            tracing::error!("Failed to send honeypot failure event: {}", e);
+        }
+        return;
+    }
+
+    // Wait out the backoff without blocking the other honeypots
+    let now = Instant::now();
+    let delay = backoff(config, honeypot.restarts);
+    let restart_at = *honeypot.next_restart.get_or_insert(now + delay);
+    if now < restart_at {
+        return;
+    }
+
+    honeypot.next_restart = None;
+    honeypot.restarts += 1;
+    honeypot.restarted_at = Some(now);
+
+    let listener = match bind_listener(config, honeypot.port).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to restart honeypot {} (attempt {}/{}): {}",
+                honeypot.id,
+                honeypot.restarts,
+                config.restart_attempts,
+                e
+            );
+            return;
+        }
+    };
+
+    let settings = capture_settings(config, &honeypot);
+    honeypot.listener_handle = Some(listener::spawn(
+        listener,
+        honeypot_lock.clone(),
+        settings,
+        event_sender.clone(),
+        metrics.cloned(),
+    ));
+    tracing::warn!(
+        "Restarted honeypot {} on port {} (attempt {}/{})",
+        honeypot.id,
+        honeypot.port,
+        honeypot.restarts,
+        config.restart_attempts
+    );
+
+    let event = Event::service_lifecycle(
+        "lurefield",
+        Some(serde_json::json!({
+            "action": "honeypot_restarted",
+            "honeypot_id": honeypot.id,
+            "port": honeypot.port,
+            "attempt": honeypot.restarts,
+            "max_attempts": config.restart_attempts,
+        })),
+    );
+    drop(honeypot);
+    if let Err(e) = event_sender.send(event).await {
+        tracing::error!("Failed to send honeypot restart event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HoneypotOptions, HoneypotType, Lurefield, LurefieldConfig};
+    use chame_core::events::EventType;
+    use chame_core::EventSender;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Kill a honeypot's listener as if it had crashed
+    async fn crash(lurefield: &Lurefield, id: &str) {
+        let honeypots = lurefield.honeypots.read().await;
+        let honeypot = honeypots[id].read().await;
+        honeypot.listener_handle.as_ref().unwrap().abort();
+    }
+
+    async fn next_event(rx: &mut mpsc::Receiver<chame_core::Event>) -> chame_core::Event {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dead_listener_is_restarted_then_given_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            restart_attempts: 1,
+            restart_backoff: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        lurefield.start().await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap();
+        next_event(&mut rx).await;
+
+        crash(&lurefield, &id).await;
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.event_type, EventType::ServiceLifecycle);
+        assert_eq!(event.data.unwrap()["attempt"], 1);
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok());
+
+        crash(&lurefield, &id).await;
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.event_type, EventType::SecurityAlert);
+        assert_eq!(event.data.unwrap()["action"], "honeypot_failed");
+        assert!(lurefield.get_honeypots().await.is_empty());
+
+        lurefield.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restarts_are_forgotten_once_the_listener_is_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LurefieldConfig {
+            honeypot_dir: dir.path().to_path_buf(),
+            bind_address: "127.0.0.1".parse().unwrap(),
+            restart_attempts: 1,
+            restart_backoff: Duration::from_millis(10),
+            restart_reset_after: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let lurefield = Lurefield::new(config, EventSender::from(tx)).await.unwrap();
+        lurefield.start().await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = HoneypotOptions { port, ..Default::default() };
+        let id = lurefield.deploy_honeypot(HoneypotType::Http, Some(options)).await.unwrap();
+        next_event(&mut rx).await;
+
+        // Each crash after a stable run is a first restart again
+        for _ in 0..2 {
+            crash(&lurefield, &id).await;
+            let event = next_event(&mut rx).await;
+            assert_eq!(event.event_type, EventType::ServiceLifecycle);
+            assert_eq!(event.data.unwrap()["attempt"], 1);
+
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while lurefield.honeypots.read().await[&id].read().await.restarts > 0 {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            })
+            .await
+            .unwrap();
+        }
+
+        lurefield.stop().await.unwrap();
+    }
+}
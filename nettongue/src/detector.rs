@@ -0,0 +1,321 @@
+use crate::{NetworkDetection, NetworkDetectionType};
+use chame_core::Severity0to10;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+
+/// Thresholds of the network detectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionConfig {
+    /// Distinct destination ports a source must probe to be a port scan
+    pub port_scan_threshold: usize,
+
+    /// Window the probed ports are counted over
+    pub port_scan_window: Duration,
+
+    /// SYNs to a single destination port that make a SYN flood
+    pub syn_flood_threshold: usize,
+
+    /// Window the SYNs are counted over
+    pub syn_flood_window: Duration,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            port_scan_threshold: 20,
+            port_scan_window: Duration::seconds(60),
+            syn_flood_threshold: 200,
+            syn_flood_window: Duration::seconds(10),
+        }
+    }
+}
+
+/// Transport protocol of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl Transport {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Tcp => "TCP",
+            Transport::Udp => "UDP",
+        }
+    }
+}
+
+/// What the detectors need to know about a packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketSummary {
+    /// When the packet was seen
+    pub timestamp: DateTime<Utc>,
+
+    /// Source address
+    pub source_ip: IpAddr,
+
+    /// Destination address
+    pub dest_ip: IpAddr,
+
+    /// Source port
+    pub source_port: u16,
+
+    /// Destination port
+    pub dest_port: u16,
+
+    /// Transport protocol
+    pub transport: Transport,
+
+    /// Whether the TCP SYN flag is set
+    pub syn: bool,
+
+    /// Whether the TCP ACK flag is set
+    pub ack: bool,
+}
+
+impl PacketSummary {
+    /// Whether the packet opens a connection or probes a port
+    ///
+    /// TCP SYNs without ACK and every UDP datagram count as probes.
+    fn is_probe(&self) -> bool {
+        match self.transport {
+            Transport::Tcp => self.syn && !self.ack,
+            Transport::Udp => true,
+        }
+    }
+}
+
+/// Stateful port scan and SYN flood detection
+///
+/// Packets must be fed in capture order; windows follow the packet
+/// timestamps, so a capture file is judged the same way as live traffic.
+/// Each scan or flood is reported once, when it crosses its threshold,
+/// and again only after it has dropped back below it.
+#[derive(Debug, Default)]
+pub struct Detector {
+    config: DetectionConfig,
+
+    /// Recent probes by source, oldest first
+    probes: HashMap<IpAddr, VecDeque<(DateTime<Utc>, u16)>>,
+
+    /// Recent SYN times by destination
+    syns: HashMap<(IpAddr, u16), VecDeque<DateTime<Utc>>>,
+
+    /// Sources currently reported as scanning
+    scanning: HashSet<IpAddr>,
+
+    /// Destinations currently reported as flooded
+    flooded: HashSet<(IpAddr, u16)>,
+}
+
+impl Detector {
+    /// Create a detector with the given thresholds
+    pub fn new(config: DetectionConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Feed a packet, returning the detections it triggers
+    pub fn process(&mut self, packet: &PacketSummary) -> Vec<NetworkDetection> {
+        if !packet.is_probe() {
+            return Vec::new();
+        }
+
+        // A dual-stack socket sees IPv4 peers as ::ffff:a.b.c.d
+        let packet = &PacketSummary {
+            source_ip: canonical_ip(packet.source_ip),
+            dest_ip: canonical_ip(packet.dest_ip),
+            ..packet.clone()
+        };
+
+        let mut detections = Vec::new();
+        if let Some(detection) = self.check_port_scan(packet) {
+            detections.push(detection);
+        }
+        if let Some(detection) = self.check_syn_flood(packet) {
+            detections.push(detection);
+        }
+        detections
+    }
+
+    fn check_port_scan(&mut self, packet: &PacketSummary) -> Option<NetworkDetection> {
+        let since = packet.timestamp - self.config.port_scan_window;
+        let probes = self.probes.entry(packet.source_ip).or_default();
+        probes.push_back((packet.timestamp, packet.dest_port));
+        while probes.front().is_some_and(|(timestamp, _)| *timestamp < since) {
+            probes.pop_front();
+        }
+
+        let ports: HashSet<u16> = probes.iter().map(|(_, port)| *port).collect();
+        if ports.len() < self.config.port_scan_threshold {
+            self.scanning.remove(&packet.source_ip);
+            return None;
+        }
+        if !self.scanning.insert(packet.source_ip) {
+            return None;
+        }
+
+        let mut ports: Vec<u16> = ports.into_iter().collect();
+        ports.sort_unstable();
+
+        let mut details = HashMap::new();
+        details.insert("ports_probed".to_string(), ports.len().to_string());
+        details.insert("ports".to_string(), ports.iter().map(u16::to_string).collect::<Vec<_>>().join(","));
+        details.insert("window_seconds".to_string(), self.config.port_scan_window.num_seconds().to_string());
+        Some(detection(NetworkDetectionType::PortScan, packet, None, 7, details))
+    }
+
+    fn check_syn_flood(&mut self, packet: &PacketSummary) -> Option<NetworkDetection> {
+        if packet.transport != Transport::Tcp {
+            return None;
+        }
+
+        let target = (packet.dest_ip, packet.dest_port);
+        let since = packet.timestamp - self.config.syn_flood_window;
+        let syns = self.syns.entry(target).or_default();
+        syns.push_back(packet.timestamp);
+        while syns.front().is_some_and(|timestamp| *timestamp < since) {
+            syns.pop_front();
+        }
+
+        if syns.len() < self.config.syn_flood_threshold {
+            self.flooded.remove(&target);
+            return None;
+        }
+        if !self.flooded.insert(target) {
+            return None;
+        }
+
+        let mut details = HashMap::new();
+        details.insert("syn_count".to_string(), syns.len().to_string());
+        details.insert("window_seconds".to_string(), self.config.syn_flood_window.num_seconds().to_string());
+        Some(detection(NetworkDetectionType::SynFlood, packet, Some(packet.dest_port), 8, details))
+    }
+}
+
+/// The IPv4 address behind an IPv4-mapped IPv6 one, like
+/// `IpAddr::to_canonical` which needs Rust 1.75
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+fn detection(
+    detection_type: NetworkDetectionType,
+    packet: &PacketSummary,
+    dest_port: Option<u16>,
+    severity: u8,
+    details: HashMap<String, String>,
+) -> NetworkDetection {
+    NetworkDetection {
+        detection_type,
+        source_ip: Some(packet.source_ip),
+        dest_ip: Some(packet.dest_ip),
+        source_port: None,
+        dest_port,
+        protocol: Some(packet.transport.as_str().to_string()),
+        details,
+        severity: Severity0to10::clamped(severity),
+        timestamp: packet.timestamp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syn(second: i64, source: &str, dest_port: u16) -> PacketSummary {
+        PacketSummary {
+            timestamp: DateTime::from_timestamp(1_700_000_000 + second, 0).unwrap(),
+            source_ip: source.parse().unwrap(),
+            dest_ip: "10.0.0.1".parse().unwrap(),
+            source_port: 40000,
+            dest_port,
+            transport: Transport::Tcp,
+            syn: true,
+            ack: false,
+        }
+    }
+
+    #[test]
+    fn test_port_scan_is_reported_once() {
+        let mut detector = Detector::new(DetectionConfig {
+            port_scan_threshold: 5,
+            ..Default::default()
+        });
+
+        let detections: Vec<_> = (0..10).flat_map(|port| detector.process(&syn(0, "10.0.0.9", 20 + port))).collect();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].detection_type, NetworkDetectionType::PortScan);
+        assert_eq!(detections[0].source_ip, Some("10.0.0.9".parse().unwrap()));
+        assert_eq!(detections[0].details["ports_probed"], "5");
+        assert_eq!(detections[0].details["ports"], "20,21,22,23,24");
+
+        // Replies and other sources don't count
+        let mut reply = syn(1, "10.0.0.9", 30);
+        reply.ack = true;
+        assert!(detector.process(&reply).is_empty());
+        assert!(detector.process(&syn(1, "10.0.0.8", 22)).is_empty());
+    }
+
+    #[test]
+    fn test_slow_scan_stays_under_the_window() {
+        let mut detector = Detector::new(DetectionConfig {
+            port_scan_threshold: 5,
+            ..Default::default()
+        });
+
+        for port in 0..10 {
+            assert!(detector.process(&syn(i64::from(port) * 30, "10.0.0.9", 20 + port)).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_ipv6_port_scan() {
+        let mut detector = Detector::new(DetectionConfig {
+            port_scan_threshold: 6,
+            ..Default::default()
+        });
+
+        // The same host, however its address is written
+        let spellings = ["2001:db8::9", "2001:0db8:0000:0000:0000:0000:0000:0009", "2001:DB8:0:0::9"];
+        let mut detections: Vec<_> = (0..6)
+            .flat_map(|port| detector.process(&syn(0, spellings[port % 3], 20 + port as u16)))
+            .collect();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].source_ip, Some("2001:db8::9".parse().unwrap()));
+        let json = serde_json::to_value(&detections[0]).unwrap();
+        assert_eq!(json["source_ip"], "2001:db8::9");
+
+        // An IPv4 host seen through a dual-stack socket is still that host
+        detections = (0..3).flat_map(|port| detector.process(&syn(0, "10.0.0.7", port))).collect();
+        detections.extend((3..6).flat_map(|port| detector.process(&syn(0, "::ffff:10.0.0.7", port))));
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].source_ip, Some("10.0.0.7".parse().unwrap()));
+
+        // Neighbouring hosts of a prefix are counted apart
+        assert!((0..5).flat_map(|port| detector.process(&syn(0, "::1", port))).next().is_none());
+        assert!((0..5).flat_map(|port| detector.process(&syn(0, "::2", port))).next().is_none());
+    }
+
+    #[test]
+    fn test_syn_flood() {
+        let mut detector = Detector::new(DetectionConfig {
+            syn_flood_threshold: 50,
+            ..Default::default()
+        });
+
+        let detections: Vec<_> = (0..100)
+            .flat_map(|i| detector.process(&syn(0, &format!("192.0.2.{}", i % 250), 80)))
+            .collect();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].detection_type, NetworkDetectionType::SynFlood);
+        assert_eq!(detections[0].dest_port, Some(80));
+    }
+}
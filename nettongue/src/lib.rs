@@ -1,8 +1,31 @@
-use chame_core::events::{Event, EventType};
+pub mod detector;
+pub mod handler;
+pub mod pcap_file;
+
+use chame_core::events::{EventType, ReportableDetection, Severity0to10};
+use chame_core::{ChameleonError, EventSender, Feature, FeatureStatus, ModuleHealth};
+use detector::{DetectionConfig, Detector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
+
+/// BPF filter of the packets the detectors look at: UDP datagrams and TCP SYNs
+///
+/// `tcp[tcpflags]` only reads TCP over IPv4, so IPv6 SYNs are matched in the
+/// fixed header instead: next header TCP (byte 6) and the SYN flag in the
+/// TCP flags (byte 40 + 13).
+pub const CAPTURE_FILTER: &str =
+    "udp or (ip and tcp[tcpflags] & tcp-syn != 0) or (ip6 and ip6[6] == 6 and ip6[53] & 0x02 != 0)";
+
+/// Live detections waiting to be recorded
+const LIVE_DETECTIONS_CAPACITY: usize = 256;
 
 /// Errors that can occur in the NetTongue module
 #[derive(Error, Debug)]
@@ -26,6 +49,12 @@ pub enum NetTongueError {
     Pcap(String),
 }
 
+impl From<NetTongueError> for ChameleonError {
+    fn from(error: NetTongueError) -> Self {
+        ChameleonError::module("nettongue", error)
+    }
+}
+
 /// Configuration for the NetTongue module
 #[derive(Debug, Clone)]
 pub struct NetTongueConfig {
@@ -43,6 +72,12 @@ pub struct NetTongueConfig {
     
     /// Maximum latency fuzz in milliseconds
     pub latency_fuzz_max_ms: u64,
+    
+    /// Seed of the fuzzed latencies, for reproducible runs
+    pub latency_fuzz_seed: Option<u64>,
+    
+    /// Port scan and SYN flood thresholds
+    pub detection: DetectionConfig,
 }
 
 impl Default for NetTongueConfig {
@@ -53,21 +88,23 @@ impl Default for NetTongueConfig {
             latency_fuzz_enabled: false,
             latency_fuzz_min_ms: 50,
             latency_fuzz_max_ms: 200,
+            latency_fuzz_seed: None,
+            detection: DetectionConfig::default(),
         }
     }
 }
 
 /// A network detection
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NetworkDetection {
     /// Type of detection
     pub detection_type: NetworkDetectionType,
     
     /// Source IP address
-    pub source_ip: Option<String>,
+    pub source_ip: Option<IpAddr>,
     
     /// Destination IP address
-    pub dest_ip: Option<String>,
+    pub dest_ip: Option<IpAddr>,
     
     /// Source port
     pub source_port: Option<u16>,
@@ -82,14 +119,35 @@ pub struct NetworkDetection {
     pub details: HashMap<String, String>,
     
     /// Severity level (0-10)
-    pub severity: u8,
+    pub severity: Severity0to10,
     
     /// Timestamp of the detection
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl ReportableDetection for NetworkDetection {
+    fn module(&self) -> &str {
+        "nettongue"
+    }
+    
+    fn event_type(&self) -> EventType {
+        EventType::NetworkActivity
+    }
+    
+    fn detection_type(&self) -> String {
+        match &self.detection_type {
+            NetworkDetectionType::Other(name) => name.clone(),
+            detection_type => format!("{:?}", detection_type),
+        }
+    }
+    
+    fn severity(&self) -> Severity0to10 {
+        self.severity
+    }
+}
+
 /// Types of network detections
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum NetworkDetectionType {
     /// Port scan detection
     PortScan,
@@ -113,17 +171,20 @@ pub enum NetworkDetectionType {
 /// Main NetTongue network monitoring service
 pub struct NetTongue {
     /// Configuration
-    config: NetTongueConfig,
+    config: RwLock<NetTongueConfig>,
     
     /// Detection history
-    detections: RwLock<Vec<NetworkDetection>>,
+    detections: Arc<RwLock<Vec<NetworkDetection>>>,
     
     /// Event sender
-    event_sender: tokio::sync::mpsc::Sender<Event>,
+    event_sender: EventSender,
     
     /// Packet capture monitor
     pcap_monitor: Option<Arc<PcapMonitor>>,
     
+    /// Whether packet capture could be set up
+    pcap_status: FeatureStatus,
+    
     /// Latency fuzzer
     latency_fuzzer: Option<Arc<LatencyFuzzer>>,
 }
@@ -132,34 +193,36 @@ impl NetTongue {
     /// Create a new NetTongue instance
     pub async fn new(
         config: NetTongueConfig,
-        event_sender: tokio::sync::mpsc::Sender<Event>,
+        event_sender: EventSender,
     ) -> Result<Self, NetTongueError> {
-        let pcap_monitor = if config.pcap_enabled {
+        let (pcap_monitor, pcap_status) = if config.pcap_enabled {
             match PcapMonitor::new(&config.interface) {
-                Ok(monitor) => Some(Arc::new(monitor)),
+                Ok(monitor) => (Some(Arc::new(monitor)), FeatureStatus::Functional),
                 Err(e) => {
                     tracing::warn!("Failed to initialize PCAP monitor: {}", e);
-                    None
+                    (None, FeatureStatus::Unavailable(e.to_string()))
                 }
             }
         } else {
-            None
+            (None, FeatureStatus::Disabled)
         };
         
         let latency_fuzzer = if config.latency_fuzz_enabled {
-            Some(Arc::new(LatencyFuzzer::new(
-                config.latency_fuzz_min_ms,
-                config.latency_fuzz_max_ms,
-            )))
+            let fuzzer = LatencyFuzzer::new(config.latency_fuzz_min_ms, config.latency_fuzz_max_ms);
+            Some(Arc::new(match config.latency_fuzz_seed {
+                Some(seed) => fuzzer.with_seed(seed),
+                None => fuzzer,
+            }))
         } else {
             None
         };
         
         Ok(Self {
-            config,
-            detections: RwLock::new(Vec::new()),
+            config: RwLock::new(config),
+            detections: Arc::new(RwLock::new(Vec::new())),
             event_sender,
             pcap_monitor,
+            pcap_status,
             latency_fuzzer,
         })
     }
@@ -168,9 +231,20 @@ impl NetTongue {
     pub async fn start(&self) -> Result<(), NetTongueError> {
         tracing::info!("Starting NetTongue network monitoring");
         
-        // Start packet capture if enabled
+        // Start packet capture if enabled, its packets go through the
+        // same detectors as capture files
         if let Some(monitor) = &self.pcap_monitor {
-            monitor.start().await?;
+            let (sender, mut receiver) = mpsc::channel(LIVE_DETECTIONS_CAPACITY);
+            let detector = Detector::new(self.config.read().await.detection.clone());
+            monitor.start(detector, sender).await?;
+            
+            let history = self.detections.clone();
+            let events = self.event_sender.clone();
+            tokio::spawn(async move {
+                while let Some(detection) = receiver.recv().await {
+                    record_detection(&history, &events, detection).await;
+                }
+            });
         }
         
         // Start latency fuzzing if enabled
@@ -198,25 +272,76 @@ impl NetTongue {
         Ok(())
     }
     
+    /// Health of the enabled capture and fuzzer
+    pub async fn health(&self) -> ModuleHealth {
+        let mut parts = Vec::new();
+        if let Some(monitor) = &self.pcap_monitor {
+            parts.push(("packet capture", monitor.is_running().await));
+        }
+        if let Some(fuzzer) = &self.latency_fuzzer {
+            parts.push(("latency fuzzer", fuzzer.is_running().await));
+        }
+        
+        ModuleHealth::from_parts(parts)
+    }
+    
+    /// Privileged features and whether they could be set up
+    pub fn features(&self) -> Vec<Feature> {
+        vec![Feature::new("nettongue packet capture", self.pcap_status.clone())]
+    }
+    
+    /// Apply a new configuration to the running service
+    ///
+    /// Only the latency fuzz range is applied live; enabling or disabling
+    /// packet capture or fuzzing requires a restart.
+    pub async fn apply_config(&self, new: NetTongueConfig) -> Result<(), NetTongueError> {
+        if let Some(fuzzer) = &self.latency_fuzzer {
+            fuzzer.set_range(new.latency_fuzz_min_ms, new.latency_fuzz_max_ms)?;
+        }
+        
+        let mut config = self.config.write().await;
+        config.latency_fuzz_min_ms = new.latency_fuzz_min_ms;
+        config.latency_fuzz_max_ms = new.latency_fuzz_max_ms;
+        
+        Ok(())
+    }
+    
     /// Add a detection
     pub async fn add_detection(&self, detection: NetworkDetection) -> Result<(), NetTongueError> {
-        // Add to history
-        {
-            let mut detections = self.detections.write().await;
-            detections.push(detection.clone());
-        }
+        record_detection(&self.detections, &self.event_sender, detection).await;
+        Ok(())
+    }
+    
+    /// Run the detectors over a saved capture file
+    ///
+    /// Packets are replayed in capture order through the port scan and SYN
+    /// flood detectors, whose windows follow the capture timestamps, so no
+    /// live interface or root is needed. Detections are recorded and sent as
+    /// events like any other, and returned.
+    pub async fn analyze_pcap_file(&self, path: impl AsRef<Path>) -> Result<Vec<NetworkDetection>, NetTongueError> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path).await?;
+        let packets = pcap_file::read_packets(&bytes)?;
+        
+        let mut detector = Detector::new(self.config.read().await.detection.clone());
+        let detections: Vec<_> = packets
+            .iter()
+            .filter_map(pcap_file::summarize)
+            .flat_map(|packet| detector.process(&packet))
+            .collect();
         
-        // Send event
-        let event = Event::network_activity(
-            "nettongue",
-            Some(serde_json::to_value(&detection).unwrap_or_default()),
+        tracing::info!(
+            "Analyzed {} packets from {}: {} detections",
+            packets.len(),
+            path.display(),
+            detections.len()
         );
         
-        if let Err(e) = self.event_sender.send(event).await {
-            tracing::error!("Failed to send detection event: {}", e);
+        for detection in &detections {
+            self.add_detection(detection.clone()).await?;
         }
         
-        Ok(())
+        Ok(detections)
     }
     
     /// Get detection history
@@ -226,13 +351,28 @@ impl NetTongue {
     }
 }
 
+/// Add a detection to the history and report it
+async fn record_detection(
+    history: &RwLock<Vec<NetworkDetection>>,
+    events: &EventSender,
+    detection: NetworkDetection,
+) {
+    // Add to history
+    history.write().await.push(detection.clone());
+    
+    // Send event
+    if let Err(e) = events.send(detection.to_event()).await {
+        tracing::error!("Failed to send detection event: {}", e);
+    }
+}
+
 /// Monitor for packet capture
 pub struct PcapMonitor {
     /// Network interface
     interface: String,
     
-    /// Whether the monitor is running
-    running: RwLock<bool>,
+    /// Flag of the running capture, cleared to stop it or when it fails
+    running: RwLock<Option<Arc<AtomicBool>>>,
 }
 
 impl PcapMonitor {
@@ -255,57 +395,140 @@ impl PcapMonitor {
         
         Ok(Self {
             interface: interface.to_string(),
-            running: RwLock::new(false),
+            running: RwLock::new(None),
         })
     }
     
-    /// Start monitoring
-    pub async fn start(&self) -> Result<(), NetTongueError> {
+    /// Start capturing, feeding the packets matching [`CAPTURE_FILTER`] to
+    /// `detector` and its detections to `detections`
+    ///
+    /// Packets are read on a dedicated thread; the capture stops with
+    /// [`stop`](Self::stop) or once `detections` is closed.
+    pub async fn start(
+        &self,
+        detector: Detector,
+        detections: mpsc::Sender<NetworkDetection>,
+    ) -> Result<(), NetTongueError> {
         let mut running = self.running.write().await;
-        *running = true;
+        if running.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Ok(());
+        }
+        
+        let pcap_error = |e: pcap::Error| NetTongueError::Pcap(format!("{}: {}", self.interface, e));
+        let mut capture = pcap::Capture::from_device(self.interface.as_str())
+            .and_then(|capture| capture.snaplen(256).timeout(500).immediate_mode(true).open())
+            .map_err(pcap_error)?;
+        capture.filter(CAPTURE_FILTER, true).map_err(pcap_error)?;
+        
+        let flag = Arc::new(AtomicBool::new(true));
+        let thread_flag = flag.clone();
+        let interface = self.interface.clone();
+        std::thread::Builder::new()
+            .name("nettongue-capture".to_string())
+            .spawn(move || {
+                if let Err(e) = capture_packets(&mut capture, &thread_flag, detector, &detections) {
+                    tracing::error!("Packet capture on {} failed: {}", interface, e);
+                }
+                thread_flag.store(false, Ordering::Relaxed);
+            })?;
+        
+        *running = Some(flag);
         tracing::info!("Packet capture started on interface {}", self.interface);
         Ok(())
     }
     
     /// Stop monitoring
     pub async fn stop(&self) -> Result<(), NetTongueError> {
-        let mut running = self.running.write().await;
-        *running = false;
+        if let Some(flag) = self.running.write().await.take() {
+            flag.store(false, Ordering::Relaxed);
+        }
         tracing::info!("Packet capture stopped");
         Ok(())
     }
+    
+    /// Whether the monitor is running
+    pub async fn is_running(&self) -> bool {
+        self.running.read().await.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
+
+/// Read packets until `running` is cleared, the read timeout letting the
+/// flag be checked on a quiet interface
+fn capture_packets(
+    capture: &mut pcap::Capture<pcap::Active>,
+    running: &AtomicBool,
+    mut detector: Detector,
+    detections: &mpsc::Sender<NetworkDetection>,
+) -> Result<(), pcap::Error> {
+    let link_type = capture.get_datalink().0 as u32;
+    while running.load(Ordering::Relaxed) {
+        let packet = match capture.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::TimeoutExpired) => continue,
+            Err(e) => return Err(e),
+        };
+        
+        let ts = packet.header.ts;
+        let since_epoch = std::time::Duration::new(ts.tv_sec as u64, (ts.tv_usec as u32).saturating_mul(1000));
+        let packet = pcap_file::CapturedPacket {
+            timestamp: (std::time::UNIX_EPOCH + since_epoch).into(),
+            link_type,
+            data: packet.data.to_vec(),
+        };
+        
+        let Some(summary) = pcap_file::summarize(&packet) else {
+            continue;
+        };
+        for detection in detector.process(&summary) {
+            if detections.blocking_send(detection).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    
+    Ok(())
 }
 
 /// Latency fuzzer for confusing timing attacks
 pub struct LatencyFuzzer {
     /// Minimum latency in milliseconds
-    min_ms: u64,
+    min_ms: AtomicU64,
     
     /// Maximum latency in milliseconds
-    max_ms: u64,
+    max_ms: AtomicU64,
     
     /// Whether the fuzzer is running
     running: RwLock<bool>,
+    
+    /// Source of the latencies, seeded from entropy unless configured
+    rng: std::sync::Mutex<StdRng>,
 }
 
 impl LatencyFuzzer {
     /// Create a new latency fuzzer
     pub fn new(min_ms: u64, max_ms: u64) -> Self {
         Self {
-            min_ms,
-            max_ms,
+            min_ms: AtomicU64::new(min_ms),
+            max_ms: AtomicU64::new(max_ms),
             running: RwLock::new(false),
+            rng: std::sync::Mutex::new(StdRng::from_entropy()),
         }
     }
     
+    /// Draw latencies from `seed`, making them reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = std::sync::Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+    
     /// Start fuzzing
     pub async fn start(&self) -> Result<(), NetTongueError> {
         let mut running = self.running.write().await;
         *running = true;
         tracing::info!(
             "Latency fuzzing started (range: {}-{} ms)",
-            self.min_ms,
-            self.max_ms
+            self.min_ms.load(Ordering::Relaxed),
+            self.max_ms.load(Ordering::Relaxed)
         );
         Ok(())
     }
@@ -318,10 +541,86 @@ impl LatencyFuzzer {
         Ok(())
     }
     
+    /// Whether the fuzzer is running
+    pub async fn is_running(&self) -> bool {
+        *self.running.read().await
+    }
+    
+    /// Change the latency range while the fuzzer is running
+    pub fn set_range(&self, min_ms: u64, max_ms: u64) -> Result<(), NetTongueError> {
+        if min_ms > max_ms {
+            return Err(NetTongueError::LatencyFuzz(format!(
+                "Invalid latency range: {}-{} ms",
+                min_ms, max_ms
+            )));
+        }
+        
+        self.min_ms.store(min_ms, Ordering::Relaxed);
+        self.max_ms.store(max_ms, Ordering::Relaxed);
+        
+        tracing::info!("Latency fuzz range set to {}-{} ms", min_ms, max_ms);
+        Ok(())
+    }
+    
     /// Get a random latency value
     pub fn get_latency(&self) -> u64 {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        rng.gen_range(self.min_ms..=self.max_ms)
+        let min_ms = self.min_ms.load(Ordering::Relaxed);
+        // A concurrent set_range may briefly leave max below min
+        let max_ms = self.max_ms.load(Ordering::Relaxed).max(min_ms);
+        let mut rng = self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        rng.gen_range(min_ms..=max_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    
+    #[tokio::test]
+    async fn test_analyze_pcap_file() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let config = NetTongueConfig {
+            pcap_enabled: false,
+            detection: DetectionConfig {
+                port_scan_threshold: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let nettongue = NetTongue::new(config, EventSender::from(tx)).await.unwrap();
+        
+        // A scan of 30 ports from one host, and a few connections from another
+        let mut frames: Vec<_> = (0..30)
+            .map(|i| (1_700_000_000 + i / 10, pcap_file::syn_frame("203.0.113.7", "10.0.0.1", 50000, 1000 + i as u16)))
+            .collect();
+        frames.extend((0..3).map(|i| (1_700_000_005, pcap_file::syn_frame("10.0.0.2", "10.0.0.1", 50000 + i, 443))));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("incident.pcap");
+        std::fs::write(&path, pcap_file::write_pcap(&frames)).unwrap();
+        
+        let detections = nettongue.analyze_pcap_file(&path).await.unwrap();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].detection_type, NetworkDetectionType::PortScan);
+        assert_eq!(detections[0].source_ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(detections[0].timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(nettongue.get_detections().await.len(), 1);
+        
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::NetworkActivity);
+        assert_eq!(event.schema_version(), chame_core::DETECTION_SCHEMA_VERSION);
+        assert_eq!(event.data.unwrap()["detection_type"], "PortScan");
+        
+        assert!(nettongue.analyze_pcap_file(dir.path().join("missing.pcap")).await.is_err());
+    }
+    
+    #[test]
+    fn test_seeded_latencies_are_reproducible() {
+        let latencies = |fuzzer: LatencyFuzzer| (0..16).map(|_| fuzzer.get_latency()).collect::<Vec<_>>();
+        
+        let first = latencies(LatencyFuzzer::new(50, 200).with_seed(42));
+        assert_eq!(first, latencies(LatencyFuzzer::new(50, 200).with_seed(42)));
+        assert_ne!(first, latencies(LatencyFuzzer::new(50, 200).with_seed(43)));
+        assert!(first.iter().all(|latency| (50..=200).contains(latency)));
     }
 }
@@ -0,0 +1,259 @@
+//! Reading classic pcap capture files, without libpcap
+//!
+//! Only the classic format is supported; pcapng files can be converted
+//! with `editcap -F pcap in.pcapng out.pcap`.
+
+use crate::detector::{PacketSummary, Transport};
+use crate::NetTongueError;
+use chrono::{DateTime, Utc};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::udp::UdpPacket;
+use pnet::packet::Packet;
+use std::net::IpAddr;
+
+/// Ethernet frames
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// Bare IPv4 or IPv6 packets
+const LINKTYPE_RAW: u32 = 101;
+
+/// Linux cooked capture, as written by `tcpdump -i any`
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+/// A captured packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    /// When the packet was captured
+    pub timestamp: DateTime<Utc>,
+
+    /// Link layer type of the capture
+    pub link_type: u32,
+
+    /// Captured bytes, possibly cut at the snapshot length
+    pub data: Vec<u8>,
+}
+
+/// Parse every packet of a pcap file
+pub fn read_packets(bytes: &[u8]) -> Result<Vec<CapturedPacket>, NetTongueError> {
+    let invalid = |reason: &str| NetTongueError::PacketCapture(format!("Invalid pcap file: {}", reason));
+
+    let header = bytes.get(..24).ok_or_else(|| invalid("truncated header"))?;
+    let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let (little_endian, nanoseconds) = match magic {
+        0xa1b2c3d4 => (true, false),
+        0xa1b23c4d => (true, true),
+        0xd4c3b2a1 => (false, false),
+        0x4d3cb2a1 => (false, true),
+        0x0a0d0d0a => return Err(invalid("pcapng is not supported, convert it with editcap -F pcap")),
+        _ => return Err(invalid("unknown magic number")),
+    };
+    let read_u32 = |at: &[u8]| {
+        let word = [at[0], at[1], at[2], at[3]];
+        if little_endian {
+            u32::from_le_bytes(word)
+        } else {
+            u32::from_be_bytes(word)
+        }
+    };
+    let link_type = read_u32(&header[20..24]) & 0x0fff_ffff;
+
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset < bytes.len() {
+        let record = bytes.get(offset..offset + 16).ok_or_else(|| invalid("truncated packet header"))?;
+        let seconds = read_u32(&record[0..4]);
+        let fraction = read_u32(&record[4..8]);
+        let length = read_u32(&record[8..12]) as usize;
+        offset += 16;
+
+        let data = bytes.get(offset..offset + length).ok_or_else(|| invalid("truncated packet"))?;
+        offset += length;
+
+        let nanos = if nanoseconds { fraction } else { fraction.saturating_mul(1000) };
+        let timestamp = DateTime::from_timestamp(i64::from(seconds), nanos.min(999_999_999))
+            .ok_or_else(|| invalid("timestamp out of range"))?;
+
+        packets.push(CapturedPacket {
+            timestamp,
+            link_type,
+            data: data.to_vec(),
+        });
+    }
+
+    Ok(packets)
+}
+
+/// Decode the addressing of a TCP or UDP packet
+///
+/// Other protocols, fragments and malformed packets yield `None`.
+pub fn summarize(packet: &CapturedPacket) -> Option<PacketSummary> {
+    let (ether_type, payload) = match packet.link_type {
+        LINKTYPE_ETHERNET => {
+            let frame = EthernetPacket::new(&packet.data)?;
+            (frame.get_ethertype().0, &packet.data[14..])
+        }
+        LINKTYPE_LINUX_SLL => {
+            let header = packet.data.get(..16)?;
+            (u16::from_be_bytes([header[14], header[15]]), &packet.data[16..])
+        }
+        LINKTYPE_RAW => match packet.data.first()? >> 4 {
+            4 => (EtherTypes::Ipv4.0, &packet.data[..]),
+            6 => (EtherTypes::Ipv6.0, &packet.data[..]),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let (source_ip, dest_ip, protocol, segment): (IpAddr, IpAddr, IpNextHeaderProtocol, Vec<u8>) =
+        if ether_type == EtherTypes::Ipv4.0 {
+            let ip = Ipv4Packet::new(payload)?;
+            if ip.get_fragment_offset() != 0 {
+                return None;
+            }
+            (
+                ip.get_source().into(),
+                ip.get_destination().into(),
+                ip.get_next_level_protocol(),
+                ip.payload().to_vec(),
+            )
+        } else if ether_type == EtherTypes::Ipv6.0 {
+            let ip = Ipv6Packet::new(payload)?;
+            (
+                ip.get_source().into(),
+                ip.get_destination().into(),
+                ip.get_next_header(),
+                ip.payload().to_vec(),
+            )
+        } else {
+            return None;
+        };
+
+    let (transport, source_port, dest_port, flags) = match protocol {
+        IpNextHeaderProtocols::Tcp => {
+            let tcp = TcpPacket::new(&segment)?;
+            (Transport::Tcp, tcp.get_source(), tcp.get_destination(), tcp.get_flags())
+        }
+        IpNextHeaderProtocols::Udp => {
+            let udp = UdpPacket::new(&segment)?;
+            (Transport::Udp, udp.get_source(), udp.get_destination(), 0)
+        }
+        _ => return None,
+    };
+
+    Some(PacketSummary {
+        timestamp: packet.timestamp,
+        source_ip,
+        dest_ip,
+        source_port,
+        dest_port,
+        transport,
+        syn: flags & TcpFlags::SYN != 0,
+        ack: flags & TcpFlags::ACK != 0,
+    })
+}
+
+/// Build a pcap file of Ethernet frames, for tests
+#[cfg(test)]
+pub(crate) fn write_pcap(frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&65535u32.to_le_bytes());
+    bytes.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+
+    for (seconds, frame) in frames {
+        bytes.extend_from_slice(&seconds.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(frame);
+    }
+    bytes
+}
+
+/// Build an Ethernet frame carrying a TCP SYN, for tests
+///
+/// Both addresses must be of the same family.
+#[cfg(test)]
+pub(crate) fn syn_frame(source: &str, dest: &str, source_port: u16, dest_port: u16) -> Vec<u8> {
+    let mut frame = match (source.parse().unwrap(), dest.parse().unwrap()) {
+        (IpAddr::V4(source), IpAddr::V4(dest)) => {
+            let mut frame = vec![0u8; 14 + 20 + 20];
+            frame[12..14].copy_from_slice(&EtherTypes::Ipv4.0.to_be_bytes());
+            let ip = &mut frame[14..34];
+            ip[0] = 0x45;
+            ip[2..4].copy_from_slice(&40u16.to_be_bytes());
+            ip[8] = 64;
+            ip[9] = 6;
+            ip[12..16].copy_from_slice(&source.octets());
+            ip[16..20].copy_from_slice(&dest.octets());
+            frame
+        }
+        (IpAddr::V6(source), IpAddr::V6(dest)) => {
+            let mut frame = vec![0u8; 14 + 40 + 20];
+            frame[12..14].copy_from_slice(&EtherTypes::Ipv6.0.to_be_bytes());
+            let ip = &mut frame[14..54];
+            ip[0] = 0x60;
+            ip[4..6].copy_from_slice(&20u16.to_be_bytes());
+            ip[6] = 6;
+            ip[7] = 64;
+            ip[8..24].copy_from_slice(&source.octets());
+            ip[24..40].copy_from_slice(&dest.octets());
+            frame
+        }
+        _ => panic!("mixed address families"),
+    };
+
+    let start = frame.len() - 20;
+    let tcp = &mut frame[start..];
+    tcp[0..2].copy_from_slice(&source_port.to_be_bytes());
+    tcp[2..4].copy_from_slice(&dest_port.to_be_bytes());
+    tcp[12] = 5 << 4;
+    tcp[13] = 0x02;
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_and_summarize() {
+        let bytes = write_pcap(&[
+            (1_700_000_000, syn_frame("10.0.0.9", "10.0.0.1", 40000, 22)),
+            (1_700_000_001, syn_frame("2001:db8::9", "2001:db8::1", 40001, 443)),
+        ]);
+        let packets = read_packets(&bytes).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].timestamp.timestamp(), 1_700_000_000);
+
+        let summary = summarize(&packets[0]).unwrap();
+        assert_eq!(summary.source_ip, "10.0.0.9".parse::<IpAddr>().unwrap());
+        assert_eq!(summary.dest_port, 22);
+        assert_eq!(summary.transport, Transport::Tcp);
+        assert!(summary.syn && !summary.ack);
+
+        let summary = summarize(&packets[1]).unwrap();
+        assert_eq!(summary.source_ip, "2001:db8::9".parse::<IpAddr>().unwrap());
+        assert_eq!(summary.dest_ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(summary.dest_port, 443);
+        assert!(summary.syn);
+    }
+
+    #[test]
+    fn test_rejects_invalid_files() {
+        assert!(read_packets(b"not a capture").is_err());
+        assert!(read_packets(&[0x0a, 0x0d, 0x0d, 0x0a, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+        let mut bytes = write_pcap(&[(0, syn_frame("10.0.0.9", "10.0.0.1", 40000, 22))]);
+        bytes.truncate(bytes.len() - 1);
+        assert!(read_packets(&bytes).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! One log line per API request, for auditing
+//!
+//! Lines are written at info level under the `pigment_api::access` target
+//! with the method, path, status, latency and client of the request.
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Header each reverse proxy appends the address it received a request from to
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Client of a request, as resolved by the access log
+///
+/// Stored in the request extensions so the rate limiter counts the same
+/// client that gets logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// How the access log resolves clients
+#[derive(Debug, Clone, Default)]
+pub struct AccessLog {
+    /// Take the client from `X-Forwarded-For` rather than the connection
+    pub trust_forwarded_for: bool,
+    
+    /// Proxies in front of the one connecting, skipped in `X-Forwarded-For`
+    pub trusted_proxies: Arc<[IpAddr]>,
+}
+
+/// Resolve the client that sent `request`
+///
+/// With `trust_forwarded_for`, the last address of `X-Forwarded-For` that
+/// isn't one of `trusted_proxies` wins: the entries before it come from the
+/// client and could be forged. Otherwise, or when that header is missing or
+/// invalid, the peer address.
+pub fn client_ip<B>(
+    request: &Request<B>,
+    trust_forwarded_for: bool,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    trust_forwarded_for
+        .then(|| forwarded_for(request.headers(), trusted_proxies))
+        .flatten()
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+}
+
+fn forwarded_for(headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let hops = headers.get(FORWARDED_FOR)?.to_str().ok()?.rsplit(',');
+    let mut last = None;
+    for hop in hops {
+        let hop: IpAddr = hop.trim().parse().ok()?;
+        last = Some(hop);
+        if !trusted_proxies.contains(&hop) {
+            return last;
+        }
+    }
+    // Only proxies, the first of them is as close to the client as it gets
+    last
+}
+
+/// What a write request does, logged in place of its body
+fn action(method: &Method, path: &str) -> Option<&'static str> {
+    match (method, path) {
+        (&Method::POST, "/api/posture") => Some("change_posture"),
+        (&Method::POST, path) if path.starts_with("/api/modules/") => Some("toggle_module"),
+        (&Method::POST, "/api/events") => Some("inject_event"),
+        (&Method::DELETE, "/api/events") => Some("clear_events"),
+        (&Method::DELETE, "/api/detections") => Some("clear_detections"),
+        (&Method::POST, "/api/formats/patterns") => Some("replace_patterns"),
+        _ => None,
+    }
+}
+
+/// Middleware writing the access log line of each request
+///
+/// Query strings and bodies are never logged since they may carry secrets;
+/// write requests are described by their action instead.
+pub async fn log_request<B>(State(log): State<AccessLog>, mut request: Request<B>, next: Next<B>) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let client = client_ip(&request, log.trust_forwarded_for, &log.trusted_proxies);
+    if let Some(client) = client {
+        request.extensions_mut().insert(ClientIp(client));
+    }
+
+    let response = next.run(request).await;
+
+    tracing::info!(
+        target: "pigment_api::access",
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+        client = %client.map_or_else(|| "-".to_string(), |client| client.to_string()),
+        action = action(&method, &path).unwrap_or("-"),
+        "API request"
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip_honors_forwarded_for_when_trusted() {
+        let mut request = Request::builder()
+            .uri("/api/status")
+            .header(FORWARDED_FOR, "203.0.113.7, 10.0.0.2")
+            .body(())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo::<SocketAddr>("10.0.0.2:41000".parse().unwrap()));
+
+        let proxies = ["10.0.0.2".parse().unwrap()];
+        assert_eq!(client_ip(&request, false, &proxies), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(client_ip(&request, true, &proxies), Some("203.0.113.7".parse().unwrap()));
+
+        // Without trusted proxies, the hop the connecting proxy appended
+        assert_eq!(client_ip(&request, true, &[]), Some("10.0.0.2".parse().unwrap()));
+
+        // A garbled header falls back to the peer
+        request.headers_mut().insert(FORWARDED_FOR, "unknown".parse().unwrap());
+        assert_eq!(client_ip(&request, true, &proxies), Some("10.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_forged_forwarded_for_entries_are_ignored() {
+        let proxy = "10.0.0.2".parse().unwrap();
+        let headers = |value: &'static str| {
+            HeaderMap::from_iter([(FORWARDED_FOR.parse().unwrap(), value.parse().unwrap())])
+        };
+
+        let forwarded = headers("1.2.3.4, 10.0.0.2");
+        assert_eq!(forwarded_for(&forwarded, &[proxy]), Some("1.2.3.4".parse().unwrap()));
+        // A client can put anything before the address the proxy saw
+        let forged = headers("6.6.6.6, 1.2.3.4, 10.0.0.2");
+        assert_eq!(forwarded_for(&forged, &[proxy]), Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(forwarded_for(&forged, &[]), Some(proxy));
+        assert_eq!(forwarded_for(&headers("10.0.0.2"), &[proxy]), Some(proxy));
+    }
+
+    #[test]
+    fn test_write_requests_are_logged_by_action() {
+        assert_eq!(action(&Method::POST, "/api/posture"), Some("change_posture"));
+        assert_eq!(action(&Method::POST, "/api/modules/eye360"), Some("toggle_module"));
+        assert_eq!(action(&Method::DELETE, "/api/detections"), Some("clear_detections"));
+        assert_eq!(action(&Method::GET, "/api/posture"), None);
+    }
+}
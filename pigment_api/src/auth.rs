@@ -0,0 +1,141 @@
+//! API key authentication
+//!
+//! When keys are configured, every request but the probes must carry one of
+//! them as `Authorization: Bearer <key>`. Browsers can't set that header on
+//! a WebSocket, so upgrade requests may instead send it as
+//! `Sec-WebSocket-Protocol: bearer, <key>` or an `api_key` query parameter.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, Request, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Subprotocol announcing that the next one is an API key
+pub const BEARER_PROTOCOL: &str = "bearer";
+
+/// Keys accepted by [`require_api_key`]
+#[derive(Debug, Clone)]
+pub struct ApiKeys(pub Arc<[String]>);
+
+/// The key of an `Authorization: Bearer <key>` header
+fn bearer_key(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, key) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| key.trim())
+}
+
+/// The key of a `Sec-WebSocket-Protocol: bearer, <key>` header
+fn websocket_protocol_key(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+    let mut protocols = value.split(',').map(str::trim);
+    protocols.find(|protocol| protocol.eq_ignore_ascii_case(BEARER_PROTOCOL))?;
+    protocols.next()
+}
+
+/// The key of an `api_key` query parameter
+fn query_key(uri: &Uri) -> Option<String> {
+    let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(uri).ok()?;
+    params.remove("api_key")
+}
+
+/// Whether the request asks to switch to a WebSocket
+fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// Key sent with the request, if any
+fn request_key<B>(request: &Request<B>) -> Option<String> {
+    let headers = request.headers();
+    if let Some(key) = bearer_key(headers) {
+        return Some(key.to_string());
+    }
+    if !is_websocket_upgrade(headers) {
+        return None;
+    }
+    websocket_protocol_key(headers)
+        .map(str::to_string)
+        .or_else(|| query_key(request.uri()))
+}
+
+/// Compare without returning early, so timing doesn't reveal how much of a
+/// key was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Middleware rejecting requests without a configured key with a `401`
+///
+/// Without keys, every request goes through.
+pub async fn require_api_key<B>(State(keys): State<ApiKeys>, request: Request<B>, next: Next<B>) -> Response {
+    let authorized = keys.0.is_empty()
+        || request_key(&request)
+            .is_some_and(|key| keys.0.iter().any(|known| constant_time_eq(known.as_bytes(), key.as_bytes())));
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(serde_json::json!({ "error": "Missing or invalid API key" })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_bearer_key_is_read_from_the_authorization_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_key(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer s3cret"));
+        assert_eq!(bearer_key(&headers), Some("s3cret"));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("bearer  s3cret "));
+        assert_eq!(bearer_key(&headers), Some("s3cret"));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic czNjcmV0"));
+        assert_eq!(bearer_key(&headers), None);
+    }
+
+    #[test]
+    fn test_websocket_upgrades_may_send_the_key_elsewhere() {
+        let request = |uri: &str, headers: &[(header::HeaderName, &'static str)]| {
+            let mut request = Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(name, HeaderValue::from_static(value));
+            }
+            request.body(()).unwrap()
+        };
+        let upgrade = || (header::UPGRADE, "websocket");
+
+        let protocol = (header::SEC_WEBSOCKET_PROTOCOL, "bearer, s3cret");
+        let key = request_key(&request("/api/events/ws", &[upgrade(), protocol]));
+        assert_eq!(key.as_deref(), Some("s3cret"));
+        let query = "/api/events/ws?source=eye360&api_key=s3cret";
+        assert_eq!(request_key(&request(query, &[upgrade()])).as_deref(), Some("s3cret"));
+
+        // Other requests only use the Authorization header
+        assert_eq!(request_key(&request(query, &[])), None);
+        let other = (header::SEC_WEBSOCKET_PROTOCOL, "chat, s3cret");
+        assert_eq!(request_key(&request("/api/events/ws", &[upgrade(), other])), None);
+    }
+
+    #[test]
+    fn test_keys_must_match_exactly() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cre"));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+    }
+}
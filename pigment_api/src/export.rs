@@ -0,0 +1,157 @@
+use crate::EventInfo;
+use axum::body::StreamBody;
+use chame_core::events::Event;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::convert::Infallible;
+use utoipa::ToSchema;
+
+/// Columns of the CSV export
+const CSV_COLUMNS: [&str; 6] = ["id", "timestamp", "event_type", "source", "severity", "data"];
+
+/// Format of `GET /api/events/export`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One row per event, the payload flattened into a single column
+    #[default]
+    Csv,
+    
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// MIME type of the export
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+    
+    /// File name suggested to the client
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "camaleon-events.csv",
+            ExportFormat::Jsonl => "camaleon-events.jsonl",
+        }
+    }
+    
+    /// Bytes written before the first event
+    fn header(self) -> Option<Vec<u8>> {
+        match self {
+            ExportFormat::Csv => Some(csv_line(CSV_COLUMNS)),
+            ExportFormat::Jsonl => None,
+        }
+    }
+    
+    /// Render one event
+    pub fn render(self, info: &EventInfo) -> Vec<u8> {
+        match self {
+            ExportFormat::Csv => {
+                let timestamp = info.timestamp.to_rfc3339();
+                let data = info.data.as_ref().map(flatten_data).unwrap_or_default();
+                csv_line([
+                    info.id.as_str(),
+                    timestamp.as_str(),
+                    info.event_type.as_str(),
+                    info.source.as_str(),
+                    info.severity.as_str(),
+                    data.as_str(),
+                ])
+            }
+            ExportFormat::Jsonl => {
+                let mut line = serde_json::to_vec(info).unwrap_or_default();
+                line.push(b'\n');
+                line
+            }
+        }
+    }
+}
+
+/// Stream `(sequence number, event)` pairs, rendering each one as it is sent
+pub fn stream_events(
+    format: ExportFormat,
+    events: Vec<(u64, Event)>,
+) -> StreamBody<impl Stream<Item = Result<Vec<u8>, Infallible>>> {
+    let rows = events
+        .into_iter()
+        .map(move |(id, event)| format.render(&EventInfo::from_event(id.to_string(), &event)));
+    
+    StreamBody::new(stream::iter(format.header().into_iter().chain(rows)).map(Ok))
+}
+
+/// Encode one CSV record, quoting fields as needed
+fn csv_line<'a>(fields: impl IntoIterator<Item = &'a str>) -> Vec<u8> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    if writer.write_record(fields).is_err() {
+        return Vec::new();
+    }
+    writer.into_inner().unwrap_or_default()
+}
+
+/// Flatten a JSON payload into `key=value` pairs, nested keys joined with dots
+pub fn flatten_data(data: &serde_json::Value) -> String {
+    let mut pairs = Vec::new();
+    flatten_into(data, String::new(), &mut pairs);
+    pairs.join("; ")
+}
+
+fn flatten_into(value: &serde_json::Value, prefix: String, pairs: &mut Vec<String>) {
+    let key = |name: String| {
+        if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+    
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, value) in map {
+                flatten_into(value, key(name.clone()), pairs);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                flatten_into(value, key(index.to_string()), pairs);
+            }
+        }
+        leaf => {
+            let text = match leaf {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            if prefix.is_empty() {
+                pairs.push(text);
+            } else {
+                pairs.push(format!("{}={}", prefix, text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_flatten_nested_data() {
+        let data = serde_json::json!({
+            "ip": "10.0.0.1",
+            "ports": [22, 80],
+            "scan": { "kind": "syn", "stealth": true },
+        });
+        
+        assert_eq!(
+            flatten_data(&data),
+            "ip=10.0.0.1; ports.0=22; ports.1=80; scan.kind=syn; scan.stealth=true"
+        );
+    }
+    
+    #[test]
+    fn test_csv_fields_are_quoted() {
+        assert_eq!(csv_line(["a", "b,c", "say \"hi\""]), b"a,\"b,c\",\"say \"\"hi\"\"\"\n");
+    }
+}
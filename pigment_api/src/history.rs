@@ -0,0 +1,116 @@
+use chame_core::events::Event;
+use std::collections::VecDeque;
+
+/// Events kept by the API before the oldest ones are dropped
+pub const MAX_EVENTS: usize = 1000;
+
+/// Events received by the API, numbered in arrival order
+///
+/// Sequence numbers start at 0 and are never reused, even after the history
+/// is cleared, so a client can poll for the events newer than the last one
+/// it saw.
+#[derive(Debug)]
+pub struct EventHistory {
+    /// Events with their sequence number, oldest first
+    events: VecDeque<(u64, Event)>,
+
+    /// Sequence number of the next event
+    next_seq: u64,
+
+    /// Events kept before the oldest ones are dropped
+    capacity: usize,
+}
+
+impl Default for EventHistory {
+    fn default() -> Self {
+        Self::new(MAX_EVENTS)
+    }
+}
+
+impl EventHistory {
+    /// Create a history keeping the last `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            next_seq: 0,
+            capacity,
+        }
+    }
+
+    /// Store an event, dropping the oldest one when full, and return its sequence number
+    pub fn push(&mut self, event: Event) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.push_back((seq, event));
+
+        if self.events.len() > self.capacity {
+            self.events.pop_front();
+        }
+
+        seq
+    }
+
+    /// Stored events with their sequence number, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (u64, &Event)> {
+        self.events.iter().map(|(seq, event)| (*seq, event))
+    }
+
+    /// Stored events newer than sequence number `seq`, oldest first
+    pub fn since(&self, seq: u64) -> impl DoubleEndedIterator<Item = (u64, &Event)> {
+        let start = self.events.partition_point(|(stored, _)| *stored <= seq);
+        self.events.range(start..).map(|(seq, event)| (*seq, event))
+    }
+
+    /// Sequence number of the last event received, if any
+    pub fn latest_seq(&self) -> Option<u64> {
+        self.next_seq.checked_sub(1)
+    }
+
+    /// Number of stored events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no event is stored
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Drop every stored event, keeping the numbering going
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Keep only the events for which `keep` returns true
+    pub fn retain(&mut self, mut keep: impl FnMut(&Event) -> bool) {
+        self.events.retain(|(_, event)| keep(event));
+    }
+}
+
+impl Extend<Event> for EventHistory {
+    fn extend<I: IntoIterator<Item = Event>>(&mut self, events: I) {
+        for event in events {
+            self.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_survives_trimming_and_clearing() {
+        let mut history = EventHistory::new(2);
+        assert_eq!(history.latest_seq(), None);
+
+        history.extend((0..3).map(|_| Event::posture_change("test", None)));
+        assert_eq!(history.iter().map(|(seq, _)| seq).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(history.since(1).map(|(seq, _)| seq).collect::<Vec<_>>(), [2]);
+        assert_eq!(history.since(2).count(), 0);
+
+        history.clear();
+        assert_eq!(history.push(Event::posture_change("test", None)), 3);
+        assert_eq!(history.latest_seq(), Some(3));
+    }
+}
@@ -1,18 +1,42 @@
-use chame_core::events::{Event, EventType};
+pub mod access_log;
+pub mod auth;
+pub mod export;
+pub mod handler;
+pub mod history;
+pub mod live;
+pub mod openapi;
+pub mod rate_limit;
+
+use chame_core::events::{Event, EventType, Severity};
+use chame_core::state::Status;
+use chame_core::{ChameleonService, EventSender, Feature, MetricsCollector, ModuleHealth, Posture, Severity0to10};
+use formats::{FileFormat, Formats, FormatsError, PatternRule};
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use thiserror::Error;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinSet;
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware,
     response::{IntoResponse, Json},
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
+use access_log::AccessLog;
+use auth::ApiKeys;
+use export::ExportFormat;
+use history::EventHistory;
+use live::LiveEvent;
+use openapi::ApiDoc;
+use rate_limit::RateLimitLayer;
 use serde::{Deserialize, Serialize};
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
 /// Errors that can occur in the PigmentAPI module
 #[derive(Error, Debug)]
@@ -26,6 +50,9 @@ pub enum PigmentApiError {
     #[error("Not found: {0}")]
     NotFound(String),
     
+    #[error("Event listener already started")]
+    ListenerAlreadyStarted,
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -33,24 +60,80 @@ pub enum PigmentApiError {
 /// Configuration for the PigmentAPI module
 #[derive(Debug, Clone)]
 pub struct PigmentApiConfig {
-    /// Address to bind to
-    pub bind_address: SocketAddr,
+    /// Addresses to serve the API on, sharing the same state
+    pub bind_addresses: Vec<SocketAddr>,
+    
+    /// Origins allowed to call the API from a browser
+    ///
+    /// `None` disables CORS, an empty list only allows same-origin requests.
+    pub cors_origins: Option<Vec<String>>,
+    
+    /// Sustained requests per second allowed per client IP on read endpoints
+    pub requests_per_second: u32,
+    
+    /// Requests a client can burst above the sustained rate on read endpoints
+    pub burst: u32,
+    
+    /// Sustained requests per second allowed per client IP on write endpoints
+    pub write_requests_per_second: u32,
+    
+    /// Requests a client can burst above the sustained rate on write endpoints
+    pub write_burst: u32,
+    
+    /// Whether `POST /api/events` may publish synthetic events onto the bus
+    pub allow_event_injection: bool,
     
-    /// Whether to enable CORS
-    pub enable_cors: bool,
+    /// Whether `POST /api/formats/patterns` may replace detection patterns
+    pub allow_pattern_updates: bool,
+    
+    /// Whether clients are identified by `X-Forwarded-For`, behind a proxy
+    ///
+    /// Applies to both the access log and the rate limits. Only enable it
+    /// when a proxy sets the header, since clients could forge it otherwise.
+    pub trust_forwarded_for: bool,
+    
+    /// Proxies chained before the one connecting to the API
+    ///
+    /// With `trust_forwarded_for`, the client is the last `X-Forwarded-For`
+    /// address that isn't one of these.
+    pub trusted_proxies: Vec<IpAddr>,
+    
+    /// Keys clients must send as `Authorization: Bearer <key>`
+    ///
+    /// Empty, the API is open. The health probes never require a key.
+    pub api_keys: Vec<String>,
 }
 
 impl Default for PigmentApiConfig {
     fn default() -> Self {
         Self {
-            bind_address: "127.0.0.1:8080".parse().unwrap(),
-            enable_cors: true,
+            bind_addresses: vec!["127.0.0.1:8080".parse().unwrap()],
+            cors_origins: None,
+            requests_per_second: 20,
+            burst: 40,
+            write_requests_per_second: 1,
+            write_burst: 5,
+            allow_event_injection: false,
+            allow_pattern_updates: false,
+            trust_forwarded_for: false,
+            trusted_proxies: Vec::new(),
+            api_keys: Vec::new(),
+        }
+    }
+}
+
+impl PigmentApiConfig {
+    /// Create a configuration serving on a single address
+    pub fn new(bind_address: SocketAddr) -> Self {
+        Self {
+            bind_addresses: vec![bind_address],
+            ..Default::default()
         }
     }
 }
 
 /// API response for system status
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SystemStatusResponse {
     /// Current status
     pub status: String,
@@ -64,12 +147,15 @@ pub struct SystemStatusResponse {
     /// System metrics
     pub metrics: HashMap<String, serde_json::Value>,
     
+    /// Health of each running module
+    pub health: HashMap<String, HealthResponse>,
+    
     /// Timestamp
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 /// API response for events
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct EventsResponse {
     /// Events
     pub events: Vec<EventInfo>,
@@ -82,12 +168,15 @@ pub struct EventsResponse {
     
     /// Page size
     pub page_size: usize,
+    
+    /// Cursor to pass as `since` on the next poll, absent until an event is received
+    pub latest_seq: Option<u64>,
 }
 
 /// Event information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct EventInfo {
-    /// Event ID
+    /// Event ID, its sequence number in the history
     pub id: String,
     
     /// Event type
@@ -106,15 +195,154 @@ pub struct EventInfo {
     pub data: Option<serde_json::Value>,
 }
 
+impl EventInfo {
+    /// Describe an event, `id` being its sequence number in the history
+    pub fn from_event(id: impl Into<String>, event: &Event) -> Self {
+        Self {
+            id: id.into(),
+            event_type: event_type_name(&event.event_type),
+            source: event.source.clone(),
+            severity: format!("{:?}", event.severity()),
+            timestamp: event.timestamp,
+            data: event.data.clone(),
+        }
+    }
+}
+
+/// Name an event type the way the API reports and filters it
+fn event_type_name(event_type: &EventType) -> String {
+    match event_type {
+        EventType::Custom(name) => name.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// API response for detections
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DetectionsResponse {
+    /// Detections
+    pub detections: Vec<DetectionInfo>,
+    
+    /// Total count
+    pub total: usize,
+    
+    /// Page
+    pub page: usize,
+    
+    /// Page size
+    pub page_size: usize,
+}
+
+/// Modules whose events carry structured detections
+const DETECTION_SOURCES: &[&str] = &["eye360", "nettongue", "formats"];
+
+/// Detection reported by eye360, nettongue or formats
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DetectionInfo {
+    /// Module that reported the detection
+    pub source: String,
+    
+    /// Type of detection
+    pub detection_type: String,
+    
+    /// Severity level (0-10)
+    pub severity: u8,
+    
+    /// Source IP address
+    pub source_ip: Option<String>,
+    
+    /// Source port
+    pub source_port: Option<u16>,
+    
+    /// Destination IP address
+    pub dest_ip: Option<String>,
+    
+    /// Destination port
+    pub dest_port: Option<u16>,
+    
+    /// Protocol
+    pub protocol: Option<String>,
+    
+    /// Where the detection was made (file location, process, ...)
+    pub location: Option<String>,
+    
+    /// Details about the detection
+    pub details: serde_json::Value,
+    
+    /// Timestamp
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl DetectionInfo {
+    /// Rebuild a detection from the event a module sent for it
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if !DETECTION_SOURCES.contains(&event.source.as_str()) {
+            return None;
+        }
+        
+        let data = event.data.as_ref()?;
+        let severity = data.get("severity")?.as_u64()?.min(10) as u8;
+        
+        // Enum variants serialize as "Name" or {"Other": "name"}
+        let detection_type = match data.get("detection_type")? {
+            serde_json::Value::String(name) => name.clone(),
+            serde_json::Value::Object(map) => map
+                .values()
+                .next()
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            other => other.to_string(),
+        };
+        
+        let string_field = |key: &str| data.get(key).and_then(|v| v.as_str()).map(String::from);
+        let port_field = |key: &str| {
+            data.get(key)
+                .and_then(|v| v.as_u64())
+                .and_then(|port| u16::try_from(port).ok())
+        };
+        
+        // eye360 names the location "source", formats also reports the file
+        let location = match (string_field("file"), string_field("location")) {
+            (Some(file), Some(location)) => Some(format!("{}:{}", file, location)),
+            (None, Some(location)) => Some(location),
+            _ if event.source == "eye360" => string_field("source"),
+            _ => None,
+        };
+        
+        Some(Self {
+            source: event.source.clone(),
+            detection_type,
+            severity,
+            source_ip: string_field("source_ip"),
+            source_port: port_field("source_port"),
+            dest_ip: string_field("dest_ip"),
+            dest_port: port_field("dest_port"),
+            protocol: string_field("protocol"),
+            location,
+            details: data.get("details").cloned().unwrap_or_default(),
+            timestamp: event.timestamp,
+        })
+    }
+}
+
 /// API request to change posture
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChangePostureRequest {
-    /// New posture
+    /// New posture (silent, neutral, mimetic, fulgurant or unstable)
     pub posture: String,
+    
+    /// Why the posture is changed, kept in the posture history
+    #[serde(default)]
+    pub reason: Option<String>,
+    
+    /// Switch straight to the posture, ignoring the allowed transitions
+    #[serde(default)]
+    pub force: bool,
 }
 
 /// API response for posture change
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChangePostureResponse {
     /// Success status
     pub success: bool,
@@ -129,19 +357,312 @@ pub struct ChangePostureResponse {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+/// API response for the current posture
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PostureResponse {
+    /// Current posture
+    pub posture: String,
+}
+
+/// API request to enable or disable a module
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ToggleModuleRequest {
+    /// Whether the module should be active
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// API response for a module toggle
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ToggleModuleResponse {
+    /// Module name
+    pub module: String,
+    
+    /// Whether the module is now active
+    pub active: bool,
+    
+    /// Timestamp
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// A detection pattern of the formats module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DetectionPattern {
+    /// Regular expression
+    pub pattern: String,
+    
+    /// Detection type reported on a match
+    pub detection_type: String,
+    
+    /// Severity reported on a match (0-10)
+    pub severity: u8,
+}
+
+/// API response listing the detection patterns
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PatternsResponse {
+    /// Patterns by file format (`csv`, `log`)
+    #[schema(value_type = Object)]
+    pub patterns: HashMap<String, Vec<DetectionPattern>>,
+}
+
+/// Request to replace detection patterns
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReplacePatternsRequest {
+    /// New patterns by file format (`csv`, `log`), formats not listed keep theirs
+    #[schema(value_type = Object)]
+    pub patterns: HashMap<String, Vec<DetectionPattern>>,
+}
+
+impl PatternsResponse {
+    fn new(formats: &Formats) -> Self {
+        let patterns = formats
+            .patterns()
+            .into_iter()
+            .map(|(format, rules)| {
+                let rules = rules
+                    .into_iter()
+                    .map(|rule| DetectionPattern {
+                        pattern: rule.pattern,
+                        detection_type: rule.detection_type,
+                        severity: rule.severity.get(),
+                    })
+                    .collect();
+                (format.name().to_string(), rules)
+            })
+            .collect();
+        
+        Self { patterns }
+    }
+}
+
+impl ReplacePatternsRequest {
+    /// Pattern sets keyed by format, rejecting unknown formats and severities above 10
+    fn into_sets(self) -> Result<HashMap<FileFormat, Vec<PatternRule>>, PigmentApiError> {
+        self.patterns
+            .into_iter()
+            .map(|(name, patterns)| {
+                let format = FileFormat::from_extension(&name);
+                if format == FileFormat::Unknown {
+                    return Err(PigmentApiError::InvalidRequest(format!("Unknown file format: {}", name)));
+                }
+                
+                let rules = patterns
+                    .into_iter()
+                    .map(|pattern| {
+                        let severity = Severity0to10::new(pattern.severity)
+                            .map_err(|e| PigmentApiError::InvalidRequest(format!("{}: {}", pattern.pattern, e)))?;
+                        Ok(PatternRule {
+                            pattern: pattern.pattern,
+                            detection_type: pattern.detection_type,
+                            severity,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, PigmentApiError>>()?;
+                Ok((format, rules))
+            })
+            .collect()
+    }
+}
+
+/// API response for the threat level
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThreatResponse {
+    /// Latest threat level, between 0.0 and 1.0
+    pub threat_level: f64,
+    
+    /// Threat levels of the last hour, oldest first
+    pub history: Vec<ThreatPoint>,
+}
+
+/// Threat level at a point in time
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThreatPoint {
+    /// When the threat level was evaluated
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    
+    /// Threat level, between 0.0 and 1.0
+    pub value: f64,
+}
+
+/// API response for clearing history
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClearResponse {
+    /// Number of records removed
+    pub cleared: usize,
+    
+    /// Timestamp
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// API request to publish a synthetic event, shaped like `EventInfo`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct InjectEventRequest {
+    /// Event type, e.g. `SecurityAlert`; unknown names become custom events
+    pub event_type: String,
+    
+    /// Source
+    #[serde(default = "default_inject_source")]
+    pub source: String,
+    
+    /// Severity override, e.g. `High`
+    pub severity: Option<String>,
+    
+    /// Timestamp, defaults to now
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    
+    /// Data
+    pub data: Option<serde_json::Value>,
+}
+
+fn default_inject_source() -> String {
+    "pigment_api".to_string()
+}
+
+impl InjectEventRequest {
+    /// Rebuild the event described by the request
+    fn into_event(self) -> Result<Event, PigmentApiError> {
+        // Known types are serialized by their variant name, anything else is custom
+        let event_type = serde_json::from_value(serde_json::Value::String(self.event_type.clone()))
+            .unwrap_or(EventType::Custom(self.event_type));
+        
+        let mut event = Event::new(event_type, self.source, self.data);
+        if let Some(timestamp) = self.timestamp {
+            event.timestamp = timestamp;
+        }
+        if let Some(severity) = self.severity {
+            let severity: Severity = serde_json::from_value(serde_json::Value::String(severity.clone()))
+                .map_err(|_| PigmentApiError::InvalidRequest(format!("Unknown severity: {}", severity)))?;
+            event = event.with_severity(severity);
+        }
+        
+        Ok(event)
+    }
+}
+
+/// API response for the health and readiness probes
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// `ok`, `ready` or `not_ready`; for modules `healthy`, `degraded`, `unhealthy` or `stopped`
+    pub status: String,
+    
+    /// Why the API is not ready or the module not healthy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl From<&ModuleHealth> for HealthResponse {
+    fn from(health: &ModuleHealth) -> Self {
+        Self {
+            status: health.name().to_string(),
+            reason: health.reason().map(str::to_string),
+        }
+    }
+}
+
+/// API response for the capabilities report
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CapabilitiesResponse {
+    /// Whether any enabled feature is simulated or unavailable
+    pub degraded: bool,
+    
+    /// Privileged features of the running modules
+    pub features: Vec<FeatureResponse>,
+}
+
+/// How a privileged feature runs
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureResponse {
+    /// Module and feature name
+    pub name: String,
+    
+    /// `functional`, `simulated`, `unavailable` or `disabled`
+    pub status: String,
+    
+    /// Why the feature is simulated or unavailable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl From<&Feature> for FeatureResponse {
+    fn from(feature: &Feature) -> Self {
+        Self {
+            name: feature.name.clone(),
+            status: feature.status.name().to_string(),
+            reason: feature.status.reason().map(str::to_string),
+        }
+    }
+}
+
+/// API error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Error message
+    pub error: String,
+}
+
+/// Starts and stops modules for `POST /api/modules/{name}`
+#[async_trait::async_trait]
+pub trait ModuleControl: Send + Sync {
+    /// Start or stop a module
+    ///
+    /// Unknown modules are `NotFound`; modules that can't be toggled at
+    /// runtime, e.g. disabled in the configuration, are `InvalidRequest`.
+    /// Modules already in the requested state are left alone.
+    async fn set_active(&self, module: &str, active: bool) -> Result<(), PigmentApiError>;
+    
+    /// Health of every running module, by name
+    async fn health(&self) -> HashMap<String, ModuleHealth> {
+        HashMap::new()
+    }
+    
+    /// Privileged features of the running modules and how they run
+    async fn features(&self) -> Vec<Feature> {
+        Vec::new()
+    }
+}
+
+/// CORS layer only allowing the given origins
+fn cors_layer(origins: &[String]) -> Result<CorsLayer, PigmentApiError> {
+    let origins = origins
+        .iter()
+        .map(|origin| {
+            if origin == "*" {
+                return Err(PigmentApiError::InvalidRequest(
+                    "CORS origins must be listed explicitly, \"*\" is not allowed".to_string(),
+                ));
+            }
+            HeaderValue::from_str(origin)
+                .map_err(|_| PigmentApiError::InvalidRequest(format!("Invalid CORS origin: {}", origin)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]))
+}
+
 /// Main PigmentAPI service
 pub struct PigmentApi {
     /// Configuration
     config: PigmentApiConfig,
     
     /// Event sender
-    event_sender: mpsc::Sender<Event>,
+    event_sender: EventSender,
     
-    /// Event receiver
-    event_receiver: Arc<RwLock<mpsc::Receiver<Event>>>,
+    /// Event receiver, taken by the event listener when it starts
+    event_receiver: std::sync::Mutex<Option<mpsc::Receiver<Event>>>,
     
     /// Event history
-    events: Arc<RwLock<Vec<Event>>>,
+    events: Arc<RwLock<EventHistory>>,
+    
+    /// Events fanned out to WebSocket clients as they are received
+    live_events: broadcast::Sender<LiveEvent>,
     
     /// Current posture
     current_posture: Arc<RwLock<String>>,
@@ -151,109 +672,236 @@ pub struct PigmentApi {
     
     /// System metrics
     metrics: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    
+    /// Whether the event listener task is running
+    listener_running: Arc<AtomicBool>,
+    
+    /// Core service whose status gates readiness
+    core: Option<Arc<dyn ChameleonService>>,
+    
+    /// Starts and stops modules when they are toggled
+    module_control: Option<Arc<dyn ModuleControl>>,
+    
+    /// Collector holding the threat level history
+    metrics_collector: Option<Arc<MetricsCollector>>,
+    
+    /// File analysis whose patterns are served and replaced
+    formats: Option<Arc<Formats>>,
 }
 
 impl PigmentApi {
     /// Create a new PigmentAPI instance
     pub async fn new(
         config: PigmentApiConfig,
-        event_sender: mpsc::Sender<Event>,
+        event_sender: EventSender,
         event_receiver: mpsc::Receiver<Event>,
     ) -> Result<Self, PigmentApiError> {
         Ok(Self {
             config,
             event_sender,
-            event_receiver: Arc::new(RwLock::new(event_receiver)),
-            events: Arc::new(RwLock::new(Vec::new())),
+            event_receiver: std::sync::Mutex::new(Some(event_receiver)),
+            events: Arc::new(RwLock::new(EventHistory::default())),
+            live_events: broadcast::channel(live::LIVE_EVENTS_CAPACITY).0,
             current_posture: Arc::new(RwLock::new("neutral".to_string())),
             active_modules: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(RwLock::new(HashMap::new())),
+            listener_running: Arc::new(AtomicBool::new(false)),
+            core: None,
+            module_control: None,
+            metrics_collector: None,
+            formats: None,
         })
     }
     
+    /// Only report ready once `core` is running
+    pub fn with_core(mut self, core: Arc<dyn ChameleonService>) -> Self {
+        self.core = Some(core);
+        self
+    }
+    
+    /// Serve the `threat_level` time series of `metrics_collector` on `GET /api/threat`
+    pub fn with_metrics_collector(mut self, metrics_collector: Arc<MetricsCollector>) -> Self {
+        self.metrics_collector = Some(metrics_collector);
+        self
+    }
+    
+    /// Known modules and whether they are active, as listed by `GET /api/modules`
+    pub fn with_modules(mut self, modules: HashMap<String, bool>) -> Self {
+        self.active_modules = Arc::new(RwLock::new(modules));
+        self
+    }
+    
+    /// Actually start and stop modules when they are toggled
+    pub fn with_module_control(mut self, module_control: Arc<dyn ModuleControl>) -> Self {
+        self.module_control = Some(module_control);
+        self
+    }
+    
+    /// Serve and replace the detection patterns of `formats` on `/api/formats/patterns`
+    pub fn with_formats(mut self, formats: Arc<Formats>) -> Self {
+        self.formats = Some(formats);
+        self
+    }
+    
     /// Start the API server
+    ///
+    /// One server is spawned per bind address. If any of them stops, the
+    /// others are aborted and the error is returned.
     pub async fn start(&self) -> Result<(), PigmentApiError> {
-        tracing::info!("Starting PigmentAPI server on {}", self.config.bind_address);
+        if self.config.bind_addresses.is_empty() {
+            return Err(PigmentApiError::InvalidRequest("No bind address configured".to_string()));
+        }
         
-        // Start event listener
-        self.start_event_listener().await;
+        // Create router, rejecting an invalid configuration before binding
+        let router = self.create_router().await?;
         
-        // Create router
-        let router = self.create_router().await;
+        // Bind every address first so a taken port fails the whole start
+        let mut builders = Vec::with_capacity(self.config.bind_addresses.len());
+        for address in &self.config.bind_addresses {
+            let builder = axum::Server::try_bind(address).map_err(|e| {
+                PigmentApiError::ServerError(format!("Failed to bind {}: {}", address, e))
+            })?;
+            builders.push((*address, builder));
+        }
+        
+        // Start event listener
+        self.start_event_listener()?;
         
-        // Start server
-        let server = axum::Server::bind(&self.config.bind_address)
-            .serve(router.into_make_service());
+        // Start servers
+        let mut servers = JoinSet::new();
+        for (address, builder) in builders {
+            tracing::info!("Starting PigmentAPI server on {}", address);
+            let server = builder.serve(router.clone().into_make_service_with_connect_info::<SocketAddr>());
+            servers.spawn(async move {
+                server.await.map_err(|e| format!("Server error on {}: {}", address, e))
+            });
+        }
         
-        // Run the server
-        if let Err(e) = server.await {
-            return Err(PigmentApiError::ServerError(format!("Server error: {}", e)));
+        // Dropping the set on return aborts the remaining servers
+        while let Some(result) = servers.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => return Err(PigmentApiError::ServerError(e)),
+                Err(e) => return Err(PigmentApiError::ServerError(format!("Server task failed: {}", e))),
+            }
         }
         
         Ok(())
     }
     
     /// Create the API router
-    async fn create_router(&self) -> Router {
+    async fn create_router(&self) -> Result<Router, PigmentApiError> {
         // Create state
         let state = AppState {
             events: self.events.clone(),
+            live_events: self.live_events.clone(),
             current_posture: self.current_posture.clone(),
             active_modules: self.active_modules.clone(),
             metrics: self.metrics.clone(),
             event_sender: self.event_sender.clone(),
+            allow_event_injection: self.config.allow_event_injection,
+            allow_pattern_updates: self.config.allow_pattern_updates,
+            listener_running: self.listener_running.clone(),
+            core: self.core.clone(),
+            module_control: self.module_control.clone(),
+            metrics_collector: self.metrics_collector.clone(),
+            formats: self.formats.clone(),
         };
         
         // Create CORS layer if enabled
-        let cors = if self.config.enable_cors {
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any)
-        } else {
-            CorsLayer::permissive()
+        let cors = match &self.config.cors_origins {
+            Some(origins) => Some(cors_layer(origins)?),
+            None => None,
         };
         
-        // Create router
-        Router::new()
+        // Checked before the rate limits, which then also slow down key guessing
+        let keys = ApiKeys(self.config.api_keys.clone().into());
+        let require_key = middleware::from_fn_with_state(keys, auth::require_api_key);
+        
+        // Read endpoints
+        let read = Router::new()
             .route("/api/status", get(get_status))
+            .route("/api/state", get(get_state))
+            .route("/api/capabilities", get(get_capabilities))
             .route("/api/events", get(get_events))
+            .route("/api/events/export", get(export_events))
+            .route("/api/events/ws", get(stream_events))
+            .route("/api/detections", get(get_detections))
             .route("/api/posture", get(get_posture))
-            .route("/api/posture", post(change_posture))
             .route("/api/modules", get(get_modules))
-            .route("/api/modules/:name", post(toggle_module))
             .route("/api/metrics", get(get_metrics))
-            .layer(cors)
-            .with_state(state)
+            .route("/api/threat", get(get_threat))
+            .route("/api/formats/patterns", get(get_patterns))
+            .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
+            .route_layer(require_key.clone())
+            .route_layer(RateLimitLayer::new(self.config.requests_per_second, self.config.burst));
+        
+        // Write endpoints get a stricter limit
+        let write = Router::new()
+            .route("/api/posture", post(change_posture))
+            .route("/api/modules/:name", post(toggle_module))
+            .route("/api/events", post(inject_event).delete(clear_events))
+            .route("/api/detections", delete(clear_detections))
+            .route("/api/formats/patterns", post(replace_patterns))
+            .route_layer(require_key)
+            .route_layer(RateLimitLayer::new(
+                self.config.write_requests_per_second,
+                self.config.write_burst,
+            ));
+        
+        // Probes are never rate limited nor require a key
+        let probes = Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz));
+        
+        // Create router
+        let router = read.merge(write).merge(probes);
+        let router = match cors {
+            Some(cors) => router.layer(cors),
+            None => router,
+        };
+        
+        // Outermost, so rejected and preflight requests are logged too
+        let access_log = AccessLog {
+            trust_forwarded_for: self.config.trust_forwarded_for,
+            trusted_proxies: self.config.trusted_proxies.clone().into(),
+        };
+        let router = router.layer(middleware::from_fn_with_state(access_log, access_log::log_request));
+        
+        Ok(router.with_state(state))
     }
     
     /// Start the event listener
-    async fn start_event_listener(&self) {
+    ///
+    /// The listener owns the event receiver, so it can only be started once.
+    fn start_event_listener(&self) -> Result<(), PigmentApiError> {
+        let mut event_receiver = self
+            .event_receiver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+            .ok_or(PigmentApiError::ListenerAlreadyStarted)?;
+        
         let events = self.events.clone();
+        let live_events = self.live_events.clone();
         let current_posture = self.current_posture.clone();
         let active_modules = self.active_modules.clone();
         let metrics = self.metrics.clone();
-        let mut event_receiver = self.event_receiver.write().await;
+        let listener_running = self.listener_running.clone();
         
         tokio::spawn(async move {
+            listener_running.store(true, Ordering::Relaxed);
             while let Some(event) = event_receiver.recv().await {
-                // Store event
-                {
-                    let mut events_lock = events.write().await;
-                    events_lock.push(event.clone());
-                    
-                    // Limit history size
-                    if events_lock.len() > 1000 {
-                        events_lock.remove(0);
-                    }
-                }
+                // Store event, the history dropping the oldest when full
+                let seq = events.write().await.push(event.clone());
                 
                 // Update posture if it's a posture change event
                 if let EventType::PostureChange = event.event_type {
                     if let Some(data) = &event.data {
-                        if let Some(posture) = data.get("posture").and_then(|p| p.as_str()) {
+                        let posture = data.get("posture").and_then(|p| p.as_str()).map(Posture::try_from);
+                        if let Some(Ok(posture)) = posture {
                             let mut posture_lock = current_posture.write().await;
-                            *posture_lock = posture.to_string();
+                            *posture_lock = posture.to_str().to_string();
                         }
                     }
                 }
@@ -284,8 +932,14 @@ impl PigmentApi {
                         }
                     }
                 }
+                
+                // Push to WebSocket clients, none may be connected
+                let _ = live_events.send(Arc::new((seq, event)));
             }
+            listener_running.store(false, Ordering::Relaxed);
         });
+        
+        Ok(())
     }
 }
 
@@ -293,7 +947,10 @@ impl PigmentApi {
 #[derive(Clone)]
 struct AppState {
     /// Event history
-    events: Arc<RwLock<Vec<Event>>>,
+    events: Arc<RwLock<EventHistory>>,
+    
+    /// Events fanned out to WebSocket clients as they are received
+    live_events: broadcast::Sender<LiveEvent>,
     
     /// Current posture
     current_posture: Arc<RwLock<String>>,
@@ -305,13 +962,92 @@ struct AppState {
     metrics: Arc<RwLock<HashMap<String, serde_json::Value>>>,
     
     /// Event sender
-    event_sender: mpsc::Sender<Event>,
+    event_sender: EventSender,
+    
+    /// Whether synthetic events may be injected
+    allow_event_injection: bool,
+    
+    /// Whether detection patterns may be replaced
+    allow_pattern_updates: bool,
+    
+    /// Whether the event listener task is running
+    listener_running: Arc<AtomicBool>,
+    
+    /// Core service whose status gates readiness
+    core: Option<Arc<dyn ChameleonService>>,
+    
+    /// Starts and stops modules when they are toggled
+    module_control: Option<Arc<dyn ModuleControl>>,
+    
+    /// Collector holding the threat level history
+    metrics_collector: Option<Arc<MetricsCollector>>,
+    
+    /// File analysis whose patterns are served and replaced
+    formats: Option<Arc<Formats>>,
+}
+
+/// Liveness probe
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "system",
+    responses((status = 200, description = "The server is up", body = HealthResponse))
+)]
+async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, Json(HealthResponse { status: "ok".to_string(), reason: None }))
+}
+
+/// Readiness probe
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "system",
+    responses(
+        (status = 200, description = "Events are being received", body = HealthResponse),
+        (status = 503, description = "Not ready yet", body = HealthResponse)
+    )
+)]
+async fn readyz(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let not_ready = |reason: String| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthResponse { status: "not_ready".to_string(), reason: Some(reason) }),
+        )
+    };
+    
+    if !state.listener_running.load(Ordering::Relaxed) {
+        return not_ready("Event listener is not running".to_string());
+    }
+    
+    if let Some(core) = &state.core {
+        match core.get_state().await {
+            Ok(core_state) if core_state.status == Status::Running => {}
+            Ok(core_state) => return not_ready(format!("Core is {:?}", core_state.status)),
+            Err(e) => return not_ready(format!("Core state unavailable: {}", e)),
+        }
+    }
+    
+    (StatusCode::OK, Json(HealthResponse { status: "ready".to_string(), reason: None }))
 }
 
 /// Get system status
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "system",
+    responses((status = 200, description = "Current system status", body = SystemStatusResponse))
+)]
 async fn get_status(
     State(state): State<AppState>,
 ) -> impl IntoResponse {
+    // Checked first, modules may take a moment to answer
+    let health = match &state.module_control {
+        Some(module_control) => module_control.health().await,
+        None => HashMap::new(),
+    };
+    
     let posture = state.current_posture.read().await.clone();
     let active_modules = state.active_modules.read().await;
     let metrics = state.metrics.read().await;
@@ -326,14 +1062,68 @@ async fn get_status(
         posture,
         active_modules: active_module_names,
         metrics: metrics.clone(),
+        health: health.iter().map(|(name, health)| (name.clone(), health.into())).collect(),
         timestamp: chrono::Utc::now(),
     };
     
     (StatusCode::OK, Json(response))
 }
 
+/// Get the state snapshot of the core
+#[utoipa::path(
+    get,
+    path = "/api/state",
+    tag = "system",
+    responses(
+        (status = 200, description = "The core's `SystemState`: posture, threat level, counts and fingerprint", body = Object),
+        (status = 503, description = "No core attached or its state is unavailable", body = ErrorResponse)
+    )
+)]
+async fn get_state(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let Some(core) = &state.core else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "No core attached to the API" })),
+        );
+    };
+    
+    match core.get_state().await {
+        Ok(system_state) => (StatusCode::OK, Json(serde_json::to_value(system_state).unwrap_or_default())),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": format!("Core state unavailable: {}", e) })),
+        ),
+    }
+}
+
+/// Get which privileged features work and which are only simulated
+#[utoipa::path(
+    get,
+    path = "/api/capabilities",
+    tag = "system",
+    responses((status = 200, description = "Privileged features of the running modules, with the reason of each degraded one", body = CapabilitiesResponse))
+)]
+async fn get_capabilities(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let features = match &state.module_control {
+        Some(module_control) => module_control.features().await,
+        None => Vec::new(),
+    };
+    
+    let response = CapabilitiesResponse {
+        degraded: features.iter().any(|feature| feature.status.is_degraded()),
+        features: features.iter().map(FeatureResponse::from).collect(),
+    };
+    
+    (StatusCode::OK, Json(response))
+}
+
 /// Query parameters for events
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct EventsQuery {
     /// Page number
     #[serde(default = "default_page")]
@@ -348,6 +1138,56 @@ struct EventsQuery {
     
     /// Source filter
     source: Option<String>,
+    
+    /// Order applied before pagination
+    #[serde(default)]
+    sort: EventSort,
+    
+    /// Only return events with a greater sequence number, oldest first
+    since: Option<u64>,
+}
+
+/// Order of the events returned by `GET /api/events`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSort {
+    /// Oldest first
+    TimestampAsc,
+    
+    /// Newest first
+    #[default]
+    TimestampDesc,
+    
+    /// Least severe first, newest first within a level
+    SeverityAsc,
+    
+    /// Most severe first, newest first within a level
+    SeverityDesc,
+}
+
+impl EventSort {
+    /// Sort `(sequence number, event)` pairs
+    ///
+    /// Ties fall back to the sequence number so page boundaries stay stable.
+    fn apply(self, events: &mut [(u64, &Event)]) {
+        let newest_first = |a: &(u64, &Event), b: &(u64, &Event)| {
+            b.1.timestamp.cmp(&a.1.timestamp).then(b.0.cmp(&a.0))
+        };
+        
+        match self {
+            EventSort::TimestampAsc => {
+                events.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then(a.0.cmp(&b.0)))
+            }
+            EventSort::TimestampDesc => events.sort_by(newest_first),
+            // `Severity` orders from Critical to Info
+            EventSort::SeverityAsc => events.sort_by(|a, b| {
+                b.1.severity().cmp(&a.1.severity()).then_with(|| newest_first(a, b))
+            }),
+            EventSort::SeverityDesc => events.sort_by(|a, b| {
+                a.1.severity().cmp(&b.1.severity()).then_with(|| newest_first(a, b))
+            }),
+        }
+    }
 }
 
 fn default_page() -> usize {
@@ -359,60 +1199,52 @@ fn default_page_size() -> usize {
 }
 
 /// Get events
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "events",
+    params(EventsQuery),
+    responses((status = 200, description = "Page of recorded events", body = EventsResponse))
+)]
 async fn get_events(
     State(state): State<AppState>,
     Query(query): Query<EventsQuery>,
 ) -> impl IntoResponse {
     let events = state.events.read().await;
     
-    // Apply filters
-    let filtered_events: Vec<&Event> = events
-        .iter()
-        .filter(|e| {
-            if let Some(ref event_type) = query.event_type {
-                match &e.event_type {
-                    EventType::Custom(name) => name == event_type,
-                    other => format!("{:?}", other) == event_type,
-                }
-            } else {
-                true
-            }
-        })
-        .filter(|e| {
-            if let Some(ref source) = query.source {
-                e.source == *source
-            } else {
-                true
-            }
-        })
-        .collect();
+    // Apply filters, keeping each event's sequence number as its ID. Polls
+    // for new events read them in arrival order.
+    let mut filtered_events: Vec<(u64, &Event)> = match query.since {
+        Some(since) => events
+            .since(since)
+            .filter(|(_, e)| matches_filters(e, query.event_type.as_deref(), query.source.as_deref()))
+            .collect(),
+        None => {
+            let mut filtered_events: Vec<_> = events
+                .iter()
+                .filter(|(_, e)| matches_filters(e, query.event_type.as_deref(), query.source.as_deref()))
+                .collect();
+            query.sort.apply(&mut filtered_events);
+            filtered_events
+        }
+    };
     
     // Paginate
     let total = filtered_events.len();
-    let start = query.page * query.page_size;
-    let end = (start + query.page_size).min(total);
+    let start = query.page.saturating_mul(query.page_size).min(total);
+    let end = start.saturating_add(query.page_size).min(total);
     
-    let paginated = if start < total {
-        filtered_events[start..end].to_vec()
-    } else {
-        Vec::new()
+    // A poll resumes after the returned page while new events are left, and
+    // after the whole history otherwise so filtered out events aren't rescanned
+    let latest_seq = match query.since {
+        Some(since) if end < total => Some(end.checked_sub(1).map_or(since, |last| filtered_events[last].0)),
+        _ => events.latest_seq(),
     };
     
     // Convert to response format
-    let event_infos: Vec<EventInfo> = paginated
-        .into_iter()
-        .enumerate()
-        .map(|(i, e)| EventInfo {
-            id: format!("{}", start + i),
-            event_type: match &e.event_type {
-                EventType::Custom(name) => name.clone(),
-                other => format!("{:?}", other),
-            },
-            source: e.source.clone(),
-            severity: format!("{:?}", e.severity()),
-            timestamp: e.timestamp,
-            data: e.data.clone(),
-        })
+    let event_infos: Vec<EventInfo> = filtered_events
+        .drain(start..end)
+        .map(|(seq, e)| EventInfo::from_event(seq.to_string(), e))
         .collect();
     
     let response = EventsResponse {
@@ -420,41 +1252,330 @@ async fn get_events(
         total,
         page: query.page,
         page_size: query.page_size,
+        latest_seq,
+    };
+    
+    (StatusCode::OK, Json(response))
+}
+
+/// Whether an event passes the optional type and source filters
+fn matches_filters(event: &Event, event_type: Option<&str>, source: Option<&str>) -> bool {
+    event_type.map_or(true, |name| event_type_name(&event.event_type) == name)
+        && source.map_or(true, |source| event.source == source)
+}
+
+/// Query parameters for the live event stream
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LiveEventsQuery {
+    /// Event type filter
+    event_type: Option<String>,
+    
+    /// Source filter
+    source: Option<String>,
+}
+
+/// Push new events over a WebSocket as they are received
+///
+/// Each event is a text message holding its `EventInfo` JSON. Clients too
+/// slow to keep up skip the events they missed, which shows in the IDs.
+/// Besides the `Authorization` header, the API key can be sent as
+/// `Sec-WebSocket-Protocol: bearer, <key>` or an `api_key` query parameter.
+#[utoipa::path(
+    get,
+    path = "/api/events/ws",
+    tag = "events",
+    params(LiveEventsQuery),
+    responses((status = 101, description = "Switching to a WebSocket streaming each new event as an `EventInfo`"))
+)]
+async fn stream_events(
+    State(state): State<AppState>,
+    Query(query): Query<LiveEventsQuery>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    // Subscribed now so events arriving during the upgrade aren't lost
+    let events = state.live_events.subscribe();
+    // Browsers drop the connection unless one of their subprotocols is picked
+    ws.protocols([auth::BEARER_PROTOCOL]).on_upgrade(move |socket| live::stream_events(socket, events, query.event_type, query.source))
+}
+
+/// Query parameters for the event export
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportQuery {
+    /// Output format
+    #[serde(default)]
+    format: ExportFormat,
+    
+    /// Event type filter
+    event_type: Option<String>,
+    
+    /// Source filter
+    source: Option<String>,
+}
+
+/// Download the event history, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/events/export",
+    tag = "events",
+    params(ExportQuery),
+    responses((status = 200, description = "CSV or newline-delimited JSON attachment"))
+)]
+async fn export_events(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    // Copy the matching events so the history isn't locked while streaming
+    let events: Vec<(u64, Event)> = state
+        .events
+        .read()
+        .await
+        .iter()
+        .filter(|(_, e)| matches_filters(e, query.event_type.as_deref(), query.source.as_deref()))
+        .map(|(seq, e)| (seq, e.clone()))
+        .collect();
+    
+    let headers = [
+        (header::CONTENT_TYPE, query.format.content_type().to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", query.format.file_name()),
+        ),
+    ];
+    
+    (headers, export::stream_events(query.format, events))
+}
+
+/// Query parameters for detections
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DetectionsQuery {
+    /// Page number
+    #[serde(default = "default_page")]
+    page: usize,
+    
+    /// Page size
+    #[serde(default = "default_page_size")]
+    page_size: usize,
+    
+    /// Reporting module filter
+    source: Option<String>,
+    
+    /// Minimum severity (0-10)
+    min_severity: Option<u8>,
+}
+
+/// Get detections
+#[utoipa::path(
+    get,
+    path = "/api/detections",
+    tag = "events",
+    params(DetectionsQuery),
+    responses((status = 200, description = "Page of detections", body = DetectionsResponse))
+)]
+async fn get_detections(
+    State(state): State<AppState>,
+    Query(query): Query<DetectionsQuery>,
+) -> impl IntoResponse {
+    let events = state.events.read().await;
+    
+    // Rebuild detections and apply filters
+    let detections: Vec<DetectionInfo> = events
+        .iter()
+        .filter_map(|(_, event)| DetectionInfo::from_event(event))
+        .filter(|d| query.source.as_ref().map_or(true, |source| d.source == *source))
+        .filter(|d| query.min_severity.map_or(true, |min| d.severity >= min))
+        .collect();
+    
+    // Paginate
+    let total = detections.len();
+    let start = query.page.saturating_mul(query.page_size).min(total);
+    let end = start.saturating_add(query.page_size).min(total);
+    
+    let response = DetectionsResponse {
+        detections: detections[start..end].to_vec(),
+        total,
+        page: query.page,
+        page_size: query.page_size,
     };
     
     (StatusCode::OK, Json(response))
 }
 
+/// Clear the event history
+#[utoipa::path(
+    delete,
+    path = "/api/events",
+    tag = "events",
+    responses(
+        (status = 200, description = "Event history cleared", body = ClearResponse),
+        (status = 429, description = "Rate limit exceeded")
+    )
+)]
+async fn clear_events(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let cleared = {
+        let mut events = state.events.write().await;
+        let cleared = events.len();
+        events.clear();
+        cleared
+    };
+    
+    audit_clear(&state, "clear_events", cleared).await;
+    
+    (StatusCode::OK, Json(ClearResponse { cleared, timestamp: chrono::Utc::now() }))
+}
+
+/// Publish a synthetic event onto the bus, for testing detections end to end
+#[utoipa::path(
+    post,
+    path = "/api/events",
+    tag = "events",
+    request_body = InjectEventRequest,
+    responses(
+        (status = 202, description = "Event published", body = EventInfo),
+        (status = 400, description = "Invalid event", body = ErrorResponse),
+        (status = 403, description = "Event injection is disabled", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "The event could not be forwarded", body = ErrorResponse)
+    )
+)]
+async fn inject_event(
+    State(state): State<AppState>,
+    Json(request): Json<InjectEventRequest>,
+) -> impl IntoResponse {
+    if !state.allow_event_injection {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Event injection is disabled" })),
+        );
+    }
+    
+    let event = match request.into_event() {
+        Ok(event) => event,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            );
+        }
+    };
+    
+    tracing::info!("Injecting synthetic {:?} event from {} via API", event.event_type, event.source);
+    
+    let info = EventInfo::from_event("injected", &event);
+    
+    if let Err(e) = state.event_sender.send(event).await {
+        tracing::error!("Failed to send injected event: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Failed to send event" })),
+        );
+    }
+    
+    (StatusCode::ACCEPTED, Json(serde_json::to_value(info).unwrap_or_default()))
+}
+
+/// Clear the detection history, keeping other events
+#[utoipa::path(
+    delete,
+    path = "/api/detections",
+    tag = "events",
+    responses(
+        (status = 200, description = "Detection history cleared", body = ClearResponse),
+        (status = 429, description = "Rate limit exceeded")
+    )
+)]
+async fn clear_detections(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let cleared = {
+        let mut events = state.events.write().await;
+        let before = events.len();
+        events.retain(|event| DetectionInfo::from_event(event).is_none());
+        before - events.len()
+    };
+    
+    audit_clear(&state, "clear_detections", cleared).await;
+    
+    (StatusCode::OK, Json(ClearResponse { cleared, timestamp: chrono::Utc::now() }))
+}
+
+/// Record an admin clear on the bus so it shows up in the audit trail
+async fn audit_clear(state: &AppState, action: &str, cleared: usize) {
+    tracing::info!("{} via API: {} records removed", action, cleared);
+    
+    let event = Event::system_change(
+        "pigment_api",
+        Some(serde_json::json!({
+            "action": action,
+            "cleared": cleared,
+            "source": "api",
+        })),
+    );
+    
+    if let Err(e) = state.event_sender.send(event).await {
+        tracing::error!("Failed to send {} event: {}", action, e);
+    }
+}
+
 /// Get current posture
+#[utoipa::path(
+    get,
+    path = "/api/posture",
+    tag = "posture",
+    responses((status = 200, description = "Current posture", body = PostureResponse))
+)]
 async fn get_posture(
     State(state): State<AppState>,
 ) -> impl IntoResponse {
     let posture = state.current_posture.read().await.clone();
     
-    (StatusCode::OK, Json(serde_json::json!({ "posture": posture })))
+    (StatusCode::OK, Json(PostureResponse { posture }))
 }
 
 /// Change posture
+#[utoipa::path(
+    post,
+    path = "/api/posture",
+    tag = "posture",
+    request_body = ChangePostureRequest,
+    responses(
+        (status = 200, description = "Posture changed", body = ChangePostureResponse),
+        (status = 400, description = "Unknown posture", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "The change could not be forwarded", body = ErrorResponse)
+    )
+)]
 async fn change_posture(
     State(state): State<AppState>,
     Json(request): Json<ChangePostureRequest>,
 ) -> impl IntoResponse {
+    let posture = match Posture::try_from(request.posture.as_str()) {
+        Ok(posture) => posture,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))),
+    };
     let previous_posture;
     
     // Update posture
     {
         let mut posture_lock = state.current_posture.write().await;
         previous_posture = posture_lock.clone();
-        *posture_lock = request.posture.clone();
+        *posture_lock = posture.to_str().to_string();
     }
     
     // Send event
     let event = Event::posture_change(
         "pigment_api",
         Some(serde_json::json!({
-            "posture": request.posture,
+            "posture": posture.to_str(),
             "previous_posture": previous_posture,
             "source": "api",
+            "actor": "api",
+            "reason": request.reason.as_deref().unwrap_or("changed through the API"),
+            "force": request.force,
         })),
     );
     
@@ -469,14 +1590,20 @@ async fn change_posture(
     let response = ChangePostureResponse {
         success: true,
         previous_posture,
-        new_posture: request.posture,
+        new_posture: posture.to_str().to_string(),
         timestamp: chrono::Utc::now(),
     };
     
-    (StatusCode::OK, Json(response))
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap_or_default()))
 }
 
 /// Get active modules
+#[utoipa::path(
+    get,
+    path = "/api/modules",
+    tag = "modules",
+    responses((status = 200, description = "Whether each known module is active", body = HashMap<String, bool>))
+)]
 async fn get_modules(
     State(state): State<AppState>,
 ) -> impl IntoResponse {
@@ -486,15 +1613,53 @@ async fn get_modules(
 }
 
 /// Toggle module status
+#[utoipa::path(
+    post,
+    path = "/api/modules/{name}",
+    tag = "modules",
+    params(("name" = String, Path, description = "Module name")),
+    request_body = ToggleModuleRequest,
+    responses(
+        (status = 200, description = "Module toggled", body = ToggleModuleResponse),
+        (status = 400, description = "The body is not a valid toggle request", body = ErrorResponse),
+        (status = 404, description = "Unknown module", body = ErrorResponse),
+        (status = 409, description = "The module can't be toggled at runtime", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "The module failed to start or stop, or the change could not be forwarded", body = ErrorResponse)
+    )
+)]
 async fn toggle_module(
     State(state): State<AppState>,
     Path(name): Path<String>,
-    Json(status): Json<serde_json::Value>,
+    body: axum::body::Bytes,
 ) -> impl IntoResponse {
-    let active = status
-        .get("active")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(true);
+    // An empty body turns the module on, as it always has
+    let request = if body.iter().all(u8::is_ascii_whitespace) {
+        ToggleModuleRequest { active: default_active() }
+    } else {
+        match serde_json::from_slice::<ToggleModuleRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({ "error": format!("Invalid module toggle: {}", e) })),
+                );
+            }
+        }
+    };
+    let active = request.active;
+    
+    // Start or stop the module itself
+    if let Some(module_control) = &state.module_control {
+        if let Err(e) = module_control.set_active(&name, active).await {
+            let status = match e {
+                PigmentApiError::NotFound(_) => StatusCode::NOT_FOUND,
+                PigmentApiError::InvalidRequest(_) => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return (status, Json(serde_json::json!({ "error": e.to_string() })));
+        }
+    }
     
     // Update module status
     {
@@ -520,17 +1685,111 @@ async fn toggle_module(
         );
     }
     
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "module": name,
-            "active": active,
-            "timestamp": chrono::Utc::now(),
-        })),
+    let response = ToggleModuleResponse {
+        module: name,
+        active,
+        timestamp: chrono::Utc::now(),
+    };
+    
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap_or_default()))
+}
+
+/// Get the detection patterns of the formats module
+#[utoipa::path(
+    get,
+    path = "/api/formats/patterns",
+    tag = "formats",
+    responses(
+        (status = 200, description = "Patterns of the built-in analyzers, by file format", body = PatternsResponse),
+        (status = 503, description = "No formats module attached", body = ErrorResponse)
     )
+)]
+async fn get_patterns(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let Some(formats) = &state.formats else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "No formats module attached to the API" })),
+        );
+    };
+    
+    (StatusCode::OK, Json(serde_json::to_value(PatternsResponse::new(formats)).unwrap_or_default()))
+}
+
+/// Replace detection patterns without restarting
+///
+/// Every pattern is compiled first: one invalid pattern rejects the whole
+/// update and the current patterns stay in place.
+#[utoipa::path(
+    post,
+    path = "/api/formats/patterns",
+    tag = "formats",
+    request_body = ReplacePatternsRequest,
+    responses(
+        (status = 200, description = "Patterns replaced, with the resulting set", body = PatternsResponse),
+        (status = 400, description = "Unknown format, invalid pattern or severity above 10", body = ErrorResponse),
+        (status = 403, description = "Pattern updates are disabled", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 503, description = "No formats module attached", body = ErrorResponse)
+    )
+)]
+async fn replace_patterns(
+    State(state): State<AppState>,
+    Json(request): Json<ReplacePatternsRequest>,
+) -> impl IntoResponse {
+    if !state.allow_pattern_updates {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Pattern updates are disabled" })),
+        );
+    }
+    
+    let Some(formats) = &state.formats else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "No formats module attached to the API" })),
+        );
+    };
+    
+    let sets = match request.into_sets() {
+        Ok(sets) => sets,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))),
+    };
+    
+    if let Err(e) = formats.replace_patterns(&sets) {
+        let status = match e {
+            FormatsError::InvalidPattern(_) | FormatsError::InvalidFormat(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        return (status, Json(serde_json::json!({ "error": e.to_string() })));
+    }
+    
+    // Record the change
+    let mut replaced: Vec<&str> = sets.keys().map(FileFormat::name).collect();
+    replaced.sort_unstable();
+    let event = Event::system_change(
+        "pigment_api",
+        Some(serde_json::json!({
+            "action": "patterns_replaced",
+            "formats": replaced,
+            "source": "api",
+        })),
+    );
+    if let Err(e) = state.event_sender.send(event).await {
+        tracing::error!("Failed to send pattern update event: {}", e);
+    }
+    
+    (StatusCode::OK, Json(serde_json::to_value(PatternsResponse::new(formats)).unwrap_or_default()))
 }
 
 /// Get system metrics
+#[utoipa::path(
+    get,
+    path = "/api/metrics",
+    tag = "system",
+    responses((status = 200, description = "Latest metrics reported by the core", body = HashMap<String, Object>))
+)]
 async fn get_metrics(
     State(state): State<AppState>,
 ) -> impl IntoResponse {
@@ -538,3 +1797,1000 @@ async fn get_metrics(
     
     (StatusCode::OK, Json(metrics))
 }
+
+/// Get the threat level and its recent history
+#[utoipa::path(
+    get,
+    path = "/api/threat",
+    tag = "system",
+    responses((status = 200, description = "Latest threat level and the last hour of evaluations", body = ThreatResponse))
+)]
+async fn get_threat(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let since = chrono::Utc::now() - chrono::Duration::hours(1);
+    let history: Vec<ThreatPoint> = state
+        .metrics_collector
+        .as_ref()
+        .map(|metrics| metrics.get_time_series("threat_level", since))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(timestamp, value)| ThreatPoint { timestamp, value })
+        .collect();
+    
+    // Fall back to the core's assessment until the posture engine evaluated events
+    let threat_level = match history.last() {
+        Some(point) => point.value,
+        None => match &state.core {
+            Some(core) => core.get_state().await.map(|s| s.threat_level).unwrap_or_default(),
+            None => 0.0,
+        },
+    };
+    
+    (StatusCode::OK, Json(ThreatResponse { threat_level, history }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+    
+    #[test]
+    fn test_detection_from_nettongue_event() {
+        let event = Event::network_activity(
+            "nettongue",
+            Some(serde_json::json!({
+                "detection_type": "PortScan",
+                "source_ip": "10.0.0.5",
+                "dest_ip": null,
+                "source_port": 40000,
+                "dest_port": 22,
+                "protocol": "tcp",
+                "details": { "ports": "22,80,443" },
+                "severity": 7,
+                "timestamp": chrono::Utc::now(),
+            })),
+        );
+        
+        let detection = DetectionInfo::from_event(&event).unwrap();
+        assert_eq!(detection.detection_type, "PortScan");
+        assert_eq!(detection.severity, 7);
+        assert_eq!(detection.source_ip.as_deref(), Some("10.0.0.5"));
+        assert_eq!(detection.dest_port, Some(22));
+        assert_eq!(detection.details["ports"], "22,80,443");
+    }
+    
+    #[test]
+    fn test_detection_from_formats_event() {
+        let event = Event::security_alert(
+            "formats",
+            Some(serde_json::json!({
+                "detection_type": "sql_injection",
+                "severity": 9,
+                "location": "line 12",
+                "details": {},
+                "file": "access.log",
+            })),
+        );
+        
+        let detection = DetectionInfo::from_event(&event).unwrap();
+        assert_eq!(detection.location.as_deref(), Some("access.log:line 12"));
+        assert!(detection.source_ip.is_none());
+    }
+    
+    #[test]
+    fn test_other_events_are_not_detections() {
+        let event = Event::posture_change("posture_engine", Some(serde_json::json!({ "severity": 5 })));
+        assert!(DetectionInfo::from_event(&event).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_api_keys_are_required_when_configured() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            api_keys: vec!["first-key".to_string(), "second-key".to_string()],
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let request = |method: &str, uri: &str, key: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            request.body(axum::body::Body::from(r#"{"posture":"silent"}"#)).unwrap()
+        };
+        
+        // Missing, unknown or partial keys get a JSON error
+        for key in [None, Some("wrong-key"), Some("first")] {
+            for (method, uri) in [("GET", "/api/status"), ("POST", "/api/posture")] {
+                let response = router.clone().oneshot(request(method, uri, key)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{} {} with {:?}", method, uri, key);
+                assert_eq!(response.headers()["www-authenticate"], "Bearer");
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(body["error"], "Missing or invalid API key");
+            }
+        }
+        
+        // Any configured key is accepted
+        for key in ["first-key", "second-key"] {
+            let response = router.clone().oneshot(request("GET", "/api/status", Some(key))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = router.clone().oneshot(request("POST", "/api/posture", Some("first-key"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        // Probes stay open
+        let response = router.clone().oneshot(request("GET", "/healthz", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.oneshot(request("GET", "/readyz", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[tokio::test]
+    async fn test_cors_origins() {
+        let allowed_origin = |cors_origins: Option<Vec<String>>| async move {
+            let (tx, _tx_rx) = mpsc::channel(16);
+            let (_rx_tx, rx) = mpsc::channel(16);
+            let config = PigmentApiConfig { cors_origins, ..Default::default() };
+            let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+            let request = axum::http::Request::builder()
+                .uri("/api/status")
+                .header("origin", "https://console.example.com")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = api.create_router().await.unwrap().oneshot(request).await.unwrap();
+            response.headers().get("access-control-allow-origin").cloned()
+        };
+        
+        assert_eq!(allowed_origin(None).await, None);
+        assert_eq!(allowed_origin(Some(Vec::new())).await, None);
+        assert_eq!(allowed_origin(Some(vec!["https://other.example.com".to_string()])).await, None);
+        assert_eq!(
+            allowed_origin(Some(vec!["https://console.example.com".to_string()])).await.unwrap(),
+            "https://console.example.com"
+        );
+        
+        assert!(cors_layer(&["*".to_string()]).is_err());
+        assert!(cors_layer(&["https://bad\norigin".to_string()]).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limit_rejects_excess_requests() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            burst: 3,
+            write_burst: 1,
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let request = |method: &str, uri: &str| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(r#"{"posture":"silent"}"#))
+                .unwrap()
+        };
+        
+        for _ in 0..3 {
+            let response = router.clone().oneshot(request("GET", "/api/status")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = router.clone().oneshot(request("GET", "/api/status")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+        
+        // Write endpoints have their own, stricter bucket
+        let response = router.clone().oneshot(request("POST", "/api/posture")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.clone().oneshot(request("POST", "/api/posture")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    
+    #[tokio::test]
+    async fn test_forwarded_clients_get_their_own_rate_limit() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            burst: 1,
+            trust_forwarded_for: true,
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let request = |client: &str| {
+            axum::http::Request::builder()
+                .uri("/api/status")
+                .header("x-forwarded-for", client)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        
+        let response = router.clone().oneshot(request("203.0.113.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.clone().oneshot(request("203.0.113.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // A forged leading entry doesn't buy a fresh limit
+        let response = router.clone().oneshot(request("198.51.100.4, 203.0.113.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = router.clone().oneshot(request("198.51.100.4")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn test_posture_change_carries_actor_and_reason() {
+        let (tx, mut tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let request = |body: &'static str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/api/posture")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        
+        // Names are parsed whatever their case and sent in lowercase
+        for (body, posture, reason) in [
+            (r#"{"posture":"Fulgurant","reason":"red team exercise","force":true}"#, "fulgurant", "red team exercise"),
+            (r#"{"posture":"neutral"}"#, "neutral", "changed through the API"),
+        ] {
+            let response = router.clone().oneshot(request(body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            
+            let data = tx_rx.recv().await.unwrap().data.unwrap();
+            assert_eq!(data["posture"], posture);
+            assert_eq!((data["actor"].as_str(), data["reason"].as_str()), (Some("api"), Some(reason)));
+            assert_eq!(data["force"], body.contains("force"));
+        }
+        
+        // Unknown postures are rejected before anything is sent
+        let response = router.clone().oneshot(request(r#"{"posture":"stealth"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Unknown posture: stealth");
+        assert!(tx_rx.try_recv().is_err());
+        
+        let current = axum::http::Request::builder()
+            .uri("/api/posture")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(current).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap()["posture"], "neutral");
+    }
+    
+    #[tokio::test]
+    async fn test_openapi_spec_is_served() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let request = axum::http::Request::builder()
+            .uri("/api/openapi.json")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for path in ["/api/status", "/api/state", "/api/capabilities", "/api/events", "/api/events/ws", "/api/posture", "/api/modules", "/api/metrics"] {
+            assert!(spec["paths"].get(path).is_some(), "missing {}", path);
+        }
+        assert!(spec["paths"]["/api/posture"].get("post").is_some());
+        assert!(spec["paths"]["/api/events"]["get"]["parameters"].is_array());
+    }
+    
+    #[tokio::test]
+    async fn test_clear_detections_then_events() {
+        let (tx, mut audit) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        
+        api.events.write().await.extend([
+            Event::network_activity(
+                "nettongue",
+                Some(serde_json::json!({ "detection_type": "PortScan", "severity": 7 })),
+            ),
+            Event::posture_change("posture_engine", None),
+        ]);
+        let router = api.create_router().await.unwrap();
+        
+        let clear = |uri: &str| {
+            axum::http::Request::builder()
+                .method("DELETE")
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        
+        for (uri, expected) in [("/api/detections", 1), ("/api/events", 1)] {
+            let response = router.clone().oneshot(clear(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["cleared"], expected);
+            
+            let event = audit.try_recv().unwrap();
+            assert_eq!(event.event_type, EventType::SystemChange);
+        }
+        assert!(api.events.read().await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_event_injection() {
+        let request = |body: &str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/api/events")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+        let body = r#"{"event_type":"SecurityAlert","source":"formats","severity":"Critical","data":{"rule":"test"}}"#;
+        
+        // Disabled by default
+        let (tx, mut bus) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let response = api.create_router().await.unwrap().oneshot(request(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(bus.try_recv().is_err());
+        
+        let (tx, mut bus) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            allow_event_injection: true,
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let response = router.clone().oneshot(request(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let event = bus.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::SecurityAlert);
+        assert_eq!(event.source, "formats");
+        assert_eq!(event.severity(), Severity::Critical);
+        assert_eq!(event.data.unwrap()["rule"], "test");
+        
+        let response = router.clone().oneshot(request(r#"{"event_type":"RedTeamDrill"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(bus.try_recv().unwrap().event_type, EventType::Custom("RedTeamDrill".to_string()));
+        
+        let response = router.oneshot(request(r#"{"event_type":"SecurityAlert","severity":"Extreme"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(bus.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_event_sort_orders_pages() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        
+        let start = chrono::Utc::now();
+        let severities = [Severity::Low, Severity::Critical, Severity::Info, Severity::High, Severity::Critical];
+        api.events.write().await.extend(severities.iter().enumerate().map(|(i, severity)| {
+            let mut event = Event::new(EventType::SecurityAlert, "test", None).with_severity(*severity);
+            event.timestamp = start + chrono::Duration::seconds(i as i64);
+            event
+        }));
+        let router = api.create_router().await.unwrap();
+        
+        let ids = |query: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri(format!("/api/events?page_size=2&{}", query))
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                body["events"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|e| e["id"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+        
+        // Newest first by default
+        assert_eq!(ids("page=0").await, ["4", "3"]);
+        assert_eq!(ids("page=2").await, ["0"]);
+        assert_eq!(ids("page=0&sort=timestamp_asc").await, ["0", "1"]);
+        assert_eq!(ids("page=1&sort=timestamp_asc").await, ["2", "3"]);
+        
+        // Equal severities keep a stable, newest-first order across pages
+        assert_eq!(ids("page=0&sort=severity_desc").await, ["4", "1"]);
+        assert_eq!(ids("page=1&sort=severity_desc").await, ["3", "0"]);
+        assert_eq!(ids("page=0&sort=severity_asc").await, ["2", "0"]);
+        assert_eq!(ids("page=2&sort=severity_asc").await, ["1"]);
+        
+        // Pages past the end are empty, however far
+        assert!(ids("page=18446744073709551615").await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_events_since_cursor() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let router = api.create_router().await.unwrap();
+        
+        let poll = |query: String| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri(format!("/api/events?{}", query))
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let ids: Vec<String> = body["events"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|e| e["id"].as_str().unwrap().to_string())
+                    .collect();
+                (ids, body["latest_seq"].as_u64())
+            }
+        };
+        
+        assert_eq!(poll("page=0".to_string()).await, (vec![], None));
+        
+        let event = |source: &str| Event::network_activity(source, None);
+        api.events.write().await.extend([event("nettongue"), event("eye360"), event("nettongue")]);
+        let (_, cursor) = poll("page_size=1".to_string()).await;
+        assert_eq!(cursor, Some(2));
+        
+        // New events come oldest first, a page at a time, without gaps or duplicates
+        api.events.write().await.extend([event("nettongue"), event("eye360"), event("nettongue")]);
+        let (ids, cursor) = poll(format!("since={}&page_size=2", cursor.unwrap())).await;
+        assert_eq!((ids, cursor), (vec!["3".to_string(), "4".to_string()], Some(4)));
+        let (ids, cursor) = poll(format!("since={}&page_size=2", cursor.unwrap())).await;
+        assert_eq!((ids, cursor), (vec!["5".to_string()], Some(5)));
+        assert_eq!(poll("since=5".to_string()).await, (vec![], Some(5)));
+        
+        // Filtered out events are skipped over by the cursor
+        let (ids, cursor) = poll("since=0&source=eye360".to_string()).await;
+        assert_eq!((ids, cursor), (vec!["1".to_string(), "4".to_string()], Some(5)));
+    }
+    
+    #[tokio::test]
+    async fn test_serves_on_every_bind_address() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let free_address = || {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let addresses = vec![free_address(), free_address()];
+        
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            bind_addresses: addresses.clone(),
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let server = tokio::spawn(async move { api.start().await });
+        
+        for address in addresses {
+            let mut stream = None;
+            for _ in 0..100 {
+                if let Ok(connected) = tokio::net::TcpStream::connect(address).await {
+                    stream = Some(connected);
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            let mut stream = stream.expect("server not listening");
+            
+            stream.write_all(b"GET /api/status HTTP/1.0\r\n\r\n").await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.0 200"), "{}: {}", address, response);
+        }
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_websocket_pushes_matching_events() {
+        use futures_util::StreamExt;
+        
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::new(address), EventSender::from(tx), rx).await.unwrap();
+        let server = tokio::spawn(async move { api.start().await });
+        
+        let url = format!("ws://{}/api/events/ws?source=eye360", address);
+        let mut socket = None;
+        for _ in 0..100 {
+            if let Ok((connected, _)) = tokio_tungstenite::connect_async(&url).await {
+                socket = Some(connected);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mut socket = socket.expect("server not listening");
+        
+        for source in ["nettongue", "eye360", "formats", "eye360"] {
+            rx_tx.send(Event::security_alert(source, None)).await.unwrap();
+        }
+        
+        // Only the matching events, numbered like in the history
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let message = socket.next().await.unwrap().unwrap();
+            let event: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            assert_eq!(event["source"], "eye360");
+            assert_eq!(event["event_type"], "SecurityAlert");
+            ids.push(event["id"].as_str().unwrap().to_string());
+        }
+        assert_eq!(ids, ["1", "3"]);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_websocket_accepts_the_api_key_without_authorization_header() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Error as WsError;
+        
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            api_keys: vec!["s3cret".to_string()],
+            ..PigmentApiConfig::new(address)
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        let server = tokio::spawn(async move { api.start().await });
+        
+        let connect = |query: &str, protocol: Option<&str>| {
+            let mut request = format!("ws://{}/api/events/ws{}", address, query).into_client_request().unwrap();
+            if let Some(protocol) = protocol {
+                request.headers_mut().insert("sec-websocket-protocol", protocol.parse().unwrap());
+            }
+            tokio_tungstenite::connect_async(request)
+        };
+        let mut listening = false;
+        for _ in 0..100 {
+            if !matches!(connect("", None).await, Err(WsError::Io(_))) {
+                listening = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(listening, "server not listening");
+        
+        // The subprotocol carrying the key is echoed back, as browsers require
+        let (_, response) = connect("", Some("bearer, s3cret")).await.unwrap();
+        assert_eq!(response.headers()["sec-websocket-protocol"], "bearer");
+        assert!(connect("?api_key=s3cret", None).await.is_ok());
+        
+        for (query, protocol) in [("", None), ("?api_key=wrong", None), ("", Some("bearer, wrong"))] {
+            match connect(query, protocol).await {
+                Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::UNAUTHORIZED),
+                other => panic!("{:?} {:?} connected: {:?}", query, protocol, other.map(|(_, response)| response)),
+            }
+        }
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_start_without_bind_address_fails() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let config = PigmentApiConfig {
+            bind_addresses: Vec::new(),
+            ..Default::default()
+        };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx).await.unwrap();
+        assert!(api.start().await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_event_listener_starts_once() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx)
+            .await
+            .unwrap();
+        
+        api.start_event_listener().unwrap();
+        assert!(matches!(api.start_event_listener(), Err(PigmentApiError::ListenerAlreadyStarted)));
+        
+        // The first listener keeps receiving events
+        rx_tx.send(Event::security_alert("eye360", None)).await.unwrap();
+        while api.events.read().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_state_is_the_core_snapshot() {
+        use chame_core::{ChameleonCore, Posture};
+        
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let request = || {
+            axum::http::Request::builder()
+                .uri("/api/state")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let response = api.create_router().await.unwrap().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        
+        let core = ChameleonCore::new();
+        core.change_posture(Posture::Mimetic).await.unwrap();
+        let api = api.with_core(Arc::new(core));
+        let response = api.create_router().await.unwrap().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let state: chame_core::SystemState = serde_json::from_slice(&body).unwrap();
+        assert_eq!(state.current_posture, Posture::Mimetic);
+        assert_eq!(state.threat_level, 0.0);
+        assert!(state.current_fingerprint.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_readyz_flips_after_startup() {
+        use chame_core::ChameleonCore;
+        
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let core = ChameleonCore::new();
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx)
+            .await
+            .unwrap()
+            .with_core(Arc::new(core.clone()));
+        let router = api.create_router().await.unwrap();
+        
+        let probe = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                router.oneshot(request).await.unwrap().status()
+            }
+        };
+        
+        assert_eq!(probe("/healthz").await, StatusCode::OK);
+        assert_eq!(probe("/readyz").await, StatusCode::SERVICE_UNAVAILABLE);
+        
+        api.start_event_listener().unwrap();
+        while !api.listener_running.load(Ordering::Relaxed) {
+            tokio::task::yield_now().await;
+        }
+        // The core is not running yet
+        assert_eq!(probe("/readyz").await, StatusCode::SERVICE_UNAVAILABLE);
+        
+        core.init().await.unwrap();
+        core.start().await.unwrap();
+        assert_eq!(probe("/readyz").await, StatusCode::OK);
+        
+        // Probes are not rate limited
+        for _ in 0..100 {
+            assert_eq!(probe("/healthz").await, StatusCode::OK);
+        }
+    }
+    
+    struct MockModules {
+        calls: std::sync::Mutex<Vec<(String, bool)>>,
+    }
+    
+    #[async_trait::async_trait]
+    impl ModuleControl for MockModules {
+        async fn set_active(&self, module: &str, active: bool) -> Result<(), PigmentApiError> {
+            match module {
+                "eye360" => {
+                    self.calls.lock().unwrap().push((module.to_string(), active));
+                    Ok(())
+                }
+                "lurefield" => Err(PigmentApiError::InvalidRequest("lurefield is disabled".to_string())),
+                _ => Err(PigmentApiError::NotFound(module.to_string())),
+            }
+        }
+        
+        async fn health(&self) -> HashMap<String, ModuleHealth> {
+            HashMap::from([
+                ("eye360".to_string(), ModuleHealth::Healthy),
+                ("lurefield".to_string(), ModuleHealth::Unhealthy("honeypot supervisor exited".to_string())),
+            ])
+        }
+        
+        async fn features(&self) -> Vec<Feature> {
+            use chame_core::FeatureStatus;
+            
+            vec![
+                Feature::new("skinshift firewall rules", FeatureStatus::Simulated("requires root".to_string())),
+                Feature::new("eye360 eBPF monitoring", FeatureStatus::Disabled),
+            ]
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_status_reports_module_health() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let control = Arc::new(MockModules { calls: std::sync::Mutex::new(Vec::new()) });
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx)
+            .await
+            .unwrap()
+            .with_module_control(control);
+        let router = api.create_router().await.unwrap();
+        
+        let request = axum::http::Request::builder()
+            .uri("/api/status")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["health"]["eye360"], serde_json::json!({"status": "healthy"}));
+        assert_eq!(
+            status["health"]["lurefield"],
+            serde_json::json!({"status": "unhealthy", "reason": "honeypot supervisor exited"})
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_capabilities_report_degraded_features() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        let capabilities = |router: Router| async move {
+            let request = axum::http::Request::builder()
+                .uri("/api/capabilities")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = router.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        
+        // Nothing to report without modules
+        let report = capabilities(api.create_router().await.unwrap()).await;
+        assert_eq!(report, serde_json::json!({"degraded": false, "features": []}));
+        
+        let control = Arc::new(MockModules { calls: std::sync::Mutex::new(Vec::new()) });
+        let api = api.with_module_control(control);
+        let report = capabilities(api.create_router().await.unwrap()).await;
+        assert_eq!(report["degraded"], true);
+        assert_eq!(
+            report["features"],
+            serde_json::json!([
+                {"name": "skinshift firewall rules", "status": "simulated", "reason": "requires root"},
+                {"name": "eye360 eBPF monitoring", "status": "disabled"},
+            ])
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_toggle_module_starts_and_stops_it() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let control = Arc::new(MockModules { calls: std::sync::Mutex::new(Vec::new()) });
+        let modules = HashMap::from([("eye360".to_string(), true), ("lurefield".to_string(), false)]);
+        let config = PigmentApiConfig { write_burst: 10, ..Default::default() };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx)
+            .await
+            .unwrap()
+            .with_modules(modules)
+            .with_module_control(control.clone());
+        let router = api.create_router().await.unwrap();
+        
+        let post = |name: &'static str, body: String| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/modules/{}", name))
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(body))
+                    .unwrap();
+                router.oneshot(request).await.unwrap().status()
+            }
+        };
+        let toggle = |name: &'static str, active: bool| post(name, format!(r#"{{"active":{}}}"#, active));
+        
+        assert_eq!(toggle("eye360", false).await, StatusCode::OK);
+        assert_eq!(toggle("lurefield", true).await, StatusCode::CONFLICT);
+        assert_eq!(toggle("skinshift", true).await, StatusCode::NOT_FOUND);
+        
+        // A malformed body never stops a module, an empty one starts it
+        assert_eq!(post("eye360", r#"{"active":"no"}"#.to_string()).await, StatusCode::BAD_REQUEST);
+        assert_eq!(post("eye360", "{".to_string()).await, StatusCode::BAD_REQUEST);
+        assert_eq!(post("eye360", String::new()).await, StatusCode::OK);
+        
+        assert_eq!(
+            *control.calls.lock().unwrap(),
+            [("eye360".to_string(), false), ("eye360".to_string(), true)]
+        );
+        let modules = api.active_modules.read().await;
+        assert_eq!(modules.get("eye360"), Some(&true));
+        assert_eq!(modules.get("lurefield"), Some(&false));
+        assert!(!modules.contains_key("skinshift"));
+    }
+    
+    #[tokio::test]
+    async fn test_detection_patterns_are_replaced_live() {
+        let (tx, mut bus) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let (formats_tx, _formats_rx) = mpsc::channel(16);
+        let formats = Arc::new(Formats::new(formats_tx));
+        let post = |router: Router, body: &'static str| async move {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/api/formats/patterns")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            router.oneshot(request).await.unwrap().status()
+        };
+        let update = r#"{"patterns":{"log":[{"pattern":"(?i)cobalt\\s*strike","detection_type":"c2_beacon","severity":10}]}}"#;
+        let defaults = formats.patterns();
+        
+        // Updates are refused unless enabled
+        let (closed_tx, _closed_rx) = mpsc::channel(16);
+        let (_closed_rx_tx, closed_rx) = mpsc::channel(16);
+        let closed = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(closed_tx), closed_rx)
+            .await
+            .unwrap()
+            .with_formats(formats.clone());
+        assert_eq!(post(closed.create_router().await.unwrap(), update).await, StatusCode::FORBIDDEN);
+        assert_eq!(formats.patterns(), defaults);
+        
+        let config = PigmentApiConfig { allow_pattern_updates: true, ..Default::default() };
+        let api = PigmentApi::new(config, EventSender::from(tx), rx)
+            .await
+            .unwrap()
+            .with_formats(formats.clone());
+        let router = api.create_router().await.unwrap();
+        let replace = |body: &'static str| post(router.clone(), body);
+        
+        // Nothing changes unless every pattern is valid
+        let invalid = r#"{"patterns":{"csv":[{"pattern":"(?i)lockbit","detection_type":"ransomware","severity":10}],"log":[{"pattern":"(","detection_type":"broken","severity":5}]}}"#;
+        assert_eq!(replace(invalid).await, StatusCode::BAD_REQUEST);
+        let off_scale = r#"{"patterns":{"log":[{"pattern":"beacon","detection_type":"c2","severity":42}]}}"#;
+        assert_eq!(replace(off_scale).await, StatusCode::BAD_REQUEST);
+        assert_eq!(replace(r#"{"patterns":{"pdf":[]}}"#).await, StatusCode::BAD_REQUEST);
+        assert_eq!(formats.patterns(), defaults);
+        assert!(bus.try_recv().is_err());
+        
+        assert_eq!(replace(update).await, StatusCode::OK);
+        assert_eq!(bus.try_recv().unwrap().data.unwrap()["formats"], serde_json::json!(["log"]));
+        
+        let request = axum::http::Request::builder().uri("/api/formats/patterns").body(axum::body::Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let listed: PatternsResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            listed.patterns["log"],
+            [DetectionPattern {
+                pattern: r"(?i)cobalt\s*strike".to_string(),
+                detection_type: "c2_beacon".to_string(),
+                severity: 10,
+            }]
+        );
+        assert_eq!(listed.patterns["csv"].len(), defaults[&FileFormat::Csv].len());
+    }
+    
+    #[tokio::test]
+    async fn test_threat_history() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let metrics = Arc::new(MetricsCollector::new());
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx)
+            .await
+            .unwrap()
+            .with_metrics_collector(metrics.clone());
+        let router = api.create_router().await.unwrap();
+        
+        let threat = || {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri("/api/threat")
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        
+        let body = threat().await;
+        assert_eq!(body["threat_level"], 0.0);
+        assert_eq!(body["history"].as_array().unwrap().len(), 0);
+        
+        metrics.add_time_series_point("threat_level", 0.3);
+        metrics.add_time_series_point("threat_level", 0.8);
+        let body = threat().await;
+        assert_eq!(body["threat_level"], 0.8);
+        assert_eq!(body["history"][0]["value"], 0.3);
+        assert_eq!(body["history"][1]["value"], 0.8);
+    }
+    
+    #[tokio::test]
+    async fn test_export_events() {
+        let (tx, _tx_rx) = mpsc::channel(16);
+        let (_rx_tx, rx) = mpsc::channel(16);
+        let api = PigmentApi::new(PigmentApiConfig::default(), EventSender::from(tx), rx).await.unwrap();
+        
+        api.events.write().await.extend([
+            Event::network_activity("nettongue", Some(serde_json::json!({ "ip": "10.0.0.1", "ports": [22, 80] }))),
+            Event::posture_change("posture_engine", None),
+            Event::network_activity("nettongue", Some(serde_json::json!({ "note": "a, \"quoted\" value" }))),
+        ]);
+        let router = api.create_router().await.unwrap();
+        
+        let export = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let disposition = response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().to_string();
+                assert!(disposition.starts_with("attachment"));
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+        
+        let csv = export("/api/events/export?format=csv&source=nettongue").await;
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), vec!["id", "timestamp", "event_type", "source", "severity", "data"]);
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "0");
+        assert_eq!(&rows[0][2], "NetworkActivity");
+        assert_eq!(&rows[0][5], "ip=10.0.0.1; ports.0=22; ports.1=80");
+        assert_eq!(&rows[1][0], "2");
+        assert_eq!(&rows[1][5], "note=a, \"quoted\" value");
+        
+        let jsonl = export("/api/events/export?format=jsonl").await;
+        let lines: Vec<serde_json::Value> = jsonl.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["event_type"], "PostureChange");
+        assert_eq!(lines[2]["data"]["note"], "a, \"quoted\" value");
+    }
+}
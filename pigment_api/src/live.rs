@@ -0,0 +1,57 @@
+use crate::{matches_filters, EventInfo};
+use axum::extract::ws::{Message, WebSocket};
+use chame_core::events::Event;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events buffered for WebSocket clients before the slowest ones miss some
+pub const LIVE_EVENTS_CAPACITY: usize = 256;
+
+/// An event as received by the API, with its sequence number in the history
+pub type LiveEvent = Arc<(u64, Event)>;
+
+/// Push every event from `events` matching the filters to `socket`, as the
+/// JSON `GET /api/events` lists it, until the client goes away
+///
+/// A client too slow to keep up skips the events it missed instead of
+/// holding the others back; the gap shows in the event IDs.
+pub(crate) async fn stream_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<LiveEvent>,
+    event_type: Option<String>,
+    source: Option<String>,
+) {
+    loop {
+        let event = tokio::select! {
+            received = socket.recv() => match received {
+                // Clients only ever send pings and close frames
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+            received = events.recv() => match received {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client fell behind, skipped {} events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            },
+        };
+
+        let (seq, event) = &*event;
+        if !matches_filters(event, event_type.as_deref(), source.as_deref()) {
+            continue;
+        }
+
+        let json = match serde_json::to_string(&EventInfo::from_event(seq.to_string(), event)) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("Failed to serialize event for a WebSocket client: {}", e);
+                continue;
+            }
+        };
+        if socket.send(Message::Text(json)).await.is_err() {
+            return;
+        }
+    }
+}
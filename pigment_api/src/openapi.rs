@@ -0,0 +1,69 @@
+use crate::{
+    CapabilitiesResponse, ChangePostureRequest, ChangePostureResponse, ClearResponse, DetectionInfo, DetectionPattern,
+    DetectionsResponse, ErrorResponse, EventInfo, EventSort, EventsResponse, FeatureResponse, HealthResponse,
+    InjectEventRequest, PatternsResponse, PostureResponse, ReplacePatternsRequest, SystemStatusResponse, ThreatPoint,
+    ThreatResponse, ToggleModuleRequest, ToggleModuleResponse,
+};
+use crate::export::ExportFormat;
+use utoipa::OpenApi;
+
+/// OpenAPI description of the PigmentAPI, served at `/api/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "CAMALEON PigmentAPI", description = "Local API to monitor and steer CAMALEON"),
+    paths(
+        crate::healthz,
+        crate::readyz,
+        crate::get_status,
+        crate::get_state,
+        crate::get_capabilities,
+        crate::get_events,
+        crate::export_events,
+        crate::stream_events,
+        crate::inject_event,
+        crate::clear_events,
+        crate::get_detections,
+        crate::clear_detections,
+        crate::get_posture,
+        crate::change_posture,
+        crate::get_modules,
+        crate::toggle_module,
+        crate::get_metrics,
+        crate::get_threat,
+        crate::get_patterns,
+        crate::replace_patterns,
+    ),
+    components(schemas(
+        HealthResponse,
+        SystemStatusResponse,
+        CapabilitiesResponse,
+        FeatureResponse,
+        EventsResponse,
+        EventSort,
+        ExportFormat,
+        EventInfo,
+        InjectEventRequest,
+        DetectionsResponse,
+        DetectionInfo,
+        PostureResponse,
+        ChangePostureRequest,
+        ChangePostureResponse,
+        ToggleModuleRequest,
+        ToggleModuleResponse,
+        ThreatResponse,
+        ThreatPoint,
+        PatternsResponse,
+        ReplacePatternsRequest,
+        DetectionPattern,
+        ClearResponse,
+        ErrorResponse,
+    )),
+    tags(
+        (name = "system", description = "Status and metrics"),
+        (name = "events", description = "Event and detection history"),
+        (name = "posture", description = "Defensive posture"),
+        (name = "modules", description = "Module activation"),
+        (name = "formats", description = "File analysis detection patterns"),
+    )
+)]
+pub struct ApiDoc;
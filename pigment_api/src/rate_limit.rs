@@ -0,0 +1,194 @@
+use axum::{
+    body::{Body, BoxBody},
+    extract::ConnectInfo,
+    http::{header, Request, Response, StatusCode},
+    response::IntoResponse,
+};
+use crate::access_log::ClientIp;
+use dashmap::DashMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Number of tracked clients above which idle buckets are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token bucket of a single client
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    /// Tokens currently available
+    tokens: f64,
+
+    /// Last time the bucket was refilled
+    refilled_at: Instant,
+}
+
+/// Per client IP token-bucket rate limiter
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens added per second
+    rate: f64,
+
+    /// Maximum number of tokens a bucket holds
+    burst: f64,
+
+    /// Bucket of each client
+    buckets: DashMap<IpAddr, TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_second` with bursts of `burst`
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            rate: f64::from(requests_per_second.max(1)),
+            burst: f64::from(burst.max(1)),
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Take a token for a client, or return how long until one is available
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        if self.buckets.len() > PRUNE_THRESHOLD {
+            self.prune(now);
+        }
+
+        let mut bucket = self.buckets.entry(client).or_insert(TokenBucket {
+            tokens: self.burst,
+            refilled_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Forget clients whose bucket has refilled completely
+    fn prune(&self, now: Instant) {
+        let full_after = Duration::from_secs_f64(self.burst / self.rate);
+        self.buckets
+            .retain(|_, bucket| now.saturating_duration_since(bucket.refilled_at) < full_after);
+    }
+}
+
+/// Layer rejecting requests over the limit with `429 Too Many Requests`
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    /// Create a layer with its own set of buckets
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            limiter: Arc::new(RateLimiter::new(requests_per_second, burst)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+/// Service applying a `RateLimiter` in front of an inner service
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Same client as the access log; without connection info (e.g. in
+        // tests) every request shares one bucket
+        let client = request
+            .extensions()
+            .get::<ClientIp>()
+            .map(|ClientIp(ip)| *ip)
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            })
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        match self.limiter.check(client) {
+            Ok(()) => Box::pin(self.inner.call(request)),
+            Err(retry_after) => {
+                tracing::debug!("Rate limit exceeded for {}", client);
+                let response = too_many_requests(retry_after);
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// Build the `429` response, rounding `Retry-After` up to whole seconds
+fn too_many_requests(retry_after: Duration) -> Response<BoxBody> {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, seconds.max(1).to_string())],
+        "Too many requests",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(2, 3);
+        let client = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(client, start).is_ok());
+        }
+        let retry_after = limiter.check_at(client, start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+
+        // Another client has its own bucket
+        assert!(limiter.check_at(IpAddr::V4(Ipv4Addr::BROADCAST), start).is_ok());
+
+        assert!(limiter.check_at(client, start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.check_at(client, start + Duration::from_millis(500)).is_err());
+    }
+}
@@ -1,19 +1,19 @@
+use crate::PostureEngine;
 use chame_core::adaptive::{AdaptiveError, AdaptiveEvent, AdaptiveHandler};
-use chame_core::events::{Event, EventType};
+use chame_core::events::{Event, EventType, Severity};
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::sync::mpsc;
 
-/// Handler for PostureEngine events
+/// Handler passing detector events to the running PostureEngine
 pub struct PostureEngineHandler {
-    /// Event sender
-    event_sender: mpsc::Sender<Event>,
+    /// Engine evaluating the events
+    engine: Arc<PostureEngine>,
 }
 
 impl PostureEngineHandler {
     /// Create a new PostureEngine handler
-    pub fn new(event_sender: mpsc::Sender<Event>) -> Self {
-        Self { event_sender }
+    pub fn new(engine: Arc<PostureEngine>) -> Self {
+        Self { engine }
     }
 }
 
@@ -21,29 +21,33 @@ impl PostureEngineHandler {
 impl AdaptiveHandler for PostureEngineHandler {
     async fn handle_event(&mut self, event: &AdaptiveEvent) -> Result<(), AdaptiveError> {
         // Only handle events that are relevant to posture changes
-        if event.event_type == "SecurityAlert" || event.event_type == "SystemChange" || 
-           event.event_type == "NetworkActivity" {
-            // Convert to a posture event
-            let posture_event = Event::new(
-                EventType::PostureChange,
-                "posture_engine_handler",
-                Some(serde_json::json!({
-                    "original_source": event.source,
-                    "severity": event.severity,
-                    "details": event.data,
-                    "recommendation": "evaluate_posture_change",
-                })),
-            );
-            
-            // Send the event
-            if let Err(e) = self.event_sender.send(posture_event).await {
-                return Err(AdaptiveError::ProcessingFailed(format!(
-                    "Failed to send posture event: {}",
-                    e
-                )));
-            }
+        let event_type = match event.event_type.as_str() {
+            "SecurityAlert" => EventType::SecurityAlert,
+            "SystemChange" => EventType::SystemChange,
+            "NetworkActivity" => EventType::NetworkActivity,
+            _ => return Ok(()),
+        };
+        let severity = match event.severity {
+            10.. => Severity::Critical,
+            8..=9 => Severity::High,
+            5..=7 => Severity::Medium,
+            3..=4 => Severity::Low,
+            _ => Severity::Info,
+        };
+
+        // The engine only changes the posture when the event calls for it,
+        // and announces the change itself
+        let mut original = Event::new(event_type, event.source.clone(), Some(event.data.clone()))
+            .with_severity(severity);
+        original.timestamp = event.timestamp;
+
+        if let Err(e) = self.engine.evaluate_events(&[original]).await {
+            return Err(AdaptiveError::ProcessingFailed(format!(
+                "Failed to evaluate event: {}",
+                e
+            )));
         }
-        
+
         Ok(())
     }
 }
@@ -1,9 +1,16 @@
-use chame_core::events::{Event, EventType};
-use std::collections::HashMap;
+pub mod handler;
+
+use chame_core::events::{Event, EventType, ReportableDetection, Severity};
+use chame_core::{EventSender, InvalidPosture, MetricsCollector, ModuleHealth};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::RwLock;
 
+/// Defensive postures that the system can adopt
+pub use chame_core::Posture;
+
 /// Errors that can occur in the PostureEngine module
 #[derive(Error, Debug)]
 pub enum PostureEngineError {
@@ -16,57 +23,70 @@ pub enum PostureEngineError {
     #[error("Service rotation error: {0}")]
     ServiceRotation(String),
     
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
-/// Defensive postures that the system can adopt
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Posture {
-    /// Silent mode - minimal visibility
-    Silent,
-    
-    /// Neutral mode - standard server appearance
-    Neutral,
+impl From<InvalidPosture> for PostureEngineError {
+    fn from(error: InvalidPosture) -> Self {
+        Self::InvalidPosture(error.to_string())
+    }
+}
+
+/// Who or what triggered a posture change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostureActor {
+    /// An operator, from the CLI or an embedding application
+    Manual,
     
-    /// Mimetic mode - appears as a vulnerable system
-    Mimetic,
+    /// The engine itself, after evaluating events
+    Adaptive,
     
-    /// Fulgurant mode - actively disrupts scanning
-    Fulgurant,
+    /// A scheduled task
+    Scheduled,
     
-    /// Unstable mode - appears to be malfunctioning
-    Unstable,
+    /// A request to the REST API
+    Api,
 }
 
-impl Posture {
-    /// Convert a string to a posture
-    pub fn from_str(s: &str) -> Result<Self, PostureEngineError> {
-        match s.to_lowercase().as_str() {
-            "silent" => Ok(Self::Silent),
-            "neutral" => Ok(Self::Neutral),
-            "mimetic" => Ok(Self::Mimetic),
-            "fulgurant" => Ok(Self::Fulgurant),
-            "unstable" => Ok(Self::Unstable),
-            _ => Err(PostureEngineError::InvalidPosture(format!(
-                "Unknown posture: {}",
-                s
-            ))),
-        }
-    }
-    
-    /// Convert a posture to a string
+impl PostureActor {
+    /// Convert an actor to a string
     pub fn to_str(&self) -> &'static str {
         match self {
-            Self::Silent => "silent",
-            Self::Neutral => "neutral",
-            Self::Mimetic => "mimetic",
-            Self::Fulgurant => "fulgurant",
-            Self::Unstable => "unstable",
+            Self::Manual => "manual",
+            Self::Adaptive => "adaptive",
+            Self::Scheduled => "scheduled",
+            Self::Api => "api",
         }
     }
 }
 
+/// One entry of the posture history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PostureHistoryEntry {
+    /// Posture adopted, serialized by its lowercase name
+    #[serde(serialize_with = "serialize_posture")]
+    pub posture: Posture,
+    
+    /// Who or what triggered the change
+    pub actor: PostureActor,
+    
+    /// Why, e.g. the threat level and the event types behind it
+    pub reason: String,
+    
+    /// When the change happened
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Serialize a posture the way configuration and events name it
+fn serialize_posture<S: serde::Serializer>(posture: &Posture, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(posture.to_str())
+}
+
 /// Configuration for the PostureEngine module
 #[derive(Debug, Clone)]
 pub struct PostureEngineConfig {
@@ -81,6 +101,13 @@ pub struct PostureEngineConfig {
     
     /// Available postures
     pub postures: Vec<Posture>,
+    
+    /// Postures each posture may switch to directly
+    ///
+    /// Postures without an entry may switch to any other. A change the map
+    /// doesn't allow goes through the intermediate postures of the shortest
+    /// allowed path instead.
+    pub transitions: HashMap<Posture, Vec<Posture>>,
 }
 
 impl Default for PostureEngineConfig {
@@ -96,33 +123,79 @@ impl Default for PostureEngineConfig {
                 Posture::Fulgurant,
                 Posture::Unstable,
             ],
+            transitions: HashMap::new(),
+        }
+    }
+}
+
+impl PostureEngineConfig {
+    /// Whether `from` may switch straight to `to`
+    pub fn allows_transition(&self, from: &Posture, to: &Posture) -> bool {
+        self.transitions.get(from).map_or(true, |targets| targets.contains(to))
+    }
+    
+    /// Postures to go through from `from` to `to`, `to` included
+    ///
+    /// `None` when no allowed transitions lead to `to`.
+    pub fn transition_path(&self, from: &Posture, to: &Posture) -> Option<Vec<Posture>> {
+        if from == to || self.allows_transition(from, to) {
+            return Some(vec![*to]);
         }
+        
+        // Breadth-first search, remembering how each posture was reached
+        let mut reached_from: HashMap<&Posture, &Posture> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(posture) = queue.pop_front() {
+            for next in &self.postures {
+                if next == from || reached_from.contains_key(next) || !self.allows_transition(posture, next) {
+                    continue;
+                }
+                reached_from.insert(next, posture);
+                
+                if next == to {
+                    let mut path = vec![*to];
+                    let mut step = posture;
+                    while step != from {
+                        path.push(*step);
+                        step = reached_from[step];
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(next);
+            }
+        }
+        
+        None
     }
 }
 
 /// Main PostureEngine service
 pub struct PostureEngine {
     /// Configuration
-    config: PostureEngineConfig,
+    config: RwLock<PostureEngineConfig>,
     
     /// Current posture
     current_posture: RwLock<Posture>,
     
     /// Posture history
-    posture_history: RwLock<Vec<(Posture, chrono::DateTime<chrono::Utc>)>>,
+    posture_history: RwLock<Vec<PostureHistoryEntry>>,
     
     /// Event sender
-    event_sender: tokio::sync::mpsc::Sender<Event>,
+    event_sender: EventSender,
     
     /// Service rotator
     service_rotator: Option<Arc<ServiceRotator>>,
+    
+    /// Collector the threat level of each evaluation is recorded to
+    metrics: Option<Arc<MetricsCollector>>,
 }
 
 impl PostureEngine {
     /// Create a new PostureEngine instance
     pub async fn new(
         config: PostureEngineConfig,
-        event_sender: tokio::sync::mpsc::Sender<Event>,
+        event_sender: EventSender,
     ) -> Result<Self, PostureEngineError> {
         let service_rotator = if config.service_rotation_enabled {
             Some(Arc::new(ServiceRotator::new(config.service_rotation_interval)))
@@ -131,14 +204,21 @@ impl PostureEngine {
         };
         
         Ok(Self {
-            config,
+            config: RwLock::new(config),
             current_posture: RwLock::new(Posture::Neutral),
             posture_history: RwLock::new(Vec::new()),
             event_sender,
             service_rotator,
+            metrics: None,
         })
     }
     
+    /// Record the threat level of every evaluation in the `threat_level` time series
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
     /// Start the posture engine
     pub async fn start(&self) -> Result<(), PostureEngineError> {
         tracing::info!("Starting PostureEngine");
@@ -163,32 +243,128 @@ impl PostureEngine {
         Ok(())
     }
     
+    /// Health of the service rotation, the engine itself only reacts to events
+    pub async fn health(&self) -> ModuleHealth {
+        match &self.service_rotator {
+            Some(rotator) if !rotator.is_running().await => ModuleHealth::Stopped,
+            _ => ModuleHealth::Healthy,
+        }
+    }
+    
+    /// Apply a new configuration to the running engine
+    ///
+    /// The change threshold, allowed postures and transitions take effect
+    /// immediately.
+    /// Service rotation settings only apply on restart.
+    pub async fn apply_config(&self, new: PostureEngineConfig) -> Result<(), PostureEngineError> {
+        if !(0.0..=1.0).contains(&new.change_threshold) {
+            return Err(PostureEngineError::InvalidConfig(format!(
+                "change_threshold must be between 0.0 and 1.0, got {}",
+                new.change_threshold
+            )));
+        }
+        
+        if new.postures.is_empty() {
+            return Err(PostureEngineError::InvalidConfig(
+                "at least one posture must be allowed".to_string(),
+            ));
+        }
+        
+        let mut config = self.config.write().await;
+        config.change_threshold = new.change_threshold;
+        config.postures = new.postures;
+        config.transitions = new.transitions;
+        
+        tracing::info!(
+            "PostureEngine configuration updated (threshold: {}, postures: {})",
+            config.change_threshold,
+            config.postures.len()
+        );
+        
+        Ok(())
+    }
+    
     /// Get the current posture
     pub async fn get_current_posture(&self) -> Posture {
-        let posture = self.current_posture.read().await;
-        posture.clone()
+        *self.current_posture.read().await
     }
     
-    /// Set the current posture
+    /// Set the current posture, as a manual change
     pub async fn set_posture(&self, posture: Posture) -> Result<(), PostureEngineError> {
-        // Check if the posture is valid
-        if !self.config.postures.contains(&posture) {
-            return Err(PostureEngineError::InvalidPosture(format!(
-                "Posture not in allowed list: {:?}",
-                posture
-            )));
+        self.set_posture_by(posture, PostureActor::Manual, "manual change").await
+    }
+    
+    /// Set the current posture, recording who changed it and why
+    ///
+    /// When the configured transitions don't allow switching straight to
+    /// `posture`, every posture on the shortest allowed path is adopted in
+    /// turn, each one recorded and announced.
+    pub async fn set_posture_by(
+        &self,
+        posture: Posture,
+        actor: PostureActor,
+        reason: impl Into<String>,
+    ) -> Result<(), PostureEngineError> {
+        let reason = reason.into();
+        
+        let path = {
+            let config = self.config.read().await;
+            check_allowed(&config, &posture)?;
+            
+            let current = self.current_posture.read().await;
+            config.transition_path(&current, &posture).ok_or_else(|| {
+                PostureEngineError::PostureChange(format!(
+                    "No allowed transitions lead from {} to {}",
+                    current.to_str(),
+                    posture.to_str()
+                ))
+            })?
+        };
+        
+        let (last, steps) = path.split_last().expect("A transition path ends with the target");
+        for step in steps {
+            let step_reason = format!("{}, on the way to {}", reason, posture.to_str());
+            self.adopt_posture(*step, actor, step_reason).await;
         }
+        self.adopt_posture(*last, actor, reason).await;
+        
+        Ok(())
+    }
+    
+    /// Set the current posture directly, whatever the allowed transitions
+    ///
+    /// For operators overriding the transition map, e.g. through the API's
+    /// `force` flag.
+    pub async fn force_posture_by(
+        &self,
+        posture: Posture,
+        actor: PostureActor,
+        reason: impl Into<String>,
+    ) -> Result<(), PostureEngineError> {
+        check_allowed(&*self.config.read().await, &posture)?;
+        self.adopt_posture(posture, actor, reason.into()).await;
+        Ok(())
+    }
+    
+    /// Switch to `posture`, then record and announce the change
+    async fn adopt_posture(&self, posture: Posture, actor: PostureActor, reason: String) {
+        let timestamp = chrono::Utc::now();
         
         // Update the current posture
         {
             let mut current = self.current_posture.write().await;
-            *current = posture.clone();
+            *current = posture;
         }
         
         // Add to history
         {
             let mut history = self.posture_history.write().await;
-            history.push((posture.clone(), chrono::Utc::now()));
+            history.push(PostureHistoryEntry {
+                posture,
+                actor,
+                reason: reason.clone(),
+                timestamp,
+            });
         }
         
         // Send event
@@ -196,7 +372,9 @@ impl PostureEngine {
             "posture_engine",
             Some(serde_json::json!({
                 "posture": posture.to_str(),
-                "timestamp": chrono::Utc::now(),
+                "actor": actor.to_str(),
+                "reason": reason,
+                "timestamp": timestamp,
             })),
         );
         
@@ -204,43 +382,57 @@ impl PostureEngine {
             tracing::error!("Failed to send posture change event: {}", e);
         }
         
-        tracing::info!("Posture changed to: {:?}", posture);
-        
-        Ok(())
+        tracing::info!("Posture changed to {:?} ({}: {})", posture, actor.to_str(), reason);
+    }
+    
+    /// Threat level of a batch of events, between 0.0 and 1.0
+    ///
+    /// This is the average weight of the events. Events carrying a 0-10
+    /// detector severity weigh that severity divided by 10; the others weigh
+    /// 1.0, 0.7 or 0.3 when their severity is critical, high or medium.
+    pub fn threat_level(events: &[Event]) -> f64 {
+        average_weight(&event_signals(events))
+    }
+    
+    /// Threat level of a batch of detections, between 0.0 and 1.0
+    ///
+    /// Each detection weighs its severity divided by 10, like the event it
+    /// is reported as.
+    pub fn detection_threat_level(detections: &[impl ReportableDetection]) -> f64 {
+        average_weight(&detection_signals(detections))
     }
     
     /// Evaluate events and potentially change posture
     pub async fn evaluate_events(&self, events: &[Event]) -> Result<bool, PostureEngineError> {
-        // Count security-related events by severity
-        let mut critical_count = 0;
-        let mut high_count = 0;
-        let mut medium_count = 0;
-        
-        for event in events {
-            match event.severity() {
-                chame_core::events::Severity::Critical => critical_count += 1,
-                chame_core::events::Severity::High => high_count += 1,
-                chame_core::events::Severity::Medium => medium_count += 1,
-                _ => {}
-            }
+        self.evaluate_signals(&event_signals(events)).await
+    }
+    
+    /// Evaluate detections of a module sharing the process, without going
+    /// through their events
+    ///
+    /// Detections and the events they are reported as lead to the same
+    /// decision; the recorded reason names detection types instead of
+    /// event types.
+    pub async fn evaluate_detections(&self, detections: &[impl ReportableDetection]) -> Result<bool, PostureEngineError> {
+        self.evaluate_signals(&detection_signals(detections)).await
+    }
+    
+    /// Change the posture if the threat level of `signals` calls for it
+    async fn evaluate_signals(&self, signals: &[Signal]) -> Result<bool, PostureEngineError> {
+        let threat_level = average_weight(signals);
+        if let Some(metrics) = &self.metrics {
+            metrics.add_time_series_point("threat_level", threat_level);
         }
         
-        // Calculate threat level (0.0 - 1.0)
-        let threat_level = if events.is_empty() {
-            0.0
-        } else {
-            let weighted_sum = critical_count as f64 * 1.0 + high_count as f64 * 0.7 + medium_count as f64 * 0.3;
-            let max_possible = events.len() as f64;
-            (weighted_sum / max_possible).min(1.0)
-        };
-        
         // Determine if posture change is needed
-        if threat_level >= self.config.change_threshold {
+        let change_threshold = self.config.read().await.change_threshold;
+        if threat_level >= change_threshold {
             let current_posture = self.get_current_posture().await;
-            let new_posture = self.determine_best_posture(threat_level, events).await;
+            let new_posture = self.determine_best_posture(threat_level, signals).await;
             
             if current_posture != new_posture {
-                self.set_posture(new_posture).await?;
+                let reason = adaptive_reason(threat_level, change_threshold, signals);
+                self.set_posture_by(new_posture, PostureActor::Adaptive, reason).await?;
                 return Ok(true);
             }
         }
@@ -248,11 +440,11 @@ impl PostureEngine {
         Ok(false)
     }
     
-    /// Determine the best posture based on threat level and events
-    async fn determine_best_posture(&self, threat_level: f64, events: &[Event]) -> Posture {
+    /// Determine the best posture based on threat level and inputs
+    async fn determine_best_posture(&self, threat_level: f64, signals: &[Signal]) -> Posture {
         if threat_level >= 0.9 {
             // High threat, use fulgurant or unstable
-            if events.iter().any(|e| matches!(e.event_type, EventType::SecurityAlert)) {
+            if signals.iter().any(|signal| matches!(signal.event_type, EventType::SecurityAlert)) {
                 Posture::Fulgurant
             } else {
                 Posture::Unstable
@@ -269,13 +461,115 @@ impl PostureEngine {
         }
     }
     
-    /// Get posture history
-    pub async fn get_posture_history(&self) -> Vec<(Posture, chrono::DateTime<chrono::Utc>)> {
+    /// Get posture history, oldest change first
+    pub async fn get_posture_history(&self) -> Vec<PostureHistoryEntry> {
         let history = self.posture_history.read().await;
         history.clone()
     }
 }
 
+/// Reject postures missing from the configured list
+fn check_allowed(config: &PostureEngineConfig, posture: &Posture) -> Result<(), PostureEngineError> {
+    if !config.postures.contains(posture) {
+        return Err(PostureEngineError::InvalidPosture(format!(
+            "Posture not in allowed list: {:?}",
+            posture
+        )));
+    }
+    
+    Ok(())
+}
+
+/// One input of an evaluation, an event or a detection
+struct Signal {
+    /// Weight in the threat level
+    weight: f64,
+    
+    /// Type of the event, or of the event a detection is reported as
+    event_type: EventType,
+    
+    /// Name the input is listed under in the reason of a change
+    label: String,
+}
+
+fn event_signals(events: &[Event]) -> Vec<Signal> {
+    events
+        .iter()
+        .map(|event| Signal {
+            weight: event_weight(event),
+            event_type: event.event_type.clone(),
+            label: event.event_type.name().to_string(),
+        })
+        .collect()
+}
+
+fn detection_signals(detections: &[impl ReportableDetection]) -> Vec<Signal> {
+    detections
+        .iter()
+        .map(|detection| Signal {
+            weight: f64::from(detection.severity().get()) / 10.0,
+            event_type: detection.event_type(),
+            label: detection.detection_type(),
+        })
+        .collect()
+}
+
+/// Average weight of `signals`, capped at 1.0
+fn average_weight(signals: &[Signal]) -> f64 {
+    if signals.is_empty() {
+        return 0.0;
+    }
+    
+    let weighted_sum: f64 = signals.iter().map(|signal| signal.weight).sum();
+    (weighted_sum / signals.len() as f64).min(1.0)
+}
+
+/// Weight of one event in the threat level
+fn event_weight(event: &Event) -> f64 {
+    if let Some(score) = event.severity_score() {
+        return f64::from(score) / 10.0;
+    }
+    
+    match event.severity() {
+        Severity::Critical => 1.0,
+        Severity::High => 0.7,
+        Severity::Medium => 0.3,
+        _ => 0.0,
+    }
+}
+
+/// Event types contributing the most to a threat level, at most this many
+const REASON_EVENT_TYPES: usize = 3;
+
+/// Reason recorded for an adaptive change
+///
+/// Names the threat level and the event (or detection) types that weighed
+/// the most in it, e.g.
+/// `threat level 0.82 >= 0.75, top event types: SecurityAlert (2.40)`.
+fn adaptive_reason(threat_level: f64, threshold: f64, signals: &[Signal]) -> String {
+    let mut weights: Vec<(&str, f64)> = Vec::new();
+    for signal in signals {
+        match weights.iter_mut().find(|(name, _)| *name == signal.label) {
+            Some((_, total)) => *total += signal.weight,
+            None => weights.push((&signal.label, signal.weight)),
+        }
+    }
+    weights.retain(|(_, weight)| *weight > 0.0);
+    weights.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    
+    let top: Vec<String> = weights
+        .iter()
+        .take(REASON_EVENT_TYPES)
+        .map(|(name, weight)| format!("{} ({:.2})", name, weight))
+        .collect();
+    
+    let reason = format!("threat level {:.2} >= {:.2}", threat_level, threshold);
+    match top.is_empty() {
+        true => reason,
+        false => format!("{}, top event types: {}", reason, top.join(", ")),
+    }
+}
+
 /// Service rotator for changing exposed services
 pub struct ServiceRotator {
     /// Rotation interval in seconds
@@ -309,4 +603,235 @@ impl ServiceRotator {
         tracing::info!("Service rotation stopped");
         Ok(())
     }
+    
+    /// Whether rotation is running
+    pub async fn is_running(&self) -> bool {
+        *self.running.read().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::Severity0to10;
+    use tokio::sync::mpsc;
+    
+    /// A detection the way eye360 and nettongue report it
+    fn detection(score: u8) -> Event {
+        Event::security_alert_with_severity(
+            "eye360",
+            Some(serde_json::json!({ "detection_type": "PortScan", "severity": score })),
+            Severity::from_score(score),
+        )
+    }
+    
+    /// The same detection without its numeric severity
+    fn bucketed(score: u8) -> Event {
+        Event::security_alert_with_severity("eye360", None, Severity::from_score(score))
+    }
+    
+    #[test]
+    fn test_threat_level_uses_numeric_severity() {
+        let events = [detection(9), detection(4)];
+        assert!((PostureEngine::threat_level(&events) - 0.65).abs() < 1e-9);
+        
+        // Bucketed, the 9 weighs like an 8 and the 4 does not count
+        let events = [bucketed(9), bucketed(4)];
+        assert!((PostureEngine::threat_level(&events) - 0.35).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_threat_level_falls_back_to_severity() {
+        let events = [
+            Event::security_alert("formats", None).with_severity(Severity::Critical),
+            Event::security_alert("lurefield", None),
+            Event::system_change("skinshift", None),
+            Event::network_activity("nettongue", None),
+        ];
+        assert!((PostureEngine::threat_level(&events) - 0.5).abs() < 1e-9);
+        assert_eq!(PostureEngine::threat_level(&[]), 0.0);
+    }
+    
+    #[test]
+    fn test_unknown_posture_names_are_invalid() {
+        let error = PostureEngineError::from(Posture::try_from("stealth").unwrap_err());
+        assert!(matches!(error, PostureEngineError::InvalidPosture(_)));
+        assert_eq!(error.to_string(), "Invalid posture: Unknown posture: stealth");
+    }
+    
+    #[tokio::test]
+    async fn test_numeric_severity_changes_posture() {
+        let (tx, _rx) = mpsc::channel(16);
+        let engine = PostureEngine::new(PostureEngineConfig::default(), EventSender::from(tx)).await.unwrap();
+        
+        // Both score 0.7 bucketed, below the 0.75 threshold
+        assert!(!engine.evaluate_events(&[bucketed(8), bucketed(9)]).await.unwrap());
+        assert_eq!(engine.get_current_posture().await, Posture::Neutral);
+        
+        assert!(engine.evaluate_events(&[detection(8), detection(9)]).await.unwrap());
+        assert_eq!(engine.get_current_posture().await, Posture::Mimetic);
+    }
+    
+    #[tokio::test]
+    async fn test_history_records_actor_and_reason() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let engine = PostureEngine::new(PostureEngineConfig::default(), EventSender::from(tx)).await.unwrap();
+        
+        engine.set_posture(Posture::Silent).await.unwrap();
+        let events = [
+            detection(9),
+            detection(8),
+            Event::network_activity("nettongue", None).with_severity(Severity::High),
+            Event::system_change("skinshift", None).with_severity(Severity::Info),
+        ];
+        assert!(engine.evaluate_events(&events[..3]).await.unwrap());
+        engine.set_posture_by(Posture::Neutral, PostureActor::Api, "maintenance window").await.unwrap();
+        
+        let history = engine.get_posture_history().await;
+        let entries: Vec<_> = history.iter().map(|entry| (&entry.posture, entry.actor)).collect();
+        assert_eq!(
+            entries,
+            [
+                (&Posture::Silent, PostureActor::Manual),
+                (&Posture::Mimetic, PostureActor::Adaptive),
+                (&Posture::Neutral, PostureActor::Api),
+            ]
+        );
+        assert_eq!(
+            history[1].reason,
+            "threat level 0.80 >= 0.75, top event types: SecurityAlert (1.70), NetworkActivity (0.70)"
+        );
+        assert_eq!(history[2].reason, "maintenance window");
+        
+        let entry = serde_json::to_value(&history[1]).unwrap();
+        assert_eq!(entry["posture"], "mimetic");
+        assert_eq!(entry["actor"], "adaptive");
+        
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.data.as_ref().unwrap()["actor"], "manual");
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.data.as_ref().unwrap()["reason"], history[1].reason);
+        
+        // Events weighing nothing aren't named
+        assert_eq!(adaptive_reason(0.0, 0.75, &event_signals(&events[3..])), "threat level 0.00 >= 0.75");
+    }
+    
+    #[tokio::test]
+    async fn test_blocked_transitions_go_through_intermediate_postures() {
+        let config = PostureEngineConfig {
+            transitions: HashMap::from([
+                (Posture::Silent, vec![Posture::Neutral]),
+                (Posture::Neutral, vec![Posture::Silent, Posture::Mimetic]),
+                (Posture::Mimetic, vec![Posture::Neutral, Posture::Fulgurant]),
+                (Posture::Fulgurant, vec![Posture::Mimetic]),
+            ]),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let engine = PostureEngine::new(config, EventSender::from(tx)).await.unwrap();
+        engine.set_posture(Posture::Silent).await.unwrap();
+        rx.recv().await.unwrap();
+        
+        // Silent can't jump to Fulgurant, the adaptive change steps up
+        assert!(engine.evaluate_events(&[detection(9), detection(9)]).await.unwrap());
+        assert_eq!(engine.get_current_posture().await, Posture::Fulgurant);
+        
+        let history = engine.get_posture_history().await;
+        let postures: Vec<_> = history[1..].iter().map(|entry| entry.posture).collect();
+        assert_eq!(postures, [Posture::Neutral, Posture::Mimetic, Posture::Fulgurant]);
+        assert!(history[1].reason.ends_with(", on the way to fulgurant"), "{}", history[1].reason);
+        assert!(!history[3].reason.contains("on the way"));
+        for expected in ["neutral", "mimetic", "fulgurant"] {
+            assert_eq!(rx.recv().await.unwrap().data.unwrap()["posture"], expected);
+        }
+        
+        // Unstable has no entry and can't be reached
+        let error = engine.set_posture(Posture::Unstable).await.unwrap_err();
+        assert!(matches!(error, PostureEngineError::PostureChange(_)), "{}", error);
+        assert_eq!(engine.get_current_posture().await, Posture::Fulgurant);
+        
+        // A forced change ignores the map
+        engine.force_posture_by(Posture::Silent, PostureActor::Api, "incident closed").await.unwrap();
+        assert_eq!(engine.get_posture_history().await.len(), 5);
+        assert_eq!(engine.get_current_posture().await, Posture::Silent);
+    }
+    
+    #[tokio::test]
+    async fn test_evaluations_are_recorded() {
+        let (tx, _rx) = mpsc::channel(16);
+        let metrics = Arc::new(MetricsCollector::new());
+        let engine = PostureEngine::new(PostureEngineConfig::default(), EventSender::from(tx))
+            .await
+            .unwrap()
+            .with_metrics(metrics.clone());
+        
+        engine.evaluate_events(&[detection(2)]).await.unwrap();
+        engine.evaluate_events(&[detection(6)]).await.unwrap();
+        
+        let since = chrono::Utc::now() - chrono::Duration::minutes(1);
+        let values: Vec<f64> = metrics.get_time_series("threat_level", since).into_iter().map(|(_, v)| v).collect();
+        assert_eq!(values, [0.2, 0.6]);
+    }
+    
+    /// A detector finding, shaped like eye360's `Detection`
+    #[derive(serde::Serialize)]
+    struct Finding {
+        detection_type: &'static str,
+        severity: Severity0to10,
+        alert: bool,
+    }
+    
+    impl ReportableDetection for Finding {
+        fn module(&self) -> &str {
+            "eye360"
+        }
+        
+        fn event_type(&self) -> EventType {
+            match self.alert {
+                true => EventType::SecurityAlert,
+                false => EventType::NetworkActivity,
+            }
+        }
+        
+        fn detection_type(&self) -> String {
+            self.detection_type.to_string()
+        }
+        
+        fn severity(&self) -> Severity0to10 {
+            self.severity
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_detections_and_their_events_lead_to_the_same_postures() {
+        let finding = |detection_type, score, alert| Finding {
+            detection_type,
+            severity: Severity0to10::clamped(score),
+            alert,
+        };
+        let batches = [
+            vec![finding("PortScan", 8, false), finding("UnusualConnectionPattern", 7, false)],
+            vec![finding("BruteForce", 9, true), finding("BruteForce", 10, true)],
+            vec![finding("UnusualConnectionPattern", 1, false)],
+        ];
+        
+        let (tx, _rx) = mpsc::channel(16);
+        let from_detections = PostureEngine::new(PostureEngineConfig::default(), EventSender::from(tx)).await.unwrap();
+        let (tx, _rx) = mpsc::channel(16);
+        let from_events = PostureEngine::new(PostureEngineConfig::default(), EventSender::from(tx)).await.unwrap();
+        
+        for batch in &batches {
+            let events: Vec<Event> = batch.iter().map(ReportableDetection::to_event).collect();
+            assert!((PostureEngine::detection_threat_level(batch) - PostureEngine::threat_level(&events)).abs() < 1e-9);
+            
+            let changed = from_detections.evaluate_detections(batch).await.unwrap();
+            assert_eq!(changed, from_events.evaluate_events(&events).await.unwrap());
+            assert_eq!(from_detections.get_current_posture().await, from_events.get_current_posture().await);
+        }
+        assert_eq!(from_detections.get_current_posture().await, Posture::Fulgurant);
+        
+        // The reason names detection types rather than event types
+        let history = from_detections.get_posture_history().await;
+        assert!(history.last().unwrap().reason.contains("BruteForce (1.90)"), "{}", history.last().unwrap().reason);
+    }
 }
@@ -1,4 +1,4 @@
-use chame_core::events::{Event, EventType};
+use chame_core::events::{Event, EventType, Severity};
 use handlebars::Handlebars;
 use serde_json::json;
 use std::collections::HashMap;
@@ -6,6 +6,10 @@ use std::fs;
 use std::path::Path;
 use thiserror::Error;
 
+pub mod schedule;
+
+pub use schedule::{ReportSchedule, ReportScheduler, ReportSummary};
+
 /// Errors that can occur in the Reports module
 #[derive(Error, Debug)]
 pub enum ReportsError {
@@ -22,6 +26,87 @@ pub enum ReportsError {
     InvalidData(String),
 }
 
+/// Optional sections of a report, left out when empty
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReportSections<'a> {
+    /// Honeypot engagement, usually from `Lurefield::stats`
+    pub honeypots: &'a [lurefield::HoneypotStats],
+    
+    /// Audit trail of posture changes, from `PostureEngine::get_posture_history`
+    pub posture_history: &'a [posture_engine::PostureHistoryEntry],
+    
+    /// Period the report covers, shown under its date
+    pub period: Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>,
+}
+
+/// Which detections a report lists
+///
+/// Totals, the score, threat statistics and recommendations are still
+/// computed over every detection; only the listed detections are trimmed,
+/// and the report says so.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportOptions {
+    /// Only list detections with at least this severity (0-10)
+    pub min_severity: Option<u8>,
+    
+    /// Only list detections of these types
+    pub include_types: Option<Vec<String>>,
+    
+    /// List at most this many detections, the most severe ones
+    pub max_items: Option<usize>,
+}
+
+impl ReportOptions {
+    /// Detections to list, in their original order
+    pub fn select<'a>(&self, detections: &'a [formats::DetectionResult]) -> Vec<&'a formats::DetectionResult> {
+        let mut selected: Vec<&formats::DetectionResult> = detections
+            .iter()
+            .filter(|d| self.min_severity.map_or(true, |min| d.severity >= min))
+            .filter(|d| {
+                self.include_types
+                    .as_ref()
+                    .map_or(true, |types| types.contains(&d.detection_type))
+            })
+            .collect();
+        
+        if let Some(max_items) = self.max_items.filter(|&max| max < selected.len()) {
+            // Keep the most severe, earlier ones first on a tie
+            let mut by_severity: Vec<usize> = (0..selected.len()).collect();
+            by_severity.sort_by_key(|&i| std::cmp::Reverse(selected[i].severity));
+            let mut kept = by_severity[..max_items].to_vec();
+            kept.sort_unstable();
+            selected = kept.into_iter().map(|i| selected[i]).collect();
+        }
+        
+        selected
+    }
+    
+    /// Sentence telling readers the list is a subset, if it is
+    fn subset_note(&self, shown: usize, total: usize) -> Option<String> {
+        if shown == total {
+            return None;
+        }
+        
+        let mut criteria = Vec::new();
+        if let Some(min) = self.min_severity {
+            criteria.push(format!("sévérité ≥ {}", min));
+        }
+        if let Some(types) = &self.include_types {
+            criteria.push(format!("types : {}", types.join(", ")));
+        }
+        if let Some(max) = self.max_items {
+            criteria.push(format!("{} au maximum", max));
+        }
+        
+        Some(format!(
+            "Sous-ensemble : {} détections affichées sur {} ({}). Les totaux et statistiques portent sur l'ensemble des détections.",
+            shown,
+            total,
+            criteria.join(", ")
+        ))
+    }
+}
+
 /// Report generation service
 pub struct ReportGenerator {
     /// Handlebars template engine
@@ -55,12 +140,61 @@ impl ReportGenerator {
         })
     }
     
+    /// Directory reports are written to
+    pub fn output_dir(&self) -> &Path {
+        Path::new(&self.output_dir)
+    }
+    
     /// Generate a report from detections
     pub fn generate_report<P: AsRef<Path>>(
         &self,
         detections: &[formats::DetectionResult],
         output_file: P,
     ) -> Result<(), ReportsError> {
+        self.generate_report_with_honeypots(detections, &[], output_file)
+    }
+    
+    /// Generate a report from detections, with a honeypot engagement section
+    ///
+    /// `honeypots` usually comes from `Lurefield::stats`; the section is left
+    /// out when it is empty.
+    pub fn generate_report_with_honeypots<P: AsRef<Path>>(
+        &self,
+        detections: &[formats::DetectionResult],
+        honeypots: &[lurefield::HoneypotStats],
+        output_file: P,
+    ) -> Result<(), ReportsError> {
+        let sections = ReportSections {
+            honeypots,
+            ..Default::default()
+        };
+        self.generate_report_with(detections, &sections, output_file)
+    }
+    
+    /// Generate a report from detections, with the optional sections given
+    pub fn generate_report_with<P: AsRef<Path>>(
+        &self,
+        detections: &[formats::DetectionResult],
+        sections: &ReportSections,
+        output_file: P,
+    ) -> Result<(), ReportsError> {
+        self.generate_report_with_options(detections, sections, &ReportOptions::default(), output_file)
+    }
+    
+    /// Generate a report listing the detections picked by `options`
+    ///
+    /// Totals and statistics cover every detection, e.g. to produce an
+    /// executive summary listing only high-severity items.
+    pub fn generate_report_with_options<P: AsRef<Path>>(
+        &self,
+        detections: &[formats::DetectionResult],
+        sections: &ReportSections,
+        options: &ReportOptions,
+        output_file: P,
+    ) -> Result<(), ReportsError> {
+        let honeypots = sections.honeypots;
+        let listed = options.select(detections);
+        
         // Calculate statistics
         let mut high_count = 0;
         let mut medium_count = 0;
@@ -69,41 +203,37 @@ impl ReportGenerator {
         // Group detections by type
         let mut threat_types = HashMap::new();
         
-        // Prepare detection data for template
-        let detection_data: Vec<serde_json::Value> = detections
+        for d in detections {
+            // Count by severity
+            match d.severity_level() {
+                Severity::Critical | Severity::High => high_count += 1,
+                Severity::Medium => medium_count += 1,
+                Severity::Low | Severity::Info => low_count += 1,
+            }
+            
+            // Group by type
+            let entry = threat_types.entry(d.detection_type.clone()).or_insert_with(|| {
+                json!({
+                    "type": d.detection_type,
+                    "count": 0,
+                    "total_severity": 0,
+                })
+            });
+            
+            let entry_obj = entry.as_object_mut().unwrap();
+            entry_obj["count"] = json!(entry_obj["count"].as_i64().unwrap() + 1);
+            entry_obj["total_severity"] = json!(entry_obj["total_severity"].as_i64().unwrap() + i64::from(d.severity.get()));
+        }
+        
+        // Prepare detection data for template, only for the listed detections
+        let detection_data: Vec<serde_json::Value> = listed
             .iter()
             .map(|d| {
-                // Count by severity
-                match d.severity {
-                    8..=10 => high_count += 1,
-                    5..=7 => medium_count += 1,
-                    _ => low_count += 1,
-                }
-                
-                // Group by type
-                let entry = threat_types.entry(d.detection_type.clone()).or_insert_with(|| {
-                    json!({
-                        "type": d.detection_type,
-                        "count": 0,
-                        "total_severity": 0,
-                    })
-                });
-                
-                let mut entry_obj = entry.as_object_mut().unwrap();
-                entry_obj["count"] = json!(entry_obj["count"].as_i64().unwrap() + 1);
-                entry_obj["total_severity"] = json!(entry_obj["total_severity"].as_i64().unwrap() + d.severity as i64);
-                
                 // Create detection entry
-                let severity_class = match d.severity {
-                    8..=10 => "severity-high",
-                    5..=7 => "severity-medium",
-                    _ => "severity-low",
-                };
-                
-                let severity_text = match d.severity {
-                    8..=10 => "Critique",
-                    5..=7 => "Moyenne",
-                    _ => "Faible",
+                let (severity_class, severity_text) = match d.severity_level() {
+                    Severity::Critical | Severity::High => ("severity-high", "Critique"),
+                    Severity::Medium => ("severity-medium", "Moyenne"),
+                    Severity::Low | Severity::Info => ("severity-low", "Faible"),
                 };
                 
                 json!({
@@ -134,16 +264,10 @@ impl ReportGenerator {
                     0.0
                 };
                 
-                let status_class = match avg_severity as u8 {
-                    8..=10 => "badge-danger",
-                    5..=7 => "badge-warning",
-                    _ => "badge-success",
-                };
-                
-                let status = match avg_severity as u8 {
-                    8..=10 => "Critique",
-                    5..=7 => "Attention",
-                    _ => "Normal",
+                let (status_class, status) = match Severity::from_score(avg_severity as u8) {
+                    Severity::Critical | Severity::High => ("badge-danger", "Critique"),
+                    Severity::Medium => ("badge-warning", "Attention"),
+                    Severity::Low | Severity::Info => ("badge-success", "Normal"),
                 };
                 
                 json!({
@@ -174,6 +298,19 @@ impl ReportGenerator {
             recommendations.push("Renforcer les filtres anti-phishing sur les passerelles de messagerie.".to_string());
         }
         
+        // Lures nobody touched are worth pruning
+        let idle_honeypots: Vec<&str> = honeypots
+            .iter()
+            .filter(|h| h.interactions == 0)
+            .map(|h| h.id.as_str())
+            .collect();
+        if !idle_honeypots.is_empty() && idle_honeypots.len() < honeypots.len() {
+            recommendations.push(format!(
+                "Retirer ou remplacer les honeypots sans interaction ({}) et renforcer ceux qui attirent les attaquants.",
+                idle_honeypots.join(", ")
+            ));
+        }
+        
         // Add general recommendations
         recommendations.push("Mettre à jour tous les systèmes avec les derniers correctifs de sécurité.".to_string());
         recommendations.push("Renforcer les politiques de mot de passe et activer l'authentification à deux facteurs.".to_string());
@@ -199,9 +336,64 @@ impl ReportGenerator {
             _ => "L'analyse n'a révélé que des problèmes mineurs ou aucun problème de sécurité significatif.",
         };
         
+        // Honeypot engagement, most engaged first
+        let honeypot_data: Vec<serde_json::Value> = honeypots
+            .iter()
+            .map(|h| {
+                let format_time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                    at.map(|at| at.format("%d/%m/%Y %H:%M").to_string()).unwrap_or_else(|| "-".to_string())
+                };
+                let (engagement_class, engagement) = match (h.interactions, h.unique_sources) {
+                    (0, _) => ("badge-warning", "Aucune interaction"),
+                    (_, 0..=1) => ("badge-success", "Faible"),
+                    _ => ("badge-danger", "Élevé"),
+                };
+                
+                json!({
+                    "id": h.id,
+                    "type": h.honeypot_type,
+                    "port": h.port,
+                    "status": if h.active { "Actif" } else { "Arrêté" },
+                    "interactions": h.interactions,
+                    "unique_sources": h.unique_sources,
+                    "first_interaction": format_time(h.first_interaction),
+                    "last_interaction": format_time(h.last_interaction),
+                    "engagement": engagement,
+                    "engagement_class": engagement_class,
+                })
+            })
+            .collect();
+        
+        // Posture audit trail, most recent change first
+        let posture_history: Vec<serde_json::Value> = sections
+            .posture_history
+            .iter()
+            .rev()
+            .map(|entry| {
+                let actor = match entry.actor {
+                    posture_engine::PostureActor::Manual => "Manuel",
+                    posture_engine::PostureActor::Adaptive => "Adaptatif",
+                    posture_engine::PostureActor::Scheduled => "Planifié",
+                    posture_engine::PostureActor::Api => "API",
+                };
+                
+                json!({
+                    "timestamp": entry.timestamp.format("%d/%m/%Y %H:%M:%S").to_string(),
+                    "posture": entry.posture.to_str(),
+                    "actor": actor,
+                    "reason": entry.reason,
+                })
+            })
+            .collect();
+        
+        let period = sections.period.map(|(from, to)| {
+            format!("Du {} au {}", from.format("%d/%m/%Y %H:%M"), to.format("%d/%m/%Y %H:%M"))
+        });
+        
         // Prepare template data
         let data = json!({
             "date": chrono::Utc::now().format("%d/%m/%Y %H:%M").to_string(),
+            "period": period,
             "score": weighted_score,
             "score_class": score_class,
             "summary_text": summary_text,
@@ -210,9 +402,12 @@ impl ReportGenerator {
             "low_count": low_count,
             "total_count": total_count,
             "detections": detection_data,
+            "subset_note": options.subset_note(listed.len(), detections.len()),
             "threat_stats": threat_stats,
             "recommendations": recommendations,
             "activities": detection_data,  // Reuse detection data for activities
+            "honeypots": honeypot_data,
+            "posture_history": posture_history,
         });
         
         // Render template
@@ -225,3 +420,6 @@ impl ReportGenerator {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests;
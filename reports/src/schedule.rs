@@ -0,0 +1,359 @@
+use crate::{ReportGenerator, ReportOptions, ReportSections, ReportsError};
+use chame_core::events::{Event, EventType, Severity};
+use chame_core::{with_backoff, CircuitBreaker, CircuitBreakerConfig, EventStore, RetryPolicy, Severity0to10};
+use chrono::{DateTime, Utc};
+use hyper::{header, Body, Client, Request};
+use lurefield::HoneypotStats;
+use posture_engine::{Posture, PostureActor, PostureHistoryEntry};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// When scheduled reports are produced and where they go
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSchedule {
+    /// Time between two reports, each covering the period since the previous one
+    pub interval: Duration,
+
+    /// Reports are written as `<file_prefix>-<end of period>.html` in the
+    /// generator's output directory
+    pub file_prefix: String,
+
+    /// Detections listed in each report
+    pub options: ReportOptions,
+
+    /// URL the [`ReportSummary`] of each report is posted to, over plain HTTP
+    pub webhook_url: Option<String>,
+
+    /// How a failed webhook post is retried
+    pub webhook_retry: RetryPolicy,
+}
+
+impl Default for ReportSchedule {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(24 * 60 * 60),
+            file_prefix: "summary".to_string(),
+            options: ReportOptions::default(),
+            webhook_url: None,
+            webhook_retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// What a scheduled report covered, posted to the webhook
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportSummary {
+    /// Report written
+    pub path: PathBuf,
+
+    /// Start of the period
+    pub from: DateTime<Utc>,
+
+    /// End of the period
+    pub to: DateTime<Utc>,
+
+    /// Detections of the period
+    pub detections: usize,
+
+    /// Posture changes of the period
+    pub posture_changes: usize,
+
+    /// Honeypot interactions of the period
+    pub honeypot_interactions: u32,
+}
+
+/// Produces a summary report of every period from the event store
+///
+/// Detections are the `SecurityAlert` and `NetworkActivity` events of the
+/// period, posture changes its `PostureChange` events and honeypot
+/// engagement is counted from its `HoneypotActivity` events. The store must
+/// be the one the core records every event in.
+pub struct ReportScheduler {
+    generator: ReportGenerator,
+    store: Arc<EventStore>,
+    schedule: ReportSchedule,
+    webhook: CircuitBreaker,
+}
+
+impl ReportScheduler {
+    /// Create a scheduler writing reports with `generator`
+    pub fn new(generator: ReportGenerator, store: Arc<EventStore>, schedule: ReportSchedule) -> Self {
+        Self {
+            generator,
+            store,
+            schedule,
+            webhook: CircuitBreaker::new("report_webhook", CircuitBreakerConfig::default()),
+        }
+    }
+
+    /// Write the report of `[from, to]` and post its summary to the webhook
+    ///
+    /// A failed webhook post is retried with backoff, then logged; the
+    /// report is still written.
+    pub async fn generate(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<ReportSummary, ReportsError> {
+        let period = Period::collect(&self.store, from, to).await?;
+
+        let file = format!("{}-{}.html", self.schedule.file_prefix, to.format("%Y%m%d-%H%M%S"));
+        let sections = ReportSections {
+            honeypots: &period.honeypots,
+            posture_history: &period.posture_history,
+            period: Some((from, to)),
+        };
+        self.generator
+            .generate_report_with_options(&period.detections, &sections, &self.schedule.options, &file)?;
+
+        let summary = ReportSummary {
+            path: self.generator.output_dir().join(&file),
+            from,
+            to,
+            detections: period.detections.len(),
+            posture_changes: period.posture_history.len(),
+            honeypot_interactions: period.honeypots.iter().map(|honeypot| honeypot.interactions).sum(),
+        };
+        info!(
+            "Wrote report {} ({} detections, {} posture changes)",
+            summary.path.display(),
+            summary.detections,
+            summary.posture_changes
+        );
+
+        if let Some(url) = &self.schedule.webhook_url {
+            let post = || with_backoff(|| post_summary(url, &summary), &self.schedule.webhook_retry);
+            if let Err(e) = self.webhook.call(post).await {
+                warn!("Failed to post report summary to {}: {}", url, e);
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Write a report at the end of every `interval` until `cancel` fires
+    ///
+    /// The first period starts now.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let scheduler = &self;
+            every_period(self.schedule.interval, cancel, |from, to| async move {
+                if let Err(e) = scheduler.generate(from, to).await {
+                    warn!("Failed to generate the scheduled report: {}", e);
+                }
+            })
+            .await;
+            debug!("Report scheduler stopped");
+        })
+    }
+}
+
+/// Call `run` with the bounds of each `interval` once it is over, until
+/// `cancel` fires
+///
+/// Bounds follow the runtime's clock, so consecutive periods share their
+/// bound and pausing time in tests moves them too.
+pub(crate) async fn every_period<F, Fut>(interval: Duration, cancel: CancellationToken, mut run: F)
+where
+    F: FnMut(DateTime<Utc>, DateTime<Utc>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let started = tokio::time::Instant::now();
+    let origin = Utc::now();
+    let at = |instant: tokio::time::Instant| {
+        origin + chrono::Duration::from_std(instant - started).unwrap_or_else(|_| chrono::Duration::zero())
+    };
+
+    let mut ticker = tokio::time::interval_at(started + interval, interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let mut from = origin;
+    loop {
+        let tick = tokio::select! {
+            _ = cancel.cancelled() => return,
+            tick = ticker.tick() => tick,
+        };
+
+        let to = at(tick);
+        run(from, to).await;
+        from = to;
+    }
+}
+
+/// Post `summary` as JSON to `url`, failing on a non-2xx status
+async fn post_summary(url: &str, summary: &ReportSummary) -> Result<(), String> {
+    let body = serde_json::to_vec(summary).map_err(|e| e.to_string())?;
+    let request = Request::post(url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .map_err(|e| format!("Invalid webhook URL: {}", e))?;
+
+    let response = Client::new().request(request).await.map_err(|e| e.to_string())?;
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("webhook answered {}", response.status())),
+    }
+}
+
+/// What happened during a period, read back from the event store
+#[derive(Default)]
+struct Period {
+    detections: Vec<formats::DetectionResult>,
+    posture_history: Vec<PostureHistoryEntry>,
+    honeypots: Vec<HoneypotStats>,
+}
+
+impl Period {
+    async fn collect(store: &EventStore, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self, ReportsError> {
+        let events = store
+            .replay(from, to)
+            .await
+            .map_err(|e| ReportsError::InvalidData(format!("Failed to read the event store: {}", e)))?;
+        let mut events = std::pin::pin!(events);
+
+        let mut period = Period::default();
+        let mut honeypots = HoneypotTally::default();
+        while let Some(event) = events.next().await {
+            match event.event_type {
+                EventType::SecurityAlert | EventType::NetworkActivity => period.detections.push(detection(&event)),
+                EventType::PostureChange => period.posture_history.extend(posture_change(&event)),
+                EventType::HoneypotActivity => honeypots.add(&event),
+                _ => {}
+            }
+        }
+        period.honeypots = honeypots.into_stats();
+
+        Ok(period)
+    }
+}
+
+/// A detection event as a report entry
+fn detection(event: &Event) -> formats::DetectionResult {
+    let data = event.data.as_ref();
+    let detection_type = data
+        .and_then(|data| data.get("detection_type").or_else(|| data.get("action")))
+        .and_then(|value| value.as_str())
+        .unwrap_or_else(|| event.event_type.name())
+        .to_string();
+
+    // Events without a score weigh like the middle of their level
+    let score = event.severity_score().unwrap_or(match event.severity() {
+        Severity::Critical => 10,
+        Severity::High => 8,
+        Severity::Medium => 6,
+        Severity::Low => 3,
+        Severity::Info => 0,
+    });
+
+    let mut details = HashMap::new();
+    for (key, value) in data.and_then(|data| data.as_object()).into_iter().flatten() {
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        details.insert(key.clone(), value);
+    }
+
+    formats::DetectionResult {
+        detection_type,
+        severity: Severity0to10::clamped(score),
+        location: event.source.clone(),
+        details,
+        timestamp: event.timestamp,
+    }
+}
+
+/// A `PostureChange` event as a history entry, if it names a known posture
+fn posture_change(event: &Event) -> Option<PostureHistoryEntry> {
+    let data = event.data.as_ref()?;
+    let posture = Posture::try_from(data.get("posture")?.as_str()?).ok()?;
+    let actor = match data.get("actor").and_then(|actor| actor.as_str()) {
+        Some("adaptive") => PostureActor::Adaptive,
+        Some("scheduled") => PostureActor::Scheduled,
+        Some("api") => PostureActor::Api,
+        _ => PostureActor::Manual,
+    };
+
+    Some(PostureHistoryEntry {
+        posture,
+        actor,
+        reason: data.get("reason").and_then(|reason| reason.as_str()).unwrap_or_default().to_string(),
+        timestamp: event.timestamp,
+    })
+}
+
+/// Engagement of each honeypot, counted from its events
+#[derive(Default)]
+struct HoneypotTally {
+    /// Honeypots in order of their first event
+    honeypots: Vec<(HoneypotStats, HashSet<IpAddr>)>,
+}
+
+impl HoneypotTally {
+    fn add(&mut self, event: &Event) {
+        let Some(data) = event.data.as_ref() else {
+            return;
+        };
+        let Some(id) = data.get("honeypot_id").and_then(|id| id.as_str()) else {
+            return;
+        };
+
+        let index = match self.honeypots.iter().position(|(stats, _)| stats.id == id) {
+            Some(index) => index,
+            None => {
+                let stats = HoneypotStats {
+                    id: id.to_string(),
+                    honeypot_type: "-".to_string(),
+                    port: 0,
+                    active: true,
+                    interactions: 0,
+                    persisted_interactions: 0,
+                    unique_sources: 0,
+                    first_interaction: None,
+                    last_interaction: None,
+                };
+                self.honeypots.push((stats, HashSet::new()));
+                self.honeypots.len() - 1
+            }
+        };
+        let (stats, sources) = &mut self.honeypots[index];
+
+        let text = |value: &serde_json::Value, key: &str| value.get(key).and_then(|value| value.as_str()).map(String::from);
+        match data.get("action").and_then(|action| action.as_str()) {
+            Some("deploy") => {
+                stats.honeypot_type = text(data, "honeypot_type").unwrap_or_else(|| stats.honeypot_type.clone());
+                stats.port = data.get("port").and_then(|port| port.as_u64()).map_or(stats.port, |port| port as u16);
+                stats.active = true;
+            }
+            Some("stop") => stats.active = false,
+            Some("interaction") => {
+                let details = data.get("details").unwrap_or(&serde_json::Value::Null);
+                if stats.honeypot_type == "-" {
+                    stats.honeypot_type = text(details, "protocol").unwrap_or_else(|| "-".to_string());
+                }
+                let source = text(details, "remote_addr").and_then(|addr| {
+                    addr.parse::<std::net::SocketAddr>().map(|addr| addr.ip()).or_else(|_| addr.parse()).ok()
+                });
+                sources.extend(source);
+
+                stats.interactions += 1;
+                stats.unique_sources = sources.len();
+                stats.first_interaction.get_or_insert(event.timestamp);
+                stats.last_interaction = Some(event.timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    /// Stats of the honeypots, most engaged first
+    fn into_stats(self) -> Vec<HoneypotStats> {
+        let mut stats: Vec<HoneypotStats> = self.honeypots.into_iter().map(|(stats, _)| stats).collect();
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.interactions));
+        stats
+    }
+}
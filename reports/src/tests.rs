@@ -1,73 +1,343 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
-    use tempfile::tempdir;
+use crate::schedule::every_period;
+use crate::{ReportGenerator, ReportOptions, ReportSchedule, ReportScheduler, ReportSections};
+use chame_core::{Event, EventStore, RetryPolicy, Severity};
+use chrono::{TimeZone, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tempfile::tempdir;
 
-    #[tokio::test]
-    async fn test_report_generation() {
-        // Create a temporary directory for test output
-        let temp_dir = tempdir().unwrap();
-        let output_path = temp_dir.path().join("test_report.html");
-        
-        // Create sample detection results
-        let detections = vec![
-            formats::DetectionResult {
-                detection_type: "ransomware_indicator".to_string(),
-                severity: 9,
-                location: "row:5,col:3".to_string(),
-                details: {
-                    let mut map = HashMap::new();
-                    map.insert("matched_text".to_string(), "suspicious ransomware activity".to_string());
-                    map.insert("column".to_string(), "3".to_string());
-                    map
-                },
-                timestamp: chrono::Utc::now(),
+#[tokio::test]
+async fn test_report_generation() {
+    // Create a temporary directory for test output
+    let temp_dir = tempdir().unwrap();
+    let output_path = temp_dir.path().join("test_report.html");
+    
+    // Create sample detection results
+    let detections = vec![
+        formats::DetectionResult {
+            detection_type: "ransomware_indicator".to_string(),
+            severity: 9.try_into().unwrap(),
+            location: "row:5,col:3".to_string(),
+            details: {
+                let mut map = HashMap::new();
+                map.insert("matched_text".to_string(), "suspicious ransomware activity".to_string());
+                map.insert("column".to_string(), "3".to_string());
+                map
             },
-            formats::DetectionResult {
-                detection_type: "phishing_indicator".to_string(),
-                severity: 7,
-                location: "row:12,col:2".to_string(),
-                details: {
-                    let mut map = HashMap::new();
-                    map.insert("matched_text".to_string(), "phishing attempt detected".to_string());
-                    map.insert("column".to_string(), "2".to_string());
-                    map
-                },
-                timestamp: chrono::Utc::now(),
+            timestamp: chrono::Utc::now(),
+        },
+        formats::DetectionResult {
+            detection_type: "phishing_indicator".to_string(),
+            severity: 7.try_into().unwrap(),
+            location: "row:12,col:2".to_string(),
+            details: {
+                let mut map = HashMap::new();
+                map.insert("matched_text".to_string(), "phishing attempt detected".to_string());
+                map.insert("column".to_string(), "2".to_string());
+                map
             },
-            formats::DetectionResult {
-                detection_type: "suspicious_activity".to_string(),
-                severity: 4,
-                location: "row:18,col:5".to_string(),
-                details: {
-                    let mut map = HashMap::new();
-                    map.insert("matched_text".to_string(), "unusual login pattern".to_string());
-                    map.insert("column".to_string(), "5".to_string());
-                    map
-                },
-                timestamp: chrono::Utc::now(),
+            timestamp: chrono::Utc::now(),
+        },
+        formats::DetectionResult {
+            detection_type: "suspicious_activity".to_string(),
+            severity: 4.try_into().unwrap(),
+            location: "row:18,col:5".to_string(),
+            details: {
+                let mut map = HashMap::new();
+                map.insert("matched_text".to_string(), "unusual login pattern".to_string());
+                map.insert("column".to_string(), "5".to_string());
+                map
             },
-        ];
-        
-        // Create report generator
-        let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
-        let report_generator = ReportGenerator::new(
-            template_dir.to_str().unwrap(),
-            temp_dir.path().to_str().unwrap(),
-        ).unwrap();
-        
-        // Generate report
-        let result = report_generator.generate_report(&detections, "test_report.html");
-        
-        // Verify report was generated successfully
-        assert!(result.is_ok());
-        assert!(output_path.exists());
-        
-        // Verify report content
-        let content = std::fs::read_to_string(output_path).unwrap();
-        assert!(content.contains("ransomware_indicator"));
-        assert!(content.contains("phishing_indicator"));
-        assert!(content.contains("suspicious_activity"));
+            timestamp: chrono::Utc::now(),
+        },
+    ];
+    
+    // Create report generator
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let report_generator = ReportGenerator::new(
+        template_dir.to_str().unwrap(),
+        temp_dir.path().to_str().unwrap(),
+    ).unwrap();
+    
+    // Generate report
+    let result = report_generator.generate_report(&detections, "test_report.html");
+    
+    // Verify report was generated successfully
+    assert!(result.is_ok());
+    assert!(output_path.exists());
+    
+    // Verify report content
+    let content = std::fs::read_to_string(output_path).unwrap();
+    assert!(content.contains("ransomware_indicator"));
+    assert!(content.contains("phishing_indicator"));
+    assert!(content.contains("suspicious_activity"));
+}
+
+#[test]
+fn test_honeypot_engagement_section() {
+    let temp_dir = tempdir().unwrap();
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let report_generator = ReportGenerator::new(
+        template_dir.to_str().unwrap(),
+        temp_dir.path().to_str().unwrap(),
+    ).unwrap();
+    
+    let honeypots = vec![
+        lurefield::HoneypotStats {
+            id: "hp-ssh-1".to_string(),
+            honeypot_type: "ssh".to_string(),
+            port: 2222,
+            active: true,
+            interactions: 12,
+            persisted_interactions: 0,
+            unique_sources: 4,
+            first_interaction: Some(Utc.with_ymd_and_hms(2025, 3, 1, 8, 30, 0).unwrap()),
+            last_interaction: Some(Utc.with_ymd_and_hms(2025, 3, 2, 17, 5, 0).unwrap()),
+        },
+        lurefield::HoneypotStats {
+            id: "hp-ftp-2".to_string(),
+            honeypot_type: "ftp".to_string(),
+            port: 2121,
+            active: false,
+            interactions: 0,
+            persisted_interactions: 0,
+            unique_sources: 0,
+            first_interaction: None,
+            last_interaction: None,
+        },
+    ];
+    
+    report_generator.generate_report_with_honeypots(&[], &honeypots, "engagement.html").unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("engagement.html")).unwrap();
+    assert!(content.contains("Engagement des honeypots"));
+    assert!(content.contains("hp-ssh-1 (Actif)"));
+    assert!(content.contains("01/03/2025 08:30"));
+    assert!(content.contains("Aucune interaction"));
+    assert!(content.contains("honeypots sans interaction (hp-ftp-2)"));
+    
+    // Without honeypots the section is left out
+    report_generator.generate_report(&[], "plain.html").unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("plain.html")).unwrap();
+    assert!(!content.contains("Engagement des honeypots"));
+}
+
+#[test]
+fn test_posture_audit_section() {
+    let temp_dir = tempdir().unwrap();
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let report_generator = ReportGenerator::new(
+        template_dir.to_str().unwrap(),
+        temp_dir.path().to_str().unwrap(),
+    ).unwrap();
+    
+    let history = vec![
+        posture_engine::PostureHistoryEntry {
+            posture: posture_engine::Posture::Neutral,
+            actor: posture_engine::PostureActor::Manual,
+            reason: "startup posture".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 8, 0, 0).unwrap(),
+        },
+        posture_engine::PostureHistoryEntry {
+            posture: posture_engine::Posture::Mimetic,
+            actor: posture_engine::PostureActor::Adaptive,
+            reason: "threat level 0.80 >= 0.75, top event types: SecurityAlert (1.70)".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 9, 15, 30).unwrap(),
+        },
+    ];
+    let sections = ReportSections {
+        posture_history: &history,
+        ..Default::default()
+    };
+    
+    report_generator.generate_report_with(&[], &sections, "audit.html").unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("audit.html")).unwrap();
+    assert!(content.contains("Journal des postures"));
+    assert!(content.contains("Adaptatif"));
+    assert!(content.contains("top event types: SecurityAlert (1.70)"));
+    
+    // Most recent change first
+    let mimetic = content.find("01/03/2025 09:15:30").unwrap();
+    assert!(mimetic < content.find("01/03/2025 08:00:00").unwrap());
+    
+    report_generator.generate_report(&[], "plain.html").unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("plain.html")).unwrap();
+    assert!(!content.contains("Journal des postures"));
+}
+
+#[test]
+fn test_report_options_list_a_labeled_subset() {
+    let temp_dir = tempdir().unwrap();
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let report_generator = ReportGenerator::new(
+        template_dir.to_str().unwrap(),
+        temp_dir.path().to_str().unwrap(),
+    ).unwrap();
+    
+    let detection = |detection_type: &str, severity: u8, row: u32| formats::DetectionResult {
+        detection_type: detection_type.to_string(),
+        severity: severity.try_into().unwrap(),
+        location: format!("row:{}", row),
+        details: HashMap::new(),
+        timestamp: Utc::now(),
+    };
+    let detections = vec![
+        detection("suspicious_activity", 4, 1),
+        detection("ransomware_indicator", 9, 2),
+        detection("phishing_indicator", 7, 3),
+        detection("ransomware_lockbit", 10, 4),
+    ];
+    
+    let options = ReportOptions {
+        min_severity: Some(7),
+        max_items: Some(2),
+        ..Default::default()
+    };
+    let listed: Vec<_> = options.select(&detections).iter().map(|d| d.location.as_str()).collect();
+    assert_eq!(listed, ["row:2", "row:4"]);
+    
+    let types = ReportOptions {
+        include_types: Some(vec!["phishing_indicator".to_string()]),
+        ..Default::default()
+    };
+    assert_eq!(types.select(&detections).len(), 1);
+    
+    report_generator
+        .generate_report_with_options(&detections, &ReportSections::default(), &options, "summary.html")
+        .unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("summary.html")).unwrap();
+    assert!(content.contains("Sous-ensemble : 2 détections affichées sur 4"));
+    assert!(!content.contains("Détection: phishing_indicator"));
+    // Totals still cover every detection
+    assert!(content.contains(r#"<div class="stat-value">4</div>"#));
+    
+    report_generator.generate_report(&detections, "full.html").unwrap();
+    let content = std::fs::read_to_string(temp_dir.path().join("full.html")).unwrap();
+    assert!(!content.contains("Sous-ensemble"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_scheduled_reports_are_triggered_every_interval() {
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let task = tokio::spawn(every_period(day, cancel.clone(), move |from, to| {
+        let tx = tx.clone();
+        async move { tx.send((from, to)).unwrap() }
+    }));
+    
+    // Nothing before the end of the first period
+    tokio::time::advance(day - std::time::Duration::from_secs(1)).await;
+    tokio::task::yield_now().await;
+    assert!(rx.try_recv().is_err());
+    
+    tokio::time::advance(std::time::Duration::from_secs(1)).await;
+    let (from, to) = rx.recv().await.unwrap();
+    assert_eq!(to - from, chrono::Duration::days(1));
+    
+    // Periods follow each other without a gap
+    tokio::time::advance(day).await;
+    let (next_from, next_to) = rx.recv().await.unwrap();
+    assert_eq!((next_from, next_to - next_from), (to, chrono::Duration::days(1)));
+    assert!(rx.try_recv().is_err());
+    
+    cancel.cancel();
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_scheduled_report_summarizes_the_period() {
+    let temp_dir = tempdir().unwrap();
+    let store = EventStore::open(temp_dir.path().join("events.jsonl")).await.unwrap();
+    let events = [
+        Event::security_alert_with_severity(
+            "eye360",
+            Some(serde_json::json!({ "detection_type": "BruteForce", "severity": 9 })),
+            Severity::High,
+        ),
+        Event::network_activity("nettongue", Some(serde_json::json!({ "detection_type": "PortScan" }))),
+        Event::posture_change(
+            "posture_engine",
+            Some(serde_json::json!({ "posture": "fulgurant", "actor": "adaptive", "reason": "threat level 0.95" })),
+        ),
+        Event::honeypot_activity(
+            "lurefield",
+            Some(serde_json::json!({ "action": "deploy", "honeypot_id": "hp-ssh", "honeypot_type": "ssh", "port": 2222 })),
+        ),
+        Event::honeypot_activity(
+            "lurefield",
+            Some(serde_json::json!({
+                "action": "interaction",
+                "honeypot_id": "hp-ssh",
+                "details": { "remote_addr": "203.0.113.7:40000", "protocol": "ssh" },
+            })),
+        ),
+    ];
+    for event in &events {
+        store.append(event).await.unwrap();
     }
+    
+    let output_dir = temp_dir.path().join("reports");
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let generator = ReportGenerator::new(template_dir.to_str().unwrap(), output_dir.to_str().unwrap()).unwrap();
+    let scheduler = ReportScheduler::new(generator, std::sync::Arc::new(store), ReportSchedule::default());
+    
+    let to = Utc::now();
+    let summary = scheduler.generate(to - chrono::Duration::hours(1), to).await.unwrap();
+    assert_eq!((summary.detections, summary.posture_changes, summary.honeypot_interactions), (2, 1, 1));
+    assert!(summary.path.starts_with(&output_dir));
+    
+    let content = std::fs::read_to_string(&summary.path).unwrap();
+    assert!(content.contains("Détection: BruteForce"));
+    assert!(content.contains("threat level 0.95"));
+    assert!(content.contains("hp-ssh"));
+    assert!(content.contains(&format!("Du {}", (to - chrono::Duration::hours(1)).format("%d/%m/%Y %H:%M"))));
+    
+    // An earlier period has nothing
+    let summary = scheduler.generate(to - chrono::Duration::days(2), to - chrono::Duration::days(1)).await.unwrap();
+    assert_eq!((summary.detections, summary.posture_changes, summary.honeypot_interactions), (0, 0, 0));
+}
+
+#[tokio::test]
+async fn test_webhook_posts_are_retried() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    // Answers 503 twice, then 200
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/camaleon", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        for status in ["503 Service Unavailable", "503 Service Unavailable", "200 OK"] {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            while !String::from_utf8_lossy(&request).contains("\"honeypot_interactions\"") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                assert!(read > 0, "request cut short");
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    
+    let temp_dir = tempdir().unwrap();
+    let store = EventStore::open(temp_dir.path().join("events.jsonl")).await.unwrap();
+    let output_dir = temp_dir.path().join("reports");
+    let template_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
+    let generator = ReportGenerator::new(template_dir.to_str().unwrap(), output_dir.to_str().unwrap()).unwrap();
+    let schedule = ReportSchedule {
+        webhook_url: Some(url),
+        webhook_retry: RetryPolicy {
+            max_attempts: 3,
+            initial_delay: std::time::Duration::from_millis(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let scheduler = ReportScheduler::new(generator, std::sync::Arc::new(store), schedule);
+    
+    let to = Utc::now();
+    scheduler.generate(to - chrono::Duration::hours(1), to).await.unwrap();
+    
+    // The third attempt got through
+    tokio::time::timeout(std::time::Duration::from_secs(5), server).await.unwrap().unwrap();
 }
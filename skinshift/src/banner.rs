@@ -1,11 +1,19 @@
 use crate::errors::SkinshiftError;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+/// Services whose banner can be changed
+pub(crate) const SUPPORTED_SERVICES: &[&str] = &[
+    "ssh", "http", "apache", "nginx", "ftp", "smtp", "telnet", "mysql", "rdp",
+];
+
 /// Banner modification configuration
 #[derive(Debug, Clone)]
 pub struct BannerConfig {
@@ -66,6 +74,16 @@ pub struct BannerManager {
     
     /// Service config paths (for reverting changes)
     service_configs: HashMap<String, String>,
+    
+    /// Banners for services that are not configured through a file,
+    /// served directly by the honeypot listeners
+    memory_banners: RwLock<HashMap<String, String>>,
+    
+    /// Banners appended to config files, by file path
+    managed_banners: RwLock<HashMap<String, ManagedBanner>>,
+    
+    /// Where `managed_banners` is kept across restarts
+    state_path: Option<PathBuf>,
 }
 
 impl BannerManager {
@@ -75,9 +93,31 @@ impl BannerManager {
             original_banners: HashMap::new(),
             current_banners: HashMap::new(),
             service_configs: HashMap::new(),
+            memory_banners: RwLock::new(HashMap::new()),
+            managed_banners: RwLock::new(HashMap::new()),
+            state_path: None,
         }
     }
     
+    /// Keep track of the appended banners in `path`, so they can still be
+    /// removed after a restart
+    ///
+    /// The banners themselves carry no marker; the state file records where
+    /// each one is and a hash of it.
+    pub fn with_state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(managed) => self.managed_banners = RwLock::new(managed),
+                Err(e) => warn!("Ignoring invalid banner state {}: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read banner state {}: {}", path.display(), e),
+        }
+        self.state_path = Some(path);
+        self
+    }
+    
     /// Initialize the banner manager
     pub async fn init(&self) -> Result<(), SkinshiftError> {
         info!("Initializing banner manager");
@@ -99,6 +139,8 @@ impl BannerManager {
             "ftp" => self.get_ftp_config(banner)?,
             "smtp" => self.get_smtp_config(banner)?,
             "telnet" => self.get_telnet_config(banner)?,
+            "mysql" => self.get_mysql_config(banner)?,
+            "rdp" => self.get_rdp_config(banner)?,
             _ => {
                 warn!("Unknown service: {}, cannot set banner", service_name);
                 return Err(SkinshiftError::BannerError(
@@ -128,6 +170,10 @@ impl BannerManager {
                 "ftp" => self.get_ftp_config(original_banner)?,
                 "smtp" => self.get_smtp_config(original_banner)?,
                 "telnet" => self.get_telnet_config(original_banner)?,
+                "mysql" | "rdp" => {
+                    // In-memory banners are simply dropped below
+                    continue;
+                }
                 _ => {
                     warn!("Unknown service: {}, cannot reset banner", service);
                     continue;
@@ -141,6 +187,13 @@ impl BannerManager {
             }
         }
         
+        // Drop the appended banners, wherever they were written
+        if let Err(e) = self.remove_managed_banners(|_| true).await {
+            warn!("Failed to remove managed banners: {}", e);
+        }
+        
+        self.memory_banners.write().await.clear();
+        
         debug!("All banners reset successfully");
         Ok(())
     }
@@ -149,6 +202,17 @@ impl BannerManager {
     pub async fn reset_banner(&self, service_name: &str) -> Result<(), SkinshiftError> {
         info!("Resetting banner for service: {}", service_name);
         
+        // In-memory banners have no original to restore
+        if self.memory_banners.write().await.remove(&service_name.to_lowercase()).is_some() {
+            debug!("In-memory banner removed for {}", service_name);
+            return Ok(());
+        }
+        
+        // Neither have appended banners
+        if self.remove_managed_banner(service_name).await? {
+            return Ok(());
+        }
+        
         if let Some(original_banner) = self.original_banners.get(service_name) {
             // Build a config for the original banner
             let config = match service_name.to_lowercase().as_str() {
@@ -157,6 +221,8 @@ impl BannerManager {
                 "ftp" => self.get_ftp_config(original_banner)?,
                 "smtp" => self.get_smtp_config(original_banner)?,
                 "telnet" => self.get_telnet_config(original_banner)?,
+                "mysql" => self.get_mysql_config(original_banner)?,
+                "rdp" => self.get_rdp_config(original_banner)?,
                 _ => {
                     return Err(SkinshiftError::BannerError(
                         format!("Unknown service: {}", service_name)
@@ -218,23 +284,38 @@ impl BannerManager {
             }
             
             // Modify the content based on the pattern
-            let new_content = if let Some(pattern) = &config.pattern {
-                let re = Regex::new(pattern).map_err(|e| {
-                    SkinshiftError::BannerError(
-                        format!("Invalid regex pattern: {}", e)
-                    )
-                })?;
-                
-                if config.replace {
+            let mut appended = None;
+            let new_content = match &config.pattern {
+                Some(pattern) if config.replace => {
+                    let re = Regex::new(pattern).map_err(|e| {
+                        SkinshiftError::BannerError(
+                            format!("Invalid regex pattern: {}", e)
+                        )
+                    })?;
+                    
                     re.replace_all(&content, config.banner_text.as_str()).to_string()
-                } else {
-                    content + &config.banner_text
                 }
-            } else {
-                // No pattern, just append
-                content + &config.banner_text
+                // Tracked in the state, so applying again replaces it
+                _ => {
+                    let previous = self.managed_banners.read().await.get(config_path).cloned();
+                    if previous.as_ref().is_some_and(|banner| banner.range_in(&content).is_none()) {
+                        warn!(
+                            "The {} banner in {} was changed since it was set, appending anew",
+                            service_name, config_path
+                        );
+                    }
+                    let (new_content, banner) =
+                        place_banner(&content, previous.as_ref(), service_name, &config.banner_text);
+                    appended = Some(banner);
+                    new_content
+                }
             };
             
+            if new_content == content {
+                debug!("Banner for {} already applied", service_name);
+                return Ok(());
+            }
+            
             // Write the new content
             {
                 let mut file = File::create(path).map_err(|e| {
@@ -252,20 +333,95 @@ impl BannerManager {
                 })?;
             }
             
+            if let Some(banner) = appended {
+                let mut managed = self.managed_banners.write().await;
+                managed.insert(config_path.clone(), banner);
+                self.save_state(&managed)?;
+            }
+            
             // Update the current banner
             // self.current_banners.insert(service_name.clone(), config.banner_text.clone());
             
             debug!("Banner applied successfully for {}", service_name);
         } else {
-            warn!("No config path specified for service: {}", config.service_name);
-            // This might be a service where the banner is set in memory
-            // For now, just simulate success
-            debug!("Banner applied in-memory (simulated) for {}", config.service_name);
+            // No config file, keep the banner in memory for the honeypot listeners
+            let mut banners = self.memory_banners.write().await;
+            banners.insert(config.service_name.clone(), config.banner_text.clone());
+            
+            debug!("Banner applied in-memory for {}", config.service_name);
         }
         
         Ok(())
     }
     
+    /// Remove the banners CAMALEON appended for a service, whatever file
+    /// they were written to
+    ///
+    /// Returns whether one was removed. A banner changed since it was set is
+    /// left in place, as are banners replacing a line matched by a pattern.
+    pub async fn remove_managed_banner(&self, service_name: &str) -> Result<bool, SkinshiftError> {
+        let service = match service_name.to_lowercase().as_str() {
+            "apache" | "nginx" => "http".to_string(),
+            service => service.to_string(),
+        };
+        self.remove_managed_banners(|banner| banner.service == service).await
+    }
+    
+    /// Remove the appended banners matching `filter` and forget them
+    async fn remove_managed_banners(&self, filter: impl Fn(&ManagedBanner) -> bool) -> Result<bool, SkinshiftError> {
+        let mut managed = self.managed_banners.write().await;
+        let paths: Vec<String> = managed
+            .iter()
+            .filter(|(_, banner)| filter(banner))
+            .map(|(path, _)| path.clone())
+            .collect();
+        
+        let mut removed = false;
+        for path in paths {
+            let Some(banner) = managed.remove(&path) else {
+                continue;
+            };
+            match remove_banner_from(Path::new(&path), &banner) {
+                Ok(found) => removed |= found,
+                Err(e) => {
+                    // Kept so the next reset tries again
+                    warn!("Failed to remove the {} banner from {}: {}", banner.service, path, e);
+                    managed.insert(path, banner);
+                }
+            }
+        }
+        
+        self.save_state(&managed)?;
+        Ok(removed)
+    }
+    
+    /// Write the appended banners to the state file, if there is one
+    fn save_state(&self, managed: &HashMap<String, ManagedBanner>) -> Result<(), SkinshiftError> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        
+        if managed.is_empty() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(managed)
+            .map_err(|e| SkinshiftError::BannerError(format!("Failed to serialize banner state: {}", e)))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+    
+    /// Get the in-memory banner for a service, if one is set
+    pub async fn in_memory_banner(&self, service_name: &str) -> Option<String> {
+        self.memory_banners.read().await.get(&service_name.to_lowercase()).cloned()
+    }
+    
     /// Get SSH banner configuration
     fn get_ssh_config(&self, banner: &str) -> Result<BannerConfig, SkinshiftError> {
         let config_path = "/etc/ssh/sshd_config";
@@ -333,6 +489,170 @@ impl BannerManager {
             .with_config_path(config_path)
             .with_replace(true))
     }
+    
+    /// Get MySQL banner configuration
+    ///
+    /// The MySQL version string lives in the server greeting packet rather
+    /// than a config file, so it is kept in memory (see `mysql_greeting`).
+    fn get_mysql_config(&self, banner: &str) -> Result<BannerConfig, SkinshiftError> {
+        Ok(BannerConfig::new("mysql", banner))
+    }
+    
+    /// Get RDP banner configuration
+    ///
+    /// RDP has no text banner, it is kept in memory and used to pick the
+    /// negotiation response (see `rdp_negotiation_response`).
+    fn get_rdp_config(&self, banner: &str) -> Result<BannerConfig, SkinshiftError> {
+        Ok(BannerConfig::new("rdp", banner))
+    }
+}
+
+/// A banner appended to a config file, as recorded in the state file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ManagedBanner {
+    /// Service the banner was set for
+    service: String,
+    
+    /// Byte offset of the appended text
+    offset: usize,
+    
+    /// Length of the appended text in bytes
+    len: usize,
+    
+    /// Whether the appended text starts with a newline ending the file's
+    /// last line
+    #[serde(default)]
+    separated: bool,
+    
+    /// FNV-1a hash of the appended text
+    hash: u64,
+}
+
+impl ManagedBanner {
+    /// Where the appended text still is in `content`, unless it was changed
+    fn range_in(&self, content: &str) -> Option<Range<usize>> {
+        let range = self.offset..self.offset.checked_add(self.len)?;
+        let text = content.get(range.clone())?;
+        (fnv1a(text.as_bytes()) == self.hash).then_some(range)
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// `content` with `banner` in place of the `previous` one, or appended if
+/// it can't be found, and where the banner now is
+fn place_banner(
+    content: &str,
+    previous: Option<&ManagedBanner>,
+    service: &str,
+    banner: &str,
+) -> (String, ManagedBanner) {
+    let mut new_content = content.to_string();
+    let found = previous.and_then(|previous| previous.range_in(content).map(|range| (range, previous.separated)));
+    // Appended text goes on its own line
+    let (range, separated) =
+        found.unwrap_or_else(|| (content.len()..content.len(), !content.is_empty() && !content.ends_with('\n')));
+    
+    let text = format!("{}{}\n", if separated { "\n" } else { "" }, banner.trim_end_matches('\n'));
+    new_content.replace_range(range.clone(), &text);
+    
+    let managed = ManagedBanner {
+        service: service.to_string(),
+        offset: range.start,
+        len: text.len(),
+        separated,
+        hash: fnv1a(text.as_bytes()),
+    };
+    (new_content, managed)
+}
+
+/// Remove `banner` from the file at `path`, unless it was changed
+fn remove_banner_from(path: &Path, banner: &ManagedBanner) -> Result<bool, SkinshiftError> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    
+    let content = fs::read_to_string(path)?;
+    let Some(range) = banner.range_in(&content) else {
+        warn!("The {} banner in {} was changed since it was set, leaving it", banner.service, path.display());
+        return Ok(false);
+    };
+    
+    let mut new_content = content;
+    new_content.replace_range(range, "");
+    fs::write(path, new_content)?;
+    
+    info!("Removed managed {} banner from {}", banner.service, path.display());
+    Ok(true)
+}
+
+/// Build a MySQL protocol v10 server greeting announcing `version`
+///
+/// This is the first packet a MySQL server sends and where scanners read
+/// the version from (e.g. `5.7.33-0ubuntu0.18.04.1`).
+pub fn mysql_greeting(version: &str, connection_id: u32) -> Vec<u8> {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    
+    // Printable scramble, as real servers send
+    let mut scramble = [0u8; 20];
+    for byte in scramble.iter_mut() {
+        *byte = rng.gen_range(0x21..0x7f);
+    }
+    
+    let mut payload = Vec::new();
+    payload.push(0x0a); // Protocol version
+    payload.extend_from_slice(version.as_bytes());
+    payload.push(0);
+    payload.extend_from_slice(&connection_id.to_le_bytes());
+    payload.extend_from_slice(&scramble[..8]);
+    payload.push(0); // Filler
+    payload.extend_from_slice(&0xf7ffu16.to_le_bytes()); // Capabilities (lower), no SSL
+    payload.push(0x21); // utf8_general_ci
+    payload.extend_from_slice(&0x0002u16.to_le_bytes()); // SERVER_STATUS_AUTOCOMMIT
+    payload.extend_from_slice(&0x81ffu16.to_le_bytes()); // Capabilities (upper)
+    payload.push(21); // Auth plugin data length
+    payload.extend_from_slice(&[0; 10]); // Reserved
+    payload.extend_from_slice(&scramble[8..]);
+    payload.push(0);
+    payload.extend_from_slice(b"mysql_native_password\0");
+    
+    // Packet header: 3-byte length and sequence id 0
+    let len = payload.len() as u32;
+    let mut packet = len.to_le_bytes()[..3].to_vec();
+    packet.push(0);
+    packet.extend(payload);
+    packet
+}
+
+/// Build the X.224 Connection Confirm an RDP server sends in reply to a
+/// client's Connection Request
+///
+/// RDP has no text banner: scanners send a Connection Request (usually
+/// carrying a `Cookie: mstshash=<user>` line, which can be logged as-is)
+/// and fingerprint the server from the RDP_NEG_RSP in this reply. The
+/// `selected_protocol` it announces tells them what to expect: 0 for
+/// standard RDP security (pre-Vista hosts), 1 for TLS, 2 for CredSSP/NLA
+/// (modern Windows defaults) and 8 for RDSTLS. Answering with the value a
+/// given Windows version would pick is enough to pass nmap's `rdp-*`
+/// scripts; the handshake that follows is out of scope.
+pub fn rdp_negotiation_response(selected_protocol: u32) -> Vec<u8> {
+    let mut packet = vec![
+        0x03, 0x00, 0x00, 0x13, // TPKT header, total length 19
+        0x0e, 0xd0, // X.224 length indicator, Connection Confirm
+        0x00, 0x00, // Destination reference
+        0x12, 0x34, // Source reference
+        0x00, // Class 0
+        0x02, 0x00, // RDP_NEG_RSP, no flags
+        0x08, 0x00, // Length
+    ];
+    packet.extend_from_slice(&selected_protocol.to_le_bytes());
+    packet
 }
 
 impl Default for BannerManager {
@@ -358,4 +678,90 @@ mod tests {
         assert_eq!(config.pattern, Some(r"^Banner\s+.*$".to_string()));
         assert_eq!(config.replace, true);
     }
+    
+    #[tokio::test]
+    async fn test_in_memory_banners() {
+        let manager = BannerManager::new();
+        
+        manager.set_banner("mysql", "5.7.33-0ubuntu0.18.04.1").await.unwrap();
+        manager.set_banner("rdp", "Windows Server 2019").await.unwrap();
+        assert_eq!(manager.in_memory_banner("mysql").await.as_deref(), Some("5.7.33-0ubuntu0.18.04.1"));
+        assert_eq!(manager.in_memory_banner("rdp").await.as_deref(), Some("Windows Server 2019"));
+        
+        manager.reset_all().await.unwrap();
+        assert!(manager.in_memory_banner("mysql").await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_appended_banner_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("issue.net");
+        fs::write(&path, "Ubuntu 20.04.6 LTS").unwrap();
+        
+        let manager = BannerManager::new();
+        let config = |banner: &str| BannerConfig::new("telnet", banner).with_config_path(path.to_string_lossy());
+        
+        manager.apply_banner_config(&config("Debian GNU/Linux 9")).await.unwrap();
+        let applied = fs::read_to_string(&path).unwrap();
+        manager.apply_banner_config(&config("Debian GNU/Linux 9")).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), applied);
+        assert_eq!(applied, "Ubuntu 20.04.6 LTS\nDebian GNU/Linux 9\n");
+        
+        // Another banner takes the place of the managed one
+        manager.apply_banner_config(&config("CentOS Linux 7\n")).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Ubuntu 20.04.6 LTS\nCentOS Linux 7\n");
+        
+        // Removing it restores the file as it was
+        assert!(manager.remove_managed_banner("telnet").await.unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Ubuntu 20.04.6 LTS");
+        assert!(!manager.remove_managed_banner("telnet").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_managed_banners_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state/banners.json");
+        let custom = dir.path().join("motd");
+        let edited = dir.path().join("issue");
+        fs::write(&custom, "Welcome\n").unwrap();
+        fs::write(&edited, "").unwrap();
+        
+        let manager = BannerManager::new().with_state_path(&state);
+        for path in [&custom, &edited] {
+            let config = BannerConfig::new("telnet", "Debian GNU/Linux 9").with_config_path(path.to_string_lossy());
+            manager.apply_banner_config(&config).await.unwrap();
+        }
+        assert!(state.exists());
+        fs::write(&edited, "Debian GNU/Linux 10\n").unwrap();
+        
+        // A new manager resets files outside the default paths, but not
+        // banners changed in the meantime
+        let manager = BannerManager::new().with_state_path(&state);
+        manager.reset_all().await.unwrap();
+        assert_eq!(fs::read_to_string(&custom).unwrap(), "Welcome\n");
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "Debian GNU/Linux 10\n");
+        assert!(!state.exists());
+    }
+    
+    #[test]
+    fn test_mysql_greeting() {
+        let packet = mysql_greeting("5.7.33", 42);
+        
+        let len = u32::from_le_bytes([packet[0], packet[1], packet[2], 0]) as usize;
+        assert_eq!(len, packet.len() - 4);
+        assert_eq!(packet[3], 0);
+        assert_eq!(packet[4], 0x0a);
+        assert_eq!(&packet[5..11], b"5.7.33");
+        assert_eq!(packet[11], 0);
+        assert_eq!(&packet[12..16], &42u32.to_le_bytes());
+    }
+    
+    #[test]
+    fn test_rdp_negotiation_response() {
+        let packet = rdp_negotiation_response(2);
+        
+        assert_eq!(packet.len(), 19);
+        assert_eq!(packet[3] as usize, packet.len());
+        assert_eq!(&packet[15..], &2u32.to_le_bytes());
+    }
 }
@@ -0,0 +1,105 @@
+//! Running the system tools skinshift drives (`iptables`, `sysctl`, `nft`...)
+//!
+//! Commands run through `tokio::process` so waiting on them never blocks a
+//! runtime thread.
+
+use crate::errors::SkinshiftError;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::debug;
+
+/// Run `program` with `args` and return its standard output
+///
+/// A program that cannot be started is a `ProcessError`. A non-zero exit is
+/// reported through `on_failure`, e.g. `SkinshiftError::FirewallError`, with
+/// the command line and what the program printed on stderr.
+pub(crate) async fn run_command(
+    program: &str,
+    args: &[&str],
+    on_failure: fn(String) -> SkinshiftError,
+) -> Result<String, SkinshiftError> {
+    execute(program, args, None, on_failure).await
+}
+
+/// Run `program` with `args`, writing `input` to its standard input
+pub(crate) async fn run_command_with_input(
+    program: &str,
+    args: &[&str],
+    input: &[u8],
+    on_failure: fn(String) -> SkinshiftError,
+) -> Result<String, SkinshiftError> {
+    execute(program, args, Some(input), on_failure).await
+}
+
+/// Whether `program` runs and exits successfully
+pub(crate) async fn command_succeeds(program: &str, args: &[&str]) -> bool {
+    run_command(program, args, SkinshiftError::ProcessError).await.is_ok()
+}
+
+async fn execute(
+    program: &str,
+    args: &[&str],
+    input: Option<&[u8]>,
+    on_failure: fn(String) -> SkinshiftError,
+) -> Result<String, SkinshiftError> {
+    let command_line = std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+    debug!("Running {}", command_line);
+
+    let spawn_error = |e: std::io::Error| SkinshiftError::ProcessError(format!("Error executing {}: {}", program, e));
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(spawn_error)?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input).await.map_err(spawn_error)?;
+    }
+
+    let output = child.wait_with_output().await.map_err(spawn_error)?;
+
+    if !output.status.success() {
+        // Some tools, e.g. netsh, report errors on stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = match stderr.trim() {
+            "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(on_failure(format!("`{}` failed ({}): {}", command_line, output.status, message)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failure_surfaces_stderr() {
+        let error = run_command("sh", &["-c", "echo 'bad rule' >&2; exit 2"], SkinshiftError::FirewallError)
+            .await
+            .unwrap_err();
+        match error {
+            SkinshiftError::FirewallError(message) => {
+                assert!(message.contains("bad rule"), "{}", message);
+                assert!(message.contains("exit status: 2"), "{}", message);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let output = run_command_with_input("cat", &[], b"*filter\nCOMMIT\n", SkinshiftError::FirewallError).await;
+        assert_eq!(output.unwrap(), "*filter\nCOMMIT\n");
+
+        assert!(matches!(
+            run_command("camaleon-no-such-tool", &[], SkinshiftError::FirewallError).await,
+            Err(SkinshiftError::ProcessError(_))
+        ));
+        assert!(!command_succeeds("sh", &["-c", "exit 1"]).await);
+    }
+}
@@ -42,21 +42,26 @@ pub enum SkinshiftError {
 }
 
 /// Implement conversion from SkinshiftError to ChameleonError
+///
+/// The original error is kept so callers can downcast it back.
 impl From<SkinshiftError> for ChameleonError {
     fn from(error: SkinshiftError) -> Self {
-        match error {
-            SkinshiftError::FingerprintError(msg) => ChameleonError::SystemError(format!("Fingerprint: {}", msg)),
-            SkinshiftError::BannerError(msg) => ChameleonError::SystemError(format!("Banner: {}", msg)),
-            SkinshiftError::FirewallError(msg) => ChameleonError::SystemError(format!("Firewall: {}", msg)),
-            SkinshiftError::PresetError(msg) => ChameleonError::ConfigError(format!("Preset: {}", msg)),
-            SkinshiftError::ServiceError(msg) => ChameleonError::ServiceUnavailable(msg),
-            SkinshiftError::ConfigError(msg) => ChameleonError::ConfigError(msg),
-            SkinshiftError::IOError(e) => ChameleonError::IOError(e),
-            SkinshiftError::SerializationError(e) => ChameleonError::SerializationError(e.to_string()),
-            SkinshiftError::PermissionDenied(msg) => ChameleonError::SystemError(format!("Permission denied: {}", msg)),
-            SkinshiftError::ProcessError(msg) => ChameleonError::SystemError(format!("Process error: {}", msg)),
-            SkinshiftError::SystemError(msg) => ChameleonError::SystemError(msg),
-            SkinshiftError::NotImplemented(msg) => ChameleonError::InvalidOperation(format!("Not implemented: {}", msg)),
-        }
+        ChameleonError::module("skinshift", error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_conversion_preserves_variant() {
+        let error: ChameleonError = SkinshiftError::PermissionDenied("root required".to_string()).into();
+        
+        assert_eq!(error.to_string(), "skinshift: Permission denied: root required");
+        assert!(matches!(
+            error.downcast_ref::<SkinshiftError>(),
+            Some(SkinshiftError::PermissionDenied(_))
+        ));
     }
 }
@@ -1,8 +1,23 @@
+use crate::command::run_command;
 use crate::errors::SkinshiftError;
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(windows)]
 use std::process::Command;
-use tracing::{debug, error, info, warn};
+use std::sync::Mutex;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// nftables table used to rewrite the IP ID field
+#[cfg(not(windows))]
+const IP_ID_TABLE: &str = "camaleon";
+
+/// nftables chain used to rewrite the IP ID field
+#[cfg(not(windows))]
+const IP_ID_CHAIN: &str = "ipid";
 
 /// TCP/IP stack fingerprint properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,9 +114,11 @@ impl OSFingerprint {
     
     /// Create a minimal fingerprint with random/unpredictable properties
     pub fn minimal() -> Self {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        
+        Self::minimal_with(&mut rand::thread_rng())
+    }
+    
+    /// Create a minimal fingerprint drawing its properties from `rng`
+    pub fn minimal_with(rng: &mut impl Rng) -> Self {
         let mut fingerprint = Self::new("Unknown");
         fingerprint.ttl = Some(rng.gen_range(10..200));
         fingerprint.window_size = Some(rng.gen_range(1024..65535));
@@ -114,30 +131,78 @@ impl OSFingerprint {
     }
 }
 
+/// A fingerprint that has been applied to the system
+#[derive(Debug, Clone)]
+pub struct AppliedFingerprint {
+    /// The applied fingerprint
+    pub fingerprint: OSFingerprint,
+    
+    /// When the fingerprint was applied
+    pub applied_at: DateTime<Utc>,
+}
+
 /// Manager for handling OS fingerprint changes
 pub struct FingerprintManager {
+    /// Whether we can modify the TCP/IP stack
+    has_root: bool,
+    
     /// The original system fingerprint (before any changes)
     original_fingerprint: Option<OSFingerprint>,
     
     /// The currently active fingerprint
-    current_fingerprint: Option<OSFingerprint>,
+    current_fingerprint: RwLock<Option<AppliedFingerprint>>,
+    
+    /// Source of the random fingerprints, seeded from entropy unless configured
+    rng: Mutex<StdRng>,
 }
 
 impl FingerprintManager {
     /// Create a new fingerprint manager
     pub fn new() -> Self {
         Self {
+            has_root: Self::check_root_permissions(),
             original_fingerprint: None,
-            current_fingerprint: None,
+            current_fingerprint: RwLock::new(None),
+            rng: Mutex::new(StdRng::from_entropy()),
         }
     }
     
+    /// Draw random fingerprints from `seed`, making them reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+    
+    /// Next random fingerprint, as applied by the Unstable posture
+    pub fn random_fingerprint(&self) -> OSFingerprint {
+        let mut rng = self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        OSFingerprint::minimal_with(&mut *rng)
+    }
+    
+    /// Create a fingerprint manager that only logs changes
+    pub fn simulated() -> Self {
+        Self {
+            has_root: false,
+            ..Self::new()
+        }
+    }
+    
+    /// Whether fingerprints are applied to the TCP/IP stack rather than simulated
+    pub fn has_root(&self) -> bool {
+        self.has_root
+    }
+    
+    /// Get the currently applied fingerprint, if any
+    pub async fn current_fingerprint(&self) -> Option<AppliedFingerprint> {
+        self.current_fingerprint.read().await.clone()
+    }
+    
     /// Initialize the fingerprint manager
     pub async fn init(&self) -> Result<(), SkinshiftError> {
         info!("Initializing fingerprint manager");
         
         // Check for required permissions
-        if !Self::check_root_permissions() {
+        if !self.has_root {
             warn!("FingerprintManager requires root permissions for complete functionality");
         }
         
@@ -150,6 +215,13 @@ impl FingerprintManager {
               fingerprint.os_family, 
               fingerprint.os_version.as_deref().unwrap_or(""));
         
+        if !self.has_root {
+            warn!("Root permissions not available, simulating fingerprint application");
+            debug!("Would apply fingerprint: {:?}", fingerprint);
+            self.record_fingerprint(fingerprint).await;
+            return Ok(());
+        }
+        
         // Back up original fingerprint if this is the first change
         if self.original_fingerprint.is_none() {
             debug!("Backing up original fingerprint");
@@ -159,33 +231,42 @@ impl FingerprintManager {
         
         // Apply TTL changes if specified
         if let Some(ttl) = fingerprint.ttl {
-            self.set_ip_ttl(ttl).await?;
+            Self::skip_unsupported(self.set_ip_ttl(ttl).await)?;
         }
         
         // Apply MSS changes if specified
         if let Some(mss) = fingerprint.mss {
-            self.set_tcp_mss(mss).await?;
+            Self::skip_unsupported(self.set_tcp_mss(mss).await)?;
         }
         
         // Apply window size changes if specified
         if let Some(window_size) = fingerprint.window_size {
-            self.set_tcp_window_size(window_size).await?;
+            Self::skip_unsupported(self.set_tcp_window_size(window_size).await)?;
         }
         
         // Apply window scaling if specified
         if let Some(scaling) = fingerprint.window_scaling {
-            self.set_tcp_window_scaling(scaling).await?;
+            Self::skip_unsupported(self.set_tcp_window_scaling(scaling).await)?;
         }
         
         // Apply timestamp behavior if specified
         if let Some(use_timestamps) = fingerprint.timestamps {
-            self.set_tcp_timestamps(use_timestamps).await?;
+            Self::skip_unsupported(self.set_tcp_timestamps(use_timestamps).await)?;
+        }
+        
+        // Apply IP ID sequence behavior if specified
+        if let Some(behavior) = &fingerprint.ip_id_behavior {
+            Self::skip_unsupported(self.set_ip_id_behavior(behavior).await)?;
+        }
+        
+        // Apply DF bit behavior if specified
+        if let Some(df_bit) = fingerprint.df_bit {
+            Self::skip_unsupported(self.set_df_bit(df_bit).await)?;
         }
         
         info!("Fingerprint applied successfully");
         
-        // In a real implementation, we'd store the current fingerprint
-        // self.current_fingerprint = Some(fingerprint.clone());
+        self.record_fingerprint(fingerprint).await;
         
         Ok(())
     }
@@ -203,36 +284,87 @@ impl FingerprintManager {
             self.apply_system_defaults().await?;
         }
         
+        *self.current_fingerprint.write().await = None;
+        
+        Ok(())
+    }
+    
+    /// Remember the fingerprint that is now active
+    async fn record_fingerprint(&self, fingerprint: &OSFingerprint) {
+        let mut current = self.current_fingerprint.write().await;
+        *current = Some(AppliedFingerprint {
+            fingerprint: fingerprint.clone(),
+            applied_at: Utc::now(),
+        });
+    }
+    
+    /// Apply system defaults for fingerprint
+    async fn apply_system_defaults(&self) -> Result<(), SkinshiftError> {
+        debug!("Applying system defaults for fingerprint");
+        
+        if !self.has_root {
+            debug!("System defaults applied (simulated)");
+            return Ok(());
+        }
+        
+        // Drop the IP ID rewriting table, the kernel then uses its own IDs again
+        #[cfg(not(windows))]
+        if let Err(e) = Self::run_nft(&["delete", "table", "ip", IP_ID_TABLE]).await {
+            debug!("No IP ID rules to remove: {}", e);
+        }
+        
+        // This would reset all TCP/IP stack parameters to system defaults
+        // For now, just simulate success
+        debug!("System defaults applied (simulated)");
+        
         Ok(())
     }
     
+    /// Check if we have root permissions
+    fn check_root_permissions() -> bool {
+        #[cfg(target_family = "unix")]
+        {
+            unsafe { libc::geteuid() == 0 }
+        }
+        
+        #[cfg(windows)]
+        {
+            // `net session` only succeeds from an elevated token
+            Command::new("net")
+                .arg("session")
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        }
+        
+        #[cfg(not(any(target_family = "unix", windows)))]
+        {
+            warn!("Root permission check not implemented for this platform");
+            false
+        }
+    }
+    
+    /// Treat parameters the platform cannot change as a warning rather than a failure
+    fn skip_unsupported(result: Result<(), SkinshiftError>) -> Result<(), SkinshiftError> {
+        match result {
+            Err(SkinshiftError::NotImplemented(msg)) => {
+                warn!("Skipping unsupported fingerprint parameter: {}", msg);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(not(windows))]
+impl FingerprintManager {
     /// Set the IP TTL value
     async fn set_ip_ttl(&self, ttl: u8) -> Result<(), SkinshiftError> {
         debug!("Setting IP TTL to {}", ttl);
         
         // On Linux, this would be done with sysctl
-        let output = Command::new("sysctl")
-            .args(&["-w", &format!("net.ipv4.ip_default_ttl={}", ttl)])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to set TTL: {}", error);
-                    return Err(SkinshiftError::FingerprintError(
-                        format!("Failed to set TTL: {}", error)
-                    ));
-                }
-                debug!("TTL set successfully");
-            }
-            Err(e) => {
-                error!("Error executing sysctl: {}", e);
-                return Err(SkinshiftError::ProcessError(
-                    format!("Error executing sysctl: {}", e)
-                ));
-            }
-        }
+        run_command("sysctl", &["-w", &format!("net.ipv4.ip_default_ttl={}", ttl)], SkinshiftError::FingerprintError).await?;
+        debug!("TTL set successfully");
         
         Ok(())
     }
@@ -253,28 +385,8 @@ impl FingerprintManager {
         debug!("Setting TCP window size to {}", size);
         
         // This would be done with sysctl on Linux
-        let output = Command::new("sysctl")
-            .args(&["-w", &format!("net.ipv4.tcp_rmem=\"4096 {} {}\"", size, size * 2)])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to set window size: {}", error);
-                    return Err(SkinshiftError::FingerprintError(
-                        format!("Failed to set window size: {}", error)
-                    ));
-                }
-                debug!("Window size set successfully");
-            }
-            Err(e) => {
-                error!("Error executing sysctl: {}", e);
-                return Err(SkinshiftError::ProcessError(
-                    format!("Error executing sysctl: {}", e)
-                ));
-            }
-        }
+        run_command("sysctl", &["-w", &format!("net.ipv4.tcp_rmem=\"4096 {} {}\"", size, size * 2)], SkinshiftError::FingerprintError).await?;
+        debug!("Window size set successfully");
         
         Ok(())
     }
@@ -285,28 +397,8 @@ impl FingerprintManager {
         
         let enable = if scaling > 0 { "1" } else { "0" };
         
-        let output = Command::new("sysctl")
-            .args(&["-w", &format!("net.ipv4.tcp_window_scaling={}", enable)])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to set window scaling: {}", error);
-                    return Err(SkinshiftError::FingerprintError(
-                        format!("Failed to set window scaling: {}", error)
-                    ));
-                }
-                debug!("Window scaling set successfully");
-            }
-            Err(e) => {
-                error!("Error executing sysctl: {}", e);
-                return Err(SkinshiftError::ProcessError(
-                    format!("Error executing sysctl: {}", e)
-                ));
-            }
-        }
+        run_command("sysctl", &["-w", &format!("net.ipv4.tcp_window_scaling={}", enable)], SkinshiftError::FingerprintError).await?;
+        debug!("Window scaling set successfully");
         
         Ok(())
     }
@@ -317,55 +409,127 @@ impl FingerprintManager {
         
         let value = if enabled { "1" } else { "0" };
         
-        let output = Command::new("sysctl")
-            .args(&["-w", &format!("net.ipv4.tcp_timestamps={}", value)])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to set TCP timestamps: {}", error);
-                    return Err(SkinshiftError::FingerprintError(
-                        format!("Failed to set TCP timestamps: {}", error)
-                    ));
-                }
-                debug!("TCP timestamps set successfully");
-            }
-            Err(e) => {
-                error!("Error executing sysctl: {}", e);
-                return Err(SkinshiftError::ProcessError(
-                    format!("Error executing sysctl: {}", e)
+        run_command("sysctl", &["-w", &format!("net.ipv4.tcp_timestamps={}", value)], SkinshiftError::FingerprintError).await?;
+        debug!("TCP timestamps set successfully");
+        
+        Ok(())
+    }
+    
+    /// Set the IP ID sequence behavior (zero, random or incremental)
+    ///
+    /// Linux has no sysctl for this, so the ID field of outgoing packets is
+    /// rewritten by an nftables rule in a dedicated `camaleon` table.
+    /// Requires root (CAP_NET_ADMIN) and the `nft` binary.
+    async fn set_ip_id_behavior(&self, behavior: &str) -> Result<(), SkinshiftError> {
+        debug!("Setting IP ID behavior to {}", behavior);
+        
+        let expression: &[&str] = match behavior.to_lowercase().as_str() {
+            "zero" => &["0"],
+            "random" => &["numgen", "random", "mod", "65536"],
+            "incremental" => &["numgen", "inc", "mod", "65536"],
+            other => {
+                return Err(SkinshiftError::FingerprintError(
+                    format!("Unsupported IP ID behavior: {}", other)
                 ));
             }
-        }
+        };
+        
+        // Create our table and output chain (no-ops if they already exist)
+        Self::run_nft(&["add", "table", "ip", IP_ID_TABLE]).await?;
+        Self::run_nft(&[
+            "add", "chain", "ip", IP_ID_TABLE, IP_ID_CHAIN,
+            "{ type filter hook output priority -150 ; }",
+        ]).await?;
+        
+        // Replace any previous IP ID rule
+        Self::run_nft(&["flush", "chain", "ip", IP_ID_TABLE, IP_ID_CHAIN]).await?;
+        
+        let mut args = vec!["add", "rule", "ip", IP_ID_TABLE, IP_ID_CHAIN, "ip", "id", "set"];
+        args.extend_from_slice(expression);
+        Self::run_nft(&args).await?;
+        
+        debug!("IP ID behavior set successfully");
         
         Ok(())
     }
     
-    /// Apply system defaults for fingerprint
-    async fn apply_system_defaults(&self) -> Result<(), SkinshiftError> {
-        debug!("Applying system defaults for fingerprint");
+    /// Set whether outgoing packets carry the DF (Don't Fragment) bit
+    ///
+    /// Controlled through `net.ipv4.ip_no_pmtu_disc`: path MTU discovery sets
+    /// DF on outgoing packets, disabling it clears the bit. Requires root.
+    async fn set_df_bit(&self, enabled: bool) -> Result<(), SkinshiftError> {
+        debug!("Setting DF bit to {}", enabled);
         
-        // This would reset all TCP/IP stack parameters to system defaults
-        // For now, just simulate success
-        debug!("System defaults applied (simulated)");
+        let value = if enabled { "0" } else { "1" };
+        
+        run_command("sysctl", &["-w", &format!("net.ipv4.ip_no_pmtu_disc={}", value)], SkinshiftError::FingerprintError).await?;
+        debug!("DF bit set successfully");
         
         Ok(())
     }
     
-    /// Check if we have root permissions
-    fn check_root_permissions() -> bool {
-        #[cfg(target_family = "unix")]
-        {
-            unsafe { libc::geteuid() == 0 }
-        }
+    /// Run an nft command
+    async fn run_nft(args: &[&str]) -> Result<(), SkinshiftError> {
+        run_command("nft", args, SkinshiftError::FingerprintError).await?;
         
-        #[cfg(not(target_family = "unix"))]
-        {
-            warn!("Root permission check not implemented for this platform");
-            false
-        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl FingerprintManager {
+    /// Set the default IP TTL (hop limit)
+    async fn set_ip_ttl(&self, ttl: u8) -> Result<(), SkinshiftError> {
+        debug!("Setting IP TTL to {}", ttl);
+        
+        Self::run_netsh(&["int", "ipv4", "set", "global", &format!("defaultcurhoplimit={}", ttl)], "TTL").await
+    }
+    
+    /// Set the TCP MSS value (no Windows equivalent)
+    async fn set_tcp_mss(&self, _mss: u16) -> Result<(), SkinshiftError> {
+        Err(SkinshiftError::NotImplemented("TCP MSS cannot be set on Windows".to_string()))
+    }
+    
+    /// Set the TCP window size (no Windows equivalent)
+    async fn set_tcp_window_size(&self, _size: u32) -> Result<(), SkinshiftError> {
+        Err(SkinshiftError::NotImplemented("TCP window size cannot be set on Windows".to_string()))
+    }
+    
+    /// Enable or disable window scaling through receive window auto-tuning
+    async fn set_tcp_window_scaling(&self, scaling: u8) -> Result<(), SkinshiftError> {
+        debug!("Setting TCP window scaling to {}", scaling);
+        
+        let level = if scaling > 0 { "normal" } else { "disabled" };
+        
+        Self::run_netsh(&["int", "tcp", "set", "global", &format!("autotuninglevel={}", level)], "window scaling").await
+    }
+    
+    /// Set whether to use TCP timestamps
+    async fn set_tcp_timestamps(&self, enabled: bool) -> Result<(), SkinshiftError> {
+        debug!("Setting TCP timestamps to {}", enabled);
+        
+        let value = if enabled { "enabled" } else { "disabled" };
+        
+        Self::run_netsh(&["int", "tcp", "set", "global", &format!("timestamps={}", value)], "TCP timestamps").await
+    }
+    
+    /// Set the IP ID sequence behavior (no Windows equivalent)
+    async fn set_ip_id_behavior(&self, _behavior: &str) -> Result<(), SkinshiftError> {
+        Err(SkinshiftError::NotImplemented("IP ID behavior cannot be set on Windows".to_string()))
+    }
+    
+    /// Set the DF bit behavior (no Windows equivalent)
+    async fn set_df_bit(&self, _enabled: bool) -> Result<(), SkinshiftError> {
+        Err(SkinshiftError::NotImplemented("DF bit cannot be set on Windows".to_string()))
+    }
+    
+    /// Run a netsh command, requires an elevated token
+    async fn run_netsh(args: &[&str], what: &str) -> Result<(), SkinshiftError> {
+        run_command("netsh", args, SkinshiftError::FingerprintError).await?;
+        
+        debug!("{} set successfully", what);
+        
+        Ok(())
     }
 }
 
@@ -374,3 +538,24 @@ impl Default for FingerprintManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_seeded_fingerprints_are_reproducible() {
+        let fingerprints = |manager: FingerprintManager| {
+            (0..3)
+                .map(|_| serde_json::to_value(manager.random_fingerprint()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        
+        let first = fingerprints(FingerprintManager::simulated().with_seed(42));
+        assert_eq!(first, fingerprints(FingerprintManager::simulated().with_seed(42)));
+        assert_ne!(first, fingerprints(FingerprintManager::simulated().with_seed(43)));
+        
+        // Each draw moves the sequence on
+        assert_ne!(first[0], first[1]);
+    }
+}
@@ -1,8 +1,10 @@
+use crate::command::{command_succeeds, run_command, run_command_with_input};
 use crate::errors::SkinshiftError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Command;
-use tracing::{debug, error, info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
 
 /// Firewall rule configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -155,18 +157,33 @@ pub struct FirewallManager {
     /// Original firewall rules (for restoration)
     original_rules: Vec<String>,
     
+    /// File the original rules are persisted to
+    backup_path: PathBuf,
+    
     /// Currently active custom rules
     active_rules: Vec<FirewallRule>,
 }
 
 impl FirewallManager {
     /// Create a new firewall manager
-    pub async fn new() -> Result<Self, SkinshiftError> {
+    ///
+    /// The current ruleset is written to `backup_path` so it survives a crash.
+    /// If a CAMALEON chain is still present from an unclean shutdown, it is
+    /// cleaned up first: when `restore_leftover` is set and a backup exists,
+    /// the saved ruleset is restored, otherwise the chain is simply removed.
+    /// With `dry_run` set, rules are only logged and the system is left untouched.
+    pub async fn new(
+        backup_path: impl Into<PathBuf>,
+        restore_leftover: bool,
+        dry_run: bool,
+    ) -> Result<Self, SkinshiftError> {
+        let backup_path = backup_path.into();
+        
         // Check for iptables
-        let has_iptables = Self::check_iptables();
+        let has_iptables = Self::check_iptables().await;
         
         // Check for superuser privileges
-        let has_superuser = Self::check_superuser();
+        let has_superuser = !dry_run && Self::check_superuser();
         
         if !has_iptables {
             warn!("iptables not found, firewall functionality will be limited");
@@ -177,7 +194,14 @@ impl FirewallManager {
         }
         
         let original_rules = if has_iptables && has_superuser {
-            Self::backup_rules()?
+            // Clean up after a previous run that did not shut down properly
+            if Self::camaleon_chain_exists().await {
+                Self::recover_leftover_chain(&backup_path, restore_leftover).await?;
+            }
+            
+            let rules = Self::backup_rules().await?;
+            Self::save_backup(&backup_path, &rules)?;
+            rules
         } else {
             Vec::new()
         };
@@ -186,10 +210,44 @@ impl FirewallManager {
             has_iptables,
             has_superuser,
             original_rules,
+            backup_path,
             active_rules: Vec::new(),
         })
     }
     
+    /// Why rules are only logged instead of applied, if they are
+    pub fn simulation_reason(&self) -> Option<&'static str> {
+        if !self.has_iptables {
+            Some("iptables not found")
+        } else if !self.has_superuser {
+            Some("requires root")
+        } else {
+            None
+        }
+    }
+    
+    /// Path of the persisted firewall backup
+    pub fn backup_path(&self) -> &Path {
+        &self.backup_path
+    }
+    
+    /// Restore the firewall from a backup file written by `iptables-save`
+    pub async fn restore_from_backup(&self, path: impl AsRef<Path>) -> Result<(), SkinshiftError> {
+        let path = path.as_ref();
+        info!("Restoring firewall rules from backup: {}", path.display());
+        
+        if !self.has_iptables || !self.has_superuser {
+            warn!("Firewall functionality limited, simulating backup restoration");
+            return Ok(());
+        }
+        
+        Self::restore_rules(path).await?;
+        
+        info!("Firewall rules restored successfully");
+        
+        Ok(())
+    }
+    
     /// Apply a set of firewall rules
     pub async fn apply_rules(&self, rules: &[FirewallRule]) -> Result<(), SkinshiftError> {
         info!("Applying {} firewall rules", rules.len());
@@ -203,33 +261,22 @@ impl FirewallManager {
         }
         
         // Add CAMALEON chain if it doesn't exist
-        self.ensure_camaleon_chain()?;
+        self.ensure_camaleon_chain().await?;
         
         // Apply each rule
         for rule in rules {
             debug!("Applying rule: {:?}", rule);
             
             let args = rule.to_iptables_args();
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
             
-            let output = Command::new("iptables")
-                .args(&args)
-                .output();
-                
-            match output {
-                Ok(output) => {
-                    if !output.status.success() {
-                        let error = String::from_utf8_lossy(&output.stderr);
-                        warn!("Failed to apply rule '{}': {}", rule.name, error);
-                        // Continue with other rules
-                    } else {
-                        debug!("Rule applied successfully: {}", rule.name);
-                    }
-                }
+            match run_command("iptables", &args, SkinshiftError::FirewallError).await {
+                Ok(_) => debug!("Rule applied successfully: {}", rule.name),
+                // iptables could not be run at all, the other rules would fail too
+                Err(e @ SkinshiftError::ProcessError(_)) => return Err(e),
                 Err(e) => {
-                    error!("Error executing iptables: {}", e);
-                    return Err(SkinshiftError::FirewallError(
-                        format!("Error executing iptables: {}", e)
-                    ));
+                    warn!("Failed to apply rule '{}': {}", rule.name, e);
+                    // Continue with other rules
                 }
             }
         }
@@ -249,7 +296,7 @@ impl FirewallManager {
         }
         
         // Clear CAMALEON-specific rules
-        self.clear_camaleon_rules()?;
+        self.clear_camaleon_rules().await?;
         
         info!("Firewall rules reset successfully");
         
@@ -257,15 +304,8 @@ impl FirewallManager {
     }
     
     /// Check if iptables is available
-    fn check_iptables() -> bool {
-        let output = Command::new("which")
-            .arg("iptables")
-            .output();
-            
-        match output {
-            Ok(output) => output.status.success(),
-            Err(_) => false,
-        }
+    async fn check_iptables() -> bool {
+        command_succeeds("which", &["iptables"]).await
     }
     
     /// Check if we have superuser privileges
@@ -282,23 +322,12 @@ impl FirewallManager {
     }
     
     /// Backup current firewall rules
-    fn backup_rules() -> Result<Vec<String>, SkinshiftError> {
+    async fn backup_rules() -> Result<Vec<String>, SkinshiftError> {
         debug!("Backing up current firewall rules");
         
-        let output = Command::new("iptables-save")
-            .output()
-            .map_err(|e| {
-                SkinshiftError::FirewallError(format!("Failed to backup rules: {}", e))
-            })?;
-            
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(SkinshiftError::FirewallError(
-                format!("Failed to backup rules: {}", error)
-            ));
-        }
+        let output = run_command("iptables-save", &[], SkinshiftError::FirewallError).await?;
         
-        let rules = String::from_utf8_lossy(&output.stdout)
+        let rules = output
             .lines()
             .map(String::from)
             .collect();
@@ -306,54 +335,81 @@ impl FirewallManager {
         Ok(rules)
     }
     
+    /// Write backed up rules to disk
+    fn save_backup(path: &Path, rules: &[String]) -> Result<(), SkinshiftError> {
+        debug!("Writing firewall backup to {}", path.display());
+        
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    SkinshiftError::FirewallError(format!("Failed to create backup directory: {}", e))
+                })?;
+            }
+        }
+        
+        let mut content = rules.join("\n");
+        content.push('\n');
+        
+        fs::write(path, content).map_err(|e| {
+            SkinshiftError::FirewallError(format!("Failed to write firewall backup: {}", e))
+        })
+    }
+    
+    /// Feed a saved ruleset to iptables-restore
+    async fn restore_rules(path: &Path) -> Result<(), SkinshiftError> {
+        let content = fs::read(path).map_err(|e| {
+            SkinshiftError::FirewallError(format!("Failed to read firewall backup: {}", e))
+        })?;
+        
+        run_command_with_input("iptables-restore", &[], &content, SkinshiftError::FirewallError).await?;
+        
+        Ok(())
+    }
+    
+    /// Check whether the CAMALEON chain is present
+    async fn camaleon_chain_exists() -> bool {
+        command_succeeds("iptables", &["-L", "CAMALEON", "-n"]).await
+    }
+    
+    /// Deal with a CAMALEON chain left behind by an unclean shutdown
+    async fn recover_leftover_chain(backup_path: &Path, restore: bool) -> Result<(), SkinshiftError> {
+        warn!("Found leftover CAMALEON chain from a previous run");
+        
+        if backup_path.exists() {
+            if restore {
+                info!("Restoring firewall rules from {}", backup_path.display());
+                return Self::restore_rules(backup_path).await;
+            }
+            
+            warn!(
+                "A firewall backup is available at {}, call restore_from_backup to restore it",
+                backup_path.display()
+            );
+        } else {
+            warn!("No firewall backup found at {}", backup_path.display());
+        }
+        
+        // Without a restore, at least drop the stale chain
+        Self::clear_chain().await;
+        
+        Ok(())
+    }
+    
     /// Ensure the CAMALEON chain exists
-    fn ensure_camaleon_chain(&self) -> Result<(), SkinshiftError> {
+    async fn ensure_camaleon_chain(&self) -> Result<(), SkinshiftError> {
         debug!("Ensuring CAMALEON chain exists");
         
         // Check if the chain already exists
-        let output = Command::new("iptables")
-            .args(&["-L", "CAMALEON"])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if output.status.success() {
-                    debug!("CAMALEON chain already exists");
-                    return Ok(());
-                }
-            }
-            Err(_) => {}
+        if command_succeeds("iptables", &["-L", "CAMALEON"]).await {
+            debug!("CAMALEON chain already exists");
+            return Ok(());
         }
         
         // Create the chain
-        let output = Command::new("iptables")
-            .args(&["-N", "CAMALEON"])
-            .output()
-            .map_err(|e| {
-                SkinshiftError::FirewallError(format!("Failed to create CAMALEON chain: {}", e))
-            })?;
-            
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(SkinshiftError::FirewallError(
-                format!("Failed to create CAMALEON chain: {}", error)
-            ));
-        }
+        run_command("iptables", &["-N", "CAMALEON"], SkinshiftError::FirewallError).await?;
         
         // Add a jump to the CAMALEON chain from INPUT
-        let output = Command::new("iptables")
-            .args(&["-I", "INPUT", "1", "-j", "CAMALEON"])
-            .output()
-            .map_err(|e| {
-                SkinshiftError::FirewallError(format!("Failed to add jump to CAMALEON chain: {}", e))
-            })?;
-            
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(SkinshiftError::FirewallError(
-                format!("Failed to add jump to CAMALEON chain: {}", error)
-            ));
-        }
+        run_command("iptables", &["-I", "INPUT", "1", "-j", "CAMALEON"], SkinshiftError::FirewallError).await?;
         
         debug!("CAMALEON chain created successfully");
         
@@ -361,69 +417,30 @@ impl FirewallManager {
     }
     
     /// Clear CAMALEON-specific rules
-    fn clear_camaleon_rules(&self) -> Result<(), SkinshiftError> {
-        debug!("Clearing CAMALEON-specific firewall rules");
+    async fn clear_camaleon_rules(&self) -> Result<(), SkinshiftError> {
+        Self::clear_chain().await;
         
-        // Flush the CAMALEON chain
-        let output = Command::new("iptables")
-            .args(&["-F", "CAMALEON"])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to flush CAMALEON chain: {}", error);
-                    // Continue anyway
-                }
-            }
-            Err(e) => {
-                warn!("Error flushing CAMALEON chain: {}", e);
-                // Continue anyway
-            }
-        }
+        Ok(())
+    }
+    
+    /// Flush and delete the CAMALEON chain
+    async fn clear_chain() {
+        debug!("Clearing CAMALEON-specific firewall rules");
         
-        // Remove the jump to the CAMALEON chain
-        let output = Command::new("iptables")
-            .args(&["-D", "INPUT", "-j", "CAMALEON"])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to remove jump to CAMALEON chain: {}", error);
-                    // Continue anyway
-                }
-            }
-            Err(e) => {
-                warn!("Error removing jump to CAMALEON chain: {}", e);
-                // Continue anyway
-            }
-        }
+        let steps: [(&str, &[&str]); 3] = [
+            ("flush CAMALEON chain", &["-F", "CAMALEON"]),
+            ("remove jump to CAMALEON chain", &["-D", "INPUT", "-j", "CAMALEON"]),
+            ("delete CAMALEON chain", &["-X", "CAMALEON"]),
+        ];
         
-        // Delete the CAMALEON chain
-        let output = Command::new("iptables")
-            .args(&["-X", "CAMALEON"])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to delete CAMALEON chain: {}", error);
-                    // Continue anyway
-                }
-            }
-            Err(e) => {
-                warn!("Error deleting CAMALEON chain: {}", e);
+        for (what, args) in steps {
+            if let Err(e) = run_command("iptables", args, SkinshiftError::FirewallError).await {
+                warn!("Failed to {}: {}", what, e);
                 // Continue anyway
             }
         }
         
         debug!("CAMALEON-specific firewall rules cleared");
-        
-        Ok(())
     }
 }
 
@@ -461,4 +478,16 @@ mod tests {
         assert!(args.contains(&"-j".to_string()));
         assert!(args.contains(&"ACCEPT".to_string()));
     }
+    
+    #[test]
+    fn test_save_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("iptables.backup");
+        let rules = vec!["*filter".to_string(), "COMMIT".to_string()];
+        
+        FirewallManager::save_backup(&path, &rules).unwrap();
+        
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "*filter\nCOMMIT\n");
+    }
 }
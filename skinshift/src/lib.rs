@@ -1,22 +1,77 @@
 mod banner;
+mod command;
 mod errors;
 mod fingerprint;
 mod firewall;
+pub mod mimetic;
+mod nmap;
 mod preset;
 mod service;
 
 use async_trait::async_trait;
 use banner::BannerManager;
-use chame_core::{ChameleonError, ChameleonService, Event, Posture, SystemState};
+pub use banner::{mysql_greeting, rdp_negotiation_response};
+pub use fingerprint::OSFingerprint;
+pub use firewall::FirewallRule;
+pub use preset::FingerprintPreset;
+use chame_core::state::FingerprintInfo;
+use chame_core::{
+    ChameleonError, ChameleonService, Event, EventSender, Feature, FeatureStatus, ModuleHealth, Posture, SystemState,
+};
 use errors::SkinshiftError;
 use fingerprint::FingerprintManager;
 use firewall::FirewallManager;
-use preset::PresetManager;
+use mimetic::{ObservedProbe, DEFAULT_MIMETIC_PRESET, MAX_OBSERVED_PROBES};
+use preset::{PresetManager, RANDOM_PRESET};
 use service::ServiceManager;
-use std::path::Path;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use std::time::Duration;
+use notify::RecommendedWatcher;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Configuration for the Skinshift service
+#[derive(Debug, Clone)]
+pub struct SkinshiftConfig {
+    /// Directory holding fingerprint presets
+    pub presets_dir: String,
+    
+    /// File the original firewall ruleset is persisted to
+    pub firewall_backup_path: PathBuf,
+    
+    /// Whether to restore the saved ruleset when a stale CAMALEON chain is found
+    pub restore_leftover_rules: bool,
+    
+    /// Only log fingerprint and firewall changes instead of applying them
+    pub dry_run: bool,
+    
+    /// Reload presets automatically when their files change
+    pub watch_presets: bool,
+    
+    /// Seed of the random fingerprints, for reproducible Unstable postures
+    pub random_seed: Option<u64>,
+    
+    /// How often the Unstable posture draws a new fingerprint, never when unset
+    pub random_rotation_interval: Option<Duration>,
+}
+
+impl Default for SkinshiftConfig {
+    fn default() -> Self {
+        Self {
+            presets_dir: "./presets".to_string(),
+            firewall_backup_path: PathBuf::from("/var/lib/camaleon/iptables.backup"),
+            restore_leftover_rules: true,
+            dry_run: false,
+            watch_presets: false,
+            random_seed: None,
+            random_rotation_interval: None,
+        }
+    }
+}
 
 /// Main Skinshift service for OS fingerprint and banner morphing
 pub struct SkinshiftService {
@@ -38,20 +93,54 @@ pub struct SkinshiftService {
     /// Current posture
     current_posture: Arc<RwLock<Posture>>,
     
-    /// Configuration directory
-    config_dir: String,
+    /// Name of the last applied preset
+    current_preset: Arc<RwLock<Option<String>>>,
+    
+    /// Recent network probes, for choosing the Mimetic preset
+    observed_probes: RwLock<VecDeque<ObservedProbe>>,
+    
+    /// Watcher reloading presets on change, kept alive while set
+    preset_watcher: Mutex<Option<RecommendedWatcher>>,
+    
+    /// Whether the service was started and not stopped since
+    running: AtomicBool,
+    
+    /// Task drawing new fingerprints while the random preset is applied
+    rotation: Mutex<Option<JoinHandle<()>>>,
+    
+    /// Where fingerprint changes are announced, if anywhere
+    events: Option<EventSender>,
+    
+    /// Service configuration
+    config: SkinshiftConfig,
 }
 
 impl SkinshiftService {
     /// Create a new Skinshift service
-    pub async fn new(config_dir: impl Into<String>) -> Result<Self, SkinshiftError> {
-        let config_dir = config_dir.into();
-        
+    pub async fn new(config: SkinshiftConfig) -> Result<Self, SkinshiftError> {
         // Initialize components
-        let fingerprint_manager = Arc::new(FingerprintManager::new());
-        let banner_manager = Arc::new(BannerManager::new());
-        let firewall_manager = Arc::new(FirewallManager::new().await?);
-        let preset_manager = Arc::new(PresetManager::new(&config_dir));
+        let fingerprint_manager = if config.dry_run {
+            FingerprintManager::simulated()
+        } else {
+            FingerprintManager::new()
+        };
+        let fingerprint_manager = Arc::new(match config.random_seed {
+            Some(seed) => fingerprint_manager.with_seed(seed),
+            None => fingerprint_manager,
+        });
+        // Appended banners are tracked next to the firewall backup
+        let banner_manager = Arc::new(
+            BannerManager::new().with_state_path(config.firewall_backup_path.with_file_name("banners.json")),
+        );
+        let firewall_manager = Arc::new(
+            FirewallManager::new(
+                &config.firewall_backup_path,
+                config.restore_leftover_rules,
+                config.dry_run,
+            )
+            .await?,
+        );
+        let preset_manager = Arc::new(PresetManager::new(&config.presets_dir));
         let service_manager = Arc::new(ServiceManager::new());
         
         Ok(Self {
@@ -61,7 +150,13 @@ impl SkinshiftService {
             preset_manager,
             service_manager,
             current_posture: Arc::new(RwLock::new(Posture::Neutral)),
-            config_dir,
+            current_preset: Arc::new(RwLock::new(None)),
+            observed_probes: RwLock::new(VecDeque::new()),
+            preset_watcher: Mutex::new(None),
+            rotation: Mutex::new(None),
+            events: None,
+            running: AtomicBool::new(false),
+            config,
         })
     }
     
@@ -72,8 +167,13 @@ impl SkinshiftService {
         // Load the preset
         let preset = self.preset_manager.load_preset(preset_name).await?;
         
-        // Apply OS fingerprint settings
-        self.fingerprint_manager.apply_fingerprint(&preset.fingerprint).await?;
+        // Apply OS fingerprint settings, drawing a new one each time for the random preset
+        if preset.name == RANDOM_PRESET {
+            let fingerprint = self.fingerprint_manager.random_fingerprint();
+            self.fingerprint_manager.apply_fingerprint(&fingerprint).await?;
+        } else {
+            self.fingerprint_manager.apply_fingerprint(&preset.fingerprint).await?;
+        }
         
         // Apply banner changes
         for (service_name, banner) in &preset.banners {
@@ -90,6 +190,8 @@ impl SkinshiftService {
             self.service_manager.configure_service(service_name, config).await?;
         }
         
+        *self.current_preset.write().await = Some(preset.name.clone());
+        
         // Register the change event
         info!("Successfully applied preset: {}", preset_name);
         
@@ -105,6 +207,7 @@ impl SkinshiftService {
         
         // Apply OS fingerprint
         self.fingerprint_manager.apply_fingerprint(&custom_config.fingerprint).await?;
+        *self.current_preset.write().await = Some(custom_config.name.clone());
         
         // Apply other settings
         // (Similar to load_preset, but from custom config)
@@ -130,15 +233,188 @@ impl SkinshiftService {
         // Reset service configurations
         self.service_manager.reset_all().await?;
         
+        *self.current_preset.write().await = None;
+        
         info!("Successfully reset system fingerprint");
         
         Ok(())
     }
     
+    /// Restore the firewall from a saved `iptables-save` backup
+    pub async fn restore_firewall_backup(&self, path: Option<&Path>) -> Result<(), SkinshiftError> {
+        let path = path.unwrap_or(self.firewall_manager.backup_path());
+        self.firewall_manager.restore_from_backup(path).await
+    }
+    
     /// List available presets
     pub async fn list_presets(&self) -> Result<Vec<String>, SkinshiftError> {
         self.preset_manager.list_presets().await
     }
+    
+    /// Get a preset without applying it
+    pub async fn get_preset(&self, preset_name: &str) -> Result<FingerprintPreset, SkinshiftError> {
+        self.preset_manager.load_preset(preset_name).await
+    }
+    
+    /// Get the in-memory banner a honeypot listener should serve for a service
+    pub async fn in_memory_banner(&self, service_name: &str) -> Option<String> {
+        self.banner_manager.in_memory_banner(service_name).await
+    }
+    
+    /// Restore the banner of a single service, removing the one appended
+    /// to its config file if any
+    pub async fn reset_banner(&self, service_name: &str) -> Result<(), SkinshiftError> {
+        self.banner_manager.reset_banner(service_name).await
+    }
+    
+    /// Ports of the services the applied presets configured
+    pub fn service_ports(&self) -> Vec<u16> {
+        self.service_manager.registered_ports()
+    }
+    
+    /// Import the fingerprints of an nmap `os-db` file as presets
+    pub async fn import_nmap_db(&self, path: &Path) -> Result<Vec<String>, SkinshiftError> {
+        self.preset_manager.import_nmap_db(path).await
+    }
+    
+    /// Re-read presets from disk, keeping the last good version of broken files
+    pub async fn reload_presets(&self) -> Result<usize, SkinshiftError> {
+        self.preset_manager.reload().await
+    }
+    
+    /// How the firewall and fingerprint changes are carried out
+    pub fn features(&self) -> Vec<Feature> {
+        let status = |reason: Option<&str>| match (self.config.dry_run, reason) {
+            (true, _) => FeatureStatus::Simulated("dry_run is set".to_string()),
+            (false, Some(reason)) => FeatureStatus::Simulated(reason.to_string()),
+            (false, None) => FeatureStatus::Functional,
+        };
+        
+        vec![
+            Feature::new("skinshift firewall rules", status(self.firewall_manager.simulation_reason())),
+            Feature::new(
+                "skinshift OS fingerprint",
+                status((!self.fingerprint_manager.has_root()).then_some("requires root")),
+            ),
+        ]
+    }
+    
+    /// Announce fingerprint changes through `events`
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = Some(events);
+        self
+    }
+    
+    /// Whether the random preset is being re-drawn on a timer
+    pub async fn is_rotating(&self) -> bool {
+        self.rotation.lock().await.as_ref().is_some_and(|task| !task.is_finished())
+    }
+    
+    /// Draw and apply a new random fingerprint every `random_rotation_interval`
+    async fn start_rotation(&self) {
+        let Some(interval) = self.config.random_rotation_interval else {
+            return;
+        };
+        let mut rotation = self.rotation.lock().await;
+        if rotation.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        
+        let fingerprint_manager = self.fingerprint_manager.clone();
+        let events = self.events.clone();
+        *rotation = Some(tokio::spawn(async move {
+            let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticks.tick().await;
+                
+                let fingerprint = fingerprint_manager.random_fingerprint();
+                if let Err(e) = fingerprint_manager.apply_fingerprint(&fingerprint).await {
+                    warn!("Failed to apply a new random fingerprint: {}", e);
+                    continue;
+                }
+                debug!("Applied a new random fingerprint (TTL {:?})", fingerprint.ttl);
+                
+                if let Some(events) = &events {
+                    let event = Event::fingerprint_change(
+                        "skinshift",
+                        Some(serde_json::json!({
+                            "action": "rotate",
+                            "preset": RANDOM_PRESET,
+                            "fingerprint": fingerprint,
+                        })),
+                    );
+                    if events.send(event).await.is_err() {
+                        warn!("Event channel closed, stopping fingerprint rotation");
+                        break;
+                    }
+                }
+            }
+        }));
+        info!("Rotating the {} fingerprint every {:?}", RANDOM_PRESET, interval);
+    }
+    
+    /// Stop drawing new random fingerprints
+    async fn stop_rotation(&self) {
+        if let Some(task) = self.rotation.lock().await.take() {
+            task.abort();
+            info!("Stopped rotating the {} fingerprint", RANDOM_PRESET);
+        }
+    }
+    
+    /// Start reloading presets automatically when their files change
+    pub async fn watch_presets(&self) -> Result<(), SkinshiftError> {
+        let mut watcher = self.preset_watcher.lock().await;
+        
+        if watcher.is_none() {
+            *watcher = Some(self.preset_manager.watch()?);
+        }
+        
+        Ok(())
+    }
+    
+    /// Stop reloading presets automatically
+    pub async fn unwatch_presets(&self) {
+        self.preset_watcher.lock().await.take();
+    }
+    
+    /// Describe the fingerprint currently worn by the system
+    pub async fn current_fingerprint(&self) -> Option<FingerprintInfo> {
+        let applied = self.fingerprint_manager.current_fingerprint().await?;
+        let fingerprint = applied.fingerprint;
+        
+        let name = self
+            .current_preset
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(|| fingerprint.os_family.clone());
+        
+        // Expose the stack properties that have no dedicated field
+        let mut properties = fingerprint.properties.clone();
+        if let Some(scaling) = fingerprint.window_scaling {
+            properties.insert("window_scaling".to_string(), scaling.into());
+        }
+        if let Some(timestamps) = fingerprint.timestamps {
+            properties.insert("timestamps".to_string(), timestamps.into());
+        }
+        if let Some(behavior) = &fingerprint.ip_id_behavior {
+            properties.insert("ip_id_behavior".to_string(), behavior.clone().into());
+        }
+        if let Some(df_bit) = fingerprint.df_bit {
+            properties.insert("df_bit".to_string(), df_bit.into());
+        }
+        
+        Some(FingerprintInfo {
+            name,
+            os_family: fingerprint.os_family,
+            os_version: fingerprint.os_version,
+            ttl: fingerprint.ttl,
+            mss: fingerprint.mss,
+            window_size: fingerprint.window_size,
+            applied_at: applied.applied_at,
+            properties,
+        })
+    }
 }
 
 #[async_trait]
@@ -171,6 +447,14 @@ impl ChameleonService for SkinshiftService {
             }
         }
         
+        // Pick up preset edits without a restart
+        if self.config.watch_presets {
+            if let Err(e) = self.watch_presets().await {
+                warn!("Failed to watch presets directory: {}", e);
+                // Non-fatal error, continue
+            }
+        }
+        
         Ok(())
     }
     
@@ -179,12 +463,15 @@ impl ChameleonService for SkinshiftService {
         
         // Apply initial neutral posture
         self.apply_posture_fingerprint(Posture::Neutral).await?;
+        self.running.store(true, Ordering::SeqCst);
         
         Ok(())
     }
     
     async fn stop(&self) -> Result<(), ChameleonError> {
         info!("Stopping Skinshift service");
+        self.running.store(false, Ordering::SeqCst);
+        self.stop_rotation().await;
         
         // Optionally reset to default fingerprint on shutdown
         if let Err(e) = self.reset_fingerprint().await {
@@ -214,6 +501,12 @@ impl ChameleonService for SkinshiftService {
                     }
                 }
             }
+            // Scans and fingerprinting attempts tell what to mimic
+            chame_core::EventType::NetworkActivity => {
+                if let Some(probe) = ObservedProbe::from_event(&event) {
+                    self.observe_probe(probe).await?;
+                }
+            }
             // Handle other event types as needed
             _ => {}
         }
@@ -242,7 +535,7 @@ impl ChameleonService for SkinshiftService {
             *posture
         };
         
-        let mut state = SystemState {
+        let state = SystemState {
             status: chame_core::state::Status::Running,
             current_posture: posture,
             started_at: Some(chrono::Utc::now()),
@@ -250,25 +543,93 @@ impl ChameleonService for SkinshiftService {
             threat_level: 0.0,
             active_services_count: 0,
             active_honeypots_count: 0,
-            current_fingerprint: None,
+            current_fingerprint: self.current_fingerprint().await,
         };
         
-        // Add more detailed state as needed
-        
         Ok(state)
     }
+    
+    async fn health(&self) -> ModuleHealth {
+        if !self.running.load(Ordering::SeqCst) {
+            return ModuleHealth::Stopped;
+        }
+        
+        // Presets still apply, edits just need a restart
+        if self.config.watch_presets && self.preset_watcher.lock().await.is_none() {
+            return ModuleHealth::Degraded("preset watcher not running".to_string());
+        }
+        
+        ModuleHealth::Healthy
+    }
+}
+
+/// Name of the preset applied for a given posture
+pub fn posture_preset(posture: Posture) -> &'static str {
+    match posture {
+        Posture::Silent => "silent_minimal",
+        Posture::Neutral => "linux_standard",
+        Posture::Mimetic => DEFAULT_MIMETIC_PRESET, // Until probes are observed, see select_mimetic_preset
+        Posture::Fulgurant => "router_vulnerable",
+        Posture::Unstable => RANDOM_PRESET,
+    }
 }
 
 impl SkinshiftService {
+    /// Preset most likely to keep the attacker behind `recent_detections` engaged
+    ///
+    /// Scores every available preset with [`mimetic::score_preset`] and falls
+    /// back to [`DEFAULT_MIMETIC_PRESET`] when no probe points anywhere.
+    pub async fn select_mimetic_preset(&self, recent_detections: &[ObservedProbe]) -> String {
+        let mut presets = Vec::new();
+        for name in self.list_presets().await.unwrap_or_default() {
+            match self.get_preset(&name).await {
+                Ok(preset) => presets.push(preset),
+                Err(e) => warn!("Skipping preset '{}' for Mimetic selection: {}", name, e),
+            }
+        }
+        
+        mimetic::best_preset(&presets, recent_detections)
+            .map(|preset| preset.name.clone())
+            .unwrap_or_else(|| DEFAULT_MIMETIC_PRESET.to_string())
+    }
+    
+    /// Remember a probe and, in Mimetic posture, follow it if another preset fits better
+    async fn observe_probe(&self, probe: ObservedProbe) -> Result<(), ChameleonError> {
+        {
+            let mut probes = self.observed_probes.write().await;
+            if probes.len() == MAX_OBSERVED_PROBES {
+                probes.pop_front();
+            }
+            probes.push_back(probe);
+        }
+        
+        if *self.current_posture.read().await == Posture::Mimetic {
+            self.apply_posture_fingerprint(Posture::Mimetic).await?;
+        }
+        
+        Ok(())
+    }
+    
     /// Apply the appropriate fingerprint for a given posture
     async fn apply_posture_fingerprint(&self, posture: Posture) -> Result<(), ChameleonError> {
         let preset_name = match posture {
-            Posture::Silent => "silent_minimal",
-            Posture::Neutral => "linux_standard",
-            Posture::Mimetic => "windows_server2019", // Could be dynamic based on observed attacker interests
-            Posture::Fulgurant => "router_vulnerable",
-            Posture::Unstable => "random_changing",
+            Posture::Mimetic => {
+                let probes: Vec<_> = self.observed_probes.read().await.iter().cloned().collect();
+                self.select_mimetic_preset(&probes).await
+            }
+            _ => posture_preset(posture).to_string(),
         };
+        let preset_name = preset_name.as_str();
+        
+        // Only the random preset keeps moving
+        if preset_name != RANDOM_PRESET {
+            self.stop_rotation().await;
+        }
+        
+        // Re-applying the same Mimetic preset on every probe is not needed
+        if posture == Posture::Mimetic && self.current_preset.read().await.as_deref() == Some(preset_name) {
+            return Ok(());
+        }
         
         info!("Applying {} fingerprint for posture: {:?}", preset_name, posture);
         
@@ -279,6 +640,175 @@ impl SkinshiftService {
             )));
         }
         
+        if preset_name == RANDOM_PRESET {
+            self.start_rotation().await;
+        }
+        
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    #[tokio::test]
+    async fn test_state_reflects_applied_preset() {
+        let temp_dir = tempdir().unwrap();
+        let config = SkinshiftConfig {
+            presets_dir: temp_dir.path().join("presets").to_string_lossy().into_owned(),
+            firewall_backup_path: temp_dir.path().join("iptables.backup"),
+            restore_leftover_rules: false,
+            dry_run: true,
+            ..Default::default()
+        };
+        
+        let service = SkinshiftService::new(config).await.unwrap();
+        service.init().await.unwrap();
+        
+        let state = service.get_state().await.unwrap();
+        assert!(state.current_fingerprint.is_none());
+        
+        service.load_preset("windows_server2019").await.unwrap();
+        
+        let state = service.get_state().await.unwrap();
+        let fingerprint = state.current_fingerprint.unwrap();
+        assert_eq!(fingerprint.name, "windows_server2019");
+        assert_eq!(fingerprint.os_family, "Windows");
+        assert_eq!(fingerprint.os_version.as_deref(), Some("Server 2019"));
+        assert_eq!(fingerprint.ttl, Some(128));
+        assert_eq!(fingerprint.mss, Some(1460));
+        assert_eq!(fingerprint.window_size, Some(64240));
+    }
+    
+    #[tokio::test]
+    async fn test_every_posture_has_a_default_preset() {
+        let temp_dir = tempdir().unwrap();
+        let config = SkinshiftConfig {
+            presets_dir: temp_dir.path().join("presets").to_string_lossy().into_owned(),
+            firewall_backup_path: temp_dir.path().join("iptables.backup"),
+            restore_leftover_rules: false,
+            dry_run: true,
+            ..Default::default()
+        };
+        
+        let service = SkinshiftService::new(config).await.unwrap();
+        service.init().await.unwrap();
+        
+        for posture in Posture::ALL {
+            let preset = service.get_preset(posture_preset(posture)).await.unwrap();
+            assert_eq!(preset.name, posture_preset(posture));
+        }
+        
+        // Looking a preset up doesn't apply it
+        assert!(service.current_fingerprint().await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_mimetic_follows_observed_probes() {
+        let temp_dir = tempdir().unwrap();
+        let config = SkinshiftConfig {
+            presets_dir: temp_dir.path().join("presets").to_string_lossy().into_owned(),
+            firewall_backup_path: temp_dir.path().join("iptables.backup"),
+            restore_leftover_rules: false,
+            dry_run: true,
+            ..Default::default()
+        };
+        
+        let service = SkinshiftService::new(config).await.unwrap();
+        service.init().await.unwrap();
+        
+        let scan = |ports: &str| Event::network_activity(
+            "nettongue",
+            Some(serde_json::json!({"detection_type": "PortScan", "details": {"ports": ports}})),
+        );
+        let preset = || async { service.current_fingerprint().await.unwrap().name };
+        
+        // Without probes Mimetic shows the default preset
+        assert_eq!(service.select_mimetic_preset(&[]).await, DEFAULT_MIMETIC_PRESET);
+        let ssh = ObservedProbe { ports: vec![22] };
+        assert_eq!(service.select_mimetic_preset(&[ssh]).await, "linux_standard");
+        
+        // Probes observed before the posture change are taken into account
+        service.handle_event(scan("23,161")).await.unwrap();
+        service.change_posture(Posture::Mimetic).await.unwrap();
+        assert_eq!(preset().await, "router_vulnerable");
+        
+        // Later probes move the preset while in Mimetic
+        service.handle_event(scan("135,139,445,3389")).await.unwrap();
+        assert_eq!(preset().await, "windows_server2019");
+        
+        // Other postures keep their own preset
+        service.change_posture(Posture::Silent).await.unwrap();
+        service.handle_event(scan("23,161")).await.unwrap();
+        assert_eq!(preset().await, "silent_minimal");
+    }
+    
+    #[tokio::test]
+    async fn test_unstable_posture_is_reproducible_with_a_seed() {
+        let temp_dir = tempdir().unwrap();
+        let fingerprints = |seed: u64| {
+            let config = SkinshiftConfig {
+                presets_dir: temp_dir.path().join("presets").to_string_lossy().into_owned(),
+                firewall_backup_path: temp_dir.path().join("iptables.backup"),
+                restore_leftover_rules: false,
+                dry_run: true,
+                random_seed: Some(seed),
+                ..Default::default()
+            };
+            async move {
+                let service = SkinshiftService::new(config).await.unwrap();
+                service.init().await.unwrap();
+                
+                let mut fingerprints = Vec::new();
+                for _ in 0..2 {
+                    service.change_posture(Posture::Unstable).await.unwrap();
+                    let fingerprint = service.current_fingerprint().await.unwrap();
+                    assert_eq!(fingerprint.name, RANDOM_PRESET);
+                    fingerprints.push((fingerprint.ttl, fingerprint.mss, fingerprint.window_size));
+                }
+                fingerprints
+            }
+        };
+        
+        let first = fingerprints(7).await;
+        assert_eq!(first, fingerprints(7).await);
+        // Every application draws a new fingerprint
+        assert_ne!(first[0], first[1]);
+    }
+    
+    #[tokio::test]
+    async fn test_unstable_posture_rotates_the_fingerprint() {
+        let temp_dir = tempdir().unwrap();
+        let config = SkinshiftConfig {
+            presets_dir: temp_dir.path().join("presets").to_string_lossy().into_owned(),
+            firewall_backup_path: temp_dir.path().join("iptables.backup"),
+            restore_leftover_rules: false,
+            dry_run: true,
+            random_seed: Some(7),
+            random_rotation_interval: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        
+        let service = SkinshiftService::new(config).await.unwrap().with_events(EventSender::from(tx));
+        service.init().await.unwrap();
+        assert!(!service.is_rotating().await);
+        
+        service.change_posture(Posture::Unstable).await.unwrap();
+        assert!(service.is_rotating().await);
+        for _ in 0..2 {
+            let event = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.unwrap().unwrap();
+            assert_eq!(event.event_type, chame_core::EventType::FingerprintChange);
+            assert_eq!(event.data.unwrap()["preset"], RANDOM_PRESET);
+        }
+        
+        // Leaving the posture stops the rotation
+        service.change_posture(Posture::Silent).await.unwrap();
+        assert!(!service.is_rotating().await);
+        while rx.try_recv().is_ok() {}
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Mimetic preset selection
+//!
+//! In Mimetic posture Skinshift presents whatever the attacker seems to be
+//! looking for: probes on SMB or RDP favour a Windows preset, probes on SSH
+//! a Linux one. Scoring is kept free of I/O so it can be tested directly.
+
+use crate::preset::FingerprintPreset;
+use chame_core::{Event, EventType};
+
+/// Preset shown in Mimetic posture until a probe tells us better
+pub const DEFAULT_MIMETIC_PRESET: &str = "windows_server2019";
+
+/// Number of recent probes the selection is based on
+pub const MAX_OBSERVED_PROBES: usize = 64;
+
+/// Points for a preset whose OS family matches what a port suggests
+const FAMILY_SCORE: u32 = 3;
+
+/// Points for a preset with a banner or service for a probed port
+const SERVICE_SCORE: u32 = 2;
+
+/// Ports an attacker probed, from one nettongue scan or fingerprinting detection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedProbe {
+    /// Destination ports, without duplicates
+    pub ports: Vec<u16>,
+}
+
+impl ObservedProbe {
+    /// Extract a probe from a nettongue `PortScan` or `FingerprintingAttempt` event
+    ///
+    /// The ports come from the detection's `dest_port` and its comma
+    /// separated `ports` detail.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if event.event_type != EventType::NetworkActivity {
+            return None;
+        }
+        let data = event.data.as_ref()?;
+        match data.get("detection_type")?.as_str()? {
+            "PortScan" | "FingerprintingAttempt" => {}
+            _ => return None,
+        }
+
+        let mut ports: Vec<u16> = data
+            .pointer("/details/ports")
+            .and_then(|ports| ports.as_str())
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|port| port.trim().parse().ok())
+            .chain(data.get("dest_port").and_then(|port| port.as_u64()).and_then(|port| u16::try_from(port).ok()))
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+
+        (!ports.is_empty()).then_some(Self { ports })
+    }
+}
+
+/// Service and OS family a probe on `port` is most likely after
+fn port_interest(port: u16) -> Option<(&'static str, Option<&'static str>)> {
+    match port {
+        135 | 139 | 445 => Some(("smb", Some("Windows"))),
+        3389 => Some(("rdp", Some("Windows"))),
+        1433 => Some(("mssql", Some("Windows"))),
+        5985 | 5986 => Some(("winrm", Some("Windows"))),
+        22 => Some(("ssh", Some("Linux"))),
+        3306 => Some(("mysql", Some("Linux"))),
+        5432 => Some(("postgresql", Some("Linux"))),
+        23 => Some(("telnet", Some("Router"))),
+        161 => Some(("snmp", Some("Router"))),
+        21 => Some(("ftp", None)),
+        25 => Some(("smtp", None)),
+        80 | 443 | 8080 | 8443 => Some(("http", None)),
+        _ => None,
+    }
+}
+
+/// How well `preset` matches what the attacker probed, higher is better
+///
+/// Every probed port scores for a preset of the OS family it suggests and
+/// for a preset exposing a banner or service for it.
+pub fn score_preset(preset: &FingerprintPreset, probes: &[ObservedProbe]) -> u32 {
+    probes
+        .iter()
+        .flat_map(|probe| &probe.ports)
+        .filter_map(|port| port_interest(*port))
+        .map(|(service, family)| {
+            let family_score = match family {
+                Some(family) if preset.fingerprint.os_family.eq_ignore_ascii_case(family) => FAMILY_SCORE,
+                _ => 0,
+            };
+            let service_score = match preset.banners.contains_key(service) || preset.services.contains_key(service) {
+                true => SERVICE_SCORE,
+                false => 0,
+            };
+            family_score + service_score
+        })
+        .sum()
+}
+
+/// The best scoring preset, if any scores at all
+///
+/// Ties go to [`DEFAULT_MIMETIC_PRESET`], then to the first name in
+/// alphabetical order.
+pub fn best_preset<'a>(presets: &'a [FingerprintPreset], probes: &[ObservedProbe]) -> Option<&'a FingerprintPreset> {
+    presets
+        .iter()
+        .map(|preset| (score_preset(preset, probes), preset))
+        .filter(|(score, _)| *score > 0)
+        .max_by(|(a_score, a), (b_score, b)| {
+            a_score
+                .cmp(b_score)
+                .then_with(|| (a.name == DEFAULT_MIMETIC_PRESET).cmp(&(b.name == DEFAULT_MIMETIC_PRESET)))
+                .then_with(|| b.name.cmp(&a.name))
+        })
+        .map(|(_, preset)| preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::OSFingerprint;
+
+    fn presets() -> Vec<FingerprintPreset> {
+        let mut linux = FingerprintPreset::new("linux_standard", "Linux", OSFingerprint::linux(None));
+        linux.add_banner("ssh", "SSH-2.0-OpenSSH_7.9p1 Debian-10+deb10u2");
+        vec![
+            FingerprintPreset::new("windows_server2019", "Windows", OSFingerprint::windows(None)),
+            linux,
+            FingerprintPreset::new("router_vulnerable", "Router", OSFingerprint::router("Generic Router")),
+            FingerprintPreset::new("silent_minimal", "Minimal", OSFingerprint::minimal()),
+        ]
+    }
+
+    fn probe(ports: &[u16]) -> ObservedProbe {
+        ObservedProbe { ports: ports.to_vec() }
+    }
+
+    #[test]
+    fn test_scoring_follows_probed_services() {
+        let presets = presets();
+        let best = |probes: &[ObservedProbe]| best_preset(&presets, probes).map(|preset| preset.name.as_str());
+
+        assert_eq!(best(&[probe(&[139, 445])]), Some("windows_server2019"));
+        assert_eq!(best(&[probe(&[22])]), Some("linux_standard"));
+        assert_eq!(best(&[probe(&[23, 161])]), Some("router_vulnerable"));
+        assert_eq!(score_preset(&presets[1], &[probe(&[22, 80])]), FAMILY_SCORE + SERVICE_SCORE);
+
+        // SSH banners outweigh a single SMB probe
+        assert_eq!(best(&[probe(&[445]), probe(&[22]), probe(&[22])]), Some("linux_standard"));
+
+        // Nothing telling falls back to the caller's default
+        assert_eq!(best(&[]), None);
+        assert_eq!(best(&[probe(&[80, 9999])]), None);
+    }
+
+    #[test]
+    fn test_probe_from_event() {
+        let event = Event::network_activity(
+            "nettongue",
+            Some(serde_json::json!({
+                "detection_type": "PortScan",
+                "dest_port": null,
+                "details": {"ports": "445,22,139", "ports_probed": "3"},
+            })),
+        );
+        assert_eq!(ObservedProbe::from_event(&event), Some(probe(&[22, 139, 445])));
+
+        let event = Event::network_activity(
+            "nettongue",
+            Some(serde_json::json!({"detection_type": "FingerprintingAttempt", "dest_port": 22, "details": {}})),
+        );
+        assert_eq!(ObservedProbe::from_event(&event), Some(probe(&[22])));
+
+        let event = Event::network_activity(
+            "nettongue",
+            Some(serde_json::json!({"detection_type": "SynFlood", "dest_port": 80, "details": {}})),
+        );
+        assert_eq!(ObservedProbe::from_event(&event), None);
+        assert_eq!(ObservedProbe::from_event(&Event::security_alert("nettongue", None)), None);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::errors::SkinshiftError;
+use crate::fingerprint::OSFingerprint;
+use crate::preset::FingerprintPreset;
+use std::collections::HashMap;
+
+/// Split an nmap `os-db` file into its `Fingerprint` blocks
+pub fn split_entries(db: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut current: Option<String> = None;
+    
+    for line in db.lines() {
+        let line = line.trim();
+        
+        if line.starts_with("Fingerprint ") {
+            if let Some(entry) = current.take() {
+                entries.push(entry);
+            }
+            current = Some(String::new());
+        } else if line.is_empty() || line.starts_with('#') || line.starts_with("MatchPoints") {
+            // Blank lines, comments and the scoring block end the current entry
+            if let Some(entry) = current.take() {
+                entries.push(entry);
+            }
+            continue;
+        }
+        
+        if let Some(entry) = &mut current {
+            entry.push_str(line);
+            entry.push('\n');
+        }
+    }
+    
+    if let Some(entry) = current {
+        entries.push(entry);
+    }
+    
+    entries
+}
+
+/// Build a preset from a single nmap `os-db` fingerprint block
+///
+/// Only the subset of the grammar that maps onto `OSFingerprint` is used:
+/// the TTL guess (`TG`), the first window size (`WIN.W1`), MSS, window
+/// scale and timestamps from the first options probe (`OPS.O1`), the DF
+/// bit of `T1` and the IP ID sequence class (`SEQ.TI`).
+pub fn parse_entry(entry: &str) -> Result<FingerprintPreset, SkinshiftError> {
+    let mut title = None;
+    let mut class = None;
+    let mut cpe = None;
+    let mut tests: HashMap<String, HashMap<String, String>> = HashMap::new();
+    
+    for line in entry.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(rest) = line.strip_prefix("Fingerprint ") {
+            title = Some(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("Class ") {
+            // Only the first class line is kept
+            class.get_or_insert_with(|| rest.split('|').map(|p| p.trim().to_string()).collect::<Vec<_>>());
+        } else if let Some(rest) = line.strip_prefix("CPE ") {
+            cpe.get_or_insert_with(|| rest.split_whitespace().next().unwrap_or("").to_string());
+        } else if let Some((name, values)) = parse_test_line(line) {
+            tests.insert(name, values);
+        }
+    }
+    
+    let title = title.ok_or_else(|| {
+        SkinshiftError::PresetError("nmap entry has no Fingerprint line".to_string())
+    })?;
+    
+    // Class is "vendor | family | generation | device type"
+    let os_family = class
+        .as_ref()
+        .and_then(|c| c.get(1).cloned())
+        .filter(|f| !f.is_empty())
+        .unwrap_or_else(|| title.split_whitespace().next().unwrap_or("Unknown").to_string());
+    
+    let mut fingerprint = OSFingerprint::new(os_family);
+    fingerprint.os_version = class.as_ref().and_then(|c| c.get(2).cloned()).filter(|v| !v.is_empty());
+    
+    // Initial TTL guess, from any probe that has one
+    fingerprint.ttl = ["T1", "ECN", "IE", "T2", "T3", "T4", "T5", "T6", "T7", "U1"]
+        .iter()
+        .filter_map(|t| test_value(&tests, t, "TG"))
+        .find_map(|v| parse_hex(&v))
+        .and_then(|ttl| u8::try_from(ttl).ok());
+        
+    fingerprint.window_size = test_value(&tests, "WIN", "W1").and_then(|v| parse_hex(&v));
+    
+    if let Some(options) = test_value(&tests, "OPS", "O1") {
+        let options = parse_tcp_options(&options);
+        fingerprint.mss = options.mss;
+        fingerprint.window_scaling = options.window_scale;
+        fingerprint.timestamps = Some(options.timestamps);
+    }
+    
+    fingerprint.df_bit = test_value(&tests, "T1", "DF").map(|v| v == "Y");
+    
+    fingerprint.ip_id_behavior = test_value(&tests, "SEQ", "TI").and_then(|v| match v.as_str() {
+        "Z" => Some("zero".to_string()),
+        "RD" | "RI" => Some("random".to_string()),
+        "I" | "BI" => Some("incremental".to_string()),
+        _ => None,
+    });
+    
+    let mut preset = FingerprintPreset::new(preset_name(&title), title, fingerprint);
+    preset.add_metadata("source", serde_json::json!("nmap-os-db"));
+    if let Some(cpe) = cpe {
+        preset.add_metadata("cpe", serde_json::json!(cpe));
+    }
+    
+    Ok(preset)
+}
+
+/// TCP options relevant to the fingerprint
+#[derive(Debug, Default, PartialEq)]
+struct TcpOptions {
+    mss: Option<u16>,
+    window_scale: Option<u8>,
+    timestamps: bool,
+}
+
+/// Parse an nmap TCP options string such as `M5B4NW8ST11`
+fn parse_tcp_options(options: &str) -> TcpOptions {
+    let mut result = TcpOptions::default();
+    let chars: Vec<char> = options.chars().collect();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let kind = chars[i];
+        i += 1;
+        
+        // Option values are hex digits, option kinds never are
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        let value: String = chars[start..i].iter().collect();
+        
+        match kind {
+            'M' => result.mss = u16::from_str_radix(&value, 16).ok(),
+            'W' => result.window_scale = u8::from_str_radix(&value, 16).ok(),
+            'T' => result.timestamps = true,
+            _ => {}
+        }
+    }
+    
+    result
+}
+
+/// Parse a test line such as `T1(R=Y%DF=Y%TG=40)`
+fn parse_test_line(line: &str) -> Option<(String, HashMap<String, String>)> {
+    let open = line.find('(')?;
+    let body = line[open + 1..].strip_suffix(')')?;
+    let name = line[..open].to_string();
+    
+    let values = body
+        .split('%')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        
+    Some((name, values))
+}
+
+/// Get the first concrete value of a test attribute
+///
+/// nmap expresses alternatives with `|`, ranges with `-` and bounds with
+/// `<`/`>`; the first alternative and the lower end of a range are used.
+fn test_value(tests: &HashMap<String, HashMap<String, String>>, test: &str, key: &str) -> Option<String> {
+    let raw = tests.get(test)?.get(key)?;
+    let value = raw.split('|').next()?.trim_start_matches(['<', '>']);
+    let value = value.split('-').next()?;
+    
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Parse a hex number as used throughout the os-db
+fn parse_hex(value: &str) -> Option<u32> {
+    u32::from_str_radix(value, 16).ok()
+}
+
+/// Derive a file-friendly preset name from a fingerprint title
+fn preset_name(title: &str) -> String {
+    let mut name = String::new();
+    
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    
+    format!("nmap_{}", name.trim_matches('_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SAMPLE_DB: &str = "\
+# Sample taken from nmap-os-db
+MatchPoints
+SEQ(SP=25%GCD=75%ISR=25%TI=100%CI=50%II=100%SS=80%TS=100)
+
+Fingerprint Linux 4.15 - 5.8
+Class Linux | Linux | 4.X | general purpose
+CPE cpe:/o:linux:linux_kernel:4 auto
+Class Linux | Linux | 5.X | general purpose
+SEQ(SP=FB-105%GCD=1-6%ISR=FD-107%TI=Z%CI=Z%II=I%TS=A)
+OPS(O1=M5B4ST11NW7%O2=M5B4ST11NW7%O3=M5B4NNT11NW7%O4=M5B4ST11NW7%O5=M5B4ST11NW7%O6=M5B4ST11)
+WIN(W1=FE88%W2=FE88%W3=FE88%W4=FE88%W5=FE88%W6=FE88)
+ECN(R=Y%DF=Y%T=3B-45%TG=40%W=FAF0%O=M5B4NNSNW7%CC=Y%Q=)
+T1(R=Y%DF=Y%T=3B-45%TG=40%S=O%A=S+%F=AS%RD=0%Q=)
+IE(R=Y%DFI=N%T=3B-45%TG=40%CD=S)
+
+Fingerprint Microsoft Windows Server 2019
+Class Microsoft | Windows | 2019 | general purpose
+CPE cpe:/o:microsoft:windows_server_2019
+SEQ(SP=FC-106%GCD=1-6%ISR=108-112%TI=I%CI=I%II=I%SS=S%TS=U)
+OPS(O1=M5B4NW8NNS%O2=M5B4NW8NNS%O3=M5B4NW8%O4=M5B4NW8NNS%O5=M5B4NW8NNS%O6=M5B4NNS)
+WIN(W1=FFFF|2000%W2=FFFF%W3=FFFF%W4=FFFF%W5=FFFF%W6=FF70)
+ECN(R=Y%DF=Y%T=7B-85%TG=80%W=FFFF%O=M5B4NW8NNS%CC=N%Q=)
+T1(R=Y%DF=Y%T=7B-85%TG=80%S=O%A=S+%F=AS%RD=0%Q=)
+";
+    
+    #[test]
+    fn test_split_entries() {
+        let entries = split_entries(SAMPLE_DB);
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].starts_with("Fingerprint Linux"));
+        assert!(entries[1].starts_with("Fingerprint Microsoft"));
+    }
+    
+    #[test]
+    fn test_parse_linux_entry() {
+        let entries = split_entries(SAMPLE_DB);
+        let preset = parse_entry(&entries[0]).unwrap();
+        let fingerprint = &preset.fingerprint;
+        
+        assert_eq!(preset.name, "nmap_linux_4_15_5_8");
+        assert_eq!(fingerprint.os_family, "Linux");
+        assert_eq!(fingerprint.os_version.as_deref(), Some("4.X"));
+        assert_eq!(fingerprint.ttl, Some(64));
+        assert_eq!(fingerprint.window_size, Some(0xFE88));
+        assert_eq!(fingerprint.mss, Some(1460));
+        assert_eq!(fingerprint.window_scaling, Some(7));
+        assert_eq!(fingerprint.timestamps, Some(true));
+        assert_eq!(fingerprint.df_bit, Some(true));
+        assert_eq!(fingerprint.ip_id_behavior.as_deref(), Some("zero"));
+        assert!(preset.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_parse_windows_entry() {
+        let entries = split_entries(SAMPLE_DB);
+        let preset = parse_entry(&entries[1]).unwrap();
+        let fingerprint = &preset.fingerprint;
+        
+        assert_eq!(preset.name, "nmap_microsoft_windows_server_2019");
+        assert_eq!(fingerprint.os_family, "Windows");
+        assert_eq!(fingerprint.ttl, Some(128));
+        assert_eq!(fingerprint.window_size, Some(65535));
+        assert_eq!(fingerprint.mss, Some(1460));
+        assert_eq!(fingerprint.window_scaling, Some(8));
+        assert_eq!(fingerprint.timestamps, Some(false));
+        assert_eq!(fingerprint.ip_id_behavior.as_deref(), Some("incremental"));
+        assert!(preset.validate().is_ok());
+    }
+}
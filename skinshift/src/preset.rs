@@ -1,3 +1,4 @@
+use crate::banner::SUPPORTED_SERVICES;
 use crate::errors::SkinshiftError;
 use crate::firewall::FirewallRule;
 use crate::fingerprint::OSFingerprint;
@@ -5,9 +6,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Read;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 
+/// Preset whose fingerprint is drawn anew each time it is applied
+pub const RANDOM_PRESET: &str = "random_changing";
+
 /// Fingerprint preset configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FingerprintPreset {
@@ -82,6 +90,66 @@ impl FingerprintPreset {
             metadata.insert(key.into(), value);
         }
     }
+    
+    /// Build a preset from a single nmap `os-db` fingerprint block
+    pub fn from_nmap_entry(entry: &str) -> Result<Self, SkinshiftError> {
+        crate::nmap::parse_entry(entry)
+    }
+    
+    /// Check the preset for values that would fail or misbehave when applied
+    pub fn validate(&self) -> Result<(), SkinshiftError> {
+        let mut problems = Vec::new();
+        let fingerprint = &self.fingerprint;
+        
+        // TCP/IP stack parameters
+        if fingerprint.ttl == Some(0) {
+            problems.push("TTL must be between 1 and 255".to_string());
+        }
+        
+        if let Some(mss) = fingerprint.mss {
+            if mss < 88 {
+                problems.push(format!("MSS {} is below the minimum of 88", mss));
+            }
+        }
+        
+        if let Some(scaling) = fingerprint.window_scaling {
+            if scaling > 14 {
+                problems.push(format!("window scaling {} exceeds the maximum of 14", scaling));
+            }
+        }
+        
+        if let Some(behavior) = &fingerprint.ip_id_behavior {
+            if !["zero", "random", "incremental"].contains(&behavior.to_lowercase().as_str()) {
+                problems.push(format!("unknown IP ID behavior '{}'", behavior));
+            }
+        }
+        
+        // Firewall rules
+        for rule in self.firewall_rules.iter().flatten() {
+            if !["tcp", "udp", "icmp", "all"].contains(&rule.protocol.to_lowercase().as_str()) {
+                problems.push(format!("firewall rule '{}' has unknown protocol '{}'", rule.name, rule.protocol));
+            }
+            
+            if !["accept", "drop", "reject"].contains(&rule.action.to_lowercase().as_str()) {
+                problems.push(format!("firewall rule '{}' has unknown action '{}'", rule.name, rule.action));
+            }
+        }
+        
+        // Banners
+        for service in self.banners.keys() {
+            if !SUPPORTED_SERVICES.contains(&service.to_lowercase().as_str()) {
+                problems.push(format!("banner for unknown service '{}'", service));
+            }
+        }
+        
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(SkinshiftError::PresetError(format!(
+                "Invalid preset '{}': {}", self.name, problems.join("; ")
+            )))
+        }
+    }
 }
 
 /// Manager for handling fingerprint presets
@@ -89,8 +157,8 @@ pub struct PresetManager {
     /// Directory containing preset configurations
     presets_dir: PathBuf,
     
-    /// Loaded presets
-    presets: HashMap<String, FingerprintPreset>,
+    /// Loaded presets, keyed by file name
+    presets: RwLock<HashMap<String, FingerprintPreset>>,
 }
 
 impl PresetManager {
@@ -98,7 +166,7 @@ impl PresetManager {
     pub fn new(presets_dir: &str) -> Self {
         Self {
             presets_dir: PathBuf::from(presets_dir),
-            presets: HashMap::new(),
+            presets: RwLock::new(HashMap::new()),
         }
     }
     
@@ -117,6 +185,8 @@ impl PresetManager {
             self.create_default_presets()?;
         }
         
+        self.reload().await?;
+        
         debug!("Preset manager initialized");
         Ok(())
     }
@@ -125,6 +195,12 @@ impl PresetManager {
     pub async fn load_preset(&self, name: &str) -> Result<FingerprintPreset, SkinshiftError> {
         info!("Loading preset: {}", name);
         
+        // Serve from the cache if we already have it
+        if let Some(preset) = self.presets.read().await.get(name) {
+            debug!("Preset served from cache: {}", name);
+            return Ok(preset.clone());
+        }
+        
         let preset_path = self.presets_dir.join(format!("{}.toml", name));
         
         if !preset_path.exists() {
@@ -133,10 +209,154 @@ impl PresetManager {
             ));
         }
         
+        let preset = Self::read_preset_file(&preset_path)?;
+        
+        self.presets.write().await.insert(name.to_string(), preset.clone());
+        
+        debug!("Preset loaded successfully: {}", name);
+        
+        Ok(preset)
+    }
+    
+    /// Re-scan the presets directory and refresh the cache
+    ///
+    /// A preset file that fails to parse or validate keeps its previously
+    /// loaded version. Returns the number of presets in the cache.
+    pub async fn reload(&self) -> Result<usize, SkinshiftError> {
+        info!("Reloading presets from {:?}", self.presets_dir);
+        
+        let mut presets = self.presets.write().await;
+        let mut reloaded = HashMap::new();
+        
+        for name in self.list_presets().await? {
+            let preset_path = self.presets_dir.join(format!("{}.toml", name));
+            
+            match Self::read_preset_file(&preset_path) {
+                Ok(preset) => {
+                    reloaded.insert(name, preset);
+                }
+                Err(e) => {
+                    error!("Failed to reload preset '{}': {}", name, e);
+                    
+                    // Keep the last good version
+                    if let Some(previous) = presets.remove(&name) {
+                        warn!("Keeping previous version of preset '{}'", name);
+                        reloaded.insert(name, previous);
+                    }
+                }
+            }
+        }
+        
+        *presets = reloaded;
+        
+        info!("Reloaded {} presets", presets.len());
+        
+        Ok(presets.len())
+    }
+    
+    /// Watch the presets directory and reload whenever a preset file changes
+    ///
+    /// The returned watcher must be kept alive for as long as reloading is wanted.
+    pub fn watch(self: &Arc<Self>) -> Result<RecommendedWatcher, SkinshiftError> {
+        info!("Watching presets directory {:?}", self.presets_dir);
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            match result {
+                Ok(event) => {
+                    let is_preset = event
+                        .paths
+                        .iter()
+                        .any(|path| path.extension().and_then(|e| e.to_str()) == Some("toml"));
+                        
+                    if is_preset {
+                        let _ = tx.send(());
+                    }
+                }
+                Err(e) => warn!("Preset watcher error: {}", e),
+            }
+        })
+        .map_err(|e| SkinshiftError::PresetError(format!("Failed to create preset watcher: {}", e)))?;
+        
+        watcher
+            .watch(&self.presets_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| SkinshiftError::PresetError(format!("Failed to watch presets directory: {}", e)))?;
+            
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                // Editors often write a file in several steps, let them settle
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                while rx.try_recv().is_ok() {}
+                
+                if let Err(e) = manager.reload().await {
+                    error!("Failed to reload presets: {}", e);
+                }
+            }
+        });
+        
+        Ok(watcher)
+    }
+    
+    /// Import the fingerprints of an nmap `os-db` file as presets
+    ///
+    /// Entries that cannot be mapped onto a valid preset are skipped. An
+    /// entry whose title was already imported gets a `_2`, `_3`... suffix.
+    /// Returns the names of the presets written.
+    pub async fn import_nmap_db(&self, path: &Path) -> Result<Vec<String>, SkinshiftError> {
+        info!("Importing nmap OS database from: {}", path.display());
+        
+        let content = fs::read_to_string(path).map_err(|e| {
+            SkinshiftError::IOError(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to read nmap OS database: {}", e)
+            ))
+        })?;
+        
+        fs::create_dir_all(&self.presets_dir).map_err(|e| {
+            SkinshiftError::PresetError(format!("Failed to create presets directory: {}", e))
+        })?;
+        
+        let mut imported = Vec::new();
+        
+        for entry in crate::nmap::split_entries(&content) {
+            let mut preset = match FingerprintPreset::from_nmap_entry(&entry).and_then(|p| p.validate().map(|_| p)) {
+                Ok(preset) => preset,
+                Err(e) => {
+                    warn!("Skipping nmap entry: {}", e);
+                    continue;
+                }
+            };
+            
+            if imported.contains(&preset.name) {
+                let title = preset.name.clone();
+                let mut suffix = 2;
+                while imported.contains(&format!("{}_{}", title, suffix)) {
+                    suffix += 1;
+                }
+                preset.name = format!("{}_{}", title, suffix);
+                warn!("Duplicate nmap fingerprint {}, imported as {}", title, preset.name);
+            }
+            
+            self.save_preset(&preset)?;
+            imported.push(preset.name);
+        }
+        
+        info!("Imported {} presets from nmap OS database", imported.len());
+        
+        // Make the new presets visible to load_preset
+        self.reload().await?;
+        
+        Ok(imported)
+    }
+    
+    /// Read, parse and validate a preset file
+    fn read_preset_file(preset_path: &Path) -> Result<FingerprintPreset, SkinshiftError> {
         // Read the preset file
         let mut content = String::new();
         {
-            let mut file = File::open(&preset_path).map_err(|e| {
+            let mut file = File::open(preset_path).map_err(|e| {
                 SkinshiftError::IOError(std::io::Error::new(
                     std::io::ErrorKind::Other,
                     format!("Failed to open preset file: {}", e)
@@ -151,17 +371,23 @@ impl PresetManager {
             })?;
         }
         
-        // Parse the preset
-        let preset: FingerprintPreset = toml::from_str(&content).map_err(|e| {
+        // Parse the preset, then fill in environment variables
+        let mut value: toml::Value = toml::from_str(&content).map_err(|e| {
+            SkinshiftError::PresetError(format!("Failed to parse preset: {}", e))
+        })?;
+        expand_toml(&mut value)?;
+        let preset: FingerprintPreset = value.try_into().map_err(|e| {
             SkinshiftError::PresetError(format!("Failed to parse preset: {}", e))
         })?;
         
-        debug!("Preset loaded successfully: {}", name);
+        preset.validate()?;
         
         Ok(preset)
     }
     
     /// Load a custom fingerprint configuration
+    ///
+    /// Like presets, its strings may use `${VAR}` and `${VAR:-default}`.
     pub async fn load_custom(&self, path: &Path) -> Result<FingerprintPreset, SkinshiftError> {
         info!("Loading custom fingerprint from: {}", path.display());
         
@@ -189,19 +415,33 @@ impl PresetManager {
             })?;
         }
         
-        // Parse the custom configuration
+        // Parse the custom configuration, then fill in environment variables
         let preset: FingerprintPreset = match path.extension().and_then(|e| e.to_str()) {
-            Some("toml") => toml::from_str(&content).map_err(|e| {
-                SkinshiftError::PresetError(format!("Failed to parse TOML: {}", e))
-            })?,
-            Some("json") => serde_json::from_str(&content).map_err(|e| {
-                SkinshiftError::PresetError(format!("Failed to parse JSON: {}", e))
-            })?,
+            Some("toml") => {
+                let mut value: toml::Value = toml::from_str(&content).map_err(|e| {
+                    SkinshiftError::PresetError(format!("Failed to parse TOML: {}", e))
+                })?;
+                expand_toml(&mut value)?;
+                value.try_into().map_err(|e| {
+                    SkinshiftError::PresetError(format!("Failed to parse TOML: {}", e))
+                })?
+            }
+            Some("json") => {
+                let mut value: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
+                    SkinshiftError::PresetError(format!("Failed to parse JSON: {}", e))
+                })?;
+                expand_json(&mut value)?;
+                serde_json::from_value(value).map_err(|e| {
+                    SkinshiftError::PresetError(format!("Failed to parse JSON: {}", e))
+                })?
+            }
             _ => return Err(SkinshiftError::PresetError(
                 format!("Unsupported file format: {}", path.display())
             )),
         };
         
+        preset.validate()?;
+        
         debug!("Custom fingerprint loaded successfully");
         
         Ok(preset)
@@ -290,7 +530,7 @@ impl PresetManager {
         
         // Create Random/Changing preset
         let random = FingerprintPreset::new(
-            "random_changing",
+            RANDOM_PRESET,
             "Random Changing Profile",
             OSFingerprint::minimal(),
         );
@@ -327,6 +567,83 @@ impl PresetManager {
     }
 }
 
+/// Expand `${VAR}` and `${VAR:-default}` in `input` from `lookup`
+///
+/// A variable that is unset, or empty, takes its default; without one, an
+/// unset variable is an error. `$${` stands for a literal `${`.
+fn expand_env(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, SkinshiftError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            output.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(reference) = rest.strip_prefix("${") else {
+            output.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        
+        let end = reference.find('}').ok_or_else(|| {
+            SkinshiftError::PresetError(format!("Unterminated variable reference in '{}'", input))
+        })?;
+        let (name, default) = match reference[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+        
+        let value = match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => default.to_string(),
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_string(),
+            (None, None) => {
+                return Err(SkinshiftError::PresetError(format!(
+                    "Environment variable {} is not set, use ${{{}:-default}} to give a default",
+                    name, name
+                )))
+            }
+        };
+        output.push_str(&value);
+        rest = &reference[end + 1..];
+    }
+    
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Value of an environment variable, `None` when unset or not UTF-8
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Expand environment variables in every string of a parsed TOML preset
+fn expand_toml(value: &mut toml::Value) -> Result<(), SkinshiftError> {
+    match value {
+        toml::Value::String(text) => *text = expand_env(text, &env_var)?,
+        toml::Value::Array(items) => items.iter_mut().try_for_each(expand_toml)?,
+        toml::Value::Table(table) => table.iter_mut().try_for_each(|(_, item)| expand_toml(item))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand environment variables in every string of a parsed JSON preset
+fn expand_json(value: &mut serde_json::Value) -> Result<(), SkinshiftError> {
+    match value {
+        serde_json::Value::String(text) => *text = expand_env(text, &env_var)?,
+        serde_json::Value::Array(items) => items.iter_mut().try_for_each(expand_json)?,
+        serde_json::Value::Object(map) => map.values_mut().try_for_each(expand_json)?,
+        _ => {}
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,6 +669,117 @@ mod tests {
         assert_eq!(preset.services.get("http").and_then(|v| v.get("port")).and_then(|v| v.as_u64()), Some(80));
     }
     
+    #[test]
+    fn test_validate_preset() {
+        let preset = FingerprintPreset::new(
+            "valid",
+            "Valid Preset",
+            OSFingerprint::linux(None),
+        );
+        assert!(preset.validate().is_ok());
+        
+        let mut fingerprint = OSFingerprint::linux(None);
+        fingerprint.ttl = Some(0);
+        let mut preset = FingerprintPreset::new("invalid", "Invalid Preset", fingerprint);
+        preset.add_firewall_rule(FirewallRule::new("bad", "sctp", "allow"));
+        preset.add_banner("gopher", "Gopher server");
+        
+        let error = preset.validate().unwrap_err().to_string();
+        assert!(error.contains("TTL"));
+        assert!(error.contains("protocol 'sctp'"));
+        assert!(error.contains("action 'allow'"));
+        assert!(error.contains("service 'gopher'"));
+    }
+    
+    #[test]
+    fn test_expand_env_syntax() {
+        let lookup = |name: &str| match name {
+            "HOST" => Some("web-01".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        
+        assert_eq!(expand_env("${HOST}.example.com", &lookup).unwrap(), "web-01.example.com");
+        assert_eq!(expand_env("${MISSING:-fallback} ${EMPTY:-default}", &lookup).unwrap(), "fallback default");
+        assert_eq!(expand_env("cost: $5, literal $${HOST}", &lookup).unwrap(), "cost: $5, literal ${HOST}");
+        assert_eq!(expand_env("${EMPTY}", &lookup).unwrap(), "");
+        
+        let error = expand_env("${MISSING}", &lookup).unwrap_err().to_string();
+        assert!(error.contains("MISSING is not set"), "{}", error);
+        assert!(expand_env("${HOST", &lookup).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_presets_expand_environment_variables() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PresetManager::new(temp_dir.path().to_str().unwrap());
+        
+        let mut preset = FingerprintPreset::new("per_host", "Banner per host", OSFingerprint::linux(None));
+        preset.add_banner("ssh", "SSH-2.0-OpenSSH_8.9 ${SKINSHIFT_TEST_HOSTNAME}");
+        preset.add_banner("http", "Apache/2.4.57 (${SKINSHIFT_TEST_UNSET_DISTRO:-Debian})");
+        preset.add_service_config("ssh", serde_json::json!({ "options": { "host": "${SKINSHIFT_TEST_HOSTNAME}" } }));
+        manager.save_preset(&preset).unwrap();
+        
+        std::env::set_var("SKINSHIFT_TEST_HOSTNAME", "sensor-07");
+        let loaded = manager.load_preset("per_host").await.unwrap();
+        assert_eq!(loaded.banners["ssh"], "SSH-2.0-OpenSSH_8.9 sensor-07");
+        assert_eq!(loaded.banners["http"], "Apache/2.4.57 (Debian)");
+        assert_eq!(loaded.services["ssh"]["options"]["host"], "sensor-07");
+        
+        // Without the variable the preset doesn't load
+        let json_path = temp_dir.path().join("custom.json");
+        let mut custom = preset.clone();
+        custom.add_banner("ftp", "${SKINSHIFT_TEST_UNDEFINED}");
+        std::fs::write(&json_path, serde_json::to_string(&custom).unwrap()).unwrap();
+        let error = manager.load_custom(&json_path).await.unwrap_err().to_string();
+        assert!(error.contains("SKINSHIFT_TEST_UNDEFINED"), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_import_nmap_db_keeps_duplicate_titles() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PresetManager::new(temp_dir.path().join("presets").to_str().unwrap());
+        
+        let entry = "\
+Fingerprint Linux 4.15 - 5.8
+Class Linux | Linux | 4.X | general purpose
+OPS(O1=M5B4ST11NW7)
+WIN(W1=FE88)
+T1(R=Y%DF=Y%T=3B-45%TG=40%S=O%A=S+%F=AS%RD=0%Q=)
+";
+        let db_path = temp_dir.path().join("nmap-os-db");
+        std::fs::write(&db_path, [entry, entry, entry].join("\n")).unwrap();
+        
+        let imported = manager.import_nmap_db(&db_path).await.unwrap();
+        assert_eq!(imported, ["nmap_linux_4_15_5_8", "nmap_linux_4_15_5_8_2", "nmap_linux_4_15_5_8_3"]);
+        for name in &imported {
+            assert!(manager.load_preset(name).await.is_ok(), "{}", name);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_reload_keeps_last_good_preset() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().join("presets");
+        
+        let manager = PresetManager::new(dir_path.to_str().unwrap());
+        manager.init().await.unwrap();
+        
+        let original = manager.load_preset("linux_standard").await.unwrap();
+        
+        // A valid edit is picked up on reload
+        let mut edited = original.clone();
+        edited.description = "Edited".to_string();
+        manager.save_preset(&edited).unwrap();
+        manager.reload().await.unwrap();
+        assert_eq!(manager.load_preset("linux_standard").await.unwrap().description, "Edited");
+        
+        // A malformed edit keeps the previous version
+        std::fs::write(dir_path.join("linux_standard.toml"), "not = [valid").unwrap();
+        manager.reload().await.unwrap();
+        assert_eq!(manager.load_preset("linux_standard").await.unwrap().description, "Edited");
+    }
+    
     #[tokio::test]
     async fn test_preset_manager() {
         let temp_dir = tempdir().unwrap();
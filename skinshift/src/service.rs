@@ -2,6 +2,7 @@ use crate::errors::SkinshiftError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process::Command;
+use std::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
 /// Service configuration
@@ -50,7 +51,7 @@ pub struct ServiceManager {
     original_configs: HashMap<String, ServiceConfig>,
     
     /// Map of service names to their current configurations
-    current_configs: HashMap<String, ServiceConfig>,
+    current_configs: RwLock<HashMap<String, ServiceConfig>>,
 }
 
 impl ServiceManager {
@@ -58,10 +59,23 @@ impl ServiceManager {
     pub fn new() -> Self {
         Self {
             original_configs: HashMap::new(),
-            current_configs: HashMap::new(),
+            current_configs: RwLock::new(HashMap::new()),
         }
     }
     
+    /// Ports of the enabled services configured so far, sorted
+    pub fn registered_ports(&self) -> Vec<u16> {
+        let configs = self.current_configs.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut ports: Vec<u16> = configs
+            .values()
+            .filter(|config| config.enabled)
+            .filter_map(|config| config.port)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+    
     /// Configure a service
     pub async fn configure_service(&self, service_name: &str, config: &serde_json::Value) -> Result<(), SkinshiftError> {
         info!("Configuring service: {}", service_name);
@@ -81,6 +95,7 @@ impl ServiceManager {
     pub async fn reset_all(&self) -> Result<(), SkinshiftError> {
         info!("Resetting all service configurations");
         
+        self.current_configs.write().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
         for (service_name, config) in &self.original_configs {
             info!("Resetting service: {}", service_name);
             
@@ -157,8 +172,10 @@ impl ServiceManager {
             self.configure_service_option(service_name, key, value).await?;
         }
         
-        // In a real implementation, we would store the current configuration
-        // self.current_configs.insert(service_name.clone(), config.clone());
+        self.current_configs
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(service_name.clone(), config.clone());
         
         debug!("Service configuration applied successfully: {}", service_name);
         
@@ -250,4 +267,16 @@ mod tests {
         assert_eq!(service_config.enabled, true);
         assert_eq!(service_config.port, Some(8080));
     }
+    
+    #[tokio::test]
+    async fn test_registered_ports_follow_configured_services() {
+        let manager = ServiceManager::new();
+        manager.configure_service("ssh", &serde_json::json!({"enabled": true, "port": 2222})).await.unwrap();
+        manager.configure_service("telnet", &serde_json::json!({"enabled": false, "port": 23})).await.unwrap();
+        manager.configure_service("http", &serde_json::json!({"enabled": true})).await.unwrap();
+        assert_eq!(manager.registered_ports(), [2222]);
+        
+        manager.reset_all().await.unwrap();
+        assert!(manager.registered_ports().is_empty());
+    }
 }
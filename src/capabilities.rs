@@ -0,0 +1,180 @@
+use crate::config::CamaleonConfig;
+pub use chame_core::{Feature, FeatureStatus};
+use std::path::Path;
+
+/// Capability bits, see capabilities(7)
+const CAP_NET_RAW: u32 = 13;
+
+/// Present when the kernel was built with the bpf() syscall
+const BPF_SYSCTL: &str = "/proc/sys/kernel/unprivileged_bpf_disabled";
+
+/// What the host lets CAMALEON do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Running as root
+    pub root: bool,
+
+    /// `iptables` is on the PATH
+    pub iptables: bool,
+
+    /// `nft` is on the PATH
+    pub nftables: bool,
+
+    /// Raw sockets can be opened, as root or with CAP_NET_RAW
+    pub packet_capture: bool,
+
+    /// The kernel supports eBPF
+    pub ebpf: bool,
+}
+
+impl Capabilities {
+    /// Check the current process and host
+    pub fn probe() -> Self {
+        let root = is_root();
+        Self {
+            root,
+            iptables: in_path("iptables"),
+            nftables: in_path("nft"),
+            packet_capture: root || has_capability(CAP_NET_RAW),
+            ebpf: Path::new(BPF_SYSCTL).exists(),
+        }
+    }
+
+    /// How each privileged feature enabled in `config` will run
+    ///
+    /// Mirrors the checks the modules make when they start, which fall
+    /// back to simulating or skip the feature rather than failing.
+    pub fn features(&self, config: &CamaleonConfig) -> Vec<Feature> {
+        let skinshift = &config.skinshift;
+
+        let firewall = if !skinshift.enabled {
+            FeatureStatus::Disabled
+        } else if skinshift.dry_run {
+            FeatureStatus::Simulated("dry_run is set".to_string())
+        } else if !self.iptables {
+            FeatureStatus::Simulated(match self.nftables {
+                true => "iptables not found, nft alone is not supported".to_string(),
+                false => "iptables not found".to_string(),
+            })
+        } else if !self.root {
+            FeatureStatus::Simulated("requires root".to_string())
+        } else {
+            FeatureStatus::Functional
+        };
+
+        let fingerprint = if !skinshift.enabled {
+            FeatureStatus::Disabled
+        } else if skinshift.dry_run {
+            FeatureStatus::Simulated("dry_run is set".to_string())
+        } else if !self.root {
+            FeatureStatus::Simulated("requires root".to_string())
+        } else {
+            FeatureStatus::Functional
+        };
+
+        let ebpf = if !config.eye360.enabled || !config.eye360.ebpf_enabled {
+            FeatureStatus::Disabled
+        } else if !self.ebpf {
+            FeatureStatus::Simulated("the kernel has no eBPF support".to_string())
+        } else if !self.root {
+            FeatureStatus::Simulated("requires root".to_string())
+        } else {
+            FeatureStatus::Functional
+        };
+
+        let packet_capture = if !config.nettongue.enabled || !config.nettongue.pcap_enabled {
+            FeatureStatus::Disabled
+        } else if !self.packet_capture {
+            FeatureStatus::Simulated("requires root or CAP_NET_RAW".to_string())
+        } else {
+            FeatureStatus::Functional
+        };
+
+        vec![
+            Feature::new("skinshift firewall rules", firewall),
+            Feature::new("skinshift OS fingerprint", fingerprint),
+            Feature::new("eye360 eBPF monitoring", ebpf),
+            Feature::new("nettongue packet capture", packet_capture),
+        ]
+    }
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+/// Whether an executable named `name` is on the PATH
+fn in_path(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+        .unwrap_or(false)
+}
+
+/// Whether the process holds a capability, from /proc on Linux
+fn has_capability(capability: u32) -> bool {
+    let Ok(status) = std::fs::read_to_string("/proc/self/status") else {
+        return false;
+    };
+
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .is_some_and(|mask| mask & (1 << capability) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features_explain_simulation() {
+        let mut config = CamaleonConfig::default();
+        config.skinshift.dry_run = false;
+        config.eye360.ebpf_enabled = true;
+
+        let unprivileged = Capabilities {
+            nftables: true,
+            ebpf: true,
+            ..Default::default()
+        };
+        let features = unprivileged.features(&config);
+        assert_eq!(
+            features.iter().map(|feature| &feature.status).collect::<Vec<_>>(),
+            [
+                &FeatureStatus::Simulated("iptables not found, nft alone is not supported".to_string()),
+                &FeatureStatus::Simulated("requires root".to_string()),
+                &FeatureStatus::Simulated("requires root".to_string()),
+                &FeatureStatus::Simulated("requires root or CAP_NET_RAW".to_string()),
+            ]
+        );
+
+        let root = Capabilities {
+            root: true,
+            iptables: true,
+            nftables: false,
+            packet_capture: true,
+            ebpf: true,
+        };
+        assert!(root.features(&config).iter().all(|feature| feature.status == FeatureStatus::Functional));
+
+        config.eye360.enabled = false;
+        config.skinshift.dry_run = true;
+        let features = root.features(&config);
+        assert_eq!(features[0].status, FeatureStatus::Simulated("dry_run is set".to_string()));
+        assert_eq!(features[2].status, FeatureStatus::Disabled);
+    }
+
+    #[test]
+    fn test_probe() {
+        let capabilities = Capabilities::probe();
+        assert_eq!(capabilities.root, is_root());
+        assert!(!capabilities.root || capabilities.packet_capture);
+    }
+}
@@ -1,9 +1,12 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use chame_core::OverflowPolicy;
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
-use std::path::Path;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct CamaleonConfig {
     pub general: GeneralConfig,
     pub skinshift: SkinshiftConfig,
@@ -11,31 +14,126 @@ pub struct CamaleonConfig {
     pub nettongue: NettongueConfig,
     pub lurefield: LurefieldConfig,
     pub posture: PostureConfig,
+    #[serde(default)]
+    pub correlation: CorrelationConfig,
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    #[serde(default)]
+    pub formats: FormatsConfig,
+    pub api: ApiConfig,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct GeneralConfig {
     pub log_level: String,
+    #[serde(default)]
+    pub log_format: LogFormat,
     pub adaptive_mode: bool,
     pub default_posture: String,
+    pub event_store_path: Option<String>,
+    #[serde(default = "default_event_store_max_mb")]
+    pub event_store_max_mb: u64,
+    #[serde(default = "default_event_store_max_age_hours")]
+    pub event_store_max_age_hours: u64,
+    #[serde(default = "default_event_store_keep")]
+    pub event_store_keep: usize,
+    #[serde(default)]
+    pub event_store_compress: bool,
 }
 
-#[derive(Debug, Deserialize)]
+fn default_event_store_max_mb() -> u64 {
+    chame_core::RotationPolicy::default().max_bytes / (1024 * 1024)
+}
+
+fn default_event_store_max_age_hours() -> u64 {
+    chame_core::RotationPolicy::default().max_age.map_or(0, |max_age| max_age.as_secs() / 3600)
+}
+
+fn default_event_store_keep() -> usize {
+    chame_core::RotationPolicy::default().keep
+}
+
+impl GeneralConfig {
+    /// When the event store rolls over, 0 disabling a limit
+    pub fn event_store_rotation(&self) -> chame_core::RotationPolicy {
+        chame_core::RotationPolicy {
+            max_bytes: self.event_store_max_mb * 1024 * 1024,
+            max_age: (self.event_store_max_age_hours > 0)
+                .then(|| Duration::from_secs(self.event_store_max_age_hours * 3600)),
+            keep: self.event_store_keep,
+            compress: self.event_store_compress,
+        }
+    }
+}
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+
+    /// One JSON object per line, for log aggregators
+    Json,
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct SkinshiftConfig {
     pub enabled: bool,
     pub presets_dir: String,
     pub rotation_interval: Option<u64>,
+    #[serde(default = "default_firewall_backup_path")]
+    pub firewall_backup_path: String,
+    #[serde(default = "default_restore_leftover_rules")]
+    pub restore_leftover_rules: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub watch_presets: bool,
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+    #[serde(default)]
+    pub random_rotation_interval: Option<u64>,
+}
+
+fn default_firewall_backup_path() -> String {
+    skinshift::SkinshiftConfig::default().firewall_backup_path.display().to_string()
+}
+
+fn default_restore_leftover_rules() -> bool {
+    skinshift::SkinshiftConfig::default().restore_leftover_rules
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct Eye360Config {
     pub enabled: bool,
     pub syscall_monitoring: bool,
     pub log_suspicious: bool,
     pub ebpf_enabled: bool,
+    #[serde(default = "default_monitored_syscalls")]
+    pub monitored_syscalls: Vec<String>,
+    #[serde(default)]
+    pub event_overflow: OverflowPolicy,
 }
 
-#[derive(Debug, Deserialize)]
+fn default_monitored_syscalls() -> Vec<String> {
+    eye360::Eye360Config::default().monitored_syscalls
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct NettongueConfig {
     pub enabled: bool,
     pub pcap_enabled: bool,
@@ -43,29 +141,270 @@ pub struct NettongueConfig {
     pub latency_fuzz_enabled: bool,
     pub latency_fuzz_min_ms: u64,
     pub latency_fuzz_max_ms: u64,
+    #[serde(default)]
+    pub latency_fuzz_seed: Option<u64>,
+    #[serde(default)]
+    pub event_overflow: OverflowPolicy,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct LurefieldConfig {
     pub enabled: bool,
     pub honeypot_dir: String,
     pub max_honeypots: u32,
     pub auto_deploy: bool,
+    #[serde(default)]
+    pub event_overflow: OverflowPolicy,
+    #[serde(default = "default_honeypot_bind_address")]
+    pub bind_address: String,
+    #[serde(default = "default_capture_bytes")]
+    pub capture_bytes: usize,
+    #[serde(default = "default_restart_attempts")]
+    pub restart_attempts: u32,
+    #[serde(default = "default_restart_backoff_ms")]
+    pub restart_backoff_ms: u64,
+    #[serde(default = "default_restart_reset_after_secs")]
+    pub restart_reset_after_secs: u64,
+    #[serde(default = "default_posture_honeypots")]
+    pub posture_honeypots: HashMap<String, Vec<String>>,
+    #[serde(default = "default_interaction_milestones")]
+    pub interaction_milestones: Vec<u32>,
+    #[serde(default)]
+    pub registry_path: Option<String>,
+    #[serde(default)]
+    pub port_policy: lurefield::PortPolicy,
+    #[serde(default = "default_max_concurrent_connections")]
+    pub max_concurrent_connections: usize,
+    #[serde(default = "default_max_connections_per_honeypot")]
+    pub max_connections_per_honeypot: usize,
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
 }
 
-#[derive(Debug, Deserialize)]
+fn default_honeypot_bind_address() -> String {
+    lurefield::LurefieldConfig::default().bind_address.to_string()
+}
+
+fn default_capture_bytes() -> usize {
+    lurefield::LurefieldConfig::default().capture_bytes
+}
+
+fn default_restart_attempts() -> u32 {
+    lurefield::LurefieldConfig::default().restart_attempts
+}
+
+fn default_restart_backoff_ms() -> u64 {
+    lurefield::LurefieldConfig::default().restart_backoff.as_millis() as u64
+}
+
+fn default_restart_reset_after_secs() -> u64 {
+    lurefield::LurefieldConfig::default().restart_reset_after.as_secs()
+}
+
+fn default_posture_honeypots() -> HashMap<String, Vec<String>> {
+    lurefield::LurefieldConfig::default().posture_honeypots
+}
+
+fn default_interaction_milestones() -> Vec<u32> {
+    lurefield::LurefieldConfig::default().interaction_milestones
+}
+
+fn default_max_concurrent_connections() -> usize {
+    lurefield::LurefieldConfig::default().max_concurrent_connections
+}
+
+fn default_max_connections_per_honeypot() -> usize {
+    lurefield::LurefieldConfig::default().max_connections_per_honeypot
+}
+
+fn default_listen_backlog() -> u32 {
+    lurefield::LurefieldConfig::default().listen_backlog
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct PostureConfig {
     pub change_threshold: f64,
     pub service_rotation_enabled: bool,
     pub service_rotation_interval: u64,
     pub postures: Vec<String>,
+    #[serde(default)]
+    pub transitions: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub event_overflow: OverflowPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    pub enabled: bool,
+    pub rules: Vec<chame_core::CorrelationRule>,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        let defaults = chame_core::CorrelationConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            rules: defaults.rules,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MetricsConfig {
+    pub sample_interval_secs: u64,
+    pub rate_window_secs: u64,
+    #[serde(default = "default_report_interval_secs")]
+    pub report_interval_secs: u64,
+    #[serde(default = "default_report_window_secs")]
+    pub report_window_secs: u64,
+}
+
+fn default_report_interval_secs() -> u64 {
+    30
+}
+
+fn default_report_window_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub interval_secs: u64,
+    pub heartbeat_timeout_secs: u64,
+    pub max_restarts: u32,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        let defaults = crate::watchdog::WatchdogConfig::default();
+        Self {
+            interval_secs: defaults.interval.as_secs(),
+            heartbeat_timeout_secs: defaults.heartbeat_timeout.as_secs(),
+            max_restarts: defaults.restarts.max_attempts,
+            max_backoff_secs: defaults.restarts.max_delay.as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ReportsConfig {
+    pub enabled: bool,
+    pub interval_hours: u64,
+    pub template_dir: String,
+    pub output_dir: String,
+    pub webhook_url: Option<String>,
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: reports::ReportSchedule::default().interval.as_secs() / 3600,
+            template_dir: "./reports/templates".to_string(),
+            output_dir: "./reports/output".to_string(),
+            webhook_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FormatsConfig {
+    pub enabled: bool,
+    pub yara_rules_dir: Option<String>,
+    pub plugin_dir: Option<String>,
+}
+
+impl Default for FormatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            yara_rules_dir: None,
+            plugin_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    #[serde(alias = "bind_address", deserialize_with = "one_or_many")]
+    pub bind_addresses: Vec<String>,
+    #[serde(default)]
+    pub cors_origins: Option<Vec<String>>,
+    #[serde(default)]
+    pub event_overflow: OverflowPolicy,
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: u32,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    #[serde(default = "default_write_requests_per_second")]
+    pub write_requests_per_second: u32,
+    #[serde(default = "default_write_burst")]
+    pub write_burst: u32,
+    #[serde(default)]
+    pub allow_event_injection: bool,
+    #[serde(default)]
+    pub allow_pattern_updates: bool,
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+}
+
+/// Accept a single value where a list is expected
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+fn default_requests_per_second() -> u32 {
+    pigment_api::PigmentApiConfig::default().requests_per_second
+}
+
+fn default_burst() -> u32 {
+    pigment_api::PigmentApiConfig::default().burst
+}
+
+fn default_write_requests_per_second() -> u32 {
+    pigment_api::PigmentApiConfig::default().write_requests_per_second
+}
+
+fn default_write_burst() -> u32 {
+    pigment_api::PigmentApiConfig::default().write_burst
+}
+
+impl Default for CamaleonConfig {
+    /// The configuration written by `config init`
+    fn default() -> Self {
+        Config::builder()
+            .add_source(File::from_str(&default_config_toml(), config::FileFormat::Toml))
+            .build()
+            .and_then(Config::try_deserialize)
+            .expect("The default configuration is complete")
+    }
 }
 
 impl CamaleonConfig {
     pub fn load(config_path: Option<&Path>) -> Result<Self, ConfigError> {
         let mut builder = Config::builder()
-            // Start with default config
-            .add_source(File::with_name("config/default"))
+            // Start with default config, a file from `config init` can stand alone
+            .add_source(File::with_name("config/default").required(false))
             // Add environment variables with prefix CAMALEON
             .add_source(Environment::with_prefix("CAMALEON").separator("__"));
 
@@ -78,9 +417,875 @@ impl CamaleonConfig {
         let config = builder.build()?;
         config.try_deserialize()
     }
+    
+    /// Check values that deserialize fine but can't be used
+    pub fn validate(&self) -> Result<()> {
+        if self.general.log_level.parse::<tracing_subscriber::filter::LevelFilter>().is_err() {
+            bail!("Invalid log level: {}", self.general.log_level);
+        }
+        
+        self.posture.to_engine_config()?;
+        self.correlation.to_engine_config()?;
+        chame_core::Posture::try_from(self.general.default_posture.as_str())?;
+        
+        self.lurefield.to_module_config()?;
+        self.api.to_module_config()?;
+        eye360::syscalls::resolve_all(&self.eye360.monitored_syscalls)?;
+        self.watchdog.to_watchdog_config()?;
+        
+        if self.reports.enabled {
+            if self.general.event_store_path.is_none() {
+                bail!("reports.enabled needs general.event_store_path, reports are built from the event store");
+            }
+            self.reports.to_schedule()?;
+        }
+        
+        if self.nettongue.latency_fuzz_min_ms > self.nettongue.latency_fuzz_max_ms {
+            bail!(
+                "Invalid latency fuzz range: {}-{} ms",
+                self.nettongue.latency_fuzz_min_ms,
+                self.nettongue.latency_fuzz_max_ms
+            );
+        }
+        
+        Ok(())
+    }
+}
+
+impl SkinshiftConfig {
+    /// Build the Skinshift service configuration
+    pub fn to_module_config(&self) -> skinshift::SkinshiftConfig {
+        skinshift::SkinshiftConfig {
+            presets_dir: self.presets_dir.clone(),
+            firewall_backup_path: PathBuf::from(&self.firewall_backup_path),
+            restore_leftover_rules: self.restore_leftover_rules,
+            dry_run: self.dry_run,
+            watch_presets: self.watch_presets,
+            random_seed: self.random_seed,
+            random_rotation_interval: self
+                .random_rotation_interval
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+impl FormatsConfig {
+    /// Build the Formats module, reporting its detections to `events`
+    pub fn to_formats(&self, events: tokio::sync::mpsc::Sender<chame_core::Event>) -> Result<formats::Formats> {
+        let formats = formats::Formats::new(events);
+        let formats = match &self.yara_rules_dir {
+            #[cfg(feature = "yara")]
+            Some(dir) => formats
+                .with_yara_rules(dir)
+                .with_context(|| format!("Failed to load YARA rules from {}", dir))?,
+            #[cfg(not(feature = "yara"))]
+            Some(_) => bail!("formats.yara_rules_dir needs a build with the yara feature"),
+            None => formats,
+        };
+        match &self.plugin_dir {
+            #[cfg(feature = "plugins")]
+            Some(dir) => {
+                let mut formats = formats;
+                formats
+                    .load_plugins(dir)
+                    .with_context(|| format!("Failed to read analyzer plugins from {}", dir))?;
+                Ok(formats)
+            }
+            #[cfg(not(feature = "plugins"))]
+            Some(_) => bail!("formats.plugin_dir needs a build with the plugins feature"),
+            None => Ok(formats),
+        }
+    }
+}
+
+impl Eye360Config {
+    /// Build the Eye360 module configuration
+    pub fn to_module_config(&self) -> eye360::Eye360Config {
+        eye360::Eye360Config {
+            syscall_monitoring: self.syscall_monitoring,
+            log_suspicious: self.log_suspicious,
+            ebpf_enabled: self.ebpf_enabled,
+            monitored_syscalls: self.monitored_syscalls.clone(),
+        }
+    }
+}
+
+impl NettongueConfig {
+    /// Build the NetTongue module configuration
+    pub fn to_module_config(&self) -> nettongue::NetTongueConfig {
+        nettongue::NetTongueConfig {
+            pcap_enabled: self.pcap_enabled,
+            interface: self.interface.clone(),
+            latency_fuzz_enabled: self.latency_fuzz_enabled,
+            latency_fuzz_min_ms: self.latency_fuzz_min_ms,
+            latency_fuzz_max_ms: self.latency_fuzz_max_ms,
+            latency_fuzz_seed: self.latency_fuzz_seed,
+            ..Default::default()
+        }
+    }
+}
+
+impl LurefieldConfig {
+    /// Build the Lurefield module configuration, rejecting invalid addresses
+    /// and unknown postures
+    pub fn to_module_config(&self) -> Result<lurefield::LurefieldConfig> {
+        let bind_address = self
+            .bind_address
+            .parse()
+            .with_context(|| format!("Invalid honeypot bind address: {}", self.bind_address))?;
+        
+        let mut posture_honeypots = HashMap::new();
+        for (posture, honeypots) in &self.posture_honeypots {
+            let posture = chame_core::Posture::try_from(posture.as_str())
+                .context("Unknown posture in lurefield.posture_honeypots")?;
+            posture_honeypots.insert(posture.to_str().to_string(), honeypots.clone());
+        }
+        
+        if self.interaction_milestones.contains(&0) {
+            bail!("lurefield.interaction_milestones must be positive interaction counts");
+        }
+        
+        Ok(lurefield::LurefieldConfig {
+            honeypot_dir: PathBuf::from(&self.honeypot_dir),
+            max_honeypots: self.max_honeypots,
+            auto_deploy: self.auto_deploy,
+            bind_address,
+            capture_bytes: self.capture_bytes,
+            restart_attempts: self.restart_attempts,
+            restart_backoff: Duration::from_millis(self.restart_backoff_ms),
+            restart_reset_after: Duration::from_secs(self.restart_reset_after_secs),
+            posture_honeypots,
+            interaction_milestones: self.interaction_milestones.clone(),
+            registry_path: self.registry_path.as_ref().map(PathBuf::from),
+            port_policy: self.port_policy,
+            max_concurrent_connections: self.max_concurrent_connections,
+            max_connections_per_honeypot: self.max_connections_per_honeypot,
+            listen_backlog: self.listen_backlog,
+            ..Default::default()
+        })
+    }
+}
+
+impl ApiConfig {
+    /// Build the PigmentAPI configuration, rejecting invalid addresses
+    pub fn to_module_config(&self) -> Result<pigment_api::PigmentApiConfig> {
+        if self.bind_addresses.is_empty() {
+            bail!("At least one API bind address is required");
+        }
+        
+        let bind_addresses = self
+            .bind_addresses
+            .iter()
+            .map(|address| {
+                address
+                    .parse()
+                    .with_context(|| format!("Invalid API bind address: {}", address))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        let trusted_proxies = self
+            .trusted_proxies
+            .iter()
+            .map(|proxy| proxy.parse().with_context(|| format!("Invalid trusted proxy: {}", proxy)))
+            .collect::<Result<Vec<_>>>()?;
+        
+        if self.requests_per_second == 0 || self.write_requests_per_second == 0 {
+            bail!("API requests_per_second must be at least 1");
+        }
+        
+        if self.burst == 0 || self.write_burst == 0 {
+            bail!("API burst must be at least 1");
+        }
+        
+        if self.api_keys.iter().any(|key| key.trim().is_empty()) {
+            bail!("API keys can't be empty");
+        }
+        
+        Ok(pigment_api::PigmentApiConfig {
+            bind_addresses,
+            cors_origins: self.cors_origins.clone(),
+            requests_per_second: self.requests_per_second,
+            burst: self.burst,
+            write_requests_per_second: self.write_requests_per_second,
+            write_burst: self.write_burst,
+            allow_event_injection: self.allow_event_injection,
+            allow_pattern_updates: self.allow_pattern_updates,
+            trust_forwarded_for: self.trust_forwarded_for,
+            trusted_proxies,
+            api_keys: self.api_keys.clone(),
+        })
+    }
+}
+
+impl PostureConfig {
+    /// Build the PostureEngine configuration, rejecting unknown postures
+    pub fn to_engine_config(&self) -> Result<posture_engine::PostureEngineConfig> {
+        if !(0.0..=1.0).contains(&self.change_threshold) {
+            bail!(
+                "Posture change_threshold must be between 0.0 and 1.0, got {}",
+                self.change_threshold
+            );
+        }
+        
+        if self.postures.is_empty() {
+            bail!("At least one posture must be allowed");
+        }
+        
+        let postures = self
+            .postures
+            .iter()
+            .map(|name| chame_core::Posture::try_from(name.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        let transitions = self
+            .transitions
+            .iter()
+            .map(|(from, targets)| {
+                let targets = targets
+                    .iter()
+                    .map(|name| chame_core::Posture::try_from(name.as_str()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((chame_core::Posture::try_from(from.as_str())?, targets))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        
+        Ok(posture_engine::PostureEngineConfig {
+            change_threshold: self.change_threshold,
+            service_rotation_enabled: self.service_rotation_enabled,
+            service_rotation_interval: self.service_rotation_interval,
+            postures,
+            transitions,
+        })
+    }
+}
+
+impl CorrelationConfig {
+    /// Build the correlation engine configuration, rejecting rules that can't fire
+    pub fn to_engine_config(&self) -> Result<chame_core::CorrelationConfig> {
+        for rule in &self.rules {
+            if rule.sequence.is_empty() {
+                bail!("Correlation rule {} has no event types in its sequence", rule.name);
+            }
+        }
+        
+        Ok(chame_core::CorrelationConfig {
+            enabled: self.enabled,
+            rules: self.rules.clone(),
+        })
+    }
+}
+
+impl WatchdogConfig {
+    /// Build the watchdog configuration, `None` when restarts are disabled
+    pub fn to_watchdog_config(&self) -> Result<Option<crate::watchdog::WatchdogConfig>> {
+        if self.interval_secs == 0 {
+            return Ok(None);
+        }
+        
+        if self.heartbeat_timeout_secs == 0 {
+            bail!("Watchdog heartbeat_timeout_secs must be positive");
+        }
+        
+        let interval = Duration::from_secs(self.interval_secs);
+        Ok(Some(crate::watchdog::WatchdogConfig {
+            interval,
+            heartbeat_timeout: Duration::from_secs(self.heartbeat_timeout_secs),
+            restarts: chame_core::RetryPolicy {
+                max_attempts: self.max_restarts,
+                initial_delay: interval,
+                max_delay: Duration::from_secs(self.max_backoff_secs).max(interval),
+                ..Default::default()
+            },
+        }))
+    }
+}
+
+impl ReportsConfig {
+    /// Build the report schedule
+    pub fn to_schedule(&self) -> Result<reports::ReportSchedule> {
+        if self.interval_hours == 0 {
+            bail!("reports.interval_hours must be positive");
+        }
+        // The scheduler adds the interval to the current instant
+        let interval = self.interval_hours.checked_mul(3600).map(Duration::from_secs);
+        let Some(interval) =
+            interval.filter(|interval| std::time::Instant::now().checked_add(*interval).is_some())
+        else {
+            bail!("reports.interval_hours is too large: {}", self.interval_hours);
+        };
+        if let Some(url) = &self.webhook_url {
+            if !url.starts_with("http://") {
+                bail!("reports.webhook_url must be an http:// URL: {}", url);
+            }
+        }
+        
+        Ok(reports::ReportSchedule {
+            interval,
+            webhook_url: self.webhook_url.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Render a fully commented configuration using the modules' defaults
+pub fn default_config_toml() -> String {
+    let skinshift = skinshift::SkinshiftConfig::default();
+    let eye360 = eye360::Eye360Config::default();
+    let nettongue = nettongue::NetTongueConfig::default();
+    let lurefield = lurefield::LurefieldConfig::default();
+    let posture = posture_engine::PostureEngineConfig::default();
+    let api = pigment_api::PigmentApiConfig::default();
+    
+    let mut posture_honeypots: Vec<String> = lurefield
+        .posture_honeypots
+        .iter()
+        .map(|(posture, honeypots)| {
+            let honeypots: Vec<String> = honeypots.iter().map(|honeypot| format!("\"{}\"", honeypot)).collect();
+            format!("{} = [{}]", posture, honeypots.join(", "))
+        })
+        .collect();
+    posture_honeypots.sort();
+    let posture_honeypots = posture_honeypots.join("\n");
+    
+    let postures = posture
+        .postures
+        .iter()
+        .map(|p| format!("    \"{}\",", p.to_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let interaction_milestones = lurefield
+        .interaction_milestones
+        .iter()
+        .map(|count| count.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    
+    let monitored_syscalls = eye360
+        .monitored_syscalls
+        .iter()
+        .map(|syscall| format!("\"{}\"", syscall))
+        .collect::<Vec<_>>()
+        .join(", ");
+    
+    let bind_addresses = api
+        .bind_addresses
+        .iter()
+        .map(|address| format!("\"{}\"", address))
+        .collect::<Vec<_>>()
+        .join(", ");
+    
+    let cors_origins = match &api.cors_origins {
+        Some(origins) => format!(
+            "cors_origins = [{}]",
+            origins.iter().map(|origin| format!("\"{}\"", origin)).collect::<Vec<_>>().join(", ")
+        ),
+        None => "# cors_origins = []".to_string(),
+    };
+    
+    format!(
+        r#"# CAMALEON configuration
+# Generated by `camaleon config init`. Pass it with `--config <file>`;
+# environment variables such as CAMALEON__GENERAL__LOG_LEVEL also apply.
+
+[general]
+# trace, debug, info, warn or error. Reloaded on SIGHUP
+log_level = "info"
+# text, or json for log aggregators
+log_format = "text"
+# Let the adaptive engine react to events
+adaptive_mode = true
+# Posture used at startup: silent, neutral, mimetic, fulgurant or unstable
+default_posture = "neutral"
+# Record every event as JSONL for audit/replay
+# event_store_path = "/var/lib/camaleon/events.jsonl"
+# Roll the event store over past this size or age, 0 = no limit
+event_store_max_mb = {event_store_max_mb}
+event_store_max_age_hours = {event_store_max_age_hours}
+# Rolled over files kept as events.jsonl.1, .2...
+event_store_keep = {event_store_keep}
+# Gzip rolled over files, which are then no longer replayed
+event_store_compress = false
+
+[skinshift]
+# OS fingerprint and banner morphing
+enabled = true
+# Directory holding fingerprint presets
+presets_dir = "{presets_dir}"
+# Seconds between preset rotations, 0 = disabled
+rotation_interval = 3600
+# The original firewall ruleset is saved here on startup
+firewall_backup_path = "{firewall_backup_path}"
+# Restore the backup if a stale CAMALEON chain is found
+restore_leftover_rules = {restore_leftover_rules}
+# Log fingerprint/firewall changes without applying them
+dry_run = {dry_run}
+# Reload presets automatically when their files change
+watch_presets = {watch_presets}
+# Seed the Unstable posture's random fingerprints to reproduce them
+# random_seed = 42
+# Seconds between new random fingerprints in the Unstable posture, 0 = disabled
+random_rotation_interval = 3600
+
+[eye360]
+# System activity monitoring
+enabled = true
+syscall_monitoring = {syscall_monitoring}
+# Syscalls to trace, checked against this architecture's syscall table;
+# open, fork, stat... map to openat, clone, newfstatat where only those exist
+monitored_syscalls = [{monitored_syscalls}]
+log_suspicious = {log_suspicious}
+# Requires root permissions
+ebpf_enabled = {ebpf_enabled}
+# block, drop or spill when the event channel is full
+event_overflow = "drop"
+
+[nettongue]
+# Network monitoring
+enabled = true
+pcap_enabled = {pcap_enabled}
+# Interface to capture on
+interface = "{interface}"
+# Add random latency to confuse timing attacks
+latency_fuzz_enabled = {latency_fuzz_enabled}
+# Latency range in milliseconds. Reloaded on SIGHUP
+latency_fuzz_min_ms = {latency_fuzz_min_ms}
+latency_fuzz_max_ms = {latency_fuzz_max_ms}
+# Seed the fuzzed latencies to reproduce them
+# latency_fuzz_seed = 42
+event_overflow = "drop"
+
+[lurefield]
+# Honeypot deployment
+enabled = true
+# Directory containing honeypot templates
+honeypot_dir = "{honeypot_dir}"
+# Maximum number of honeypots deployed at once
+max_honeypots = {max_honeypots}
+auto_deploy = {auto_deploy}
+event_overflow = "spill"
+# Address the honeypot listeners bind to
+bind_address = "{honeypot_bind_address}"
+# Bytes captured from each connection; the payload is kept when a
+# honeypot logs keystrokes
+capture_bytes = {capture_bytes}
+# Restarts of a crashed honeypot listener before it is given up, and the
+# delay before the first one, doubled for each further attempt
+restart_attempts = {restart_attempts}
+restart_backoff_ms = {restart_backoff_ms}
+# Seconds a restarted listener must stay up for its restarts to be forgotten
+restart_reset_after_secs = {restart_reset_after_secs}
+# Interaction counts at which a honeypot raises a SecurityAlert
+interaction_milestones = [{interaction_milestones}]
+# Save the active honeypots with their interaction counts and deploy them
+# again on the next start
+# registry_path = "/var/lib/camaleon/honeypots.json"
+# Honeypots on a privileged port without root, or on the port of a service
+# Skinshift configured: "warn" deploys them anyway, "deny" refuses them
+port_policy = "warn"
+# Connections handled at once across all honeypots and by each one, 0 for
+# no limit; connections over them are closed right away
+max_concurrent_connections = {max_concurrent_connections}
+max_connections_per_honeypot = {max_connections_per_honeypot}
+# Pending connections queued by the kernel for each honeypot listener
+listen_backlog = {listen_backlog}
+
+# Honeypots run in each posture: definition names from honeypot_dir or
+# types such as ssh, http, ftp or db:mysql. On a posture change the ones
+# of the previous posture are stopped; postures not listed run none
+[lurefield.posture_honeypots]
+{posture_honeypots}
+
+[posture]
+# Threat level (0.0 - 1.0) that triggers a posture change. Reloaded on SIGHUP
+change_threshold = {change_threshold}
+service_rotation_enabled = {service_rotation_enabled}
+# Seconds between service rotations
+service_rotation_interval = {service_rotation_interval}
+# Postures the engine may switch to. Reloaded on SIGHUP
+postures = [
+{postures}
+]
+# Posture changes must not be lost
+event_overflow = "block"
+# Postures each posture may switch to directly. Other changes go through
+# the intermediate postures of the shortest allowed path; postures not
+# listed may switch to any other. Reloaded on SIGHUP
+# [posture.transitions]
+# silent = ["neutral"]
+# neutral = ["silent", "mimetic"]
+# mimetic = ["neutral", "fulgurant", "unstable"]
+
+[correlation]
+# Raise a SecurityAlert when related events follow each other
+enabled = {correlation_enabled}
+# Rules replacing the built-in scan_then_honeypot and alert_burst: the
+# event types of sequence seen in that order within window_secs, all from
+# one source address when same_source is set
+# [[correlation.rules]]
+# name = "scan_then_honeypot"
+# sequence = ["NetworkActivity", "HoneypotActivity"]
+# window_secs = 60
+# same_source = true
+# severity = "Critical"
+
+[metrics]
+# How often the events_per_second/threat_level gauges are refreshed
+sample_interval_secs = 10
+# Rolling window for the event rate
+rate_window_secs = 60
+# How often a MetricsReport event is published, 0 to disable
+report_interval_secs = 30
+# Time range covered by each MetricsReport
+report_window_secs = 300
+
+[watchdog]
+# Seconds between module health checks, 0 to disable restarts
+interval_secs = 10
+# A module not answering a health check within this many seconds is restarted
+heartbeat_timeout_secs = 5
+# Restarts in a row before a module is given up on
+max_restarts = 3
+# Cap on the wait between two restarts, which doubles from interval_secs
+max_backoff_secs = 300
+
+[reports]
+# Write a summary of detections, posture changes and honeypot engagement
+# every interval_hours; needs general.event_store_path
+enabled = false
+interval_hours = {report_interval_hours}
+template_dir = "./reports/templates"
+output_dir = "./reports/output"
+# POST a JSON summary of each report here, plain HTTP only
+# webhook_url = "http://127.0.0.1:9000/camaleon"
+
+[formats]
+# File analysis (CSV, logs...), its detection patterns are served by the API
+enabled = true
+# Also scan every file with the .yar/.yara rules of this directory; needs a
+# build with the yara feature
+# yara_rules_dir = "./rules"
+# Load the analyzer plugins (shared libraries) of this directory; needs a
+# build with the plugins feature
+# plugin_dir = "./plugins"
+
+[api]
+# Local control API
+enabled = true
+# One or more addresses, e.g. ["127.0.0.1:8080", "[::1]:8080"]
+bind_addresses = [{bind_addresses}]
+# Browser origins allowed to call the API, e.g. ["https://console.example.com"].
+# Leave unset to disable CORS, [] allows same-origin requests only
+{cors_origins}
+# Requests sent through the API must not be lost
+event_overflow = "block"
+# Per client IP rate limits; excess requests get 429 Too Many Requests
+requests_per_second = {requests_per_second}
+burst = {burst}
+# Stricter limits for posture changes and module toggles
+write_requests_per_second = {write_requests_per_second}
+write_burst = {write_burst}
+# Let POST /api/events publish synthetic events; only enable for testing
+allow_event_injection = {allow_event_injection}
+# Let POST /api/formats/patterns replace the detection patterns
+allow_pattern_updates = {allow_pattern_updates}
+# Identify clients by the X-Forwarded-For header in the access log and the
+# rate limits; only enable behind a reverse proxy that sets it
+trust_forwarded_for = {trust_forwarded_for}
+# Proxies chained before the one connecting, skipped in X-Forwarded-For;
+# the client is the last address that isn't one of them
+# trusted_proxies = ["10.0.0.2"]
+# Keys clients must send as "Authorization: Bearer <key>". Unset leaves the
+# API open; /healthz and /readyz never require one
+# api_keys = ["change-me"]
+"#,
+        event_store_max_mb = default_event_store_max_mb(),
+        event_store_max_age_hours = default_event_store_max_age_hours(),
+        event_store_keep = default_event_store_keep(),
+        presets_dir = skinshift.presets_dir,
+        firewall_backup_path = skinshift.firewall_backup_path.display(),
+        restore_leftover_rules = skinshift.restore_leftover_rules,
+        dry_run = skinshift.dry_run,
+        watch_presets = skinshift.watch_presets,
+        syscall_monitoring = eye360.syscall_monitoring,
+        monitored_syscalls = monitored_syscalls,
+        log_suspicious = eye360.log_suspicious,
+        ebpf_enabled = eye360.ebpf_enabled,
+        pcap_enabled = nettongue.pcap_enabled,
+        interface = nettongue.interface,
+        latency_fuzz_enabled = nettongue.latency_fuzz_enabled,
+        latency_fuzz_min_ms = nettongue.latency_fuzz_min_ms,
+        latency_fuzz_max_ms = nettongue.latency_fuzz_max_ms,
+        honeypot_dir = lurefield.honeypot_dir.display(),
+        max_honeypots = lurefield.max_honeypots,
+        auto_deploy = lurefield.auto_deploy,
+        honeypot_bind_address = lurefield.bind_address,
+        capture_bytes = lurefield.capture_bytes,
+        restart_attempts = lurefield.restart_attempts,
+        restart_backoff_ms = lurefield.restart_backoff.as_millis(),
+        restart_reset_after_secs = lurefield.restart_reset_after.as_secs(),
+        posture_honeypots = posture_honeypots,
+        interaction_milestones = interaction_milestones,
+        report_interval_hours = ReportsConfig::default().interval_hours,
+        max_concurrent_connections = lurefield.max_concurrent_connections,
+        max_connections_per_honeypot = lurefield.max_connections_per_honeypot,
+        listen_backlog = lurefield.listen_backlog,
+        change_threshold = posture.change_threshold,
+        service_rotation_enabled = posture.service_rotation_enabled,
+        service_rotation_interval = posture.service_rotation_interval,
+        postures = postures,
+        correlation_enabled = CorrelationConfig::default().enabled,
+        bind_addresses = bind_addresses,
+        cors_origins = cors_origins,
+        requests_per_second = api.requests_per_second,
+        burst = api.burst,
+        write_requests_per_second = api.write_requests_per_second,
+        write_burst = api.write_burst,
+        allow_event_injection = api.allow_event_injection,
+        allow_pattern_updates = api.allow_pattern_updates,
+        trust_forwarded_for = api.trust_forwarded_for,
+    )
+}
+
+/// Write the default configuration to `path`
+///
+/// An existing file is only replaced when `force` is set.
+pub fn write_default_config(path: &Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        bail!("{} already exists, use --force to overwrite it", path.display());
+    }
+    
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    
+    std::fs::write(path, default_config_toml())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
 }
 
 pub fn init_config(config_path: Option<&Path>) -> Result<CamaleonConfig> {
     let config = CamaleonConfig::load(config_path)?;
+    config.validate()?;
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_config_toml_is_complete() {
+        let config: CamaleonConfig = Config::builder()
+            .add_source(File::from_str(&default_config_toml(), config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        
+        config.validate().unwrap();
+        assert_eq!(config.nettongue.latency_fuzz_max_ms, 200);
+        assert_eq!(config.api.bind_addresses, ["127.0.0.1:8080"]);
+        assert_eq!(config.posture.postures.len(), 5);
+        assert_eq!(config.watchdog, WatchdogConfig::default());
+        assert_eq!(config.reports, ReportsConfig::default());
+        assert_eq!(config.formats, FormatsConfig::default());
+        
+        // Reports are built from the event store
+        let mut reporting = config.clone();
+        reporting.reports.enabled = true;
+        assert!(reporting.validate().is_err());
+        reporting.general.event_store_path = Some("events.jsonl".to_string());
+        assert_eq!(reporting.reports.to_schedule().unwrap().interval, Duration::from_secs(24 * 3600));
+        reporting.validate().unwrap();
+        for interval_hours in [u64::MAX, u64::MAX / 3600] {
+            reporting.reports.interval_hours = interval_hours;
+            assert!(reporting.validate().is_err());
+        }
+        assert_eq!(config.general.event_store_rotation(), chame_core::RotationPolicy::default());
+        assert_eq!(config.eye360.monitored_syscalls, eye360::Eye360Config::default().monitored_syscalls);
+        
+        let mut typo = config.clone();
+        typo.eye360.monitored_syscalls.push("execv".to_string());
+        assert!(typo.validate().is_err());
+        assert_eq!(config.lurefield.posture_honeypots["mimetic"], ["ssh", "http", "db:mysql"]);
+        
+        let mut lurefield = config.lurefield.clone();
+        lurefield.posture_honeypots.insert("Silent".to_string(), Vec::new());
+        assert!(lurefield.to_module_config().unwrap().posture_honeypots.contains_key("silent"));
+        lurefield.posture_honeypots.insert("stealthy".to_string(), Vec::new());
+        assert!(lurefield.to_module_config().is_err());
+        
+        assert_eq!(config.lurefield.to_module_config().unwrap().interaction_milestones, [1, 10, 100]);
+        let mut lurefield = config.lurefield.clone();
+        lurefield.interaction_milestones.push(0);
+        assert!(lurefield.to_module_config().is_err());
+    }
+    
+    #[test]
+    fn test_write_default_config_refuses_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/camaleon.toml");
+        
+        write_default_config(&path, false).unwrap();
+        assert!(write_default_config(&path, false).is_err());
+        write_default_config(&path, true).unwrap();
+    }
+    
+    #[test]
+    fn test_api_accepts_single_bind_address() {
+        let toml = default_config_toml().replace(
+            r#"bind_addresses = ["127.0.0.1:8080"]"#,
+            r#"bind_address = "0.0.0.0:9090""#,
+        );
+        let config: CamaleonConfig = Config::builder()
+            .add_source(File::from_str(&toml, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        
+        let api = config.api.to_module_config().unwrap();
+        assert_eq!(api.bind_addresses, ["0.0.0.0:9090".parse().unwrap()]);
+    }
+    
+    #[test]
+    fn test_api_keys() {
+        let parse = |toml: String| -> CamaleonConfig {
+            Config::builder()
+                .add_source(File::from_str(&toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        
+        // None by default, the API stays open
+        assert!(CamaleonConfig::default().api.to_module_config().unwrap().api_keys.is_empty());
+        
+        let mut config = parse(default_config_toml().replace("# api_keys = ", "api_keys = "));
+        assert_eq!(config.api.to_module_config().unwrap().api_keys, ["change-me"]);
+        
+        config.api.api_keys.push(" ".to_string());
+        assert!(config.api.to_module_config().is_err());
+    }
+
+    #[test]
+    fn test_correlation_section() {
+        let parse = |toml: String| -> CamaleonConfig {
+            Config::builder()
+                .add_source(File::from_str(&toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        
+        // The built-in rules by default
+        let config = CamaleonConfig::default();
+        assert_eq!(config.correlation.to_engine_config().unwrap(), chame_core::CorrelationConfig::default());
+        
+        let mut config = parse(default_config_toml().replace(
+            "enabled = true\n# Rules replacing",
+            "enabled = false\n\
+             [[correlation.rules]]\n\
+             name = \"scan_then_alert\"\n\
+             sequence = [\"NetworkActivity\", \"SecurityAlert\"]\n\
+             window_secs = 10\n\
+             same_source = false\n\
+             severity = \"High\"\n\
+             # Rules replacing",
+        ));
+        let correlation = config.correlation.to_engine_config().unwrap();
+        assert!(!correlation.enabled);
+        assert_eq!(
+            correlation.rules,
+            [chame_core::CorrelationRule::new(
+                "scan_then_alert",
+                vec![chame_core::EventType::NetworkActivity, chame_core::EventType::SecurityAlert],
+                10,
+                chame_core::Severity::High,
+            )
+            .with_same_source(false)]
+        );
+        config.validate().unwrap();
+        
+        config.correlation.rules[0].sequence.clear();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_log_format() {
+        let parse = |toml: String| -> CamaleonConfig {
+            Config::builder()
+                .add_source(File::from_str(&toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+
+        let json = default_config_toml().replace(r#"log_format = "text""#, r#"log_format = "json""#);
+        assert_eq!(parse(json).general.log_format, LogFormat::Json);
+
+        // Older files without the setting keep text logs
+        let config = parse(default_config_toml().replace(r#"log_format = "text""#, ""));
+        assert_eq!(config.general.log_format, LogFormat::Text);
+    }
+
+    #[test]
+    fn test_older_skinshift_sections_parse() {
+        let mut toml = default_config_toml();
+        for key in [
+            "firewall_backup_path",
+            "restore_leftover_rules",
+            "dry_run",
+            "watch_presets",
+            "random_rotation_interval",
+        ] {
+            toml = toml
+                .lines()
+                .filter(|line| !line.starts_with(key))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        let config: CamaleonConfig = Config::builder()
+            .add_source(File::from_str(&toml, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        let defaults = skinshift::SkinshiftConfig::default();
+        let skinshift = config.skinshift.to_module_config();
+        assert_eq!(skinshift.firewall_backup_path, defaults.firewall_backup_path);
+        assert_eq!(skinshift.restore_leftover_rules, defaults.restore_leftover_rules);
+        assert!(!skinshift.dry_run);
+        assert!(!skinshift.watch_presets);
+        assert_eq!(skinshift.random_rotation_interval, None);
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    #[test]
+    fn test_plugins_need_the_plugins_feature() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let formats = FormatsConfig {
+            plugin_dir: Some("./plugins".to_string()),
+            ..Default::default()
+        };
+        assert!(formats.to_formats(tx).is_err());
+    }
+
+    #[cfg(not(feature = "yara"))]
+    #[test]
+    fn test_yara_rules_need_the_yara_feature() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let mut formats = FormatsConfig::default();
+        assert!(formats.to_formats(tx.clone()).is_ok());
+
+        formats.yara_rules_dir = Some("./rules".to_string());
+        assert!(formats.to_formats(tx).is_err());
+    }
+}
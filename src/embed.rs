@@ -0,0 +1,214 @@
+use crate::config::{
+    ApiConfig, CamaleonConfig, Eye360Config, LurefieldConfig, NettongueConfig, PostureConfig, SkinshiftConfig,
+};
+use crate::orchestrator::Orchestrator;
+use anyhow::{bail, Result};
+use chame_core::{Event, EventBus, Posture, SystemState};
+use tokio::sync::{broadcast, Mutex};
+
+/// Builds a [`Camaleon`] instance
+///
+/// Starts from the default configuration with every optional module
+/// disabled; each `with_*` module call enables that module.
+pub struct CamaleonBuilder {
+    config: CamaleonConfig,
+    posture: Option<Posture>,
+}
+
+impl CamaleonBuilder {
+    /// Create a builder with no optional module enabled
+    pub fn new() -> Self {
+        let mut config = CamaleonConfig::default();
+        config.skinshift.enabled = false;
+        config.eye360.enabled = false;
+        config.nettongue.enabled = false;
+        config.lurefield.enabled = false;
+        config.api.enabled = false;
+
+        Self { config, posture: None }
+    }
+
+    /// Use a complete configuration, e.g. one loaded from a file
+    pub fn with_config(mut self, config: CamaleonConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Enable Skinshift
+    pub fn with_skinshift(mut self, config: SkinshiftConfig) -> Self {
+        self.config.skinshift = SkinshiftConfig { enabled: true, ..config };
+        self
+    }
+
+    /// Enable Eye360
+    pub fn with_eye360(mut self, config: Eye360Config) -> Self {
+        self.config.eye360 = Eye360Config { enabled: true, ..config };
+        self
+    }
+
+    /// Enable NetTongue
+    pub fn with_nettongue(mut self, config: NettongueConfig) -> Self {
+        self.config.nettongue = NettongueConfig { enabled: true, ..config };
+        self
+    }
+
+    /// Enable Lurefield
+    pub fn with_lurefield(mut self, config: LurefieldConfig) -> Self {
+        self.config.lurefield = LurefieldConfig { enabled: true, ..config };
+        self
+    }
+
+    /// Enable the REST API
+    pub fn with_api(mut self, config: ApiConfig) -> Self {
+        self.config.api = ApiConfig { enabled: true, ..config };
+        self
+    }
+
+    /// Configure the posture engine, which always runs
+    pub fn with_posture_engine(mut self, config: PostureConfig) -> Self {
+        self.config.posture = config;
+        self
+    }
+
+    /// Set the posture to start in, `default_posture` otherwise
+    pub fn with_posture(mut self, posture: Posture) -> Self {
+        self.posture = Some(posture);
+        self
+    }
+
+    /// Check the configuration and set up the event bus
+    ///
+    /// Modules are constructed when the instance is started.
+    pub async fn build(self) -> Result<Camaleon> {
+        self.config.validate()?;
+
+        let posture = match self.posture {
+            Some(posture) => posture,
+            None => Posture::try_from(self.config.general.default_posture.as_str())?,
+        };
+
+        Ok(Camaleon {
+            config: self.config,
+            posture,
+            bus: EventBus::default(),
+            orchestrator: Mutex::new(None),
+        })
+    }
+}
+
+impl Default for CamaleonBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A CAMALEON instance embedded in another service
+///
+/// The event bus outlives start and stop, so subscribers keep receiving
+/// events across restarts.
+pub struct Camaleon {
+    config: CamaleonConfig,
+    posture: Posture,
+    bus: EventBus,
+    orchestrator: Mutex<Option<Orchestrator>>,
+}
+
+impl Camaleon {
+    /// Start the core and the enabled modules
+    ///
+    /// Like `camaleon start`, a module that fails to start is logged and
+    /// left out, see [`Camaleon::running_modules`].
+    pub async fn start(&self) -> Result<()> {
+        let mut orchestrator = self.orchestrator.lock().await;
+        if orchestrator.is_some() {
+            bail!("CAMALEON is already running");
+        }
+
+        *orchestrator =
+            Some(Orchestrator::start(&self.config, self.posture, self.bus.clone()).await?);
+        Ok(())
+    }
+
+    /// Stop every module, then the core
+    pub async fn stop(&self) -> Result<()> {
+        match self.orchestrator.lock().await.take() {
+            Some(orchestrator) => {
+                orchestrator.shutdown().await;
+                Ok(())
+            }
+            None => bail!("CAMALEON is not running"),
+        }
+    }
+
+    /// Switch to a new posture
+    pub async fn set_posture(&self, posture: Posture) -> Result<()> {
+        match &*self.orchestrator.lock().await {
+            Some(orchestrator) => orchestrator.set_posture(posture).await,
+            None => bail!("CAMALEON is not running"),
+        }
+    }
+
+    /// Receive every event handled by the core
+    pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
+        self.bus.subscribe()
+    }
+
+    /// Current state of the core
+    pub async fn get_state(&self) -> Result<SystemState> {
+        match &*self.orchestrator.lock().await {
+            Some(orchestrator) => orchestrator.state().await,
+            None => bail!("CAMALEON is not running"),
+        }
+    }
+
+    /// Names of the running modules, empty when stopped
+    pub async fn running_modules(&self) -> Vec<&'static str> {
+        match &*self.orchestrator.lock().await {
+            Some(orchestrator) => orchestrator.running_modules(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::EventType;
+
+    #[tokio::test]
+    async fn test_embedded_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let camaleon = CamaleonBuilder::new()
+            .with_lurefield(LurefieldConfig {
+                honeypot_dir: dir.path().display().to_string(),
+                auto_deploy: false,
+                ..CamaleonConfig::default().lurefield
+            })
+            .with_posture(Posture::Silent)
+            .build()
+            .await
+            .unwrap();
+        let mut events = camaleon.subscribe_events();
+        assert!(camaleon.get_state().await.is_err());
+
+        camaleon.start().await.unwrap();
+        assert_eq!(camaleon.running_modules().await, ["lurefield", "posture_engine"]);
+        assert_eq!(camaleon.get_state().await.unwrap().current_posture, Posture::Silent);
+
+        camaleon.set_posture(Posture::Fulgurant).await.unwrap();
+        assert_eq!(camaleon.get_state().await.unwrap().current_posture, Posture::Fulgurant);
+
+        // The bus carried both the starting posture and the change
+        let mut postures = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if event.event_type == EventType::PostureChange && event.source == "core" {
+                postures.push(event.data.unwrap()["new_posture"].clone());
+            }
+        }
+        assert_eq!(postures, ["Silent", "Fulgurant"]);
+
+        camaleon.stop().await.unwrap();
+        assert!(camaleon.running_modules().await.is_empty());
+        assert!(camaleon.stop().await.is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, bail, Context, Result};
+use camaleon::config::ApiConfig;
+use chame_core::Severity;
+use hyper::{header, http::request::Builder, Body, Client, Request, StatusCode};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+
+/// Every severity, for case-insensitive parsing
+const SEVERITIES: [Severity; 5] = [
+    Severity::Critical,
+    Severity::High,
+    Severity::Medium,
+    Severity::Low,
+    Severity::Info,
+];
+
+/// Build the `POST /api/events` body for `camaleon emit`
+///
+/// `data` must be a JSON document; `severity` is one of critical, high,
+/// medium, low or info, in any case.
+pub fn event_request(
+    event_type: &str,
+    source: &str,
+    data: Option<&str>,
+    severity: Option<&str>,
+) -> Result<serde_json::Value> {
+    if event_type.trim().is_empty() {
+        bail!("The event type can't be empty");
+    }
+
+    let data = data
+        .map(|data| serde_json::from_str::<serde_json::Value>(data).context("Invalid --data JSON"))
+        .transpose()?;
+
+    let severity = severity
+        .map(|name| {
+            SEVERITIES
+                .into_iter()
+                .find(|severity| format!("{:?}", severity).eq_ignore_ascii_case(name))
+                .ok_or_else(|| anyhow!("Unknown severity: {} (critical, high, medium, low or info)", name))
+        })
+        .transpose()?;
+
+    Ok(serde_json::json!({
+        "event_type": event_type,
+        "source": source,
+        "severity": severity,
+        "data": data,
+    }))
+}
+
+/// URL of the local API, from its first bind address
+///
+/// A wildcard bind address is reached through the loopback address.
+pub fn api_url(config: &ApiConfig) -> Result<String> {
+    let address = config.bind_addresses.first().ok_or_else(|| anyhow!("No API bind address configured"))?;
+    let mut address: SocketAddr =
+        address.parse().with_context(|| format!("Invalid API bind address: {}", address))?;
+
+    if address.ip().is_unspecified() {
+        address.set_ip(match address.ip() {
+            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+
+    Ok(format!("http://{}", address))
+}
+
+/// URL and key to reach the local API, taken from the configuration unless
+/// given on the command line
+///
+/// The key defaults to the first of `api_keys`, none when the list is empty.
+pub fn api_access(url: Option<&str>, key: Option<&str>, config_path: Option<&Path>) -> Result<(String, Option<String>)> {
+    if let (Some(url), Some(key)) = (url, key) {
+        return Ok((url.to_string(), Some(key.to_string())));
+    }
+
+    let config = camaleon::config::init_config(config_path)?.api;
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => api_url(&config)?,
+    };
+    Ok((url, key.map(str::to_string).or_else(|| config.api_keys.first().cloned())))
+}
+
+/// Send `api_key` as a bearer token, if any
+pub fn with_api_key(request: Builder, api_key: Option<&str>) -> Builder {
+    match api_key {
+        Some(key) => request.header(header::AUTHORIZATION, format!("Bearer {}", key)),
+        None => request,
+    }
+}
+
+/// Publish the event through a running instance's API
+///
+/// Returns the event as recorded by the API.
+pub async fn publish(api_url: &str, api_key: Option<&str>, request: &serde_json::Value) -> Result<serde_json::Value> {
+    let url = format!("{}/api/events", api_url.trim_end_matches('/'));
+    let http_request = with_api_key(Request::post(&url), api_key)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(request)?))
+        .with_context(|| format!("Invalid API URL: {}", api_url))?;
+
+    let response = Client::new()
+        .request(http_request)
+        .await
+        .with_context(|| format!("Failed to reach the CAMALEON API at {}, is it running?", api_url))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+
+    match status {
+        StatusCode::ACCEPTED => Ok(body),
+        StatusCode::UNAUTHORIZED => bail!("The API refused the key, pass --api-key or set api_keys in [api]"),
+        StatusCode::FORBIDDEN => bail!("The API refused the event, set allow_event_injection = true in [api]"),
+        _ => bail!(
+            "The API rejected the event ({}): {}",
+            status,
+            body["error"].as_str().unwrap_or("no details")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::{EventSender, EventType};
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_event_request() {
+        let request = event_request("scanner.finding", "nmap", Some(r#"{"host": "10.0.0.5"}"#), Some("high")).unwrap();
+        assert_eq!(request["severity"], "High");
+        assert_eq!(request["data"]["host"], "10.0.0.5");
+
+        let request = event_request("scanner.finding", "nmap", None, None).unwrap();
+        assert!(request["severity"].is_null() && request["data"].is_null());
+
+        let error = event_request("scanner.finding", "nmap", Some("{host: 1}"), None).unwrap_err();
+        assert!(format!("{:#}", error).contains("line 1 column 2"), "{:#}", error);
+        assert!(event_request("scanner.finding", "nmap", None, Some("severe")).is_err());
+        assert!(event_request(" ", "nmap", None, None).is_err());
+    }
+
+    #[test]
+    fn test_api_url() {
+        let mut config = camaleon::CamaleonConfig::default().api;
+        config.bind_addresses = vec!["0.0.0.0:9090".to_string(), "127.0.0.1:8080".to_string()];
+        assert_eq!(api_url(&config).unwrap(), "http://127.0.0.1:9090");
+
+        config.bind_addresses = vec!["[::]:8080".to_string()];
+        assert_eq!(api_url(&config).unwrap(), "http://[::1]:8080");
+    }
+
+    #[tokio::test]
+    async fn test_publish_custom_event() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut config = camaleon::CamaleonConfig::default().api;
+        config.bind_addresses = vec![format!("127.0.0.1:{}", port)];
+        config.allow_event_injection = true;
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let (_api_tx, api_rx) = mpsc::channel(16);
+        let api = pigment_api::PigmentApi::new(config.to_module_config().unwrap(), EventSender::from(tx), api_rx)
+            .await
+            .unwrap();
+        let server = tokio::spawn(async move { api.start().await });
+
+        let url = api_url(&config).unwrap();
+        let request = event_request("scanner.finding", "nmap", Some(r#"{"port": 22}"#), Some("critical")).unwrap();
+        let mut published = Err(anyhow!("not attempted"));
+        for _ in 0..50 {
+            published = publish(&url, None, &request).await;
+            if published.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(published.unwrap()["severity"], "Critical");
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::Custom("scanner.finding".to_string()));
+        assert_eq!(event.source, "nmap");
+        assert_eq!(event.severity(), Severity::Critical);
+        assert_eq!(event.data.unwrap()["port"], 22);
+
+        server.abort();
+    }
+}
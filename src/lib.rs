@@ -0,0 +1,17 @@
+//! CAMALEON as a library
+//!
+//! [`CamaleonBuilder`] wires the core, the event bus and the enabled modules
+//! the same way `camaleon start` does, for services embedding CAMALEON.
+
+pub mod capabilities;
+pub mod config;
+pub mod logging;
+pub mod orchestrator;
+pub mod reload;
+pub mod sim;
+pub mod watchdog;
+
+mod embed;
+
+pub use config::CamaleonConfig;
+pub use embed::{Camaleon, CamaleonBuilder};
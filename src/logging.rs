@@ -0,0 +1,29 @@
+use crate::config::LogFormat;
+use crate::reload::LogHandle;
+use anyhow::{Context, Result};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter};
+
+/// Install the global subscriber, writing `format` lines at `level`
+///
+/// The filter sits behind a reload layer so SIGHUP can change the level.
+pub fn init(format: LogFormat, level: &str) -> Result<LogHandle> {
+    let filter =
+        EnvFilter::try_new(level).with_context(|| format!("Invalid log level: {}", level))?;
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let (text, json) = match format {
+        LogFormat::Text => (Some(tracing_subscriber::fmt::layer()), None),
+        LogFormat::Json => (None, Some(tracing_subscriber::fmt::layer().json())),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
+        .try_init()
+        .context("Logging was already initialized")?;
+
+    tracing::info!("Logging initialized (format: {}, level: {})", format, level);
+    Ok(handle)
+}
@@ -1,8 +1,17 @@
+use anyhow::Context;
+use camaleon::capabilities::{Capabilities, FeatureStatus};
+use camaleon::{config, logging, orchestrator, reload};
+use chame_core::{ChameleonService, EventBus, InvalidPosture, Posture};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
-mod config;
+mod emit;
+mod metrics;
+mod preset;
+mod state;
 
 #[derive(Parser)]
 #[command(
@@ -30,8 +39,8 @@ enum Commands {
     /// Start the CAMALEON protective service
     Start {
         /// Operation mode (silent, neutral, mimetic, fulgurant, unstable)
-        #[arg(short, long, default_value = "neutral")]
-        mode: String,
+        #[arg(short, long, default_value = "neutral", value_parser = parse_posture)]
+        mode: Posture,
     },
 
     /// Manage skin shifting capabilities (OS fingerprint, banners)
@@ -82,6 +91,79 @@ enum Commands {
         log_keystroke: bool,
     },
 
+    /// Inspect fingerprint presets
+    Preset {
+        #[command(subcommand)]
+        action: PresetCommands,
+    },
+
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+
+    /// Publish a custom event through the running instance's API
+    Emit {
+        /// Event type name, e.g. `scanner.finding`
+        #[arg(long = "type", value_name = "NAME")]
+        event_type: String,
+
+        /// Tool or component reporting the event
+        #[arg(long, default_value = "cli")]
+        source: String,
+
+        /// JSON payload of the event
+        #[arg(long, value_name = "JSON")]
+        data: Option<String>,
+
+        /// Severity (critical, high, medium, low, info), info by default
+        #[arg(long)]
+        severity: Option<String>,
+
+        /// API base URL, derived from the [api] bind address by default
+        #[arg(long, value_name = "URL")]
+        api: Option<String>,
+
+        /// API key, the first of the [api] api_keys by default
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+
+    /// Summarize recorded activity from the event store
+    Metrics {
+        /// Window ending now, e.g. `30m`, `24h` or `7d` (1h by default)
+        #[arg(long, value_name = "DURATION", value_parser = metrics::parse_duration, conflicts_with_all = ["from", "to"])]
+        last: Option<chrono::Duration>,
+
+        /// Start of the range, as an RFC 3339 timestamp
+        #[arg(long, value_name = "TIMESTAMP")]
+        from: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// End of the range, as an RFC 3339 timestamp, now by default
+        #[arg(long, value_name = "TIMESTAMP")]
+        to: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Print the raw JSON report
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the state of the running instance, from its API
+    State {
+        /// Print the raw JSON snapshot
+        #[arg(long)]
+        json: bool,
+
+        /// API base URL, derived from the [api] bind address by default
+        #[arg(long, value_name = "URL")]
+        api: Option<String>,
+
+        /// API key, the first of the [api] api_keys by default
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+
     /// Control defensive posture of the system
     Posture {
         /// Rotate exposed services
@@ -89,26 +171,107 @@ enum Commands {
         rotate_services: bool,
 
         /// Set specific posture (silent, neutral, mimetic, fulgurant, unstable)
+        #[arg(long, value_parser = parse_posture)]
+        set: Option<Posture>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetCommands {
+    /// List available presets with their descriptions
+    List,
+
+    /// Show every setting of a preset
+    Show {
+        /// Preset name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Write a commented configuration file with the default values
+    Init {
+        /// Where to write the file
+        #[arg(short, long, default_value = "camaleon.toml")]
+        output: PathBuf,
+
+        /// Overwrite an existing file
         #[arg(long)]
-        set: Option<String>,
+        force: bool,
     },
 }
 
-fn main() -> anyhow::Result<()> {
+/// Parse a `--mode` or `--set` posture name, whatever its case
+fn parse_posture(name: &str) -> Result<Posture, InvalidPosture> {
+    Posture::try_from(name)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
     // Parse command line arguments
     let cli = Cli::parse();
     
-    // Initialize logging
-    if cli.verbose {
-        tracing_subscriber::fmt::init();
+    // Other commands only log with --verbose, `start` logs as configured
+    if cli.verbose && !matches!(cli.command, Commands::Start { .. }) {
+        logging::init(config::LogFormat::Text, "debug")?;
     }
     
     // Process commands
     match &cli.command {
         Commands::Start { mode } => {
-            println!("{} CAMALEON in {} mode", "Starting".green().bold(), mode.cyan());
+            println!("{} CAMALEON in {} mode", "Starting".green().bold(), mode.to_str().cyan());
             println!("{}...", "Initializing adaptive defense systems".yellow());
-            // TODO: Implement actual start logic
+            
+            let config = config::init_config(cli.config.as_deref())?;
+            
+            // Set up logging once, before any service starts; --verbose
+            // overrides the configured level
+            let level = if cli.verbose { "debug" } else { config.general.log_level.as_str() };
+            let log_handle = logging::init(config.general.log_format, level)?;
+            
+            // Say upfront which defenses will only be simulated, and why
+            println!("{}", "Capabilities:".bold());
+            for feature in Capabilities::probe().features(&config) {
+                match feature.status {
+                    FeatureStatus::Functional => println!("- {}: {}", feature.name, "Functional".green()),
+                    FeatureStatus::Simulated(reason) => {
+                        println!("- {}: {} ({})", feature.name, "Simulated".yellow().bold(), reason);
+                        tracing::warn!("{} will only be simulated: {}", feature.name, reason);
+                    }
+                    FeatureStatus::Unavailable(reason) => {
+                        println!("- {}: {} ({})", feature.name, "Unavailable".red().bold(), reason);
+                        tracing::warn!("{} is unavailable: {}", feature.name, reason);
+                    }
+                    FeatureStatus::Disabled => println!("- {}: {}", feature.name, "Disabled".dimmed()),
+                }
+            }
+            
+            let orchestrator = orchestrator::Orchestrator::start(&config, *mode, EventBus::default()).await?;
+            
+            // Report what came up and what didn't
+            for name in orchestrator.running_modules() {
+                println!("- {}: {}", name, "Running".green());
+            }
+            for (name, error) in orchestrator.failures() {
+                println!("- {}: {} ({:#})", name, "Failed".red().bold(), error);
+            }
+            
+            let targets = Arc::new(orchestrator.reload_targets(Some(log_handle)));
+            
+            // Reload the configuration on SIGHUP
+            #[cfg(unix)]
+            reload::spawn_sighup_handler(
+                cli.config.clone(),
+                Arc::new(RwLock::new(config)),
+                targets,
+            )?;
+            
+            println!("{} Press Ctrl-C to stop", "CAMALEON is running.".green().bold());
+            tokio::signal::ctrl_c().await?;
+            
+            println!("{} CAMALEON", "Stopping".yellow().bold());
+            orchestrator.shutdown().await;
         }
         
         Commands::Skinshift { preset, custom } => {
@@ -157,13 +320,80 @@ fn main() -> anyhow::Result<()> {
             }
         }
         
+        Commands::Preset { action } => {
+            let config = config::init_config(cli.config.as_deref())?;
+            
+            // Only read presets, never touch the firewall or the stack
+            let mut skinshift_config = config.skinshift.to_module_config();
+            skinshift_config.dry_run = true;
+            skinshift_config.watch_presets = false;
+            
+            let service = Arc::new(skinshift::SkinshiftService::new(skinshift_config).await?);
+            service.init().await?;
+            
+            match action {
+                PresetCommands::List => preset::list(service).await?,
+                PresetCommands::Show { name } => preset::show(service, name).await?,
+            }
+        }
+        
+        Commands::Config { action } => match action {
+            ConfigCommands::Init { output, force } => {
+                config::write_default_config(output, *force)?;
+                println!("{} default configuration to {}", "Wrote".green().bold(), output.display().to_string().cyan());
+                println!("Start with: chameleon --config {} start", output.display());
+            }
+        },
+        
+        Commands::Emit { event_type, source, data, severity, api, api_key } => {
+            // Reject a bad payload before touching the configuration or the network
+            let request = emit::event_request(event_type, source, data.as_deref(), severity.as_deref())?;
+            let (api_url, api_key) = emit::api_access(api.as_deref(), api_key.as_deref(), cli.config.as_deref())?;
+            
+            let event = emit::publish(&api_url, api_key.as_deref(), &request).await?;
+            println!(
+                "{} {} event from {} ({})",
+                "Published".green().bold(),
+                event_type.cyan(),
+                source,
+                event["severity"].as_str().unwrap_or("Info")
+            );
+        }
+        
+        Commands::Metrics { last, from, to, json } => {
+            let config = config::init_config(cli.config.as_deref())?;
+            let Some(store_path) = &config.general.event_store_path else {
+                anyhow::bail!("Metrics are computed from the event store, set event_store_path in [general]");
+            };
+            
+            let (start, end) = metrics::time_range(*last, *from, *to, chrono::Utc::now())?;
+            let report = metrics::from_event_store(std::path::Path::new(store_path), start, end).await?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                metrics::print_summary(&report);
+            }
+        }
+        
+        Commands::State { json, api, api_key } => {
+            let (api_url, api_key) = emit::api_access(api.as_deref(), api_key.as_deref(), cli.config.as_deref())?;
+            
+            let snapshot = state::fetch(&api_url, api_key.as_deref()).await?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&snapshot)?);
+            } else {
+                let snapshot = serde_json::from_value(snapshot).context("Unexpected state from the API")?;
+                state::print_summary(&snapshot);
+            }
+        }
+        
         Commands::Posture { rotate_services, set } => {
             if *rotate_services {
                 println!("{} service rotation", "Enabling".green().bold());
             }
             
             if let Some(posture) = set {
-                println!("{} defensive posture to: {}", "Setting".green().bold(), posture.cyan());
+                println!("{} defensive posture to: {}", "Setting".green().bold(), posture.to_str().cyan());
             }
         }
     }
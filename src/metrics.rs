@@ -0,0 +1,216 @@
+use anyhow::{bail, Context, Result};
+use chame_core::{ChameleonCore, EventStore, EventType, Severity};
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use std::path::Path;
+use tokio_stream::StreamExt;
+
+/// Parse a `--last` duration such as `90s`, `30m`, `24h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}', expected e.g. 30m or 24h", value))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => return Err(format!("Unknown unit in '{}', use s, m, h, d or w", value)),
+    };
+
+    match duration {
+        Some(duration) if amount > 0 => Ok(duration),
+        _ => Err(format!("Invalid duration '{}'", value)),
+    }
+}
+
+/// Range covered by `camaleon metrics`, the last hour by default
+pub fn time_range(
+    last: Option<Duration>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let end = to.unwrap_or(now);
+    let start = match from {
+        Some(from) => from,
+        None => {
+            let last = last.unwrap_or_else(|| Duration::hours(1));
+            match end.checked_sub_signed(last) {
+                Some(start) => start,
+                None => bail!("--last {} reaches before the earliest supported date", last),
+            }
+        }
+    };
+    if start > end {
+        bail!("--from ({}) is after --to ({})", start.to_rfc3339(), end.to_rfc3339());
+    }
+
+    Ok((start, end))
+}
+
+/// Metrics of `[start, end]`, computed by replaying the event store
+///
+/// The events are fed to a fresh core whose `get_metrics` builds the
+/// report. Gauges only live in the running instance, so the ones of the
+/// latest `MetricsReport` event in the range are restored.
+pub async fn from_event_store(path: &Path, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<serde_json::Value> {
+    if !path.is_file() {
+        bail!("No event store at {}", path.display());
+    }
+
+    let store = EventStore::open(path)
+        .await
+        .with_context(|| format!("Failed to open event store {}", path.display()))?;
+    let core = ChameleonCore::new();
+    let metrics = core.metrics();
+
+    let mut events = std::pin::pin!(store.replay(start, end).await?);
+    while let Some(event) = events.next().await {
+        if event.event_type == EventType::MetricsReport {
+            let gauges = event.data.as_ref().and_then(|data| data.pointer("/metrics/gauges")?.as_object());
+            for (name, value) in gauges.into_iter().flatten() {
+                if let Some(value) = value.as_f64() {
+                    metrics.set_gauge(name, value);
+                }
+            }
+        }
+        metrics.record_event(&event).await?;
+    }
+
+    Ok(core.get_metrics(start, end).await?)
+}
+
+/// Print event counts and gauges of a `get_metrics` report
+pub fn print_summary(metrics: &serde_json::Value) {
+    let range = &metrics["time_range"];
+    println!(
+        "{} from {} to {}",
+        "Metrics".green().bold(),
+        range["start"].as_str().unwrap_or("?"),
+        range["end"].as_str().unwrap_or("?")
+    );
+
+    let by_type = counts(&metrics["event_counters"]["by_type"]);
+    let total: u64 = by_type.iter().map(|(_, count)| count).sum();
+    println!("{}: {}", "Events".bold(), total);
+    if total == 0 {
+        return;
+    }
+
+    print_counts("By type", &by_type);
+    print_counts("By source", &counts(&metrics["event_counters"]["by_source"]));
+
+    // Most severe first rather than most frequent
+    let by_severity = counts(&metrics["event_counters"]["by_severity"]);
+    let by_severity: Vec<_> = [Severity::Critical, Severity::High, Severity::Medium, Severity::Low, Severity::Info]
+        .iter()
+        .filter_map(|severity| by_severity.iter().find(|(name, _)| name == severity.name()).cloned())
+        .collect();
+    print_counts("By severity", &by_severity);
+
+    let mut gauges: Vec<_> = metrics["gauges"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.clone(), value.as_f64()?)))
+        .collect();
+    gauges.sort_by(|a, b| a.0.cmp(&b.0));
+    if !gauges.is_empty() {
+        println!("{}", "Gauges (last report)".bold());
+        for (name, value) in gauges {
+            println!("  {}: {:.3}", name.cyan(), value);
+        }
+    }
+}
+
+/// Counts of a label → count object, most frequent first
+fn counts(object: &serde_json::Value) -> Vec<(String, u64)> {
+    let mut counts: Vec<_> = object
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, count)| Some((name.clone(), count.as_u64()?)))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+fn print_counts(title: &str, counts: &[(String, u64)]) {
+    println!("{}", title.bold());
+    for (name, count) in counts {
+        println!("  {}: {}", name.cyan(), count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::Event;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::days(7)));
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("10y").is_err());
+        assert!(parse_duration("99999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_time_range() {
+        let now = Utc::now();
+        assert_eq!(time_range(None, None, None, now).unwrap(), (now - Duration::hours(1), now));
+        assert_eq!(
+            time_range(Some(Duration::minutes(30)), None, None, now).unwrap(),
+            (now - Duration::minutes(30), now)
+        );
+
+        let from = now - Duration::days(2);
+        let to = now - Duration::days(1);
+        assert_eq!(time_range(None, Some(from), Some(to), now).unwrap(), (from, to));
+        assert_eq!(time_range(None, Some(from), None, now).unwrap(), (from, now));
+        assert!(time_range(None, Some(to), Some(from), now).is_err());
+        assert!(time_range(Some(Duration::weeks(52 * 300_000)), None, None, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_from_event_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let store = EventStore::open(&path).await.unwrap();
+
+        let mut old = Event::security_alert("eye360", None);
+        old.timestamp = Utc::now() - Duration::days(2);
+        let report = Event::metrics_report(
+            "core",
+            Some(serde_json::json!({"metrics": {"gauges": {"threat_level": 0.4, "events_per_second": 1.5}}})),
+        );
+        for event in [
+            old,
+            Event::security_alert("eye360", None),
+            Event::custom("scanner.finding", "nmap", None),
+            report,
+        ] {
+            store.append(&event).await.unwrap();
+        }
+
+        let end = Utc::now();
+        let metrics = from_event_store(&path, end - Duration::hours(1), end).await.unwrap();
+        // The report only restores gauges, it is not counted as an event
+        assert_eq!(metrics["event_counts"]["total"], 2);
+        assert_eq!(metrics["event_counters"]["by_type"]["SecurityAlert"], 1);
+        assert_eq!(metrics["event_counters"]["by_type"]["scanner.finding"], 1);
+        assert!(metrics["event_counters"]["by_source"].get("core").is_none());
+        assert_eq!(metrics["gauges"]["threat_level"], 0.4);
+
+        assert!(from_event_store(&dir.path().join("missing.jsonl"), end, end).await.is_err());
+    }
+}
@@ -0,0 +1,898 @@
+use crate::config::CamaleonConfig;
+use crate::reload::{LogHandle, ReloadTargets};
+use crate::watchdog::{Watchdog, Watched};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chame_core::{
+    AdaptiveHandler, ChameleonCore, ChameleonService, CorrelationEngine, Event, EventBus,
+    EventOrigin, EventSender, EventStore, EventType, Feature, ModuleHealth, OverflowPolicy,
+    Posture, SystemState,
+};
+use pigment_api::{ModuleControl, PigmentApiError};
+use posture_engine::PostureActor;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Capacity of the channel modules send their events on
+const MODULE_EVENT_CAPACITY: usize = 1024;
+
+/// How long detector events are gathered before the posture engine evaluates them
+const POSTURE_BATCH_WINDOW: Duration = Duration::from_secs(1);
+
+/// Modules with an `enabled` flag, which the API can start and stop
+const TOGGLEABLE_MODULES: [&str; 4] = ["skinshift", "eye360", "nettongue", "lurefield"];
+
+/// A module brought up by the orchestrator
+#[derive(Clone)]
+enum Module {
+    Skinshift(Arc<skinshift::SkinshiftService>),
+    Eye360(Arc<eye360::Eye360>),
+    NetTongue(Arc<nettongue::NetTongue>),
+    Lurefield(Arc<lurefield::Lurefield>),
+    PostureEngine(Arc<posture_engine::PostureEngine>),
+    PigmentApi(Arc<JoinHandle<()>>),
+}
+
+impl Module {
+    /// Name used in logs and lifecycle events
+    fn name(&self) -> &'static str {
+        match self {
+            Module::Skinshift(_) => "skinshift",
+            Module::Eye360(_) => "eye360",
+            Module::NetTongue(_) => "nettongue",
+            Module::Lurefield(_) => "lurefield",
+            Module::PostureEngine(_) => "posture_engine",
+            Module::PigmentApi(_) => "pigment_api",
+        }
+    }
+
+    /// Start the module again after `stop`
+    async fn start(&self) -> Result<()> {
+        match self {
+            Module::Skinshift(service) => service.start().await?,
+            Module::Eye360(service) => service.start().await?,
+            Module::NetTongue(service) => service.start().await?,
+            Module::Lurefield(service) => service.start().await?,
+            Module::PostureEngine(service) => service.start().await?,
+            Module::PigmentApi(_) => bail!("The API server can't be restarted"),
+        }
+        Ok(())
+    }
+
+    /// Stop the module
+    async fn stop(&self) -> Result<()> {
+        match self {
+            Module::Skinshift(service) => service.stop().await?,
+            Module::Eye360(service) => service.stop().await?,
+            Module::NetTongue(service) => service.stop().await?,
+            Module::Lurefield(service) => service.stop().await?,
+            Module::PostureEngine(service) => service.stop().await?,
+            // The API server has no shutdown hook, drop the server task
+            Module::PigmentApi(task) => task.abort(),
+        }
+        Ok(())
+    }
+
+    /// Health of the module
+    async fn health(&self) -> ModuleHealth {
+        match self {
+            Module::Skinshift(service) => service.health().await,
+            Module::Eye360(service) => service.health().await,
+            Module::NetTongue(service) => service.health().await,
+            Module::Lurefield(service) => service.health().await,
+            Module::PostureEngine(service) => service.health().await,
+            Module::PigmentApi(task) if task.is_finished() => {
+                ModuleHealth::Unhealthy("API server exited".to_string())
+            }
+            Module::PigmentApi(_) => ModuleHealth::Healthy,
+        }
+    }
+
+    /// Privileged features of the module and how they run
+    fn features(&self) -> Vec<Feature> {
+        match self {
+            Module::Skinshift(service) => service.features(),
+            Module::Eye360(service) => service.features(),
+            Module::NetTongue(service) => service.features(),
+            Module::Lurefield(_) | Module::PostureEngine(_) | Module::PigmentApi(_) => Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Watched for Module {
+    fn name(&self) -> &str {
+        Module::name(self)
+    }
+
+    async fn health(&self) -> ModuleHealth {
+        Module::health(self).await
+    }
+
+    async fn restart(&self) -> Result<()> {
+        if let Err(e) = self.stop().await {
+            tracing::warn!(
+                "Failed to stop {} before restarting it: {:#}",
+                Module::name(self),
+                e
+            );
+        }
+        self.start().await
+    }
+}
+
+/// Starts and stops the running modules for `POST /api/modules/{name}`
+struct ModuleController {
+    modules: Vec<Module>,
+}
+
+#[async_trait]
+impl ModuleControl for ModuleController {
+    async fn set_active(&self, name: &str, active: bool) -> Result<(), PigmentApiError> {
+        if !TOGGLEABLE_MODULES.contains(&name) {
+            return Err(PigmentApiError::NotFound(format!(
+                "Unknown module: {}",
+                name
+            )));
+        }
+
+        // Modules disabled in the configuration were never built
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.name() == name)
+            .ok_or_else(|| {
+                PigmentApiError::InvalidRequest(format!(
+                    "{} is not running, enabling it requires restart",
+                    name
+                ))
+            })?;
+
+        // Starting a running module would spawn its tasks a second time
+        let running = module.health().await != ModuleHealth::Stopped;
+        if running == active {
+            tracing::debug!(
+                "Module {} is already {}",
+                name,
+                if active { "running" } else { "stopped" }
+            );
+            return Ok(());
+        }
+
+        let result = if active {
+            module.start().await
+        } else {
+            module.stop().await
+        };
+        result.map_err(|e| {
+            PigmentApiError::ServerError(format!("Failed to toggle {}: {:#}", name, e))
+        })?;
+
+        tracing::info!(
+            "Module {} {} from the API",
+            name,
+            if active { "started" } else { "stopped" }
+        );
+        Ok(())
+    }
+
+    async fn health(&self) -> HashMap<String, ModuleHealth> {
+        let mut health = HashMap::new();
+        for module in &self.modules {
+            health.insert(module.name().to_string(), module.health().await);
+        }
+        health
+    }
+
+    async fn features(&self) -> Vec<Feature> {
+        self.modules.iter().flat_map(Module::features).collect()
+    }
+}
+
+/// Builds, starts and stops the core and all configured modules
+pub struct Orchestrator {
+    core: ChameleonCore,
+    modules: Vec<Module>,
+    failures: Vec<(&'static str, anyhow::Error)>,
+    event_sender: mpsc::Sender<Event>,
+    cancel: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
+    watchdog: Option<JoinHandle<()>>,
+}
+
+impl Orchestrator {
+    /// Start the core on `bus`, then every enabled module in dependency order
+    ///
+    /// A module that fails to start is recorded in `failures` and skipped;
+    /// only a core failure aborts startup.
+    pub async fn start(config: &CamaleonConfig, posture: Posture, bus: EventBus) -> Result<Self> {
+        // Core first, every module reports to it
+        let mut core = ChameleonCore::new().with_event_bus(bus);
+        let mut store = None;
+        if let Some(path) = &config.general.event_store_path {
+            let opened =
+                EventStore::open_with_rotation(path, config.general.event_store_rotation())
+                    .await
+                    .with_context(|| format!("Failed to open event store {}", path))?;
+            let opened = Arc::new(opened);
+            core = core.with_event_store(opened.clone());
+            store = Some(opened);
+        }
+        core.init().await.context("Failed to initialize core")?;
+        core.start().await.context("Failed to start core")?;
+
+        let (event_sender, event_receiver) = mpsc::channel(MODULE_EVENT_CAPACITY);
+        let mut orchestrator = Self {
+            core,
+            modules: Vec::new(),
+            failures: Vec::new(),
+            event_sender,
+            cancel: CancellationToken::new(),
+            tasks: Vec::new(),
+            watchdog: None,
+        };
+        orchestrator.spawn_event_forwarder(event_receiver);
+
+        // Correlated alerts go through the core like module events
+        let correlation = config.correlation.to_engine_config()?;
+        if correlation.enabled {
+            let sender = orchestrator.module_sender("correlation", OverflowPolicy::Block);
+            orchestrator
+                .tasks
+                .push(CorrelationEngine::from_config(&correlation).spawn(
+                    orchestrator.core.event_bus(),
+                    sender,
+                    orchestrator.cancel.clone(),
+                ));
+        }
+        orchestrator
+            .tasks
+            .push(orchestrator.core.spawn_metrics_sampler(
+                Duration::from_secs(config.metrics.sample_interval_secs),
+                Duration::from_secs(config.metrics.rate_window_secs),
+                orchestrator.cancel.clone(),
+            ));
+        if config.metrics.report_interval_secs > 0 {
+            orchestrator
+                .tasks
+                .push(orchestrator.core.spawn_metrics_reporter(
+                    Duration::from_secs(config.metrics.report_interval_secs),
+                    Duration::from_secs(config.metrics.report_window_secs),
+                    orchestrator.cancel.clone(),
+                ));
+        }
+
+        // Summary reports are read back from the event store
+        if let (true, Some(store)) = (config.reports.enabled, store) {
+            let reports = &config.reports;
+            let result = reports.to_schedule().and_then(|schedule| {
+                let generator =
+                    reports::ReportGenerator::new(&reports.template_dir, &reports.output_dir)?;
+                Ok(reports::ReportScheduler::new(generator, store, schedule))
+            });
+            if let Some(scheduler) = orchestrator.record("reports", result).await {
+                orchestrator
+                    .tasks
+                    .push(scheduler.spawn(orchestrator.cancel.clone()));
+            }
+        }
+
+        // Skinshift reacts to posture changes published on the bus
+        if config.skinshift.enabled {
+            let sender = orchestrator.module_sender("skinshift", OverflowPolicy::Drop);
+            let result = async {
+                let service = Arc::new(
+                    skinshift::SkinshiftService::new(config.skinshift.to_module_config())
+                        .await?
+                        .with_events(sender),
+                );
+                service.init().await?;
+                service.start().await?;
+                Ok::<_, anyhow::Error>(service)
+            }
+            .await;
+
+            if let Some(service) = orchestrator.record("skinshift", result).await {
+                let listener = service.clone();
+                orchestrator.spawn_bus_listener(move |event| {
+                    let listener = listener.clone();
+                    async move {
+                        if let Err(e) = listener.handle_event(event).await {
+                            tracing::warn!("Skinshift failed to handle event: {}", e);
+                        }
+                    }
+                });
+                orchestrator.modules.push(Module::Skinshift(service));
+            }
+        }
+
+        if config.eye360.enabled {
+            let sender = orchestrator.module_sender("eye360", config.eye360.event_overflow);
+            let result = async {
+                let service =
+                    Arc::new(eye360::Eye360::new(config.eye360.to_module_config(), sender).await?);
+                service.start().await?;
+                Ok::<_, anyhow::Error>(service)
+            }
+            .await;
+
+            if let Some(service) = orchestrator.record("eye360", result).await {
+                orchestrator.modules.push(Module::Eye360(service));
+                orchestrator
+                    .register_handler("eye360", eye360::handler::Eye360Handler::new)
+                    .await;
+            }
+        }
+
+        if config.nettongue.enabled {
+            let sender = orchestrator.module_sender("nettongue", config.nettongue.event_overflow);
+            let result = async {
+                let service = Arc::new(
+                    nettongue::NetTongue::new(config.nettongue.to_module_config(), sender).await?,
+                );
+                service.start().await?;
+                Ok::<_, anyhow::Error>(service)
+            }
+            .await;
+
+            if let Some(service) = orchestrator.record("nettongue", result).await {
+                orchestrator.modules.push(Module::NetTongue(service));
+                orchestrator
+                    .register_handler("nettongue", nettongue::handler::NetTongueHandler::new)
+                    .await;
+            }
+        }
+
+        if config.lurefield.enabled {
+            let sender = orchestrator.module_sender("lurefield", config.lurefield.event_overflow);
+            let metrics = orchestrator.core.metrics().clone();
+            let skinshift = orchestrator.modules.iter().find_map(|module| match module {
+                Module::Skinshift(service) => Some(service.clone()),
+                _ => None,
+            });
+            let result = async {
+                let mut service =
+                    lurefield::Lurefield::new(config.lurefield.to_module_config()?, sender)
+                        .await?
+                        .with_metrics(metrics);
+                // Honeypots must not shadow the services Skinshift configures
+                if let Some(skinshift) = skinshift {
+                    service = service.with_reserved_ports(move || skinshift.service_ports());
+                }
+                let service = Arc::new(service);
+                service.start().await?;
+                Ok::<_, anyhow::Error>(service)
+            }
+            .await;
+
+            if let Some(service) = orchestrator.record("lurefield", result).await {
+                // Lurefield deploys the honeypots mapped to each posture
+                let listener = service.clone();
+                orchestrator.spawn_bus_listener(move |event| {
+                    let listener = listener.clone();
+                    async move {
+                        if let Err(e) = listener.handle_event(&event).await {
+                            tracing::warn!("Lurefield failed to handle event: {}", e);
+                        }
+                    }
+                });
+                orchestrator.modules.push(Module::Lurefield(service));
+                orchestrator
+                    .register_handler("lurefield", lurefield::handler::LurefieldHandler::new)
+                    .await;
+            }
+        }
+
+        // The posture engine decides on top of the detectors started above
+        let sender = orchestrator.module_sender("posture_engine", config.posture.event_overflow);
+        let metrics = orchestrator.core.metrics().clone();
+        let result = async {
+            let service = Arc::new(
+                posture_engine::PostureEngine::new(config.posture.to_engine_config()?, sender)
+                    .await?
+                    .with_metrics(metrics),
+            );
+            service.start().await?;
+            service
+                .set_posture_by(posture, PostureActor::Manual, "startup posture")
+                .await?;
+            Ok::<_, anyhow::Error>(service)
+        }
+        .await;
+
+        if let Some(service) = orchestrator.record("posture_engine", result).await {
+            // Posture changes requested through the API go through the engine
+            let engine = service.clone();
+            let core = orchestrator.core.clone();
+            orchestrator.spawn_bus_listener(move |event| {
+                let engine = engine.clone();
+                let core = core.clone();
+                async move {
+                    if let Err(e) = apply_api_posture_change(&engine, &core, &event).await {
+                        tracing::warn!("Failed to apply posture change from the API: {}", e);
+                    }
+                }
+            });
+            orchestrator.spawn_posture_evaluator(service.clone());
+            orchestrator.modules.push(Module::PostureEngine(service));
+        }
+
+        // File analysis runs on demand, its detections reach the core
+        let formats = if config.formats.enabled {
+            let result = config.formats.to_formats(orchestrator.event_sender.clone());
+            orchestrator.record("formats", result).await.map(Arc::new)
+        } else {
+            None
+        };
+
+        // The API comes last so it only exposes modules that are up
+        if config.api.enabled {
+            let sender = orchestrator.module_sender("pigment_api", config.api.event_overflow);
+            let (api_sender, api_receiver) = mpsc::channel(MODULE_EVENT_CAPACITY);
+            let core: Arc<dyn ChameleonService> = Arc::new(orchestrator.core.clone());
+            let control = Arc::new(ModuleController {
+                modules: orchestrator.modules.clone(),
+            });
+            let modules = orchestrator.module_statuses();
+            let metrics = orchestrator.core.metrics().clone();
+            let result = async {
+                let mut api = pigment_api::PigmentApi::new(
+                    config.api.to_module_config()?,
+                    sender,
+                    api_receiver,
+                )
+                .await?
+                .with_core(core)
+                .with_metrics_collector(metrics)
+                .with_modules(modules)
+                .with_module_control(control);
+                if let Some(formats) = formats {
+                    api = api.with_formats(formats);
+                }
+                let api = Arc::new(api);
+                Ok::<_, anyhow::Error>(Arc::new(tokio::spawn(async move {
+                    if let Err(e) = api.start().await {
+                        tracing::error!("PigmentAPI server stopped: {}", e);
+                    }
+                })))
+            }
+            .await;
+
+            if let Some(task) = orchestrator.record("pigment_api", result).await {
+                orchestrator.spawn_bus_listener(move |event| {
+                    let api_sender = api_sender.clone();
+                    async move {
+                        let _ = api_sender.send(event).await;
+                    }
+                });
+                orchestrator.modules.push(Module::PigmentApi(task));
+                orchestrator
+                    .register_handler("pigment_api", pigment_api::handler::PigmentApiHandler::new)
+                    .await;
+            }
+        }
+
+        // Restart whatever stops working from now on
+        if let Some(watchdog_config) = config.watchdog.to_watchdog_config()? {
+            let mut watchdog = Watchdog::new(watchdog_config, orchestrator.core.clone());
+            for module in &orchestrator.modules {
+                watchdog.watch(Arc::new(module.clone()));
+            }
+            orchestrator.watchdog = Some(watchdog.spawn(orchestrator.cancel.clone()));
+        }
+
+        // Announce the starting posture to everyone listening on the bus
+        if let Err(e) = orchestrator.core.change_posture(posture).await {
+            tracing::warn!("Failed to set initial posture: {}", e);
+        }
+
+        Ok(orchestrator)
+    }
+
+    /// Names of the modules that are running, in start order
+    pub fn running_modules(&self) -> Vec<&'static str> {
+        self.modules.iter().map(Module::name).collect()
+    }
+
+    /// Whether each module is running, as listed by the API
+    ///
+    /// Toggleable modules that are disabled or failed to start are listed
+    /// as inactive; the API itself is listed as active since it serves the list.
+    fn module_statuses(&self) -> HashMap<String, bool> {
+        let mut statuses: HashMap<String, bool> = TOGGLEABLE_MODULES
+            .iter()
+            .map(|name| (name.to_string(), false))
+            .collect();
+        for name in self.running_modules() {
+            statuses.insert(name.to_string(), true);
+        }
+        statuses.insert("pigment_api".to_string(), true);
+        statuses
+    }
+
+    /// Switch the posture engine and the core to a new posture, as a manual change
+    pub async fn set_posture(&self, posture: Posture) -> Result<()> {
+        for module in &self.modules {
+            if let Module::PostureEngine(service) = module {
+                service.set_posture(posture).await?;
+            }
+        }
+
+        self.core.change_posture(posture).await?;
+        Ok(())
+    }
+
+    /// Current state of the core
+    pub async fn state(&self) -> Result<SystemState> {
+        Ok(self.core.get_state().await?)
+    }
+
+    /// Modules that failed to start, with the reason
+    pub fn failures(&self) -> &[(&'static str, anyhow::Error)] {
+        &self.failures
+    }
+
+    /// Services the SIGHUP handler can apply a new configuration to
+    pub fn reload_targets(&self, log_handle: Option<LogHandle>) -> ReloadTargets {
+        let mut targets = ReloadTargets {
+            log_handle,
+            ..Default::default()
+        };
+
+        for module in &self.modules {
+            match module {
+                Module::Eye360(service) => targets.eye360 = Some(service.clone()),
+                Module::NetTongue(service) => targets.nettongue = Some(service.clone()),
+                Module::Lurefield(service) => targets.lurefield = Some(service.clone()),
+                Module::PostureEngine(service) => targets.posture_engine = Some(service.clone()),
+                _ => {}
+            }
+        }
+
+        targets
+    }
+
+    /// Stop every module in reverse start order, then the core
+    pub async fn shutdown(mut self) {
+        // Modules about to be stopped must not be restarted
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+
+        while let Some(module) = self.modules.pop() {
+            match module.stop().await {
+                Ok(()) => self.announce(module.name(), "inactive").await,
+                Err(e) => tracing::error!("Failed to stop {}: {:#}", module.name(), e),
+            }
+        }
+
+        self.cancel.cancel();
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+
+        if let Err(e) = self.core.stop().await {
+            tracing::error!("Failed to stop core: {}", e);
+        }
+    }
+
+    /// Keep the outcome of a module startup and announce it
+    async fn record<T, E>(&mut self, name: &'static str, result: Result<T, E>) -> Option<T>
+    where
+        E: Into<anyhow::Error>,
+    {
+        match result {
+            Ok(service) => {
+                tracing::info!("Module {} started", name);
+                self.announce(name, "active").await;
+                Some(service)
+            }
+            Err(e) => {
+                let e = e.into();
+                tracing::error!("Module {} failed to start: {:#}", name, e);
+                self.failures.push((name, e));
+                None
+            }
+        }
+    }
+
+    /// Channel a module reports its events to the core on
+    fn module_sender(&self, module: &str, policy: OverflowPolicy) -> EventSender {
+        EventSender::new(self.event_sender.clone(), policy)
+            .with_metrics(self.core.metrics().clone(), module)
+    }
+
+    /// Route core events to a module's adaptive handler
+    ///
+    /// Handlers run inside the task draining the module channel, so they
+    /// spill instead of waiting on it.
+    async fn register_handler<H, F>(&self, name: &str, new_handler: F)
+    where
+        H: AdaptiveHandler + 'static,
+        F: FnOnce(EventSender) -> H,
+    {
+        let sender = self
+            .module_sender(&format!("{}_handler", name), OverflowPolicy::Spill)
+            .with_origin(EventOrigin::Handler);
+        self.core
+            .register_handler(name, Arc::new(Mutex::new(new_handler(sender))))
+            .await;
+    }
+
+    /// Publish a lifecycle event for a module
+    async fn announce(&self, module: &str, status: &str) {
+        let event = Event::service_lifecycle(
+            "orchestrator",
+            Some(serde_json::json!({
+                "module": module,
+                "status": status,
+            })),
+        );
+
+        if let Err(e) = self.core.handle_event(event).await {
+            tracing::warn!("Failed to record lifecycle event for {}: {}", module, e);
+        }
+    }
+
+    /// Hand every event sent by a module to the core
+    fn spawn_event_forwarder(&mut self, mut receiver: mpsc::Receiver<Event>) {
+        let core = self.core.clone();
+        let cancel = self.cancel.clone();
+
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    received = receiver.recv() => match received {
+                        Some(event) => event,
+                        None => break,
+                    },
+                };
+
+                if let Err(e) = core.handle_event(event).await {
+                    tracing::error!("Failed to handle event: {}", e);
+                }
+            }
+        }));
+    }
+
+    /// Have `engine` evaluate the detector events published on the core bus
+    ///
+    /// Events are evaluated in batches, each one gathering the alerts and
+    /// network activity seen within `POSTURE_BATCH_WINDOW` of its first event.
+    fn spawn_posture_evaluator(&mut self, engine: Arc<posture_engine::PostureEngine>) {
+        let mut receiver = self.core.event_bus().subscribe();
+        let core = self.core.clone();
+        let cancel = self.cancel.clone();
+
+        self.tasks.push(tokio::spawn(async move {
+            let mut batch = Vec::new();
+            let mut deadline = None;
+            loop {
+                let window = async move {
+                    match deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = window => {
+                        deadline = None;
+                        let events = std::mem::take(&mut batch);
+                        if let Err(e) = evaluate_detector_events(&engine, &core, &events).await {
+                            tracing::warn!("Failed to evaluate detector events: {}", e);
+                        }
+                    }
+                    received = receiver.recv() => match received {
+                        // Handlers echo detector events, which must not count twice
+                        Ok(event) if is_detector_event(&event) => {
+                            batch.push(event);
+                            deadline.get_or_insert_with(|| {
+                                tokio::time::Instant::now() + POSTURE_BATCH_WINDOW
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Posture evaluator lagged, skipped {} events", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+        }));
+    }
+
+    /// Run `handler` for every event published on the core bus
+    fn spawn_bus_listener<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(Event) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let mut receiver = self.core.event_bus().subscribe();
+        let cancel = self.cancel.clone();
+
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    received = receiver.recv() => match received {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Bus listener lagged, skipped {} events", skipped);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                };
+
+                handler(event).await;
+            }
+        }));
+    }
+}
+
+/// Whether the posture engine weighs `event` when adapting the posture
+fn is_detector_event(event: &Event) -> bool {
+    matches!(event.event_type, EventType::SecurityAlert | EventType::NetworkActivity)
+        && event.origin != EventOrigin::Handler
+}
+
+/// Have the engine evaluate a batch of detector events, then switch the core
+/// to the posture it adopted
+///
+/// Modules follow the core's posture changes, not the engine's.
+async fn evaluate_detector_events(
+    engine: &posture_engine::PostureEngine,
+    core: &ChameleonCore,
+    events: &[Event],
+) -> Result<()> {
+    if engine.evaluate_events(events).await? {
+        core.change_posture(engine.get_current_posture().await).await?;
+    }
+
+    Ok(())
+}
+
+/// Apply a posture change event published by the API to the engine and the core
+///
+/// The change is recorded in the engine's history with the API as actor and
+/// the reason given in the request. With `force` set, the engine switches
+/// straight to the posture whatever its allowed transitions.
+async fn apply_api_posture_change(
+    engine: &posture_engine::PostureEngine,
+    core: &ChameleonCore,
+    event: &Event,
+) -> Result<()> {
+    if event.event_type != EventType::PostureChange || event.source != "pigment_api" {
+        return Ok(());
+    }
+
+    let data = event
+        .data
+        .as_ref()
+        .ok_or_else(|| anyhow!("Posture change without data"))?;
+    let name = data["posture"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Posture change without a posture"))?;
+    let reason = data["reason"].as_str().unwrap_or("changed through the API");
+
+    let posture = Posture::try_from(name)?;
+    if data["force"].as_bool().unwrap_or(false) {
+        engine
+            .force_posture_by(posture, PostureActor::Api, reason)
+            .await?;
+    } else {
+        engine
+            .set_posture_by(posture, PostureActor::Api, reason)
+            .await?;
+    }
+    core.change_posture(posture).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Configuration running only the core and the posture engine
+    fn core_only_config() -> CamaleonConfig {
+        let mut config = CamaleonConfig::default();
+        config.skinshift.enabled = false;
+        config.eye360.enabled = false;
+        config.nettongue.enabled = false;
+        config.lurefield.enabled = false;
+        config.api.enabled = false;
+        config
+    }
+
+    /// Events published on the bus until one of type `until` comes through
+    async fn events_until(
+        receiver: &mut tokio::sync::broadcast::Receiver<Event>,
+        until: &EventType,
+    ) -> Vec<Event> {
+        let mut events = Vec::new();
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("The event reached the bus")
+                .unwrap();
+            let done = &event.event_type == until;
+            events.push(event);
+            if done {
+                return events;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alerts_do_not_publish_empty_posture_changes() {
+        let bus = EventBus::default();
+        let mut receiver = bus.subscribe();
+        let orchestrator = Orchestrator::start(&core_only_config(), Posture::Neutral, bus)
+            .await
+            .unwrap();
+
+        let alert = Event::security_alert("eye360", Some(serde_json::json!({ "rule": "test" })));
+        orchestrator.event_sender.send(alert).await.unwrap();
+        let mut events = events_until(&mut receiver, &EventType::SecurityAlert).await;
+
+        // Whatever the handlers sent in reaction is queued before the marker
+        let marker = EventType::Custom("test_marker".to_string());
+        orchestrator
+            .event_sender
+            .send(Event::new(marker.clone(), "test", None))
+            .await
+            .unwrap();
+        events.extend(events_until(&mut receiver, &marker).await);
+
+        for event in events.iter().filter(|e| e.event_type == EventType::PostureChange) {
+            let data = event.data.as_ref().expect("Posture changes carry data");
+            assert!(
+                data.get("posture").is_some() || data.get("new_posture").is_some(),
+                "Posture change from {} names no posture",
+                event.source
+            );
+        }
+
+        orchestrator.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_detector_events_adapt_the_posture() {
+        let bus = EventBus::default();
+        let mut receiver = bus.subscribe();
+        let orchestrator = Orchestrator::start(&core_only_config(), Posture::Neutral, bus)
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            let alert =
+                Event::security_alert_with_severity("eye360", None, chame_core::Severity::Critical);
+            orchestrator.event_sender.send(alert).await.unwrap();
+        }
+
+        // The core announces the posture the engine adopted, for the modules
+        loop {
+            let event = events_until(&mut receiver, &EventType::PostureChange)
+                .await
+                .pop()
+                .unwrap();
+            if event.source == "core" && event.data.unwrap()["new_posture"] == "Fulgurant" {
+                break;
+            }
+        }
+        let state = orchestrator.state().await.unwrap();
+        assert_eq!(state.current_posture, Posture::Fulgurant);
+
+        orchestrator.shutdown().await;
+    }
+}
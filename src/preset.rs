@@ -0,0 +1,116 @@
+use anyhow::Result;
+use chame_core::Posture;
+use colored::Colorize;
+use skinshift::{posture_preset, FingerprintPreset, SkinshiftService};
+use std::sync::Arc;
+
+/// Postures that apply the given preset
+fn postures_for(preset_name: &str) -> Vec<Posture> {
+    Posture::ALL
+        .into_iter()
+        .filter(|posture| posture_preset(*posture) == preset_name)
+        .collect()
+}
+
+/// Format the posture tag shown next to a preset name
+fn posture_tag(preset_name: &str) -> String {
+    let postures = postures_for(preset_name);
+    if postures.is_empty() {
+        return String::new();
+    }
+
+    let names: Vec<String> = postures.iter().map(|p| p.to_string()).collect();
+    format!(" [{}]", names.join(", ")).magenta().to_string()
+}
+
+/// Print the available presets with their descriptions
+pub async fn list(service: Arc<SkinshiftService>) -> Result<()> {
+    let mut names = service.list_presets().await?;
+    names.sort();
+
+    if names.is_empty() {
+        println!("{} No presets found", "Warning:".yellow().bold());
+        return Ok(());
+    }
+
+    println!("{} ({})", "Available presets".green().bold(), names.len());
+    for name in &names {
+        match service.get_preset(name).await {
+            Ok(preset) => println!("- {}{}: {}", name.cyan(), posture_tag(name), preset.description),
+            Err(e) => println!("- {}{}: {} ({})", name.cyan(), posture_tag(name), "Invalid".red(), e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print every setting of a preset
+pub async fn show(service: Arc<SkinshiftService>, name: &str) -> Result<()> {
+    let preset = service.get_preset(name).await?;
+    print_preset(&preset);
+    Ok(())
+}
+
+/// Pretty-print a preset
+fn print_preset(preset: &FingerprintPreset) {
+    let fingerprint = &preset.fingerprint;
+    let unset = || "-".dimmed().to_string();
+
+    println!("{}{}", preset.name.cyan().bold(), posture_tag(&preset.name));
+    println!("{}", preset.description);
+
+    println!("\n{}", "Fingerprint".green().bold());
+    println!("  OS family:      {}", fingerprint.os_family.yellow());
+    println!("  OS version:     {}", fingerprint.os_version.clone().unwrap_or_else(unset));
+    println!("  TTL:            {}", fingerprint.ttl.map(|v| v.to_string()).unwrap_or_else(unset));
+    println!("  MSS:            {}", fingerprint.mss.map(|v| v.to_string()).unwrap_or_else(unset));
+    println!("  Window size:    {}", fingerprint.window_size.map(|v| v.to_string()).unwrap_or_else(unset));
+    println!("  Window scaling: {}", fingerprint.window_scaling.map(|v| v.to_string()).unwrap_or_else(unset));
+    println!("  Timestamps:     {}", fingerprint.timestamps.map(|v| v.to_string()).unwrap_or_else(unset));
+    println!("  IP ID:          {}", fingerprint.ip_id_behavior.clone().unwrap_or_else(unset));
+    println!("  DF bit:         {}", fingerprint.df_bit.map(|v| v.to_string()).unwrap_or_else(unset));
+
+    let mut properties: Vec<_> = fingerprint.properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in properties {
+        println!("  {}: {}", key, value);
+    }
+
+    println!("\n{}", "Banners".green().bold());
+    if preset.banners.is_empty() {
+        println!("  {}", unset());
+    }
+    let mut banners: Vec<_> = preset.banners.iter().collect();
+    banners.sort_by(|a, b| a.0.cmp(b.0));
+    for (service, banner) in banners {
+        println!("  {}: {}", service.cyan(), banner.escape_debug());
+    }
+
+    println!("\n{}", "Firewall rules".green().bold());
+    match &preset.firewall_rules {
+        Some(rules) if !rules.is_empty() => {
+            for rule in rules {
+                let action = match rule.action.as_str() {
+                    "accept" => rule.action.green(),
+                    _ => rule.action.red(),
+                };
+                let port = rule
+                    .destination_port
+                    .as_deref()
+                    .map(|port| format!(" port {}", port))
+                    .unwrap_or_default();
+                println!("  {} {}{} ({})", action, rule.protocol, port, rule.name);
+            }
+        }
+        _ => println!("  {}", unset()),
+    }
+
+    if !preset.services.is_empty() {
+        println!("\n{}", "Services".green().bold());
+        let mut services: Vec<_> = preset.services.iter().collect();
+        services.sort_by(|a, b| a.0.cmp(b.0));
+        for (service, config) in services {
+            println!("  {}: {}", service.cyan(), config);
+        }
+    }
+}
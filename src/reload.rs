@@ -0,0 +1,257 @@
+use crate::config::CamaleonConfig;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Handle used to change the log filter at runtime
+pub type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Running services that can take a new configuration
+#[derive(Default)]
+pub struct ReloadTargets {
+    pub log_handle: Option<LogHandle>,
+    pub eye360: Option<Arc<eye360::Eye360>>,
+    pub nettongue: Option<Arc<nettongue::NetTongue>>,
+    pub lurefield: Option<Arc<lurefield::Lurefield>>,
+    pub posture_engine: Option<Arc<posture_engine::PostureEngine>>,
+}
+
+/// Settings that changed between two configurations
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    /// Settings that can be applied to the running services
+    pub live: Vec<&'static str>,
+
+    /// Settings that only take effect after a restart
+    pub restart: Vec<&'static str>,
+}
+
+impl ConfigDiff {
+    /// Compare two configurations
+    pub fn between(old: &CamaleonConfig, new: &CamaleonConfig) -> Self {
+        let mut diff = Self::default();
+
+        if old.general.log_level != new.general.log_level {
+            diff.live.push("general.log_level");
+        }
+        if old.posture.change_threshold != new.posture.change_threshold {
+            diff.live.push("posture.change_threshold");
+        }
+        if old.posture.postures != new.posture.postures {
+            diff.live.push("posture.postures");
+        }
+        if old.posture.transitions != new.posture.transitions {
+            diff.live.push("posture.transitions");
+        }
+        if old.nettongue.latency_fuzz_min_ms != new.nettongue.latency_fuzz_min_ms
+            || old.nettongue.latency_fuzz_max_ms != new.nettongue.latency_fuzz_max_ms
+        {
+            diff.live.push("nettongue.latency_fuzz");
+        }
+        if old.eye360.enabled != new.eye360.enabled {
+            diff.live.push("eye360.enabled");
+        }
+        if old.nettongue.enabled != new.nettongue.enabled {
+            diff.live.push("nettongue.enabled");
+        }
+        if old.lurefield.enabled != new.lurefield.enabled {
+            diff.live.push("lurefield.enabled");
+        }
+
+        // Anything else in a section needs a restart; copy the live fields
+        // over so they don't count
+        let mut general = new.general.clone();
+        general.log_level = old.general.log_level.clone();
+        if general != old.general {
+            diff.restart.push("general");
+        }
+
+        if new.skinshift != old.skinshift {
+            diff.restart.push("skinshift");
+        }
+
+        let mut eye360 = new.eye360.clone();
+        eye360.enabled = old.eye360.enabled;
+        if eye360 != old.eye360 {
+            diff.restart.push("eye360");
+        }
+
+        let mut nettongue = new.nettongue.clone();
+        nettongue.enabled = old.nettongue.enabled;
+        nettongue.latency_fuzz_min_ms = old.nettongue.latency_fuzz_min_ms;
+        nettongue.latency_fuzz_max_ms = old.nettongue.latency_fuzz_max_ms;
+        if nettongue != old.nettongue {
+            diff.restart.push("nettongue");
+        }
+
+        let mut lurefield = new.lurefield.clone();
+        lurefield.enabled = old.lurefield.enabled;
+        if lurefield != old.lurefield {
+            diff.restart.push("lurefield");
+        }
+
+        let mut posture = new.posture.clone();
+        posture.change_threshold = old.posture.change_threshold;
+        posture.postures = old.posture.postures.clone();
+        posture.transitions = old.posture.transitions.clone();
+        if posture != old.posture {
+            diff.restart.push("posture");
+        }
+
+        if new.metrics != old.metrics {
+            diff.restart.push("metrics");
+        }
+
+        if new.watchdog != old.watchdog {
+            diff.restart.push("watchdog");
+        }
+
+        if new.reports != old.reports {
+            diff.restart.push("reports");
+        }
+
+        if new.formats != old.formats {
+            diff.restart.push("formats");
+        }
+
+        if new.api != old.api {
+            diff.restart.push("api");
+        }
+
+        diff
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty() && self.restart.is_empty()
+    }
+}
+
+/// Reload the configuration and apply it to the running services
+pub async fn reload_config(
+    config_path: Option<&PathBuf>,
+    current: &RwLock<CamaleonConfig>,
+    targets: &ReloadTargets,
+) -> Result<ConfigDiff> {
+    // Load and validate before touching anything
+    let new = CamaleonConfig::load(config_path.map(|p| p.as_path()))?;
+    new.validate()?;
+
+    let mut current = current.write().await;
+    let diff = ConfigDiff::between(&current, &new);
+
+    for field in &diff.live {
+        if let Err(e) = apply_field(field, &new, targets).await {
+            tracing::error!("Failed to apply {}: {}", field, e);
+        }
+    }
+
+    for section in &diff.restart {
+        tracing::warn!("Changes to [{}] require restart", section);
+    }
+
+    *current = new;
+    Ok(diff)
+}
+
+/// Apply a single live setting
+async fn apply_field(field: &str, new: &CamaleonConfig, targets: &ReloadTargets) -> Result<()> {
+    match field {
+        "general.log_level" => {
+            if let Some(handle) = &targets.log_handle {
+                handle.reload(EnvFilter::new(&new.general.log_level))?;
+                tracing::info!("Log level set to {}", new.general.log_level);
+            }
+        }
+        "posture.change_threshold" | "posture.postures" | "posture.transitions" => {
+            if let Some(engine) = &targets.posture_engine {
+                engine.apply_config(new.posture.to_engine_config()?).await?;
+            }
+        }
+        "nettongue.latency_fuzz" => {
+            if let Some(nettongue) = &targets.nettongue {
+                nettongue.apply_config(new.nettongue.to_module_config()).await?;
+            }
+        }
+        "eye360.enabled" => match &targets.eye360 {
+            Some(eye360) if new.eye360.enabled => eye360.start().await?,
+            Some(eye360) => eye360.stop().await?,
+            None => tracing::warn!("Enabling eye360 requires restart"),
+        },
+        "nettongue.enabled" => match &targets.nettongue {
+            Some(nettongue) if new.nettongue.enabled => nettongue.start().await?,
+            Some(nettongue) => nettongue.stop().await?,
+            None => tracing::warn!("Enabling nettongue requires restart"),
+        },
+        "lurefield.enabled" => match &targets.lurefield {
+            Some(lurefield) if new.lurefield.enabled => lurefield.start().await?,
+            Some(lurefield) => lurefield.stop().await?,
+            None => tracing::warn!("Enabling lurefield requires restart"),
+        },
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Reload the configuration every time the process receives SIGHUP
+#[cfg(unix)]
+pub fn spawn_sighup_handler(
+    config_path: Option<PathBuf>,
+    current: Arc<RwLock<CamaleonConfig>>,
+    targets: Arc<ReloadTargets>,
+) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    Ok(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("SIGHUP received, reloading configuration");
+
+            match reload_config(config_path.as_ref(), &current, &targets).await {
+                Ok(diff) if diff.is_empty() => tracing::info!("Configuration unchanged"),
+                Ok(diff) => tracing::info!(
+                    "Configuration reloaded ({} applied, {} require restart)",
+                    diff.live.len(),
+                    diff.restart.len()
+                ),
+                Err(e) => tracing::error!("Configuration reload failed, keeping current: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_diff_splits_live_and_restart() {
+        let old = CamaleonConfig::load(None).unwrap();
+        let mut new = old.clone();
+        assert!(ConfigDiff::between(&old, &new).is_empty());
+
+        new.general.log_level = "debug".to_string();
+        new.posture.change_threshold = 0.5;
+        new.nettongue.latency_fuzz_max_ms += 100;
+        new.lurefield.enabled = !old.lurefield.enabled;
+        new.nettongue.interface = "lo".to_string();
+        new.skinshift.presets_dir = "/tmp/presets".to_string();
+        new.reports.interval_hours += 1;
+
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(
+            diff.live,
+            vec![
+                "general.log_level",
+                "posture.change_threshold",
+                "nettongue.latency_fuzz",
+                "lurefield.enabled",
+            ]
+        );
+        assert_eq!(diff.restart, vec!["skinshift", "nettongue", "reports"]);
+    }
+}
@@ -0,0 +1,290 @@
+//! Scripted attacks for testing the adaptive pipeline
+//!
+//! An [`AttackScenario`] is a sequence of stages, each a batch of events
+//! shaped like the ones the detectors send. Running it publishes every
+//! stage on the event bus and has the posture engine evaluate it, so tests
+//! can check how the posture follows an attack. The engine only acts on
+//! batches reaching its `change_threshold`, so relaxing on `calm` traffic
+//! takes a threshold of 0.4 or less:
+//!
+//! ```no_run
+//! # async fn example(bus: chame_core::EventBus, engine: posture_engine::PostureEngine) -> anyhow::Result<()> {
+//! use camaleon::sim::AttackScenario;
+//! use posture_engine::Posture;
+//! use std::time::Duration;
+//!
+//! let outcome = AttackScenario::new("scan then break in")
+//!     .port_scan("203.0.113.7", 200)
+//!     .brute_force("203.0.113.7", 5)
+//!     .honeypot_hit("203.0.113.7", 2222)
+//!     .wait(Duration::from_secs(1))
+//!     .calm(10)
+//!     .run(&bus, &engine)
+//!     .await?;
+//! outcome.assert_transitions(&[Posture::Mimetic, Posture::Fulgurant, Posture::Neutral]);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+use chame_core::{Event, EventBus, Severity0to10};
+use posture_engine::{Posture, PostureActor, PostureEngine, PostureHistoryEntry};
+use std::time::Duration;
+
+/// One step of an attack, evaluated as a single batch
+#[derive(Debug, Clone)]
+pub struct AttackStage {
+    /// Name used in assertion messages, e.g. `port_scan`
+    pub name: String,
+
+    /// Wait before the stage runs
+    pub delay: Duration,
+
+    /// Events published and evaluated together
+    pub events: Vec<Event>,
+}
+
+/// Builds and runs a scripted sequence of attack stages
+#[derive(Debug, Clone)]
+pub struct AttackScenario {
+    name: String,
+    stages: Vec<AttackStage>,
+    pace: Duration,
+    pending_wait: Duration,
+}
+
+impl AttackScenario {
+    /// Create an empty scenario, stages running back to back
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            stages: Vec::new(),
+            pace: Duration::ZERO,
+            pending_wait: Duration::ZERO,
+        }
+    }
+
+    /// Wait `pace` before each stage
+    pub fn with_pace(mut self, pace: Duration) -> Self {
+        self.pace = pace;
+        self
+    }
+
+    /// Wait `duration` more before the next stage
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.pending_wait += duration;
+        self
+    }
+
+    /// Add a stage made of arbitrary events
+    pub fn stage(mut self, name: impl Into<String>, events: Vec<Event>) -> Self {
+        self.stages.push(AttackStage {
+            name: name.into(),
+            delay: self.pace + std::mem::take(&mut self.pending_wait),
+            events,
+        });
+        self
+    }
+
+    /// `source` probing `ports` ports, as NetTongue reports it
+    pub fn port_scan(self, source: &str, ports: u16) -> Self {
+        let event = detection_event(
+            Event::network_activity_with_severity,
+            "nettongue",
+            serde_json::json!({
+                "detection_type": "PortScan",
+                "source_ip": source,
+                "details": { "ports_probed": ports },
+            }),
+            7,
+        );
+        self.stage("port_scan", vec![event])
+    }
+
+    /// `attempts` failed logins from `source`, one alert each
+    pub fn brute_force(self, source: &str, attempts: usize) -> Self {
+        let events = (0..attempts)
+            .map(|attempt| {
+                detection_event(
+                    Event::security_alert_with_severity,
+                    "eye360",
+                    serde_json::json!({
+                        "detection_type": "BruteForce",
+                        "source_ip": source,
+                        "details": { "attempt": attempt + 1 },
+                    }),
+                    8,
+                )
+            })
+            .collect();
+        self.stage("brute_force", events)
+    }
+
+    /// `source` interacting with the honeypot on `port`, raising Lurefield's
+    /// interaction milestone alert
+    pub fn honeypot_hit(self, source: &str, port: u16) -> Self {
+        let remote_addr = format!("{}:40000", source);
+        let interaction = detection_event(
+            Event::honeypot_activity_with_severity,
+            "lurefield",
+            serde_json::json!({
+                "action": "interaction",
+                "details": { "remote_addr": remote_addr, "port": port.to_string() },
+            }),
+            9,
+        );
+        let milestone = detection_event(
+            Event::security_alert_with_severity,
+            "lurefield",
+            serde_json::json!({
+                "action": "interaction_milestone",
+                "interactions": 1,
+                "remote_addr": remote_addr,
+            }),
+            9,
+        );
+        self.stage("honeypot_hit", vec![interaction, milestone])
+    }
+
+    /// `count` events of routine, low-severity traffic, once the attack is over
+    pub fn calm(self, count: usize) -> Self {
+        let events = (0..count)
+            .map(|_| {
+                detection_event(
+                    Event::network_activity_with_severity,
+                    "nettongue",
+                    serde_json::json!({ "detection_type": "UnusualConnectionPattern" }),
+                    4,
+                )
+            })
+            .collect();
+        self.stage("calm", events)
+    }
+
+    /// Stages in the order they run
+    pub fn stages(&self) -> &[AttackStage] {
+        &self.stages
+    }
+
+    /// Publish each stage on `bus` and have `engine` evaluate it
+    pub async fn run(&self, bus: &EventBus, engine: &PostureEngine) -> Result<ScenarioOutcome> {
+        let history_before = engine.get_posture_history().await.len();
+        let mut stages = Vec::with_capacity(self.stages.len());
+
+        for stage in &self.stages {
+            tokio::time::sleep(stage.delay).await;
+
+            for event in &stage.events {
+                bus.publish(event.clone());
+            }
+            engine.evaluate_events(&stage.events).await?;
+
+            stages.push(StageOutcome {
+                name: stage.name.clone(),
+                threat_level: PostureEngine::threat_level(&stage.events),
+                posture: engine.get_current_posture().await,
+            });
+        }
+
+        let mut changes = engine.get_posture_history().await;
+        changes.drain(..history_before.min(changes.len()));
+
+        Ok(ScenarioOutcome {
+            scenario: self.name.clone(),
+            stages,
+            changes,
+        })
+    }
+}
+
+/// Event of a detector with a precise 0-10 `score`
+fn detection_event(
+    new: fn(String, Option<serde_json::Value>, chame_core::Severity) -> Event,
+    source: &str,
+    mut data: serde_json::Value,
+    score: u8,
+) -> Event {
+    let severity = Severity0to10::clamped(score);
+    data["severity"] = serde_json::json!(severity.get());
+    new(source.to_string(), Some(data), severity.level())
+}
+
+/// How the engine reacted to one stage
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageOutcome {
+    /// Stage name
+    pub name: String,
+
+    /// Threat level of the stage's events
+    pub threat_level: f64,
+
+    /// Posture once the stage was evaluated
+    pub posture: Posture,
+}
+
+/// Result of running a scenario
+#[derive(Debug, Clone)]
+pub struct ScenarioOutcome {
+    /// Scenario name
+    pub scenario: String,
+
+    /// Outcome of each stage, in order
+    pub stages: Vec<StageOutcome>,
+
+    /// Posture changes made while the scenario ran, oldest first
+    pub changes: Vec<PostureHistoryEntry>,
+}
+
+impl ScenarioOutcome {
+    /// Postures adopted by the engine on its own, in order
+    pub fn adaptive_postures(&self) -> Vec<Posture> {
+        self.changes
+            .iter()
+            .filter(|change| change.actor == PostureActor::Adaptive)
+            .map(|change| change.posture)
+            .collect()
+    }
+
+    /// Posture after the last stage
+    pub fn final_posture(&self) -> Option<&Posture> {
+        self.stages.last().map(|stage| &stage.posture)
+    }
+
+    /// Panic unless the engine adopted exactly `expected`, in order
+    pub fn assert_transitions(&self, expected: &[Posture]) {
+        assert_eq!(
+            self.adaptive_postures(),
+            expected,
+            "unexpected posture transitions in scenario '{}', stages: {}",
+            self.scenario,
+            self.describe_stages()
+        );
+    }
+
+    /// Panic unless the posture after stage `name` is `expected`
+    ///
+    /// With several stages of that name, the first one is checked.
+    pub fn assert_posture_after(&self, name: &str, expected: Posture) {
+        let stage = self
+            .stages
+            .iter()
+            .find(|stage| stage.name == name)
+            .unwrap_or_else(|| panic!("scenario '{}' has no stage '{}'", self.scenario, name));
+        assert_eq!(
+            stage.posture, expected,
+            "unexpected posture after '{}' in scenario '{}', stages: {}",
+            name,
+            self.scenario,
+            self.describe_stages()
+        );
+    }
+
+    /// `name (threat level) -> posture` for each stage
+    fn describe_stages(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| format!("{} ({:.2}) -> {}", stage.name, stage.threat_level, stage.posture.to_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{bail, Context, Result};
+use chame_core::SystemState;
+use colored::Colorize;
+use hyper::{Body, Client, Request, StatusCode};
+
+/// Fetch the core's state snapshot from a running instance's API
+///
+/// Returns the raw JSON, printed as is by `camaleon state --json`.
+pub async fn fetch(api_url: &str, api_key: Option<&str>) -> Result<serde_json::Value> {
+    let url = format!("{}/api/state", api_url.trim_end_matches('/'));
+    let request = crate::emit::with_api_key(Request::get(&url), api_key)
+        .body(Body::empty())
+        .with_context(|| format!("Invalid API URL: {}", api_url))?;
+
+    let response = Client::new()
+        .request(request)
+        .await
+        .with_context(|| format!("Failed to reach the CAMALEON API at {}, is it running?", api_url))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+
+    match status {
+        StatusCode::OK => Ok(body),
+        StatusCode::UNAUTHORIZED => bail!("The API refused the key, pass --api-key or set api_keys in [api]"),
+        _ => bail!(
+            "The API could not report the state ({}): {}",
+            status,
+            body["error"].as_str().unwrap_or("no details")
+        ),
+    }
+}
+
+/// Print a state snapshot for humans
+pub fn print_summary(state: &SystemState) {
+    let since = |time: Option<chrono::DateTime<chrono::Utc>>| match time {
+        Some(time) => format!(" since {}", time.to_rfc3339()),
+        None => String::new(),
+    };
+
+    println!("{}: {:?}{}", "Status".bold(), state.status, since(state.started_at));
+    println!(
+        "{}: {}{}",
+        "Posture".bold(),
+        state.current_posture.to_string().cyan(),
+        since(state.last_posture_change)
+    );
+    println!("{}: {:.2}", "Threat level".bold(), state.threat_level);
+    println!("{}: {}", "Active services".bold(), state.active_services_count);
+    println!("{}: {}", "Active honeypots".bold(), state.active_honeypots_count);
+
+    match &state.current_fingerprint {
+        Some(fingerprint) => println!(
+            "{}: {} ({} {})",
+            "Fingerprint".bold(),
+            fingerprint.name.cyan(),
+            fingerprint.os_family,
+            fingerprint.os_version.as_deref().unwrap_or("")
+        ),
+        None => println!("{}: {}", "Fingerprint".bold(), "none".dimmed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::{ChameleonCore, ChameleonService, EventSender, Posture};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_fetch_state() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut config = camaleon::CamaleonConfig::default().api;
+        config.bind_addresses = vec![format!("127.0.0.1:{}", port)];
+        config.api_keys = vec!["s3cret".to_string()];
+
+        let core = ChameleonCore::new();
+        core.change_posture(Posture::Fulgurant).await.unwrap();
+        let (tx, _rx) = mpsc::channel(16);
+        let (_api_tx, api_rx) = mpsc::channel(16);
+        let api = pigment_api::PigmentApi::new(config.to_module_config().unwrap(), EventSender::from(tx), api_rx)
+            .await
+            .unwrap()
+            .with_core(Arc::new(core));
+        let server = tokio::spawn(async move { api.start().await });
+
+        let url = crate::emit::api_url(&config).unwrap();
+        let mut fetched = Err(anyhow::anyhow!("not attempted"));
+        for _ in 0..50 {
+            fetched = fetch(&url, Some("s3cret")).await;
+            if fetched.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let state: SystemState = serde_json::from_value(fetched.unwrap()).unwrap();
+        assert_eq!(state.current_posture, Posture::Fulgurant);
+
+        // The key is required once configured
+        let error = fetch(&url, None).await.unwrap_err();
+        assert!(error.to_string().contains("refused the key"), "{}", error);
+
+        server.abort();
+        assert!(fetch("http://127.0.0.1:1", None).await.is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Restarts modules that stopped doing their work
+//!
+//! Every `interval` the watchdog asks each module for its health. A module
+//! that reports itself unhealthy, or misses the heartbeat by not answering
+//! within `heartbeat_timeout`, is announced with a `ServiceLifecycle` event
+//! and restarted, waiting longer before each new attempt. Once it has been
+//! restarted `max_attempts` times in a row without recovering, it is
+//! announced as failed and left alone until it reports healthy again.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chame_core::{ChameleonCore, ChameleonService, Event, ModuleHealth, RetryPolicy};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// How often modules are checked and restarted
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogConfig {
+    /// Time between two health checks
+    pub interval: Duration,
+
+    /// How long a module has to answer a health check
+    pub heartbeat_timeout: Duration,
+
+    /// Restarts in a row before giving up (`max_attempts`) and the delay between them
+    pub restarts: RetryPolicy,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            heartbeat_timeout: Duration::from_secs(5),
+            restarts: RetryPolicy {
+                max_attempts: 3,
+                initial_delay: Duration::from_secs(10),
+                max_delay: Duration::from_secs(300),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// A module the watchdog looks after
+#[async_trait]
+pub trait Watched: Send + Sync {
+    /// Name used in logs and lifecycle events
+    fn name(&self) -> &str;
+
+    /// Current health of the module
+    async fn health(&self) -> ModuleHealth;
+
+    /// Bring the module back after it became unhealthy
+    async fn restart(&self) -> Result<()>;
+}
+
+/// Where a module stands with the watchdog
+#[derive(Debug, Default)]
+struct Tracker {
+    /// Restarts since the module was last seen recovered
+    restarts: u32,
+
+    /// Earliest time of the next restart
+    next_restart: Option<Instant>,
+
+    /// Whether the watchdog gave up on the module
+    failed: bool,
+}
+
+/// Checks the health of modules and restarts the unhealthy ones
+pub struct Watchdog {
+    config: WatchdogConfig,
+    core: ChameleonCore,
+    modules: Vec<(Arc<dyn Watched>, Tracker)>,
+}
+
+impl Watchdog {
+    /// Create a watchdog announcing what it does through `core`
+    pub fn new(config: WatchdogConfig, core: ChameleonCore) -> Self {
+        Self {
+            config,
+            core,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Look after `module`
+    pub fn watch(&mut self, module: Arc<dyn Watched>) {
+        self.modules.push((module, Tracker::default()));
+    }
+
+    /// Check the modules every `interval` until `cancel` fires
+    ///
+    /// The first check happens one interval in, once the modules had time
+    /// to settle after startup.
+    pub fn spawn(mut self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(Instant::now() + self.config.interval, self.config.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = interval.tick() => self.check().await,
+                }
+            }
+        })
+    }
+
+    /// Check every module once, restarting the unhealthy ones
+    pub async fn check(&mut self) {
+        for (module, tracker) in &mut self.modules {
+            let name = module.name();
+            let health = match tokio::time::timeout(self.config.heartbeat_timeout, module.health()).await {
+                Ok(health) => health,
+                Err(_) => ModuleHealth::Unhealthy(format!(
+                    "missed heartbeat, no answer within {:?}",
+                    self.config.heartbeat_timeout
+                )),
+            };
+
+            let ModuleHealth::Unhealthy(reason) = health else {
+                if tracker.restarts > 0 {
+                    tracing::info!("Module {} recovered after {} restarts", name, tracker.restarts);
+                }
+                *tracker = Tracker::default();
+                continue;
+            };
+
+            if tracker.failed || tracker.next_restart.is_some_and(|at| Instant::now() < at) {
+                continue;
+            }
+
+            let max_restarts = self.config.restarts.max_attempts;
+            if tracker.restarts >= max_restarts {
+                tracing::error!("Module {} still unhealthy after {} restarts, giving up: {}", name, max_restarts, reason);
+                tracker.failed = true;
+                announce(&self.core, name, "failed", &reason, tracker.restarts).await;
+                continue;
+            }
+
+            tracker.restarts += 1;
+            tracker.next_restart = Some(Instant::now() + self.config.restarts.delay(tracker.restarts));
+            tracing::warn!(
+                "Module {} unhealthy, restarting (attempt {}/{}): {}",
+                name,
+                tracker.restarts,
+                max_restarts,
+                reason
+            );
+            announce(&self.core, name, "unhealthy", &reason, tracker.restarts).await;
+
+            match module.restart().await {
+                Ok(()) => announce(&self.core, name, "active", "restarted by the watchdog", tracker.restarts).await,
+                Err(e) => tracing::warn!("Failed to restart {}: {:#}", name, e),
+            }
+        }
+    }
+}
+
+/// Publish a lifecycle event for a module the watchdog acted on
+async fn announce(core: &ChameleonCore, module: &str, status: &str, reason: &str, restarts: u32) {
+    let event = Event::service_lifecycle(
+        "orchestrator",
+        Some(serde_json::json!({
+            "module": module,
+            "status": status,
+            "reason": reason,
+            "restarts": restarts,
+        })),
+    );
+
+    if let Err(e) = core.handle_event(event).await {
+        tracing::warn!("Failed to record lifecycle event for {}: {}", module, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chame_core::EventType;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    /// A module whose health the test decides
+    struct FakeModule {
+        name: &'static str,
+        healthy: AtomicBool,
+        hangs: bool,
+        heals_on_restart: bool,
+        restarts: AtomicU32,
+    }
+
+    impl FakeModule {
+        fn new(name: &'static str, heals_on_restart: bool) -> Self {
+            Self {
+                name,
+                healthy: AtomicBool::new(true),
+                hangs: false,
+                heals_on_restart,
+                restarts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Watched for FakeModule {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn health(&self) -> ModuleHealth {
+            if self.hangs {
+                std::future::pending::<()>().await;
+            }
+            match self.healthy.load(Ordering::SeqCst) {
+                true => ModuleHealth::Healthy,
+                false => ModuleHealth::Unhealthy("task exited".to_string()),
+            }
+        }
+
+        async fn restart(&self) -> Result<()> {
+            self.restarts.fetch_add(1, Ordering::SeqCst);
+            if !self.heals_on_restart {
+                anyhow::bail!("still broken");
+            }
+            self.healthy.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            interval: Duration::from_millis(10),
+            heartbeat_timeout: Duration::from_millis(20),
+            restarts: RetryPolicy {
+                max_attempts: 2,
+                initial_delay: Duration::ZERO,
+                jitter: 0.0,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Status of every lifecycle event published so far
+    fn statuses(receiver: &mut tokio::sync::broadcast::Receiver<Event>) -> Vec<(String, String)> {
+        std::iter::from_fn(|| receiver.try_recv().ok())
+            .filter(|event| event.event_type == EventType::ServiceLifecycle)
+            .map(|event| {
+                let data = event.data.unwrap();
+                (data["module"].as_str().unwrap().to_string(), data["status"].as_str().unwrap().to_string())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_module_is_restarted() {
+        let core = ChameleonCore::new();
+        let mut events = core.event_bus().subscribe();
+        let module = Arc::new(FakeModule::new("eye360", true));
+        let mut watchdog = Watchdog::new(config(), core);
+        watchdog.watch(module.clone());
+
+        watchdog.check().await;
+        assert_eq!(module.restarts.load(Ordering::SeqCst), 0);
+
+        module.healthy.store(false, Ordering::SeqCst);
+        watchdog.check().await;
+        assert_eq!(module.restarts.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            statuses(&mut events),
+            [("eye360".to_string(), "unhealthy".to_string()), ("eye360".to_string(), "active".to_string())]
+        );
+
+        // Recovering resets the restart budget
+        watchdog.check().await;
+        assert_eq!(watchdog.modules[0].1.restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_restarts_are_bounded() {
+        let core = ChameleonCore::new();
+        let mut events = core.event_bus().subscribe();
+        let module = Arc::new(FakeModule::new("lurefield", false));
+        module.healthy.store(false, Ordering::SeqCst);
+        let mut watchdog = Watchdog::new(config(), core);
+        watchdog.watch(module.clone());
+
+        for _ in 0..5 {
+            watchdog.check().await;
+        }
+        assert_eq!(module.restarts.load(Ordering::SeqCst), 2);
+        let statuses: Vec<_> = statuses(&mut events).into_iter().map(|(_, status)| status).collect();
+        assert_eq!(statuses, ["unhealthy", "unhealthy", "failed"]);
+
+        // Brought back by hand, e.g. from the API
+        module.healthy.store(true, Ordering::SeqCst);
+        watchdog.check().await;
+        assert!(!watchdog.modules[0].1.failed);
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeat_is_unhealthy() {
+        let core = ChameleonCore::new();
+        let module = Arc::new(FakeModule { hangs: true, ..FakeModule::new("nettongue", false) });
+        let mut watchdog = Watchdog::new(config(), core);
+        watchdog.watch(module.clone());
+
+        watchdog.check().await;
+        assert_eq!(module.restarts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,5 +1,8 @@
+use camaleon::sim::AttackScenario;
 use chame_core::events::{Event, EventType};
-use std::sync::Arc;
+use chame_core::{EventBus, EventSender};
+use posture_engine::{Posture, PostureEngine, PostureEngineConfig};
+use std::time::Duration;
 use tokio::sync::mpsc;
 
 #[tokio::test]
@@ -51,14 +54,36 @@ async fn test_module_integration() {
 
 #[tokio::test]
 async fn test_adaptive_behavior() {
-    // This test would verify the adaptive behavior of the system
+    // The engine starts neutral and evaluates every stage of the attack
+    let (tx, _rx) = mpsc::channel(100);
+    let config = PostureEngineConfig { change_threshold: 0.3, ..Default::default() };
+    let engine = PostureEngine::new(config, EventSender::from(tx)).await.unwrap();
+    let bus = EventBus::default();
+    let mut observer = bus.subscribe();
     
-    // In a real implementation, we would:
-    // 1. Initialize the system in a neutral posture
-    // 2. Generate a series of security events
-    // 3. Verify that the system adapts its posture appropriately
-    // 4. Check that the appropriate defensive measures are activated
+    let scenario = AttackScenario::new("scan, brute force, then honeypot")
+        .with_pace(Duration::from_millis(5))
+        .port_scan("203.0.113.7", 200)
+        .brute_force("203.0.113.7", 5)
+        .honeypot_hit("203.0.113.7", 2222)
+        .wait(Duration::from_millis(20))
+        .calm(10);
+    let outcome = scenario.run(&bus, &engine).await.unwrap();
     
-    // For now, this is a placeholder that always passes
-    assert!(true);
+    // Escalates as the attack gets closer, then relaxes once it stops
+    outcome.assert_posture_after("port_scan", Posture::Mimetic);
+    outcome.assert_posture_after("brute_force", Posture::Mimetic);
+    outcome.assert_posture_after("honeypot_hit", Posture::Fulgurant);
+    outcome.assert_transitions(&[Posture::Mimetic, Posture::Fulgurant, Posture::Neutral]);
+    assert_eq!(outcome.final_posture(), Some(&Posture::Neutral));
+    assert_eq!(engine.get_current_posture().await, Posture::Neutral);
+    
+    // Every scripted event went through the bus
+    let published = scenario.stages().iter().map(|stage| stage.events.len()).sum::<usize>();
+    let mut received = 0;
+    while let Ok(event) = observer.try_recv() {
+        assert_ne!(event.event_type, EventType::PostureChange);
+        received += 1;
+    }
+    assert_eq!(received, published);
 }